@@ -61,14 +61,14 @@ pub fn main() {
 
     // set activators and optimizers
     let hidden_sizes: [usize; 1] = [HIDDEN_SIZE];
-    let activator_enums: [ActivatorEnum; 1] = [ActivatorEnum::ReLU];
+    let activator_enums: [ActivatorEnum<FF>; 1] = [ActivatorEnum::ReLU];
     let optimizer_enum: OptimizerEnum<FF> = OptimizerEnum::SGD(0.1);
     // let optimizer_enum: OptimizerEnum<FF> = OptimizerEnum::AdaGrad(0.1);
     // let optimizer_enum: OptimizerEnum<FF> = OptimizerEnum::Momentum(0.01, 0.9);
     // let optimizer_enum: OptimizerEnum<FF> = OptimizerEnum::Nesterov(0.01, 0.9);
     // let optimizer_enum: OptimizerEnum<FF> = OptimizerEnum::RMSprop(0.01, 0.99);
     // let optimizer_enum: OptimizerEnum<FF> = OptimizerEnum::Adam(0.001, 0.9, 0.999);
-    let use_batch_norm = UseBatchNormEnum::Use(0.9);
+    let normalization = NormalizationEnum::BatchNorm(0.9);
     let use_dropout_enum: UseDropoutEnum<FF> = UseDropoutEnum::None;
     let regularizer_enum: RegularizerEnum<FF> = RegularizerEnum::None;
 
@@ -76,20 +76,22 @@ pub fn main() {
     println!("initialize a model...");
     let weight_init: WeightInitEnum = WeightInitEnum::Normal;
     let weight_init_params: FF = 0.01;
-    let mut network: Box<dyn ModelBase<FF, A = Array2<FF>, B = Array2<FF>>> =
-        Box::new(MLPClassifier::new(
+    let mut network: Box<dyn ModelBase<FF, A = Array2<FF>, B = Array2<FF>>> = Box::new(
+        MLPClassifier::new(
             input_size,
             &hidden_sizes,
             output_size,
             &activator_enums,
             optimizer_enum,
-            use_batch_norm,
+            normalization,
             use_dropout_enum,
             regularizer_enum,
             batch_axis,
             weight_init,
             weight_init_params,
-        ));
+        )
+        .unwrap(),
+    );
     network.print_detail();
     println!("Initial parameters:");
     network.print_parameters();
@@ -110,7 +112,7 @@ pub fn main() {
 
     // train loop
     println!("start training...");
-    trainer.train(&mut network);
+    trainer.train(&mut network).unwrap();
     println!("training finished.");
     println!("{} sec elapsed to training.", trainer.get_elapsed_time());
     println!("Final parameters:");
@@ -61,9 +61,9 @@ pub fn main() {
 
     // set activators and optimizers
     let hidden_sizes: [usize; 1] = [HIDDEN_SIZE];
-    let activator_enums: [ActivatorEnum; 1] = [ActivatorEnum::ReLU];
+    let activator_enums: [ActivatorEnum<FF>; 1] = [ActivatorEnum::ReLU];
     let optimizer_enum: OptimizerEnum<FF> = OptimizerEnum::SGD(0.1);
-    let use_batch_norm: UseBatchNormEnum<FF> = UseBatchNormEnum::None;
+    let normalization: NormalizationEnum<FF> = NormalizationEnum::None;
     let use_dropout_enum: UseDropoutEnum<FF> = UseDropoutEnum::None;
     let regularizer_enum: RegularizerEnum<FF> = RegularizerEnum::None;
 
@@ -71,20 +71,22 @@ pub fn main() {
     println!("initialize a model...");
     let weight_init: WeightInitEnum = WeightInitEnum::Normal;
     let weight_init_std: FF = 0.01;
-    let mut network: Box<dyn ModelBase<FF, A = Array2<FF>, B = Array2<FF>>> =
-        Box::new(MLPClassifier::new(
+    let mut network: Box<dyn ModelBase<FF, A = Array2<FF>, B = Array2<FF>>> = Box::new(
+        MLPClassifier::new(
             input_size,
             &hidden_sizes,
             output_size,
             &activator_enums,
             optimizer_enum,
-            use_batch_norm,
+            normalization,
             use_dropout_enum,
             regularizer_enum,
             batch_axis,
             weight_init,
             weight_init_std,
-        ));
+        )
+        .unwrap(),
+    );
     network.print_detail();
     println!("Initial parameters:");
     network.print_parameters();
@@ -105,7 +107,7 @@ pub fn main() {
 
     // train loop
     println!("start training...");
-    trainer.train(&mut network);
+    trainer.train(&mut network).unwrap();
     println!("training finished.");
     println!("{} sec elapsed to training.", trainer.get_elapsed_time());
     println!("Final parameters:");
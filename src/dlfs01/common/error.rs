@@ -0,0 +1,35 @@
+//! error
+//!
+//! Errors of this crate
+//!
+//! Constructors of layers and models and loaders of schemes and datasets report failures
+//! with `CrateError` instead of panicking.
+
+use super::models::SchemeError;
+use super::onnx::OnnxError;
+use crate::dlfs01::dataset::mnist::DataSetError;
+use std::io;
+use thiserror::Error as ThisError;
+
+/// Errors of this crate
+#[derive(ThisError, Debug)]
+pub enum CrateError {
+    #[error("File IO error: {0}")]
+    FileIOError(#[from] io::Error),
+    #[error("Invalid shape: {0}")]
+    ShapeError(String),
+    #[error("Invalid configuration: {0}")]
+    ConfigError(String),
+    #[error("Invalid scheme: {0}")]
+    SchemeError(#[from] SchemeError),
+    #[error("Invalid dataset: {0}")]
+    DataSetError(#[from] DataSetError),
+    #[error("Invalid ONNX model: {0}")]
+    OnnxError(#[from] OnnxError),
+}
+
+impl From<ndarray::ShapeError> for CrateError {
+    fn from(err: ndarray::ShapeError) -> Self {
+        CrateError::ShapeError(err.to_string())
+    }
+}
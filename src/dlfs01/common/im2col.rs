@@ -0,0 +1,240 @@
+//! im2col
+//!
+//! Conversion between batches of images and 2D column matrices
+//!
+//! Arrays must be "channel-first", i.e. (N, C, H, W) for images and (N, C, S1, .., Sk) in general.
+
+use super::util::*;
+use ndarray::{prelude::*, Slice};
+
+/// size of the output along an axis scanned by a sliding window
+pub fn output_size(input_size: usize, filter_size: usize, stride: usize, pad_size: usize) -> usize {
+    (input_size + 2 * pad_size - filter_size) / stride + 1
+}
+
+/// pad the spatial axes of images with a constant value
+pub fn pad_images<T>(x: &Array4<T>, pad_size: usize, value: T) -> Array4<T>
+where
+    T: CrateFloat,
+{
+    if pad_size == 0 {
+        return x.clone();
+    }
+    let (n, c, h, w) = x.dim();
+    let mut dst = Array4::from_elem((n, c, h + 2 * pad_size, w + 2 * pad_size), value);
+    dst.slice_mut(s![.., .., pad_size..pad_size + h, pad_size..pad_size + w])
+        .assign(x);
+    dst
+}
+
+/// size of a filter whose elements are spread `dilation` apart
+pub fn dilated_filter_size(filter_size: usize, dilation: usize) -> usize {
+    dilation * (filter_size - 1) + 1
+}
+
+/// window of a filter element along the spatial axes, i.e. the axes except the first two
+fn window_slice(
+    axis: usize,
+    offset: &IxDyn,
+    output_shape: &[usize],
+    stride_sizes: &[usize],
+    dilations: &[usize],
+) -> Slice {
+    if axis < 2 {
+        return Slice::from(..);
+    }
+    let i: usize = axis - 2;
+    let start: usize = offset[i] * dilations[i];
+    let end: usize = start + stride_sizes[i] * (output_shape[i] - 1) + 1;
+    Slice::new(start as isize, Some(end as isize), stride_sizes[i] as isize)
+}
+
+/// output shape of the spatial axes scanned by a dilated filter
+pub fn output_shape_nd(
+    input_shape: &[usize],
+    filter_shape: &[usize],
+    stride_sizes: &[usize],
+    pad_sizes: &[usize],
+    dilations: &[usize],
+) -> Vec<usize> {
+    (0..filter_shape.len())
+        .map(|i| {
+            output_size(
+                input_shape[i + 2],
+                dilated_filter_size(filter_shape[i], dilations[i]),
+                stride_sizes[i],
+                pad_sizes[i],
+            )
+        })
+        .collect()
+}
+
+/// expand channel-first arrays with shape (N, C, S1, .., Sk) into a matrix with shape
+/// (N * O1 * .. * Ok, C * F1 * .. * Fk)
+///
+/// Each of `filter_shape`, `stride_sizes`, `pad_sizes` and `dilations` has an element
+/// for each spatial axis.
+pub fn im2col_nd<T, D>(
+    x: &Array<T, D>,
+    filter_shape: &[usize],
+    stride_sizes: &[usize],
+    pad_sizes: &[usize],
+    dilations: &[usize],
+) -> Array2<T>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    let input_shape: &[usize] = x.shape();
+    let (n, c) = (input_shape[0], input_shape[1]);
+    let output_shape: Vec<usize> = output_shape_nd(
+        input_shape,
+        filter_shape,
+        stride_sizes,
+        pad_sizes,
+        dilations,
+    );
+    let filter_len: usize = filter_shape.iter().product();
+    let mut padded_shape: Vec<usize> = input_shape.to_vec();
+    for (v, &pad_size) in padded_shape[2..].iter_mut().zip(pad_sizes.iter()) {
+        *v += 2 * pad_size;
+    }
+    let mut img = ArrayD::<T>::zeros(padded_shape);
+    img.slice_each_axis_mut(|axis| match axis.axis.index() {
+        0 | 1 => Slice::from(..),
+        i => Slice::from(pad_sizes[i - 2]..pad_sizes[i - 2] + input_shape[i]),
+    })
+    .assign(x);
+    // (N, O1, .., Ok, C, F1 * .. * Fk)
+    let mut col_shape: Vec<usize> = vec![n];
+    col_shape.extend(output_shape.iter());
+    col_shape.extend([c, filter_len].iter());
+    let mut col = ArrayD::<T>::zeros(col_shape);
+    // (N, C, O1, .., Ok) -> (N, O1, .., Ok, C)
+    let mut axes: Vec<usize> = vec![0];
+    axes.extend(2..filter_shape.len() + 2);
+    axes.push(1);
+    for (k, offset) in ndarray::indices(IxDyn(filter_shape))
+        .into_iter()
+        .enumerate()
+    {
+        let window = img.slice_each_axis(|axis| {
+            window_slice(
+                axis.axis.index(),
+                &offset,
+                &output_shape,
+                stride_sizes,
+                dilations,
+            )
+        });
+        col.index_axis_mut(Axis(filter_shape.len() + 2), k)
+            .assign(&window.permuted_axes(axes.clone()));
+    }
+    let rows: usize = n * output_shape.iter().product::<usize>();
+    col.into_shape((rows, c * filter_len)).unwrap()
+}
+
+/// fold a matrix with shape (N * O1 * .. * Ok, C * F1 * .. * Fk) back into arrays with
+/// `input_shape`, i.e. (N, C, S1, .., Sk)
+///
+/// Values of overlapping windows are summed up.
+pub fn col2im_nd<T>(
+    col: &Array2<T>,
+    input_shape: &[usize],
+    filter_shape: &[usize],
+    stride_sizes: &[usize],
+    pad_sizes: &[usize],
+    dilations: &[usize],
+) -> ArrayD<T>
+where
+    T: CrateFloat,
+{
+    let (n, c) = (input_shape[0], input_shape[1]);
+    let output_shape: Vec<usize> = output_shape_nd(
+        input_shape,
+        filter_shape,
+        stride_sizes,
+        pad_sizes,
+        dilations,
+    );
+    let filter_len: usize = filter_shape.iter().product();
+    let mut col_shape: Vec<usize> = vec![n];
+    col_shape.extend(output_shape.iter());
+    col_shape.extend([c, filter_len].iter());
+    let col = col.to_shape(col_shape).unwrap();
+    // (N, O1, .., Ok, C) -> (N, C, O1, .., Ok)
+    let mut axes: Vec<usize> = vec![0, filter_shape.len() + 1];
+    axes.extend(1..filter_shape.len() + 1);
+    let mut padded_shape: Vec<usize> = input_shape.to_vec();
+    for (v, &pad_size) in padded_shape[2..].iter_mut().zip(pad_sizes.iter()) {
+        *v += 2 * pad_size;
+    }
+    let mut img = ArrayD::<T>::zeros(padded_shape);
+    for (k, offset) in ndarray::indices(IxDyn(filter_shape))
+        .into_iter()
+        .enumerate()
+    {
+        let mut window = img.slice_each_axis_mut(|axis| {
+            window_slice(
+                axis.axis.index(),
+                &offset,
+                &output_shape,
+                stride_sizes,
+                dilations,
+            )
+        });
+        window += &col
+            .index_axis(Axis(filter_shape.len() + 2), k)
+            .permuted_axes(axes.clone());
+    }
+    img.slice_each_axis(|axis| match axis.axis.index() {
+        0 | 1 => Slice::from(..),
+        i => Slice::from(pad_sizes[i - 2]..pad_sizes[i - 2] + input_shape[i]),
+    })
+    .to_owned()
+}
+
+/// expand images with shape (N, C, H, W) into a matrix with shape (N * OH * OW, C * FH * FW)
+pub fn im2col<T>(
+    x: &Array4<T>,
+    filter_shape: (usize, usize),
+    stride_sizes: (usize, usize),
+    pad_size: usize,
+) -> Array2<T>
+where
+    T: CrateFloat,
+{
+    im2col_nd(
+        x,
+        &[filter_shape.0, filter_shape.1],
+        &[stride_sizes.0, stride_sizes.1],
+        &[pad_size, pad_size],
+        &[1, 1],
+    )
+}
+
+/// fold a matrix with shape (N * OH * OW, C * FH * FW) back into images with `input_shape`
+///
+/// Values of overlapping windows are summed up.
+pub fn col2im<T>(
+    col: &Array2<T>,
+    input_shape: (usize, usize, usize, usize),
+    filter_shape: (usize, usize),
+    stride_sizes: (usize, usize),
+    pad_size: usize,
+) -> Array4<T>
+where
+    T: CrateFloat,
+{
+    let (n, c, h, w) = input_shape;
+    col2im_nd(
+        col,
+        &[n, c, h, w],
+        &[filter_shape.0, filter_shape.1],
+        &[stride_sizes.0, stride_sizes.1],
+        &[pad_size, pad_size],
+        &[1, 1],
+    )
+    .into_dimensionality::<Ix4>()
+    .unwrap()
+}
@@ -5,10 +5,16 @@
 #![allow(unused_variables)]
 
 use super::super::util::*;
-use super::layer_base::LayerBase;
-use ndarray::{prelude::*, RemoveAxis};
+use super::layer_base::{param_grad, LayerBase, ParamGrad};
+use ndarray::{prelude::*, RemoveAxis, Zip};
 use std::marker::PhantomData;
 
+const SELU_ALPHA: f64 = 1.673_263_242_354_377_3;
+const SELU_SCALE: f64 = 1.050_700_987_355_480_5;
+/// sqrt(2 / pi)
+const GELU_COEFF: f64 = 0.797_884_560_802_865_4;
+const GELU_CUBIC: f64 = 0.044_715;
+
 // >>>>>>>>>>>>> Idwntity layer >>>>>>>>>>>>>
 /// Arbitrary-D Identity layer
 pub struct Identity<T: CrateFloat, D> {
@@ -182,7 +188,35 @@ pub type SigmoidD<T> = Sigmoid<T, IxDyn>;
 
 // >>>>>>>>>>>>> Softmax layer >>>>>>>>>>>>>
 
-/// Arbitrary-D sigmoid layer
+/// replace values with their log-softmax by the log-sum-exp `x - max - log(sum(exp(x - max)))`
+///
+/// NaN values propagate to all the outputs.
+pub(super) fn log_softmax_inplace<T, D>(mut view: ArrayViewMut<T, D>)
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    let zero: T = cast_t2u(0.0);
+    let v_max: T = view.fold(
+        T::neg_infinity(),
+        |m, &v| {
+            if v > m || v.is_nan() {
+                v
+            } else {
+                m
+            }
+        },
+    );
+    // shifting by an infinite max would give NaN for every element
+    let shift: T = if v_max.is_infinite() { zero } else { v_max };
+    // subtracting the shift first keeps the precision for large logits
+    let log_sum: T = view.fold(zero, |m, &v| m + T::exp(v - shift)).ln();
+    view.mapv_inplace(|v| (v - shift) - log_sum);
+}
+
+/// Arbitrary-D softmax layer
+///
+/// Softmax is taken over all the elements of each sample on the batch axis.
 pub struct Softmax<T: CrateFloat, D> {
     pub output: Array<T, D>,
     axis: usize,
@@ -212,21 +246,24 @@ where
     type A = Array<T, D>;
     type B = Array<T, D>;
     fn forward(&mut self, x: &Self::A) -> Self::B {
-        let zero: T = cast_t2u(0.0);
-        let batch_size: T = cast_t2u(x.len_of(Axis(0)));
-
         self.output = x.clone();
-        for mut view in self.output.axis_iter_mut(Axis(self.axis)) {
-            let v_max = view.fold(zero / zero, |m, &v| v.max(m));
-            view.mapv_inplace(|v| T::exp(v - v_max));
-            let v_exp_sum = view.sum();
-            view.mapv_inplace(|v| v / v_exp_sum);
+        for view in self.output.axis_iter_mut(Axis(self.axis)) {
+            log_softmax_inplace(view);
         }
+        self.output.mapv_inplace(T::exp);
         self.output.clone()
     }
     fn backward(&mut self, dx: &Self::B) -> Self::A {
-        // TODO: correct implementation
-        dx.clone()
+        // dy * y - y * sum(dy * y) for each sample
+        let mut dst: Self::A = dx * &self.output;
+        for (mut d, y) in dst
+            .axis_iter_mut(Axis(self.axis))
+            .zip(self.output.axis_iter(Axis(self.axis)))
+        {
+            let d_sum: T = d.sum();
+            Zip::from(&mut d).and(&y).for_each(|d, &y| *d -= y * d_sum);
+        }
+        dst
     }
     fn print_detail(&self) {
         println!("softmax activation layer.");
@@ -241,3 +278,684 @@ pub type Softmax6<T> = Softmax<T, Ix6>;
 pub type SoftmaxD<T> = Softmax<T, IxDyn>;
 
 // <<<<<<<<<<<<< Softmax layer <<<<<<<<<<<<<
+
+// >>>>>>>>>>>>> LogSoftmax layer >>>>>>>>>>>>>
+
+/// Arbitrary-D log-softmax layer
+///
+/// Log-softmax is taken over all the elements of each sample on the batch axis,
+/// with the log-sum-exp so that it is finite for any finite inputs.
+pub struct LogSoftmax<T: CrateFloat, D> {
+    pub output: Array<T, D>,
+    axis: usize,
+}
+
+impl<T: 'static, D> LogSoftmax<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    pub fn new<Sh>(shape: Sh, axis: usize) -> Self
+    where
+        Sh: ShapeBuilder<Dim = D>,
+    {
+        Self {
+            output: Array::<T, D>::zeros(shape),
+            axis,
+        }
+    }
+}
+
+impl<T: 'static, D> LayerBase<T> for LogSoftmax<T, D>
+where
+    T: CrateFloat,
+    D: Dimension + RemoveAxis,
+{
+    type A = Array<T, D>;
+    type B = Array<T, D>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        self.output = x.clone();
+        for view in self.output.axis_iter_mut(Axis(self.axis)) {
+            log_softmax_inplace(view);
+        }
+        self.output.clone()
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        // dy - softmax * sum(dy) for each sample
+        let mut dst: Self::A = dx.clone();
+        for (mut d, y) in dst
+            .axis_iter_mut(Axis(self.axis))
+            .zip(self.output.axis_iter(Axis(self.axis)))
+        {
+            let d_sum: T = d.sum();
+            Zip::from(&mut d)
+                .and(&y)
+                .for_each(|d, &y| *d -= y.exp() * d_sum);
+        }
+        dst
+    }
+    fn print_detail(&self) {
+        println!("log-softmax activation layer.");
+    }
+}
+
+pub type LogSoftmax2<T> = LogSoftmax<T, Ix2>;
+pub type LogSoftmax3<T> = LogSoftmax<T, Ix3>;
+pub type LogSoftmax4<T> = LogSoftmax<T, Ix4>;
+pub type LogSoftmax5<T> = LogSoftmax<T, Ix5>;
+pub type LogSoftmax6<T> = LogSoftmax<T, Ix6>;
+pub type LogSoftmaxD<T> = LogSoftmax<T, IxDyn>;
+
+// <<<<<<<<<<<<< LogSoftmax layer <<<<<<<<<<<<<
+
+/// logistic sigmoid of a value
+fn sigmoid<T: CrateFloat>(v: T) -> T {
+    let one: T = cast_t2u(1.0);
+    one / (one + T::exp(-v))
+}
+
+/// softplus of a value, `ln(1 + exp(v))`, computed without overflow
+fn softplus<T: CrateFloat>(v: T) -> T {
+    let zero: T = cast_t2u(0.0);
+    v.max(zero) + T::exp(-v.abs()).ln_1p()
+}
+
+// >>>>>>>>>>>>> LeakyReLU layer >>>>>>>>>>>>>
+
+/// Arbitrary-D LeakyReLU layer
+///
+/// Negative inputs are scaled by `alpha`.
+pub struct LeakyReLU<T: CrateFloat, D> {
+    alpha: T,
+    x: Array<T, D>,
+}
+
+impl<T: 'static, D> LeakyReLU<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    pub fn new<Sh>(shape: Sh, alpha: T) -> Self
+    where
+        Sh: ShapeBuilder<Dim = D>,
+    {
+        Self {
+            alpha,
+            x: Array::<T, D>::zeros(shape),
+        }
+    }
+}
+
+impl<T: 'static, D> LayerBase<T> for LeakyReLU<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    type A = Array<T, D>;
+    type B = Array<T, D>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        let zero: T = cast_t2u(0.0);
+        let alpha: T = self.alpha;
+        self.x = x.clone();
+        x.map(|&v| if v > zero { v } else { alpha * v })
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        let zero: T = cast_t2u(0.0);
+        let alpha: T = self.alpha;
+        let mut dst: Self::A = dx.clone();
+        Zip::from(&mut dst).and(&self.x).for_each(|d, &v| {
+            if v <= zero {
+                *d *= alpha;
+            }
+        });
+        dst
+    }
+    fn print_detail(&self) {
+        println!("LeakyReLU activation layer.");
+    }
+}
+
+pub type LeakyReLU1<T> = LeakyReLU<T, Ix1>;
+pub type LeakyReLU2<T> = LeakyReLU<T, Ix2>;
+pub type LeakyReLU3<T> = LeakyReLU<T, Ix3>;
+pub type LeakyReLU4<T> = LeakyReLU<T, Ix4>;
+pub type LeakyReLU5<T> = LeakyReLU<T, Ix5>;
+pub type LeakyReLU6<T> = LeakyReLU<T, Ix6>;
+pub type LeakyReLUD<T> = LeakyReLU<T, IxDyn>;
+
+// <<<<<<<<<<<<< LeakyReLU layer <<<<<<<<<<<<<
+
+// >>>>>>>>>>>>> PReLU layer >>>>>>>>>>>>>
+
+/// Arbitrary-D PReLU layer
+///
+/// Each feature has a learnable slope for negative inputs, which is updated by `update`
+/// with SGD, or by the optimizer of a model through `parameters`.
+///
+/// See https://arxiv.org/abs/1502.01852 in detail
+pub struct PReLU<T: CrateFloat, D: Dimension + RemoveAxis> {
+    pub alpha: Array<T, D::Smaller>,
+    pub dalpha: Array<T, D::Smaller>,
+    batch_axis: usize,
+    x: Array<T, D>,
+}
+
+impl<T: 'static, D> PReLU<T, D>
+where
+    T: CrateFloat,
+    D: Dimension + RemoveAxis,
+{
+    pub fn new<Sh>(shape: Sh, batch_axis: usize, alpha: T) -> Self
+    where
+        Sh: ShapeBuilder<Dim = D>,
+    {
+        let x: Array<T, D> = Array::<T, D>::zeros(shape);
+        let alpha: Array<T, D::Smaller> =
+            Array::from_elem(x.raw_dim().remove_axis(Axis(batch_axis)), alpha);
+        Self {
+            dalpha: Array::zeros(alpha.raw_dim()),
+            alpha,
+            batch_axis,
+            x,
+        }
+    }
+    /// view of `alpha` with the batch axis inserted, which is broadcast over the batch
+    fn alpha_view(&self) -> ArrayView<'_, T, D> {
+        let mut shape: D = self.x.raw_dim();
+        shape[self.batch_axis] = 1;
+        self.alpha
+            .view()
+            .into_shape(shape)
+            .expect("alpha must have the shape of the input without the batch axis")
+    }
+}
+
+impl<T: 'static, D> LayerBase<T> for PReLU<T, D>
+where
+    T: CrateFloat,
+    D: Dimension + RemoveAxis,
+{
+    type A = Array<T, D>;
+    type B = Array<T, D>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        let zero: T = cast_t2u(0.0);
+        self.x = x.clone();
+        x.map(|&v| v.max(zero)) + &(x.map(|&v| v.min(zero)) * &self.alpha_view())
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        let zero: T = cast_t2u(0.0);
+        let one: T = cast_t2u(1.0);
+        let negative: Self::A = self.x.map(|&v| v.min(zero));
+        self.dalpha = (dx * &negative).sum_axis(Axis(self.batch_axis));
+        let positive: Self::A = self.x.map(|&v| if v > zero { one } else { zero });
+        let dst: Self::A = dx * &positive;
+        dst + &(dx * &positive.map(|&v| one - v) * &self.alpha_view())
+    }
+    fn update(&mut self, lr: T) {
+        self.alpha.scaled_add(-lr, &self.dalpha);
+    }
+    fn parameters(&mut self) -> Vec<ParamGrad<'_, T>> {
+        vec![param_grad(&mut self.alpha, &self.dalpha)]
+    }
+    fn print_detail(&self) {
+        println!("PReLU activation layer.");
+        println!("alpha shape: {:?}", self.alpha.shape());
+    }
+    fn print_parameters(&self) {
+        println!("alpha: {:?}", self.alpha);
+        println!("dalpha: {:?}", self.dalpha);
+    }
+}
+
+pub type PReLU1<T> = PReLU<T, Ix1>;
+pub type PReLU2<T> = PReLU<T, Ix2>;
+pub type PReLU3<T> = PReLU<T, Ix3>;
+pub type PReLU4<T> = PReLU<T, Ix4>;
+pub type PReLU5<T> = PReLU<T, Ix5>;
+pub type PReLU6<T> = PReLU<T, Ix6>;
+pub type PReLUD<T> = PReLU<T, IxDyn>;
+
+// <<<<<<<<<<<<< PReLU layer <<<<<<<<<<<<<
+
+// >>>>>>>>>>>>> ELU layer >>>>>>>>>>>>>
+
+/// Arbitrary-D ELU layer
+///
+/// See https://arxiv.org/abs/1511.07289 in detail
+pub struct ELU<T: CrateFloat, D> {
+    alpha: T,
+    x: Array<T, D>,
+    output: Array<T, D>,
+}
+
+impl<T: 'static, D> ELU<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    pub fn new<Sh>(shape: Sh, alpha: T) -> Self
+    where
+        Sh: ShapeBuilder<Dim = D>,
+    {
+        let x: Array<T, D> = Array::<T, D>::zeros(shape);
+        Self {
+            alpha,
+            output: x.clone(),
+            x,
+        }
+    }
+}
+
+impl<T: 'static, D> LayerBase<T> for ELU<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    type A = Array<T, D>;
+    type B = Array<T, D>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        let zero: T = cast_t2u(0.0);
+        let alpha: T = self.alpha;
+        self.x = x.clone();
+        self.output = x.map(|&v| if v > zero { v } else { alpha * v.exp_m1() });
+        self.output.clone()
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        let zero: T = cast_t2u(0.0);
+        let alpha: T = self.alpha;
+        let mut dst: Self::A = dx.clone();
+        Zip::from(&mut dst)
+            .and(&self.x)
+            .and(&self.output)
+            .for_each(|d, &v, &y| {
+                if v <= zero {
+                    *d *= y + alpha;
+                }
+            });
+        dst
+    }
+    fn print_detail(&self) {
+        println!("ELU activation layer.");
+    }
+}
+
+pub type ELU1<T> = ELU<T, Ix1>;
+pub type ELU2<T> = ELU<T, Ix2>;
+pub type ELU3<T> = ELU<T, Ix3>;
+pub type ELU4<T> = ELU<T, Ix4>;
+pub type ELU5<T> = ELU<T, Ix5>;
+pub type ELU6<T> = ELU<T, Ix6>;
+pub type ELUD<T> = ELU<T, IxDyn>;
+
+// <<<<<<<<<<<<< ELU layer <<<<<<<<<<<<<
+
+// >>>>>>>>>>>>> SELU layer >>>>>>>>>>>>>
+
+/// Arbitrary-D SELU layer
+///
+/// See https://arxiv.org/abs/1706.02515 in detail
+pub struct SELU<T: CrateFloat, D> {
+    x: Array<T, D>,
+    alpha: T,
+    scale: T,
+}
+
+impl<T: 'static, D> SELU<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    pub fn new<Sh>(shape: Sh) -> Self
+    where
+        Sh: ShapeBuilder<Dim = D>,
+    {
+        Self {
+            x: Array::<T, D>::zeros(shape),
+            alpha: cast_t2u(SELU_ALPHA),
+            scale: cast_t2u(SELU_SCALE),
+        }
+    }
+}
+
+impl<T: 'static, D> LayerBase<T> for SELU<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    type A = Array<T, D>;
+    type B = Array<T, D>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        let zero: T = cast_t2u(0.0);
+        let (alpha, scale) = (self.alpha, self.scale);
+        self.x = x.clone();
+        x.map(|&v| {
+            if v > zero {
+                scale * v
+            } else {
+                scale * alpha * v.exp_m1()
+            }
+        })
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        let zero: T = cast_t2u(0.0);
+        let (alpha, scale) = (self.alpha, self.scale);
+        let mut dst: Self::A = dx.clone();
+        Zip::from(&mut dst).and(&self.x).for_each(|d, &v| {
+            if v > zero {
+                *d *= scale;
+            } else {
+                *d *= scale * alpha * v.exp();
+            }
+        });
+        dst
+    }
+    fn print_detail(&self) {
+        println!("SELU activation layer.");
+    }
+}
+
+pub type SELU1<T> = SELU<T, Ix1>;
+pub type SELU2<T> = SELU<T, Ix2>;
+pub type SELU3<T> = SELU<T, Ix3>;
+pub type SELU4<T> = SELU<T, Ix4>;
+pub type SELU5<T> = SELU<T, Ix5>;
+pub type SELU6<T> = SELU<T, Ix6>;
+pub type SELUD<T> = SELU<T, IxDyn>;
+
+// <<<<<<<<<<<<< SELU layer <<<<<<<<<<<<<
+
+// >>>>>>>>>>>>> GELU layer >>>>>>>>>>>>>
+
+/// Arbitrary-D GELU layer
+///
+/// The tanh approximation is used.
+/// See https://arxiv.org/abs/1606.08415 in detail
+pub struct GELU<T: CrateFloat, D> {
+    x: Array<T, D>,
+}
+
+impl<T: 'static, D> GELU<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    pub fn new<Sh>(shape: Sh) -> Self
+    where
+        Sh: ShapeBuilder<Dim = D>,
+    {
+        Self {
+            x: Array::<T, D>::zeros(shape),
+        }
+    }
+}
+
+impl<T: 'static, D> LayerBase<T> for GELU<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    type A = Array<T, D>;
+    type B = Array<T, D>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        let half: T = cast_t2u(0.5);
+        let one: T = cast_t2u(1.0);
+        let c: T = cast_t2u(GELU_COEFF);
+        let k: T = cast_t2u(GELU_CUBIC);
+        self.x = x.clone();
+        x.map(|&v| half * v * (one + (c * (v + k * v * v * v)).tanh()))
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        let half: T = cast_t2u(0.5);
+        let one: T = cast_t2u(1.0);
+        let three: T = cast_t2u(3.0);
+        let c: T = cast_t2u(GELU_COEFF);
+        let k: T = cast_t2u(GELU_CUBIC);
+        let mut dst: Self::A = dx.clone();
+        Zip::from(&mut dst).and(&self.x).for_each(|d, &v| {
+            let t: T = (c * (v + k * v * v * v)).tanh();
+            let grad: T =
+                half * (one + t) + half * v * (one - t * t) * c * (one + three * k * v * v);
+            *d *= grad;
+        });
+        dst
+    }
+    fn print_detail(&self) {
+        println!("GELU activation layer.");
+    }
+}
+
+pub type GELU1<T> = GELU<T, Ix1>;
+pub type GELU2<T> = GELU<T, Ix2>;
+pub type GELU3<T> = GELU<T, Ix3>;
+pub type GELU4<T> = GELU<T, Ix4>;
+pub type GELU5<T> = GELU<T, Ix5>;
+pub type GELU6<T> = GELU<T, Ix6>;
+pub type GELUD<T> = GELU<T, IxDyn>;
+
+// <<<<<<<<<<<<< GELU layer <<<<<<<<<<<<<
+
+// >>>>>>>>>>>>> Swish layer >>>>>>>>>>>>>
+
+/// Arbitrary-D Swish layer, `x * sigmoid(beta * x)`
+///
+/// SiLU is the case of `beta = 1`.
+/// See https://arxiv.org/abs/1710.05941 in detail
+pub struct Swish<T: CrateFloat, D> {
+    beta: T,
+    x: Array<T, D>,
+}
+
+impl<T: 'static, D> Swish<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    pub fn new<Sh>(shape: Sh, beta: T) -> Self
+    where
+        Sh: ShapeBuilder<Dim = D>,
+    {
+        Self {
+            beta,
+            x: Array::<T, D>::zeros(shape),
+        }
+    }
+}
+
+impl<T: 'static, D> LayerBase<T> for Swish<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    type A = Array<T, D>;
+    type B = Array<T, D>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        let beta: T = self.beta;
+        self.x = x.clone();
+        x.map(|&v| v * sigmoid(beta * v))
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        let one: T = cast_t2u(1.0);
+        let beta: T = self.beta;
+        let mut dst: Self::A = dx.clone();
+        Zip::from(&mut dst).and(&self.x).for_each(|d, &v| {
+            let s: T = sigmoid(beta * v);
+            *d *= s + beta * v * s * (one - s);
+        });
+        dst
+    }
+    fn print_detail(&self) {
+        println!("Swish activation layer.");
+    }
+}
+
+pub type Swish1<T> = Swish<T, Ix1>;
+pub type Swish2<T> = Swish<T, Ix2>;
+pub type Swish3<T> = Swish<T, Ix3>;
+pub type Swish4<T> = Swish<T, Ix4>;
+pub type Swish5<T> = Swish<T, Ix5>;
+pub type Swish6<T> = Swish<T, Ix6>;
+pub type SwishD<T> = Swish<T, IxDyn>;
+
+// <<<<<<<<<<<<< Swish layer <<<<<<<<<<<<<
+
+// >>>>>>>>>>>>> Mish layer >>>>>>>>>>>>>
+
+/// Arbitrary-D Mish layer, `x * tanh(softplus(x))`
+///
+/// See https://arxiv.org/abs/1908.08681 in detail
+pub struct Mish<T: CrateFloat, D> {
+    x: Array<T, D>,
+}
+
+impl<T: 'static, D> Mish<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    pub fn new<Sh>(shape: Sh) -> Self
+    where
+        Sh: ShapeBuilder<Dim = D>,
+    {
+        Self {
+            x: Array::<T, D>::zeros(shape),
+        }
+    }
+}
+
+impl<T: 'static, D> LayerBase<T> for Mish<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    type A = Array<T, D>;
+    type B = Array<T, D>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        self.x = x.clone();
+        x.map(|&v| v * softplus(v).tanh())
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        let one: T = cast_t2u(1.0);
+        let mut dst: Self::A = dx.clone();
+        Zip::from(&mut dst).and(&self.x).for_each(|d, &v| {
+            let t: T = softplus(v).tanh();
+            *d *= t + v * (one - t * t) * sigmoid(v);
+        });
+        dst
+    }
+    fn print_detail(&self) {
+        println!("Mish activation layer.");
+    }
+}
+
+pub type Mish1<T> = Mish<T, Ix1>;
+pub type Mish2<T> = Mish<T, Ix2>;
+pub type Mish3<T> = Mish<T, Ix3>;
+pub type Mish4<T> = Mish<T, Ix4>;
+pub type Mish5<T> = Mish<T, Ix5>;
+pub type Mish6<T> = Mish<T, Ix6>;
+pub type MishD<T> = Mish<T, IxDyn>;
+
+// <<<<<<<<<<<<< Mish layer <<<<<<<<<<<<<
+
+// >>>>>>>>>>>>> Tanh layer >>>>>>>>>>>>>
+
+/// Arbitrary-D tanh layer
+pub struct Tanh<T: CrateFloat, D> {
+    output: Array<T, D>,
+}
+
+impl<T: 'static, D> Tanh<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    pub fn new<Sh>(shape: Sh) -> Self
+    where
+        Sh: ShapeBuilder<Dim = D>,
+    {
+        Self {
+            output: Array::<T, D>::zeros(shape),
+        }
+    }
+}
+
+impl<T: 'static, D> LayerBase<T> for Tanh<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    type A = Array<T, D>;
+    type B = Array<T, D>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        self.output = x.map(|&v| v.tanh());
+        self.output.clone()
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        let one: T = cast_t2u(1.0);
+        dx * &self.output.map(|&v| one - v * v)
+    }
+    fn print_detail(&self) {
+        println!("tanh activation layer.");
+    }
+}
+
+pub type Tanh1<T> = Tanh<T, Ix1>;
+pub type Tanh2<T> = Tanh<T, Ix2>;
+pub type Tanh3<T> = Tanh<T, Ix3>;
+pub type Tanh4<T> = Tanh<T, Ix4>;
+pub type Tanh5<T> = Tanh<T, Ix5>;
+pub type Tanh6<T> = Tanh<T, Ix6>;
+pub type TanhD<T> = Tanh<T, IxDyn>;
+
+// <<<<<<<<<<<<< Tanh layer <<<<<<<<<<<<<
+
+// >>>>>>>>>>>>> Softplus layer >>>>>>>>>>>>>
+
+/// Arbitrary-D softplus layer, `ln(1 + exp(x))`
+pub struct Softplus<T: CrateFloat, D> {
+    x: Array<T, D>,
+}
+
+impl<T: 'static, D> Softplus<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    pub fn new<Sh>(shape: Sh) -> Self
+    where
+        Sh: ShapeBuilder<Dim = D>,
+    {
+        Self {
+            x: Array::<T, D>::zeros(shape),
+        }
+    }
+}
+
+impl<T: 'static, D> LayerBase<T> for Softplus<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    type A = Array<T, D>;
+    type B = Array<T, D>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        self.x = x.clone();
+        x.map(|&v| softplus(v))
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        dx * &self.x.map(|&v| sigmoid(v))
+    }
+    fn print_detail(&self) {
+        println!("softplus activation layer.");
+    }
+}
+
+pub type Softplus1<T> = Softplus<T, Ix1>;
+pub type Softplus2<T> = Softplus<T, Ix2>;
+pub type Softplus3<T> = Softplus<T, Ix3>;
+pub type Softplus4<T> = Softplus<T, Ix4>;
+pub type Softplus5<T> = Softplus<T, Ix5>;
+pub type Softplus6<T> = Softplus<T, Ix6>;
+pub type SoftplusD<T> = Softplus<T, IxDyn>;
+
+// <<<<<<<<<<<<< Softplus layer <<<<<<<<<<<<<
@@ -0,0 +1,338 @@
+//! attention
+//!
+//! Attention layers
+//!
+//! Inputs and outputs are (batch, time, features) arrays. Linear projections are applied to
+//! each time step through `Affine` layers on (batch * time, features) arrays.
+
+use super::super::error::CrateError;
+use super::super::param_initializers::weight_init::WeightInitEnum;
+use super::super::util::*;
+use super::activation::log_softmax_inplace;
+use super::affine::Affine;
+use super::layer_base::{LayerBase, ParamGrad};
+use super::normalization::LayerNormalization;
+use super::{call_activator, ActivatorEnum};
+use ndarray::{prelude::*, Zip};
+
+/// reshape (batch, time, features) into (batch * time, features)
+fn to_rows<T: CrateFloat>(x: &Array3<T>) -> Array2<T> {
+    let (batch_size, time, features) = x.dim();
+    x.as_standard_layout()
+        .into_owned()
+        .into_shape((batch_size * time, features))
+        .unwrap()
+}
+
+/// reshape (batch * time, features) into (batch, time, features)
+fn from_rows<T: CrateFloat>(x: Array2<T>, batch_size: usize, time: usize) -> Array3<T> {
+    let features = x.ncols();
+    x.as_standard_layout()
+        .into_owned()
+        .into_shape((batch_size, time, features))
+        .unwrap()
+}
+
+/// softmax of each row over the unmasked entries
+///
+/// Masked entries are set to `-inf` before the log-softmax, so that their weights are zero.
+/// A row whose entries are all masked is set to zero.
+fn masked_softmax_rows<T: CrateFloat>(scores: &mut Array2<T>, mask: &Array2<bool>) {
+    for (mut row, mask_row) in scores.outer_iter_mut().zip(mask.outer_iter()) {
+        if mask_row.iter().all(|&masked| masked) {
+            row.fill(cast_t2u(0.0));
+            continue;
+        }
+        Zip::from(&mut row).and(&mask_row).for_each(|v, &masked| {
+            if masked {
+                *v = T::neg_infinity();
+            }
+        });
+        log_softmax_inplace(row.view_mut());
+        row.mapv_inplace(T::exp);
+    }
+}
+
+// >>>>>>>>>>>>> MultiHeadAttention layer >>>>>>>>>>>>>
+
+/// Multi-head scaled dot-product self-attention layer
+///
+/// Queries, keys and values of `num_heads` heads are projected from the input by
+/// `query`, `key` and `value`, and the concatenated heads are projected by `output`.
+/// A causal layer does not attend to later time steps, and keys marked `true` in
+/// the (batch, time) padding mask are not attended to.
+///
+/// See https://arxiv.org/abs/1706.03762 in detail
+pub struct MultiHeadAttention<T: CrateFloat> {
+    pub query: Affine<T>,
+    pub key: Affine<T>,
+    pub value: Affine<T>,
+    pub output: Affine<T>,
+    num_heads: usize,
+    causal: bool,
+    padding_mask: Option<Array2<bool>>,
+    q: Array3<T>,
+    k: Array3<T>,
+    v: Array3<T>,
+    weights: Array4<T>,
+}
+
+impl<T: 'static> MultiHeadAttention<T>
+where
+    T: CrateFloat,
+{
+    pub fn new(
+        model_size: usize,
+        num_heads: usize,
+        causal: bool,
+        weight_init: WeightInitEnum,
+        weight_init_std: T,
+    ) -> Result<Self, CrateError> {
+        if num_heads == 0 || !model_size.is_multiple_of(num_heads) {
+            return Err(CrateError::ConfigError(format!(
+                "the number of heads {} must divide the model size {}",
+                num_heads, model_size
+            )));
+        }
+        let shape = (model_size, model_size);
+        Ok(Self {
+            query: Affine::new(shape, weight_init.clone(), weight_init_std),
+            key: Affine::new(shape, weight_init.clone(), weight_init_std),
+            value: Affine::new(shape, weight_init.clone(), weight_init_std),
+            output: Affine::new(shape, weight_init, weight_init_std),
+            num_heads,
+            causal,
+            padding_mask: None,
+            q: Array3::zeros((0, 0, model_size)),
+            k: Array3::zeros((0, 0, model_size)),
+            v: Array3::zeros((0, 0, model_size)),
+            weights: Array4::zeros((0, num_heads, 0, 0)),
+        })
+    }
+    /// set the (batch, time) mask of padded time steps, which are not attended to
+    pub fn set_padding_mask(&mut self, padding_mask: Option<Array2<bool>>) {
+        self.padding_mask = padding_mask;
+    }
+    /// attention weights of the last batch as a (batch, head, query time, key time) array
+    pub fn attention_weights(&self) -> &Array4<T> {
+        &self.weights
+    }
+    /// mask of the attention weights of a sample as a (query time, key time) array
+    fn mask(&self, b: usize, time: usize) -> Array2<bool> {
+        Array2::from_shape_fn((time, time), |(i, j)| {
+            (self.causal && j > i)
+                || self
+                    .padding_mask
+                    .as_ref()
+                    .is_some_and(|padding_mask| padding_mask[[b, j]])
+        })
+    }
+    fn head_size(&self) -> usize {
+        self.q.len_of(Axis(2)) / self.num_heads
+    }
+}
+
+impl<T: 'static> LayerBase<T> for MultiHeadAttention<T>
+where
+    T: CrateFloat,
+{
+    type A = Array3<T>;
+    type B = Array3<T>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        let (batch_size, time, _) = x.dim();
+        let x_rows = to_rows(x);
+        self.q = from_rows(self.query.forward(&x_rows), batch_size, time);
+        self.k = from_rows(self.key.forward(&x_rows), batch_size, time);
+        self.v = from_rows(self.value.forward(&x_rows), batch_size, time);
+        let head_size = self.head_size();
+        let scale: T = cast_t2u::<f64, T>(1.0) / cast_t2u::<usize, T>(head_size).sqrt();
+        let mut weights: Array4<T> = Array4::zeros((batch_size, self.num_heads, time, time));
+        let mut context: Array3<T> = Array3::zeros(self.q.raw_dim());
+        for b in 0..batch_size {
+            let mask = self.mask(b, time);
+            for h in 0..self.num_heads {
+                let cols = s![b, .., h * head_size..(h + 1) * head_size];
+                let mut scores = self.q.slice(cols).dot(&self.k.slice(cols).t()) * scale;
+                masked_softmax_rows(&mut scores, &mask);
+                context
+                    .slice_mut(cols)
+                    .assign(&scores.dot(&self.v.slice(cols)));
+                weights.slice_mut(s![b, h, .., ..]).assign(&scores);
+            }
+        }
+        self.weights = weights;
+        from_rows(self.output.forward(&to_rows(&context)), batch_size, time)
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        let (batch_size, time, _) = dx.dim();
+        let dcontext = from_rows(self.output.backward(&to_rows(dx)), batch_size, time);
+        let head_size = self.head_size();
+        let scale: T = cast_t2u::<f64, T>(1.0) / cast_t2u::<usize, T>(head_size).sqrt();
+        let mut dq: Array3<T> = Array3::zeros(self.q.raw_dim());
+        let mut dk: Array3<T> = Array3::zeros(self.k.raw_dim());
+        let mut dv: Array3<T> = Array3::zeros(self.v.raw_dim());
+        for b in 0..batch_size {
+            for h in 0..self.num_heads {
+                let cols = s![b, .., h * head_size..(h + 1) * head_size];
+                let weights = self.weights.slice(s![b, h, .., ..]);
+                let dc = dcontext.slice(cols);
+                let dweights = dc.dot(&self.v.slice(cols).t());
+                dv.slice_mut(cols).assign(&weights.t().dot(&dc));
+                // softmax backward; masked weights are zero and so are their gradients
+                let row_sums = (&dweights * &weights).sum_axis(Axis(1));
+                let mut dscores = (dweights - row_sums.insert_axis(Axis(1))) * weights;
+                dscores.mapv_inplace(|v| v * scale);
+                dq.slice_mut(cols).assign(&dscores.dot(&self.k.slice(cols)));
+                dk.slice_mut(cols)
+                    .assign(&dscores.t().dot(&self.q.slice(cols)));
+            }
+        }
+        let dx_rows = self.query.backward(&to_rows(&dq))
+            + self.key.backward(&to_rows(&dk))
+            + self.value.backward(&to_rows(&dv));
+        from_rows(dx_rows, batch_size, time)
+    }
+    fn update(&mut self, lr: T) {
+        self.query.update(lr);
+        self.key.update(lr);
+        self.value.update(lr);
+        self.output.update(lr);
+    }
+    fn print_detail(&self) {
+        println!("multi-head attention layer.");
+        println!("heads: {}, causal: {}", self.num_heads, self.causal);
+        println!("weight shape: {:?}", self.query.weight.shape());
+    }
+    fn print_parameters(&self) {
+        for (name, affine) in [
+            ("query", &self.query),
+            ("key", &self.key),
+            ("value", &self.value),
+            ("output", &self.output),
+        ]
+        .iter()
+        {
+            println!("{} weight: {:?}", name, affine.weight);
+            println!("{} bias: {:?}", name, affine.bias);
+        }
+    }
+}
+
+// <<<<<<<<<<<<< MultiHeadAttention layer <<<<<<<<<<<<<
+
+// >>>>>>>>>>>>> TransformerEncoderBlock layer >>>>>>>>>>>>>
+
+/// Transformer encoder block
+///
+/// `norm1(x + attention(x))` is followed by `norm2(h + ffn2(activator(ffn1(h))))`,
+/// where the feed-forward layers and the layer normalizations are applied to each time step.
+pub struct TransformerEncoderBlock<T: CrateFloat> {
+    pub attention: MultiHeadAttention<T>,
+    pub norm1: LayerNormalization<T, Ix2>,
+    pub ffn1: Affine<T>,
+    activator: Box<dyn LayerBase<T, A = Array2<T>, B = Array2<T>>>,
+    pub ffn2: Affine<T>,
+    pub norm2: LayerNormalization<T, Ix2>,
+}
+
+impl<T: 'static> TransformerEncoderBlock<T>
+where
+    T: CrateFloat,
+{
+    pub fn new(
+        model_size: usize,
+        num_heads: usize,
+        feed_forward_size: usize,
+        activator_enum: ActivatorEnum<T>,
+        causal: bool,
+        weight_init: WeightInitEnum,
+        weight_init_std: T,
+    ) -> Result<Self, CrateError> {
+        Ok(Self {
+            attention: MultiHeadAttention::new(
+                model_size,
+                num_heads,
+                causal,
+                weight_init.clone(),
+                weight_init_std,
+            )?,
+            norm1: LayerNormalization::new(0, (1, model_size)),
+            ffn1: Affine::new(
+                (model_size, feed_forward_size),
+                weight_init.clone(),
+                weight_init_std,
+            ),
+            activator: call_activator(activator_enum, (1, feed_forward_size), 0),
+            ffn2: Affine::new(
+                (feed_forward_size, model_size),
+                weight_init,
+                weight_init_std,
+            ),
+            norm2: LayerNormalization::new(0, (1, model_size)),
+        })
+    }
+    /// set the (batch, time) mask of padded time steps, which are not attended to
+    pub fn set_padding_mask(&mut self, padding_mask: Option<Array2<bool>>) {
+        self.attention.set_padding_mask(padding_mask);
+    }
+}
+
+impl<T: 'static> LayerBase<T> for TransformerEncoderBlock<T>
+where
+    T: CrateFloat,
+{
+    type A = Array3<T>;
+    type B = Array3<T>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        let (batch_size, time, _) = x.dim();
+        let h = self
+            .norm1
+            .forward(&(to_rows(x) + to_rows(&self.attention.forward(x))));
+        let f = self.ffn1.forward(&h);
+        let f = self.activator.forward(&f);
+        let f = self.ffn2.forward(&f);
+        from_rows(self.norm2.forward(&(h + f)), batch_size, time)
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        let (batch_size, time, _) = dx.dim();
+        let dh = self.norm2.backward(&to_rows(dx));
+        let df = self.ffn2.backward(&dh);
+        let df = self.activator.backward(&df);
+        let dh = dh + self.ffn1.backward(&df);
+        let dz = from_rows(self.norm1.backward(&dh), batch_size, time);
+        self.attention.backward(&dz) + dz
+    }
+    fn update(&mut self, lr: T) {
+        self.attention.update(lr);
+        self.norm1.update(lr);
+        self.ffn1.update(lr);
+        self.activator.update(lr);
+        self.ffn2.update(lr);
+        self.norm2.update(lr);
+    }
+    fn parameters(&mut self) -> Vec<ParamGrad<'_, T>> {
+        let mut params: Vec<ParamGrad<'_, T>> = self.norm1.parameters();
+        params.extend(self.activator.parameters());
+        params.extend(self.norm2.parameters());
+        params
+    }
+    fn print_detail(&self) {
+        println!("transformer encoder block.");
+        self.attention.print_detail();
+        println!(
+            "feed-forward weight shapes: {:?}, {:?}",
+            self.ffn1.weight.shape(),
+            self.ffn2.weight.shape()
+        );
+    }
+    fn print_parameters(&self) {
+        self.attention.print_parameters();
+        self.norm1.print_parameters();
+        self.ffn1.print_parameters();
+        self.activator.print_parameters();
+        self.ffn2.print_parameters();
+        self.norm2.print_parameters();
+    }
+}
+
+// <<<<<<<<<<<<< TransformerEncoderBlock layer <<<<<<<<<<<<<
@@ -15,9 +15,10 @@ use std::fmt::{Debug, Display};
 const EPS: f64 = 1E-8;
 
 /// Enum for BatchNormalization layer
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub enum UseBatchNormEnum<T: CrateFloat> {
     Use(T),
+    #[default]
     None,
 }
 
@@ -116,6 +117,40 @@ where
             running_var: zeros,
         }
     }
+    /// generate a layer from trained parameters and running statistics
+    pub fn from(
+        gamma: &Array<T, D::Smaller>,
+        beta: &Array<T, D::Smaller>,
+        running_mean: &Array<T, D::Smaller>,
+        running_var: &Array<T, D::Smaller>,
+        momentum: T,
+        batch_axis: usize,
+        eps: T,
+    ) -> Self {
+        let one: T = cast_t2u(1.0);
+        let xn: Array<T, D> = Array::<T, D>::zeros(D::zeros(gamma.ndim() + 1));
+        Self {
+            momentum,
+            batch_axis,
+            batch_size: 1,
+            gamma: gamma.clone(),
+            beta: beta.clone(),
+            xc: xn.clone(),
+            xn,
+            std: Array::zeros(gamma.raw_dim()),
+            dgamma: Array::ones(gamma.raw_dim()),
+            dbeta: Array::zeros(beta.raw_dim()),
+            trainable: true,
+            eps,
+            batch_size_t: one,
+            one,
+            two: cast_t2u(2.0),
+            half: cast_t2u(0.5),
+            one_minus_m: one - momentum,
+            running_mean: running_mean.clone(),
+            running_var: running_var.clone(),
+        }
+    }
 }
 
 impl<T: 'static, D: 'static> LayerBase<T> for BatchNormalization<T, D>
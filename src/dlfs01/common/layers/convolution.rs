@@ -6,6 +6,8 @@
 #![allow(unused_variables)]
 #![allow(unused_imports)]
 
+use super::super::error::CrateError;
+use super::super::im2col::*;
 use super::super::param_initializers::weight_init::{initialize_weight, WeightInitEnum};
 use super::super::util::*;
 use super::layer_base::LayerBase;
@@ -17,25 +19,236 @@ use std::fmt::{Debug, Display};
 /// Convolution layer
 ///
 /// Images to train with must be "channel-first".
+/// With `groups`, channels and filters are split into as many groups, and each group of
+/// filters sees only its group of channels, so weights are (F, C / groups, FH, FW) arrays.
 #[derive(Clone)]
 pub struct Convolution<T: CrateFloat, D> {
-    weight: Array<T, D>,
-    bias: Array1<T>,
+    pub weight: Array<T, D>,
+    pub bias: Array1<T>,
     stride_sizes: Vec<usize>,
     pad_size: usize,
+    dilations: Vec<usize>,
+    groups: usize,
+    /// initializer of the weight, which is `None` for explicit weights given to `from`
+    weight_init: Option<(WeightInitEnum, T)>,
     x: Array<T, D>,
-    col: Array<T, D>,
-    col_weight: Array<T, D>,
-    dw: Array<T, D>,
-    db: Array1<T>,
+    col: Array2<T>,
+    pub dw: Array<T, D>,
+    pub db: Array1<T>,
     padded_shape: Vec<usize>,
     output_data_shape: Vec<usize>,
 }
 
+/// validate the shapes along a spatial axis and calculate the output size
+fn conv_output_size(
+    input_size: usize,
+    filter_size: usize,
+    stride: usize,
+    pad_size: usize,
+) -> Result<usize, CrateError> {
+    if stride == 0 {
+        return Err(CrateError::ConfigError(
+            "stride sizes must be positive.".to_string(),
+        ));
+    }
+    let padded_size: usize = input_size + 2 * pad_size;
+    if padded_size < filter_size || !(padded_size - filter_size).is_multiple_of(stride) {
+        return Err(CrateError::ShapeError(format!(
+            "input size {} padded by {} is not compatible with filter size {} and stride {}.",
+            input_size, pad_size, filter_size, stride
+        )));
+    }
+    Ok(output_size(input_size, filter_size, stride, pad_size))
+}
+
+/// validate the filter sizes and dilations along spatial axes and calculate the output shape
+fn conv_output_shape(
+    input_shape: &[usize],
+    filter_shape: &[usize],
+    stride_sizes: &[usize],
+    pad_size: usize,
+    dilations: &[usize],
+) -> Result<Vec<usize>, CrateError> {
+    if dilations.contains(&0) {
+        return Err(CrateError::ConfigError(
+            "dilations must be positive.".to_string(),
+        ));
+    }
+    input_shape
+        .iter()
+        .zip(filter_shape.iter())
+        .zip(stride_sizes.iter().zip(dilations.iter()))
+        .map(|((&input_size, &filter_size), (&stride, &dilation))| {
+            conv_output_size(
+                input_size,
+                dilated_filter_size(filter_size, dilation),
+                stride,
+                pad_size,
+            )
+        })
+        .collect()
+}
+
+/// validate groups dividing both channels and filters
+fn check_groups(groups: usize, n_channels: usize, filter_size: usize) -> Result<(), CrateError> {
+    if groups == 0 || !n_channels.is_multiple_of(groups) || !filter_size.is_multiple_of(groups) {
+        return Err(CrateError::ConfigError(format!(
+            "groups {} must divide both {} channels and {} filters.",
+            groups, n_channels, filter_size
+        )));
+    }
+    Ok(())
+}
+
+/// forward of channel-first convolutions with (F, C / groups, F1, .., Fk) weights
+///
+/// Returns the column matrix of the inputs for backward, and the outputs.
+fn conv_forward<T>(
+    x: &ArrayD<T>,
+    weight: &ArrayD<T>,
+    bias: &Array1<T>,
+    stride_sizes: &[usize],
+    pad_size: usize,
+    dilations: &[usize],
+    groups: usize,
+) -> (Array2<T>, ArrayD<T>)
+where
+    T: CrateFloat,
+{
+    assert_eq!(
+        x.len_of(Axis(1)),
+        groups * weight.len_of(Axis(1)),
+        "inputs must have the channels given when the layer is built"
+    );
+    let filter_size: usize = weight.len_of(Axis(0));
+    let filter_shape: &[usize] = &weight.shape()[2..];
+    let pad_sizes: Vec<usize> = vec![pad_size; filter_shape.len()];
+    let col: Array2<T> = im2col_nd(x, filter_shape, stride_sizes, &pad_sizes, dilations);
+    let weight_2d = weight
+        .to_shape((filter_size, weight.len() / filter_size))
+        .unwrap();
+    // each group of filters sees only the columns of its group of channels
+    let (group_filters, group_cols) = (filter_size / groups, weight_2d.ncols());
+    let mut y: Array2<T> = Array2::zeros((col.nrows(), filter_size));
+    for g in 0..groups {
+        y.slice_mut(s![.., g * group_filters..(g + 1) * group_filters])
+            .assign(
+                &col.slice(s![.., g * group_cols..(g + 1) * group_cols]).dot(
+                    &weight_2d
+                        .slice(s![g * group_filters..(g + 1) * group_filters, ..])
+                        .t(),
+                ),
+            );
+    }
+    let mut output_shape: Vec<usize> = vec![x.len_of(Axis(0))];
+    output_shape.extend(output_shape_nd(
+        x.shape(),
+        filter_shape,
+        stride_sizes,
+        &pad_sizes,
+        dilations,
+    ));
+    output_shape.push(filter_size);
+    // (N, O1, .., Ok, F) -> (N, F, O1, .., Ok)
+    let mut axes: Vec<usize> = vec![0, filter_shape.len() + 1];
+    axes.extend(1..filter_shape.len() + 1);
+    let dst: ArrayD<T> = (y + bias)
+        .into_shape(output_shape)
+        .unwrap()
+        .permuted_axes(axes)
+        .as_standard_layout()
+        .into_owned();
+    (col, dst)
+}
+
+/// backward of channel-first convolutions, returning the gradients of inputs, weights and biases
+#[allow(clippy::too_many_arguments)]
+fn conv_backward<T>(
+    dx: &ArrayD<T>,
+    x_shape: &[usize],
+    weight: &ArrayD<T>,
+    col: &Array2<T>,
+    stride_sizes: &[usize],
+    pad_size: usize,
+    dilations: &[usize],
+    groups: usize,
+) -> (ArrayD<T>, ArrayD<T>, Array1<T>)
+where
+    T: CrateFloat,
+{
+    let weight_shape: &[usize] = weight.shape();
+    let filter_size: usize = weight_shape[0];
+    let filter_shape: &[usize] = &weight_shape[2..];
+    // (N, F, O1, .., Ok) -> (N, O1, .., Ok, F)
+    let mut axes: Vec<usize> = vec![0];
+    axes.extend(2..filter_shape.len() + 2);
+    axes.push(1);
+    let dout: Array2<T> = dx
+        .view()
+        .permuted_axes(axes)
+        .to_shape((dx.len() / filter_size, filter_size))
+        .unwrap()
+        .into_owned();
+    let db: Array1<T> = dout.sum_axis(Axis(0));
+    let weight_2d = weight
+        .to_shape((filter_size, weight.len() / filter_size))
+        .unwrap();
+    let (group_filters, group_cols) = (filter_size / groups, weight_2d.ncols());
+    let mut dw: Array2<T> = Array2::zeros(weight_2d.raw_dim());
+    let mut dcol: Array2<T> = Array2::zeros(col.raw_dim());
+    for g in 0..groups {
+        let filters = s![.., g * group_filters..(g + 1) * group_filters];
+        let cols = s![.., g * group_cols..(g + 1) * group_cols];
+        dw.slice_mut(s![g * group_filters..(g + 1) * group_filters, ..])
+            .assign(&dout.slice(filters).t().dot(&col.slice(cols)));
+        dcol.slice_mut(cols).assign(
+            &dout
+                .slice(filters)
+                .dot(&weight_2d.slice(s![g * group_filters..(g + 1) * group_filters, ..])),
+        );
+    }
+    let dw: ArrayD<T> = dw.into_shape(weight_shape).unwrap();
+    let pad_sizes: Vec<usize> = vec![pad_size; filter_shape.len()];
+    let dst: ArrayD<T> = col2im_nd(
+        &dcol,
+        x_shape,
+        filter_shape,
+        stride_sizes,
+        &pad_sizes,
+        dilations,
+    );
+    (dst, dw, db)
+}
+
 pub type Convolution1<T> = Convolution<T, Ix2>;
 pub type Convolution2<T> = Convolution<T, Ix3>;
 pub type Convolution3<T> = Convolution<T, Ix4>;
 
+impl<T, D> Convolution<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    /// number of learnable parameters
+    pub fn num_parameters(&self) -> usize {
+        self.weight.len() + self.bias.len()
+    }
+    /// spread the filter elements `dilations` apart along (height, width)
+    pub fn with_dilation(mut self, dilations: (usize, usize)) -> Result<Self, CrateError> {
+        let dilations: Vec<usize> = vec![dilations.0, dilations.1];
+        let ndim: usize = self.x.ndim();
+        self.output_data_shape = conv_output_shape(
+            &self.x.shape()[ndim - 2..],
+            &self.weight.shape()[self.weight.ndim() - 2..],
+            &self.stride_sizes,
+            self.pad_size,
+            &dilations,
+        )?;
+        self.dilations = dilations;
+        Ok(self)
+    }
+}
+
 impl<T> Convolution2<T>
 where
     T: CrateFloat,
@@ -51,7 +264,7 @@ where
         pad_size: usize,
         weight_init: WeightInitEnum,
         weight_init_std: T,
-    ) -> Self {
+    ) -> Result<Self, CrateError> {
         let weight_shape: (usize, usize, usize) = (filter_size, filter_shape.0, filter_shape.1);
         let input_shape: (usize, usize, usize) = (1usize, data_shape.0, data_shape.1);
         Self::from(
@@ -68,35 +281,31 @@ where
         data_shape: (usize, usize),
         stride_sizes: (usize, usize),
         pad_size: usize,
-    ) -> Self {
+    ) -> Result<Self, CrateError> {
         let weight_shape = weight.shape();
-        let mut output_h: usize = data_shape.0 + 2 * pad_size - weight_shape[1];
-        let mut output_w: usize = data_shape.1 + 2 * pad_size - weight_shape[2];
-        if output_h % stride_sizes.0 != 0 {
-            panic!("Invalid inputs with regard to the shapes.");
-        }
-        if output_w % stride_sizes.1 != 0 {
-            panic!("Invalid inputs with regard to the shapes.");
-        }
-        output_h = 1usize + output_h / stride_sizes.0;
-        output_w = 1usize + output_w / stride_sizes.1;
+        let output_h: usize =
+            conv_output_size(data_shape.0, weight_shape[1], stride_sizes.0, pad_size)?;
+        let output_w: usize =
+            conv_output_size(data_shape.1, weight_shape[2], stride_sizes.1, pad_size)?;
         let input_shape: (usize, usize, usize) = (1usize, data_shape.0, data_shape.1);
         let padded_shape: Vec<usize> =
-            vec![data_shape.0 + 2 * pad_size, data_shape.0 + 2 * pad_size];
+            vec![data_shape.0 + 2 * pad_size, data_shape.1 + 2 * pad_size];
         let output_data_shape: Vec<usize> = vec![output_h, output_w];
-        Self {
+        Ok(Self {
             weight: weight.clone(),
             bias: bias.clone(),
             stride_sizes: vec![stride_sizes.0, stride_sizes.1],
             pad_size,
+            dilations: vec![1, 1],
+            groups: 1,
+            weight_init: None,
             x: Array3::zeros(input_shape),
-            col: Array3::zeros(input_shape),
-            col_weight: Array3::zeros(input_shape),
+            col: Array2::zeros((1, 1)),
             dw: Array3::zeros(weight.raw_dim()),
             db: Array1::zeros(bias.raw_dim()),
             padded_shape,
             output_data_shape,
-        }
+        })
     }
     pub fn get_output_data_shape(&self) -> (usize, usize) {
         (self.output_data_shape[0], self.output_data_shape[1])
@@ -107,23 +316,52 @@ impl<T: 'static> LayerBase<T> for Convolution2<T>
 where
     T: CrateFloat,
 {
+    /// (N, H, W) single-channel images
     type A = Array3<T>;
 
-    type B = Array3<T>;
+    /// (N, filter_size, OH, OW) feature maps
+    type B = Array4<T>;
 
     fn forward(&mut self, x: &Self::A) -> Self::B {
-        let n_images: usize = x.shape()[0];
-        let dst: Self::A = Array3::zeros((
-            n_images,
-            self.output_data_shape[0],
-            self.output_data_shape[1],
-        ));
-        // let mut buff: Array2<T> = Array2::zeros((self.padded_shape[0], self.padded_shape[1]));
-        dst
+        self.x = x.clone();
+        let (col, dst) = conv_forward(
+            &x.clone().insert_axis(Axis(1)).into_dyn(),
+            &self.weight.clone().insert_axis(Axis(1)).into_dyn(),
+            &self.bias,
+            &self.stride_sizes,
+            self.pad_size,
+            &self.dilations,
+            1,
+        );
+        self.col = col;
+        dst.into_dimensionality::<Ix4>().unwrap()
     }
 
     fn backward(&mut self, dx: &Self::B) -> Self::A {
-        todo!()
+        let (n, h, w) = self.x.dim();
+        let (dst, dw, db) = conv_backward(
+            &dx.clone().into_dyn(),
+            &[n, 1, h, w],
+            &self.weight.clone().insert_axis(Axis(1)).into_dyn(),
+            &self.col,
+            &self.stride_sizes,
+            self.pad_size,
+            &self.dilations,
+            1,
+        );
+        self.dw = dw
+            .index_axis_move(Axis(1), 0)
+            .into_dimensionality::<Ix3>()
+            .unwrap();
+        self.db = db;
+        dst.index_axis_move(Axis(1), 0)
+            .into_dimensionality::<Ix3>()
+            .unwrap()
+    }
+
+    fn update(&mut self, lr: T) {
+        self.weight.scaled_add(-lr, &self.dw);
+        self.bias.scaled_add(-lr, &self.db);
     }
 
     fn print_detail(&self) {
@@ -135,6 +373,9 @@ where
             self.stride_sizes[0], self.stride_sizes[1]
         );
         println!("pad size: {}", self.pad_size);
+        println!("dilation: ({}, {})", self.dilations[0], self.dilations[1]);
+        println!("groups: {}", self.groups);
+        println!("parameters: {}", self.num_parameters());
     }
     fn print_parameters(&self) {
         println!("weight: {:?}", self.weight);
@@ -148,70 +389,112 @@ impl<T> Convolution3<T>
 where
     T: CrateFloat,
 {
-    /// generate a Convolution2 layer.
+    /// generate a Convolution3 layer.
     ///
-    /// `input_shape` is (n_channel, height, width).
+    /// `input_shape` is (n_channel, height, width), and the weight is of
+    /// (filter_size, n_channel / groups, FH, FW).
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         filter_size: usize,
         filter_shape: (usize, usize),
         data_shape: (usize, usize, usize),
+        groups: usize,
         stride_sizes: (usize, usize),
         pad_size: usize,
         weight_init: WeightInitEnum,
         weight_init_std: T,
-    ) -> Self {
-        let weight_shape: (usize, usize, usize, usize) =
-            (filter_size, data_shape.0, filter_shape.0, filter_shape.1);
-        let input_shape: (usize, usize, usize, usize) =
-            (1usize, data_shape.0, data_shape.1, data_shape.2);
-        Self::from(
+    ) -> Result<Self, CrateError> {
+        check_groups(groups, data_shape.0, filter_size)?;
+        let weight_shape: (usize, usize, usize, usize) = (
+            filter_size,
+            data_shape.0 / groups,
+            filter_shape.0,
+            filter_shape.1,
+        );
+        let mut layer = Self::from(
             &initialize_weight(weight_init.clone(), weight_init_std, weight_shape),
-            &initialize_weight(weight_init, weight_init_std, filter_size),
+            &initialize_weight(weight_init.clone(), weight_init_std, filter_size),
             data_shape,
             stride_sizes,
             pad_size,
-        )
+        )?;
+        layer.weight_init = Some((weight_init, weight_init_std));
+        Ok(layer)
     }
+    /// generate a Convolution3 layer from a (F, C / groups, FH, FW) weight
+    ///
+    /// The groups are given by the ratio of the channels of inputs to those of the weight.
     pub fn from(
         weight: &Array4<T>,
         bias: &Array1<T>,
         data_shape: (usize, usize, usize),
         stride_sizes: (usize, usize),
         pad_size: usize,
-    ) -> Self {
+    ) -> Result<Self, CrateError> {
         let weight_shape = weight.shape();
-        let mut output_h: usize = data_shape.1 + 2 * pad_size - weight_shape[2];
-        let mut output_w: usize = data_shape.2 + 2 * pad_size - weight_shape[3];
-        if output_h % stride_sizes.0 != 0 {
-            panic!("Invalid inputs with regard to the shapes.");
+        if weight_shape[1] == 0 || !data_shape.0.is_multiple_of(weight_shape[1]) {
+            return Err(CrateError::ShapeError(format!(
+                "{} channels cannot be split into groups of {} channels of the weight.",
+                data_shape.0, weight_shape[1]
+            )));
         }
-        if output_w % stride_sizes.1 != 0 {
-            panic!("Invalid inputs with regard to the shapes.");
+        let groups: usize = data_shape.0 / weight_shape[1];
+        if !weight_shape[0].is_multiple_of(groups) {
+            return Err(CrateError::ShapeError(format!(
+                "{} filters cannot be split into {} groups.",
+                weight_shape[0], groups
+            )));
         }
-        output_h = 1usize + output_h / stride_sizes.0;
-        output_w = 1usize + output_w / stride_sizes.1;
+        let output_h: usize =
+            conv_output_size(data_shape.1, weight_shape[2], stride_sizes.0, pad_size)?;
+        let output_w: usize =
+            conv_output_size(data_shape.2, weight_shape[3], stride_sizes.1, pad_size)?;
         let input_shape: (usize, usize, usize, usize) =
             (1usize, data_shape.0, data_shape.1, data_shape.2);
         let padded_shape: Vec<usize> =
             vec![data_shape.1 + 2 * pad_size, data_shape.2 + 2 * pad_size];
         let output_data_shape: Vec<usize> = vec![output_h, output_w];
-        Self {
+        Ok(Self {
             weight: weight.clone(),
             bias: bias.clone(),
             stride_sizes: vec![stride_sizes.0, stride_sizes.1],
             pad_size,
+            dilations: vec![1, 1],
+            groups,
+            weight_init: None,
             x: Array4::zeros(input_shape),
-            col: Array4::zeros(input_shape),
-            col_weight: Array4::zeros(input_shape),
+            col: Array2::zeros((1, 1)),
             dw: Array4::zeros(weight.raw_dim()),
             db: Array1::zeros(bias.raw_dim()),
             padded_shape,
             output_data_shape,
+        })
+    }
+    /// split channels and filters into `groups` with a weight initialized again
+    ///
+    /// Explicit weights given to `from` have their groups in their shape, so they are rejected.
+    pub fn with_groups(mut self, groups: usize) -> Result<Self, CrateError> {
+        let (filter_size, n_channels, filter_h, filter_w) = self.weight.dim();
+        let (weight_init, weight_init_std) = self.weight_init.clone().ok_or_else(|| {
+            CrateError::ConfigError(
+                "the groups of explicit weights are given by their shape.".to_string(),
+            )
+        })?;
+        if self.groups != 1 {
+            return Err(CrateError::ConfigError(
+                "groups are already set.".to_string(),
+            ));
         }
+        check_groups(groups, n_channels, filter_size)?;
+        let weight_shape = (filter_size, n_channels / groups, filter_h, filter_w);
+        self.weight = initialize_weight(weight_init, weight_init_std, weight_shape);
+        self.dw = Array4::zeros(weight_shape);
+        self.groups = groups;
+        Ok(self)
     }
     pub fn get_output_data_shape(&self) -> (usize, usize, usize) {
         (
-            self.weight.shape()[1],
+            self.weight.shape()[0],
             self.output_data_shape[0],
             self.output_data_shape[1],
         )
@@ -227,19 +510,39 @@ where
     type B = Array4<T>;
 
     fn forward(&mut self, x: &Self::A) -> Self::B {
-        let n_images: usize = x.shape()[0];
-        let dst: Self::A = Array4::zeros((
-            n_images,
-            self.weight.shape()[1],
-            self.output_data_shape[0],
-            self.output_data_shape[1],
-        ));
-        // let mut buff: Array2<T> = Array2::zeros((self.padded_shape[0], self.padded_shape[1]));
-        dst
+        self.x = x.clone();
+        let (col, dst) = conv_forward(
+            &x.clone().into_dyn(),
+            &self.weight.clone().into_dyn(),
+            &self.bias,
+            &self.stride_sizes,
+            self.pad_size,
+            &self.dilations,
+            self.groups,
+        );
+        self.col = col;
+        dst.into_dimensionality::<Ix4>().unwrap()
     }
 
     fn backward(&mut self, dx: &Self::B) -> Self::A {
-        todo!()
+        let (dst, dw, db) = conv_backward(
+            &dx.clone().into_dyn(),
+            self.x.shape(),
+            &self.weight.clone().into_dyn(),
+            &self.col,
+            &self.stride_sizes,
+            self.pad_size,
+            &self.dilations,
+            self.groups,
+        );
+        self.dw = dw.into_dimensionality::<Ix4>().unwrap();
+        self.db = db;
+        dst.into_dimensionality::<Ix4>().unwrap()
+    }
+
+    fn update(&mut self, lr: T) {
+        self.weight.scaled_add(-lr, &self.dw);
+        self.bias.scaled_add(-lr, &self.db);
     }
 
     fn print_detail(&self) {
@@ -251,6 +554,595 @@ where
             self.stride_sizes[0], self.stride_sizes[1]
         );
         println!("pad size: {}", self.pad_size);
+        println!("dilation: ({}, {})", self.dilations[0], self.dilations[1]);
+        println!("groups: {}", self.groups);
+        println!("parameters: {}", self.num_parameters());
+    }
+    fn print_parameters(&self) {
+        println!("weight: {:?}", self.weight);
+        println!("bias: {:?}", self.bias);
+        println!("dw: {:?}", self.dw);
+        println!("db: {:?}", self.db);
+    }
+}
+
+/// Depthwise separable convolution layer
+///
+/// A depthwise convolution filtering each channel separately, i.e. with as many groups as
+/// channels, is followed by a 1x1 pointwise convolution mixing the channels.
+///
+/// See https://arxiv.org/abs/1704.04861 in detail
+#[derive(Clone)]
+pub struct DepthwiseSeparableConv<T: CrateFloat> {
+    pub depthwise: Convolution3<T>,
+    pub pointwise: Convolution3<T>,
+}
+
+impl<T> DepthwiseSeparableConv<T>
+where
+    T: CrateFloat,
+{
+    /// generate a DepthwiseSeparableConv layer.
+    ///
+    /// `input_shape` is (n_channel, height, width).
+    pub fn new(
+        filter_size: usize,
+        filter_shape: (usize, usize),
+        data_shape: (usize, usize, usize),
+        stride_sizes: (usize, usize),
+        pad_size: usize,
+        weight_init: WeightInitEnum,
+        weight_init_std: T,
+    ) -> Result<Self, CrateError> {
+        let depthwise = Convolution3::new(
+            data_shape.0,
+            filter_shape,
+            data_shape,
+            data_shape.0,
+            stride_sizes,
+            pad_size,
+            weight_init.clone(),
+            weight_init_std,
+        )?;
+        let pointwise = Convolution3::new(
+            filter_size,
+            (1, 1),
+            depthwise.get_output_data_shape(),
+            1,
+            (1, 1),
+            0,
+            weight_init,
+            weight_init_std,
+        )?;
+        Ok(Self {
+            depthwise,
+            pointwise,
+        })
+    }
+    /// generate a layer from a (C, 1, FH, FW) depthwise weight and a (F, C, 1, 1) pointwise weight
+    pub fn from(
+        depthwise_weight: &Array4<T>,
+        depthwise_bias: &Array1<T>,
+        pointwise_weight: &Array4<T>,
+        pointwise_bias: &Array1<T>,
+        data_shape: (usize, usize, usize),
+        stride_sizes: (usize, usize),
+        pad_size: usize,
+    ) -> Result<Self, CrateError> {
+        if depthwise_weight.dim().0 != data_shape.0 || depthwise_weight.dim().1 != 1 {
+            return Err(CrateError::ShapeError(format!(
+                "the depthwise weight of shape {:?} must have a filter of 1 channel for each of {} channels.",
+                depthwise_weight.shape(),
+                data_shape.0
+            )));
+        }
+        let depthwise = Convolution3::from(
+            depthwise_weight,
+            depthwise_bias,
+            data_shape,
+            stride_sizes,
+            pad_size,
+        )?;
+        let pointwise = Convolution3::from(
+            pointwise_weight,
+            pointwise_bias,
+            depthwise.get_output_data_shape(),
+            (1, 1),
+            0,
+        )?;
+        Ok(Self {
+            depthwise,
+            pointwise,
+        })
+    }
+    /// (filter_size, height, width) of outputs
+    pub fn get_output_data_shape(&self) -> (usize, usize, usize) {
+        self.pointwise.get_output_data_shape()
+    }
+    /// number of learnable parameters
+    pub fn num_parameters(&self) -> usize {
+        self.depthwise.num_parameters() + self.pointwise.num_parameters()
+    }
+}
+
+impl<T: 'static> LayerBase<T> for DepthwiseSeparableConv<T>
+where
+    T: CrateFloat,
+{
+    type A = Array4<T>;
+
+    type B = Array4<T>;
+
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        let y: Array4<T> = self.depthwise.forward(x);
+        self.pointwise.forward(&y)
+    }
+
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        let dy: Array4<T> = self.pointwise.backward(dx);
+        self.depthwise.backward(&dy)
+    }
+
+    fn update(&mut self, lr: T) {
+        self.depthwise.update(lr);
+        self.pointwise.update(lr);
+    }
+
+    fn print_detail(&self) {
+        println!("depthwise separable convolution layer.");
+        println!("parameters: {}", self.num_parameters());
+        println!("depthwise:");
+        self.depthwise.print_detail();
+        println!("pointwise:");
+        self.pointwise.print_detail();
+    }
+    fn print_parameters(&self) {
+        self.depthwise.print_parameters();
+        self.pointwise.print_parameters();
+    }
+}
+
+/// Convolution layer along an arbitrary number of spatial axes
+///
+/// Inputs are "channel-first" (N, C, S1, .., Sk) arrays and weights are (F, C, F1, .., Fk)
+/// arrays, e.g. (batch, channels, length) time series for `Conv1d` and
+/// (batch, channels, depth, height, width) volumes for `Conv3d`.
+/// Use `Convolution3` for images.
+#[derive(Clone)]
+pub struct ConvolutionNd<T: CrateFloat, D> {
+    pub weight: Array<T, D>,
+    pub bias: Array1<T>,
+    stride_sizes: Vec<usize>,
+    pad_size: usize,
+    dilations: Vec<usize>,
+    x_shape: Vec<usize>,
+    col: Array2<T>,
+    pub dw: Array<T, D>,
+    pub db: Array1<T>,
+    data_shape: Vec<usize>,
+    output_data_shape: Vec<usize>,
+}
+
+pub type Conv1d<T> = ConvolutionNd<T, Ix3>;
+pub type Conv3d<T> = ConvolutionNd<T, Ix5>;
+
+impl<T, D> ConvolutionNd<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    /// generate a convolution layer.
+    ///
+    /// `data_shape` is (n_channel, S1, .., Sk), and `filter_shape` and `stride_sizes`
+    /// have an element for each spatial axis.
+    pub fn new(
+        filter_size: usize,
+        filter_shape: &[usize],
+        data_shape: &[usize],
+        stride_sizes: &[usize],
+        pad_size: usize,
+        weight_init: WeightInitEnum,
+        weight_init_std: T,
+    ) -> Result<Self, CrateError> {
+        if data_shape.is_empty() {
+            return Err(CrateError::ShapeError(
+                "the data shape must contain the number of channels.".to_string(),
+            ));
+        }
+        let mut weight_shape: Vec<usize> = vec![filter_size, data_shape[0]];
+        weight_shape.extend(filter_shape.iter());
+        let weight: ArrayD<T> =
+            initialize_weight(weight_init.clone(), weight_init_std, weight_shape);
+        let weight: Array<T, D> = weight.into_dimensionality::<D>().map_err(|_| {
+            CrateError::ShapeError(format!(
+                "{} spatial axes are required but the filter shape has {}.",
+                D::NDIM.unwrap_or(2) - 2,
+                filter_shape.len()
+            ))
+        })?;
+        Self::from(
+            &weight,
+            &initialize_weight(weight_init, weight_init_std, filter_size),
+            data_shape,
+            stride_sizes,
+            pad_size,
+        )
+    }
+    pub fn from(
+        weight: &Array<T, D>,
+        bias: &Array1<T>,
+        data_shape: &[usize],
+        stride_sizes: &[usize],
+        pad_size: usize,
+    ) -> Result<Self, CrateError> {
+        let n_spatial: usize = weight.ndim() - 2;
+        if data_shape.len() != n_spatial + 1 || data_shape[0] != weight.shape()[1] {
+            return Err(CrateError::ShapeError(format!(
+                "data shape {:?} is not compatible with weight shape {:?}.",
+                data_shape,
+                weight.shape()
+            )));
+        }
+        if stride_sizes.len() != n_spatial {
+            return Err(CrateError::ShapeError(format!(
+                "{} stride sizes are required but {} are given.",
+                n_spatial,
+                stride_sizes.len()
+            )));
+        }
+        if bias.len() != weight.shape()[0] {
+            return Err(CrateError::ShapeError(format!(
+                "{} biases are required but {} are given.",
+                weight.shape()[0],
+                bias.len()
+            )));
+        }
+        let dilations: Vec<usize> = vec![1; n_spatial];
+        let output_data_shape: Vec<usize> = conv_output_shape(
+            &data_shape[1..],
+            &weight.shape()[2..],
+            stride_sizes,
+            pad_size,
+            &dilations,
+        )?;
+        Ok(Self {
+            weight: weight.clone(),
+            bias: bias.clone(),
+            stride_sizes: stride_sizes.to_vec(),
+            pad_size,
+            dilations,
+            x_shape: Vec::new(),
+            col: Array2::zeros((1, 1)),
+            dw: Array::zeros(weight.raw_dim()),
+            db: Array1::zeros(bias.raw_dim()),
+            data_shape: data_shape.to_vec(),
+            output_data_shape,
+        })
+    }
+    /// spread the filter elements `dilations` apart along each spatial axis
+    pub fn with_dilation(mut self, dilations: &[usize]) -> Result<Self, CrateError> {
+        if dilations.len() != self.stride_sizes.len() {
+            return Err(CrateError::ShapeError(format!(
+                "{} dilations are required but {} are given.",
+                self.stride_sizes.len(),
+                dilations.len()
+            )));
+        }
+        self.output_data_shape = conv_output_shape(
+            &self.data_shape[1..],
+            &self.weight.shape()[2..],
+            &self.stride_sizes,
+            self.pad_size,
+            dilations,
+        )?;
+        self.dilations = dilations.to_vec();
+        Ok(self)
+    }
+    /// (filter_size, O1, .., Ok)
+    pub fn get_output_data_shape(&self) -> Vec<usize> {
+        let mut dst: Vec<usize> = vec![self.weight.shape()[0]];
+        dst.extend(self.output_data_shape.iter());
+        dst
+    }
+}
+
+impl<T: 'static, D> LayerBase<T> for ConvolutionNd<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    type A = Array<T, D>;
+
+    type B = Array<T, D>;
+
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        self.x_shape = x.shape().to_vec();
+        let (col, dst) = conv_forward(
+            &x.clone().into_dyn(),
+            &self.weight.clone().into_dyn(),
+            &self.bias,
+            &self.stride_sizes,
+            self.pad_size,
+            &self.dilations,
+            1,
+        );
+        self.col = col;
+        dst.into_dimensionality::<D>().unwrap()
+    }
+
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        let (dst, dw, db) = conv_backward(
+            &dx.clone().into_dyn(),
+            &self.x_shape,
+            &self.weight.clone().into_dyn(),
+            &self.col,
+            &self.stride_sizes,
+            self.pad_size,
+            &self.dilations,
+            1,
+        );
+        self.dw = dw.into_dimensionality::<D>().unwrap();
+        self.db = db;
+        dst.into_dimensionality::<D>().unwrap()
+    }
+
+    fn update(&mut self, lr: T) {
+        self.weight.scaled_add(-lr, &self.dw);
+        self.bias.scaled_add(-lr, &self.db);
+    }
+
+    fn print_detail(&self) {
+        println!("{}D convolution layer.", self.stride_sizes.len());
+        println!("weight shape: {:?}", self.weight.shape());
+        println!("bias shape: {:?}", self.bias.shape());
+        println!("stride size: {:?}", self.stride_sizes);
+        println!("pad size: {}", self.pad_size);
+        println!("dilation: {:?}", self.dilations);
+    }
+    fn print_parameters(&self) {
+        println!("weight: {:?}", self.weight);
+        println!("bias: {:?}", self.bias);
+        println!("dw: {:?}", self.dw);
+        println!("db: {:?}", self.db);
+    }
+}
+
+/// Transposed convolution layer for images
+///
+/// Images to train with must be "channel-first", and weights are
+/// (in_channels, out_channels, height, width) arrays.
+/// The forward and the backward are the backward and the forward of `Convolution3`
+/// with the same weight, so an output of size `(input - 1) * stride - 2 * pad + dilation *
+/// (filter - 1) + 1 + output_padding` is convolved back into an input.
+#[derive(Clone)]
+pub struct ConvTranspose2d<T: CrateFloat> {
+    pub weight: Array4<T>,
+    pub bias: Array1<T>,
+    stride_sizes: Vec<usize>,
+    pad_size: usize,
+    dilations: Vec<usize>,
+    output_padding: Vec<usize>,
+    data_shape: (usize, usize),
+    output_data_shape: (usize, usize),
+    x_rows: Array2<T>,
+    x_shape: (usize, usize, usize, usize),
+    pub dw: Array4<T>,
+    pub db: Array1<T>,
+}
+
+impl<T> ConvTranspose2d<T>
+where
+    T: CrateFloat,
+{
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        in_channels: usize,
+        out_channels: usize,
+        filter_shape: (usize, usize),
+        data_shape: (usize, usize),
+        stride_sizes: (usize, usize),
+        pad_size: usize,
+        weight_init: WeightInitEnum,
+        weight_init_std: T,
+    ) -> Result<Self, CrateError> {
+        let weight_shape: (usize, usize, usize, usize) =
+            (in_channels, out_channels, filter_shape.0, filter_shape.1);
+        Self::from(
+            &initialize_weight(weight_init.clone(), weight_init_std, weight_shape),
+            &initialize_weight(weight_init, weight_init_std, out_channels),
+            data_shape,
+            stride_sizes,
+            pad_size,
+        )
+    }
+    /// `data_shape` is (height, width) of inputs
+    pub fn from(
+        weight: &Array4<T>,
+        bias: &Array1<T>,
+        data_shape: (usize, usize),
+        stride_sizes: (usize, usize),
+        pad_size: usize,
+    ) -> Result<Self, CrateError> {
+        if stride_sizes.0 == 0 || stride_sizes.1 == 0 {
+            return Err(CrateError::ConfigError(
+                "stride sizes must be positive.".to_string(),
+            ));
+        }
+        if bias.len() != weight.len_of(Axis(1)) {
+            return Err(CrateError::ShapeError(format!(
+                "{} biases are required but {} are given.",
+                weight.len_of(Axis(1)),
+                bias.len()
+            )));
+        }
+        let mut layer = Self {
+            weight: weight.clone(),
+            bias: bias.clone(),
+            stride_sizes: vec![stride_sizes.0, stride_sizes.1],
+            pad_size,
+            dilations: vec![1, 1],
+            output_padding: vec![0, 0],
+            data_shape,
+            output_data_shape: (0, 0),
+            x_rows: Array2::zeros((1, 1)),
+            x_shape: (0, 0, 0, 0),
+            dw: Array4::zeros(weight.raw_dim()),
+            db: Array1::zeros(bias.raw_dim()),
+        };
+        layer.output_data_shape = layer.compute_output_data_shape()?;
+        Ok(layer)
+    }
+    /// spread the filter elements `dilations` apart along (height, width)
+    pub fn with_dilation(mut self, dilations: (usize, usize)) -> Result<Self, CrateError> {
+        if dilations.0 == 0 || dilations.1 == 0 {
+            return Err(CrateError::ConfigError(
+                "dilations must be positive.".to_string(),
+            ));
+        }
+        self.dilations = vec![dilations.0, dilations.1];
+        self.output_data_shape = self.compute_output_data_shape()?;
+        Ok(self)
+    }
+    /// add `output_padding` to the size of outputs along (height, width)
+    ///
+    /// It selects one of the output sizes convolved into the same input size with strides,
+    /// and must be less than the stride sizes.
+    pub fn with_output_padding(
+        mut self,
+        output_padding: (usize, usize),
+    ) -> Result<Self, CrateError> {
+        if output_padding.0 >= self.stride_sizes[0] || output_padding.1 >= self.stride_sizes[1] {
+            return Err(CrateError::ConfigError(format!(
+                "output padding {:?} must be less than the stride sizes {:?}.",
+                output_padding, self.stride_sizes
+            )));
+        }
+        self.output_padding = vec![output_padding.0, output_padding.1];
+        self.output_data_shape = self.compute_output_data_shape()?;
+        Ok(self)
+    }
+    /// (height, width) of outputs
+    pub fn get_output_data_shape(&self) -> (usize, usize) {
+        self.output_data_shape
+    }
+    /// (height, width) of outputs if inputs are of the (height, width) of the layer
+    fn check_data_shape(&self, data_shape: (usize, usize)) -> Result<(usize, usize), CrateError> {
+        if data_shape != self.data_shape {
+            return Err(CrateError::ShapeError(format!(
+                "inputs of {:?} are given to the layer for {:?}.",
+                data_shape, self.data_shape
+            )));
+        }
+        Ok(self.output_data_shape)
+    }
+    fn compute_output_data_shape(&self) -> Result<(usize, usize), CrateError> {
+        let data_shape: (usize, usize) = self.data_shape;
+        let (_, _, filter_h, filter_w) = self.weight.dim();
+        let size = |axis: usize, input_size: usize, filter_size: usize| {
+            let full: usize = input_size.saturating_sub(1) * self.stride_sizes[axis]
+                + dilated_filter_size(filter_size, self.dilations[axis])
+                + self.output_padding[axis];
+            if input_size == 0 || full <= 2 * self.pad_size {
+                return Err(CrateError::ShapeError(format!(
+                    "input size {} is too small for pad size {}.",
+                    input_size, self.pad_size
+                )));
+            }
+            Ok(full - 2 * self.pad_size)
+        };
+        Ok((
+            size(0, data_shape.0, filter_h)?,
+            size(1, data_shape.1, filter_w)?,
+        ))
+    }
+    fn weight_matrix(&self) -> Array2<T> {
+        let in_channels: usize = self.weight.len_of(Axis(0));
+        self.weight
+            .to_shape((in_channels, self.weight.len() / in_channels))
+            .unwrap()
+            .into_owned()
+    }
+}
+
+impl<T: 'static> LayerBase<T> for ConvTranspose2d<T>
+where
+    T: CrateFloat,
+{
+    type A = Array4<T>;
+
+    type B = Array4<T>;
+
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        let (n, in_channels, h, w) = x.dim();
+        let (_, out_channels, filter_h, filter_w) = self.weight.dim();
+        let (output_h, output_w) = self
+            .check_data_shape((h, w))
+            .expect("inputs must be of the (height, width) given when the layer is built");
+        self.x_shape = x.dim();
+        self.x_rows = x
+            .view()
+            .permuted_axes([0, 2, 3, 1])
+            .to_shape((n * h * w, in_channels))
+            .unwrap()
+            .into_owned();
+        let col: Array2<T> = self.x_rows.dot(&self.weight_matrix());
+        let dst: Array4<T> = col2im_nd(
+            &col,
+            &[n, out_channels, output_h, output_w],
+            &[filter_h, filter_w],
+            &self.stride_sizes,
+            &[self.pad_size, self.pad_size],
+            &self.dilations,
+        )
+        .into_dimensionality::<Ix4>()
+        .unwrap();
+        dst + self.bias.view().into_shape((out_channels, 1, 1)).unwrap()
+    }
+
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        let (n, in_channels, h, w) = self.x_shape;
+        let (_, _, filter_h, filter_w) = self.weight.dim();
+        self.db = dx.sum_axis(Axis(3)).sum_axis(Axis(2)).sum_axis(Axis(0));
+        let col: Array2<T> = im2col_nd(
+            dx,
+            &[filter_h, filter_w],
+            &self.stride_sizes,
+            &[self.pad_size, self.pad_size],
+            &self.dilations,
+        );
+        self.dw = self
+            .x_rows
+            .t()
+            .dot(&col)
+            .into_shape(self.weight.raw_dim())
+            .unwrap();
+        col.dot(&self.weight_matrix().t())
+            .into_shape((n, h, w, in_channels))
+            .unwrap()
+            .permuted_axes([0, 3, 1, 2])
+            .as_standard_layout()
+            .into_owned()
+    }
+
+    fn update(&mut self, lr: T) {
+        self.weight.scaled_add(-lr, &self.dw);
+        self.bias.scaled_add(-lr, &self.db);
+    }
+
+    fn print_detail(&self) {
+        println!("2D transposed convolution layer.");
+        println!("weight shape: {:?}", self.weight.shape());
+        println!("bias shape: {:?}", self.bias.shape());
+        println!(
+            "stride size: ({}, {})",
+            self.stride_sizes[0], self.stride_sizes[1]
+        );
+        println!("pad size: {}", self.pad_size);
+        println!("dilation: ({}, {})", self.dilations[0], self.dilations[1]);
+        println!(
+            "output padding: ({}, {})",
+            self.output_padding[0], self.output_padding[1]
+        );
     }
     fn print_parameters(&self) {
         println!("weight: {:?}", self.weight);
@@ -1,24 +1,43 @@
 //! dropout
 //!
-//! Dropout layer
+//! Dropout layers
+//!
+//! All layers draw their masks from their own `StdRng`, which is seeded from entropy
+//! unless `with_seed` is given for reproducible runs. Schemes of models give the seed
+//! of `DropOut` by `UseDropoutEnum::UseWith`.
 
 #![allow(dead_code)]
 #![allow(unused_variables)]
 #![allow(unused_imports)]
 
+use super::super::error::CrateError;
 use super::super::param_initializers::*;
 use super::super::util::*;
+use super::affine::Affine;
 use super::layer_base::LayerBase;
-use ndarray::prelude::*;
-use ndarray_rand::rand::{distributions::Distribution, thread_rng};
+use ndarray::{prelude::*, Zip};
+use ndarray_rand::rand::rngs::StdRng;
+use ndarray_rand::rand::{distributions::Distribution, thread_rng, SeedableRng};
 use ndarray_rand::rand_distr::Uniform;
 use ndarray_rand::RandomExt;
 use std::fmt::{Debug, Display};
 
 /// Enum for Dropout layer
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub enum UseDropoutEnum<T: CrateFloat> {
     Use(T),
+    /// dropout with the options of `DropOut::with_inverted` and `DropOut::with_seed`,
+    /// e.g. `{"UseWith": {"ratio": 0.5, "inverted": true, "seed": 7}}`
+    ///
+    /// Models seed the dropout layer following the `ii`-th hidden layer with `seed + ii`.
+    UseWith {
+        ratio: T,
+        #[serde(default)]
+        inverted: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        seed: Option<u64>,
+    },
+    #[default]
     None,
 }
 
@@ -29,6 +48,15 @@ where
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             UseDropoutEnum::Use(ratio) => write!(f, "Use (ratio: {})", ratio),
+            UseDropoutEnum::UseWith {
+                ratio,
+                inverted,
+                seed,
+            } => write!(
+                f,
+                "UseWith (ratio: {}, inverted: {}, seed: {:?})",
+                ratio, inverted, seed
+            ),
             UseDropoutEnum::None => write!(f, "None"),
         }
     }
@@ -38,26 +66,81 @@ where
 pub fn call_dropout_layer<T: 'static, D: 'static, Sh>(
     use_dropout_enum: UseDropoutEnum<T>,
     shape: Sh,
-) -> DropOut<T, D>
+) -> Result<DropOut<T, D>, CrateError>
 where
     T: CrateFloat,
     D: Dimension,
     Sh: ShapeBuilder<Dim = D>,
 {
-    if let UseDropoutEnum::Use(ratio) = use_dropout_enum {
-        DropOut::new(ratio, shape)
+    match use_dropout_enum {
+        UseDropoutEnum::Use(ratio) => DropOut::new(ratio, shape),
+        UseDropoutEnum::UseWith {
+            ratio,
+            inverted,
+            seed,
+        } => {
+            let layer = DropOut::new(ratio, shape)?.with_inverted(inverted);
+            Ok(match seed {
+                Some(seed) => layer.with_seed(seed),
+                None => layer,
+            })
+        }
+        UseDropoutEnum::None => DropOut::new(cast_t2u(0.0), shape),
+    }
+}
+
+/// validate a dropout ratio
+fn check_ratio<T: CrateFloat>(ratio: T) -> Result<(), CrateError> {
+    if !(ratio >= cast_t2u(0.0) && ratio <= cast_t2u(1.0)) {
+        return Err(CrateError::ConfigError(format!(
+            "the dropout ratio must be in [0, 1], found {}.",
+            ratio
+        )));
+    }
+    Ok(())
+}
+
+/// `1 / (1 - ratio)`, or 0 if all elements are dropped
+fn keep_scale<T: CrateFloat>(ratio: T) -> T {
+    let one: T = cast_t2u(1.0);
+    if ratio < one {
+        one / (one - ratio)
     } else {
-        DropOut::new(cast_t2u(0.0), shape)
+        cast_t2u(0.0)
     }
 }
 
+/// sample a mask of the given shape where each element is dropped (0) with the probability `ratio`
+fn sample_keep_mask<T, D>(rng: &mut StdRng, shape: D, ratio: f64) -> Array<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    let (one, zero): (T, T) = (cast_t2u(1.0), cast_t2u(0.0));
+    Array::<f64, D>::random_using(shape, Uniform::new(0.0, 1.0), rng).mapv(|u| {
+        if u < ratio {
+            zero
+        } else {
+            one
+        }
+    })
+}
+
+// >>>>>>>>>>>>> Dropout layer >>>>>>>>>>>>>
+
 /// Dropout
 ///
+/// By default, outputs are scaled by `1 - ratio` at inference.
+/// With `with_inverted(true)`, kept elements are scaled by `1 / (1 - ratio)` during training instead,
+/// so that inference is the identity.
+///
 /// See http://arxiv.org/abs/1207.0580 in detail
 pub struct DropOut<T: CrateFloat, D> {
     ratio: T,
     mask: Array<u8, D>,
     trainable: bool,
+    inverted: bool,
+    rng: StdRng,
     one: T,
     zero: T,
     ratio_f64: f64,
@@ -69,22 +152,48 @@ where
     T: CrateFloat,
     D: Dimension,
 {
-    pub fn new<Sh>(ratio: T, shape: Sh) -> Self
+    pub fn new<Sh>(ratio: T, shape: Sh) -> Result<Self, CrateError>
     where
         Sh: ShapeBuilder<Dim = D>,
     {
         let one: T = cast_t2u(1.0);
         let zero: T = cast_t2u(0.0);
-        assert!(ratio >= zero && ratio <= one);
-        Self {
+        check_ratio(ratio)?;
+        Ok(Self {
             ratio,
             mask: Array::<u8, D>::zeros(shape),
             trainable: true,
+            inverted: false,
+            rng: StdRng::from_entropy(),
             one,
             zero,
             ratio_f64: cast_t2u(ratio),
             one_minus_ratio: one - ratio,
+        })
+    }
+    /// seed the random number generator of masks
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+    /// scale during training instead of at inference
+    pub fn with_inverted(mut self, inverted: bool) -> Self {
+        self.inverted = inverted;
+        self
+    }
+    fn apply_mask(&self, x: &Array<T, D>) -> Array<T, D> {
+        let scale: T = if self.inverted {
+            keep_scale(self.ratio)
+        } else {
+            self.one
+        };
+        let mut dst = x * scale;
+        for (v, d) in self.mask.iter().zip(dst.iter_mut()) {
+            if *v == 0u8 {
+                *d = self.zero;
+            }
         }
+        dst
     }
 }
 
@@ -97,32 +206,19 @@ where
     type B = Array<T, D>;
     fn forward(&mut self, x: &Self::A) -> Self::B {
         if self.trainable {
-            self.mask = Array::<f64, D>::random(x.raw_dim(), Uniform::new(0.0, 1.0)).map(|&x| {
-                if x < self.ratio_f64 {
-                    0
-                } else {
-                    1
-                }
-            });
-            let mut dst = x.clone();
-            for (v, d) in self.mask.iter().zip(dst.iter_mut()) {
-                if *v == 0u8 {
-                    *d = self.zero;
-                }
-            }
-            dst
+            let ratio_f64 = self.ratio_f64;
+            self.mask =
+                Array::<f64, D>::random_using(x.raw_dim(), Uniform::new(0.0, 1.0), &mut self.rng)
+                    .map(|&x| if x < ratio_f64 { 0 } else { 1 });
+            self.apply_mask(x)
+        } else if self.inverted {
+            x.clone()
         } else {
             x * self.one_minus_ratio
         }
     }
     fn backward(&mut self, dx: &Self::B) -> Self::A {
-        let mut dst = dx.clone();
-        for (v, d) in self.mask.iter().zip(dst.iter_mut()) {
-            if *v == 0u8 {
-                *d = self.zero;
-            }
-        }
-        dst
+        self.apply_mask(dx)
     }
     fn update(&mut self, lr: T) {
         return;
@@ -133,5 +229,260 @@ where
     fn print_detail(&self) {
         println!("Dropout layer.");
         println!("ratio: {}", self.ratio);
+        println!("inverted: {}", self.inverted);
+    }
+}
+
+// <<<<<<<<<<<<< Dropout layer <<<<<<<<<<<<<
+
+// >>>>>>>>>>>>> SpatialDropout layer >>>>>>>>>>>>>
+
+/// Arbitrary-D inverted dropout dropping whole channels of (batch, channel, ...) feature maps
+///
+/// See https://arxiv.org/abs/1411.4280 in detail
+pub struct SpatialDropout<T: CrateFloat, D> {
+    ratio: T,
+    mask: Array<T, D>,
+    trainable: bool,
+    rng: StdRng,
+}
+
+impl<T, D> SpatialDropout<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    pub fn new(ratio: T) -> Result<Self, CrateError> {
+        check_ratio(ratio)?;
+        let ndim: usize = D::NDIM.unwrap_or(2);
+        Ok(Self {
+            ratio,
+            mask: Array::ones(D::zeros(ndim)),
+            trainable: true,
+            rng: StdRng::from_entropy(),
+        })
+    }
+    /// seed the random number generator of masks
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+}
+
+impl<T, D> LayerBase<T> for SpatialDropout<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    type A = Array<T, D>;
+    type B = Array<T, D>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        if !self.trainable {
+            return x.clone();
+        }
+        // one mask element for each (batch, channel), broadcast over the remaining axes
+        let mut shape: D = x.raw_dim();
+        for len in shape.slice_mut().iter_mut().skip(2) {
+            *len = 1;
+        }
+        self.mask =
+            sample_keep_mask(&mut self.rng, shape, cast_t2u(self.ratio)) * keep_scale(self.ratio);
+        x * &self.mask
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        if !self.trainable {
+            return dx.clone();
+        }
+        dx * &self.mask
+    }
+    fn set_trainable(&mut self, flag: bool) {
+        self.trainable = flag;
+    }
+    fn print_detail(&self) {
+        println!("spatial dropout layer.");
+        println!("ratio: {}", self.ratio);
+    }
+}
+
+pub type SpatialDropout3<T> = SpatialDropout<T, Ix3>;
+pub type SpatialDropout4<T> = SpatialDropout<T, Ix4>;
+pub type SpatialDropout5<T> = SpatialDropout<T, Ix5>;
+pub type SpatialDropoutD<T> = SpatialDropout<T, IxDyn>;
+
+// <<<<<<<<<<<<< SpatialDropout layer <<<<<<<<<<<<<
+
+// >>>>>>>>>>>>> AlphaDropout layer >>>>>>>>>>>>>
+
+/// Arbitrary-D dropout for SELU networks
+///
+/// Dropped elements are set to the negative saturation value of SELU, and outputs are
+/// transformed affinely so that their mean and variance are kept. Inference is the identity.
+///
+/// See https://arxiv.org/abs/1706.02515 in detail
+pub struct AlphaDropout<T: CrateFloat, D> {
+    ratio: T,
+    mask: Array<T, D>,
+    trainable: bool,
+    rng: StdRng,
+    alpha_prime: T,
+    a: T,
+    b: T,
+}
+
+impl<T, D> AlphaDropout<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    pub fn new(ratio: T) -> Result<Self, CrateError> {
+        check_ratio(ratio)?;
+        // -lambda * alpha of SELU
+        let alpha_prime: T = cast_t2u(-1.758_099_340_847_376_6);
+        let one: T = cast_t2u(1.0);
+        let keep: T = one - ratio;
+        let a: T = if keep > cast_t2u(0.0) {
+            one / (keep + alpha_prime * alpha_prime * keep * ratio).sqrt()
+        } else {
+            cast_t2u(0.0)
+        };
+        let ndim: usize = D::NDIM.unwrap_or(2);
+        Ok(Self {
+            ratio,
+            mask: Array::ones(D::zeros(ndim)),
+            trainable: true,
+            rng: StdRng::from_entropy(),
+            alpha_prime,
+            a,
+            b: -a * alpha_prime * ratio,
+        })
+    }
+    /// seed the random number generator of masks
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+}
+
+impl<T, D> LayerBase<T> for AlphaDropout<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    type A = Array<T, D>;
+    type B = Array<T, D>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        if !self.trainable {
+            return x.clone();
+        }
+        self.mask = sample_keep_mask(&mut self.rng, x.raw_dim(), cast_t2u(self.ratio));
+        let (one, alpha_prime, a, b) = (cast_t2u::<f64, T>(1.0), self.alpha_prime, self.a, self.b);
+        let mut dst: Self::B = x.clone();
+        Zip::from(&mut dst)
+            .and(&self.mask)
+            .for_each(|d, &m| *d = a * (*d * m + alpha_prime * (one - m)) + b);
+        dst
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        if !self.trainable {
+            return dx.clone();
+        }
+        dx * &self.mask * self.a
+    }
+    fn set_trainable(&mut self, flag: bool) {
+        self.trainable = flag;
+    }
+    fn print_detail(&self) {
+        println!("alpha dropout layer.");
+        println!("ratio: {}", self.ratio);
     }
 }
+
+pub type AlphaDropout2<T> = AlphaDropout<T, Ix2>;
+pub type AlphaDropout3<T> = AlphaDropout<T, Ix3>;
+pub type AlphaDropout4<T> = AlphaDropout<T, Ix4>;
+pub type AlphaDropoutD<T> = AlphaDropout<T, IxDyn>;
+
+// <<<<<<<<<<<<< AlphaDropout layer <<<<<<<<<<<<<
+
+// >>>>>>>>>>>>> DropConnect layer >>>>>>>>>>>>>
+
+/// Affine layer whose weights, instead of inputs, are dropped during training
+///
+/// Kept weights are scaled by `1 / (1 - ratio)`, so that inference uses the plain `Affine`.
+///
+/// See http://proceedings.mlr.press/v28/wan13.html in detail
+pub struct DropConnect<T: CrateFloat> {
+    pub affine: Affine<T>,
+    ratio: T,
+    mask: Array2<T>,
+    x: Array2<T>,
+    trainable: bool,
+    rng: StdRng,
+}
+
+impl<T: 'static> DropConnect<T>
+where
+    T: CrateFloat,
+{
+    pub fn new(affine: Affine<T>, ratio: T) -> Result<Self, CrateError> {
+        check_ratio(ratio)?;
+        let mask: Array2<T> = Array2::ones(affine.weight.raw_dim());
+        Ok(Self {
+            affine,
+            ratio,
+            mask,
+            x: Array2::zeros((0, 0)),
+            trainable: true,
+            rng: StdRng::from_entropy(),
+        })
+    }
+    /// seed the random number generator of masks
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+}
+
+impl<T: 'static> LayerBase<T> for DropConnect<T>
+where
+    T: CrateFloat,
+{
+    type A = Array2<T>;
+    type B = Array2<T>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        if !self.trainable {
+            return self.affine.forward(x);
+        }
+        self.x = x.clone();
+        self.mask = sample_keep_mask(
+            &mut self.rng,
+            self.affine.weight.raw_dim(),
+            cast_t2u(self.ratio),
+        ) * keep_scale(self.ratio);
+        x.dot(&(&self.affine.weight * &self.mask)) + &self.affine.bias
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        if !self.trainable {
+            return self.affine.backward(dx);
+        }
+        self.affine.dw = self.x.t().dot(dx) * &self.mask;
+        self.affine.db = dx.sum_axis(Axis(0));
+        dx.dot(&(&self.affine.weight * &self.mask).t())
+    }
+    fn update(&mut self, lr: T) {
+        self.affine.update(lr);
+    }
+    fn set_trainable(&mut self, flag: bool) {
+        self.trainable = flag;
+    }
+    fn print_detail(&self) {
+        println!("drop connect layer.");
+        println!("ratio: {}", self.ratio);
+        self.affine.print_detail();
+    }
+    fn print_parameters(&self) {
+        self.affine.print_parameters();
+    }
+}
+
+// <<<<<<<<<<<<< DropConnect layer <<<<<<<<<<<<<
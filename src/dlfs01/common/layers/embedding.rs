@@ -0,0 +1,97 @@
+//! embedding
+//!
+//! Embedding layer
+
+use super::super::optimizers::SparseOptimizerBase;
+use super::super::param_initializers::weight_init::{initialize_weight, WeightInitEnum};
+use super::super::util::*;
+use super::layer_base::LayerBase;
+use ndarray::prelude::*;
+
+/// Embedding layer mapping (batch, time) token ids to (batch, time, features) vectors
+///
+/// The gradient is kept only for the rows of the ids in the last batch:
+/// `dw` has a row for each of `dw_rows`, which are sorted and unique.
+/// Ids must be less than the vocabulary size, i.e., the number of rows of `weight`.
+/// Ids have no gradient, so `backward` returns zeros.
+pub struct Embedding<T: CrateFloat> {
+    pub weight: Array2<T>,
+    pub dw: Array2<T>,
+    pub dw_rows: Vec<usize>,
+    ids: Array2<usize>,
+}
+
+impl<T> Embedding<T>
+where
+    T: CrateFloat,
+{
+    pub fn new(
+        vocab_size: usize,
+        embedding_size: usize,
+        weight_init: WeightInitEnum,
+        weight_init_std: T,
+    ) -> Self {
+        Self::from(&initialize_weight(
+            weight_init,
+            weight_init_std,
+            (vocab_size, embedding_size),
+        ))
+    }
+    pub fn from(weight: &Array2<T>) -> Self {
+        Self {
+            weight: weight.clone(),
+            dw: Array2::zeros((0, weight.ncols())),
+            dw_rows: Vec::new(),
+            ids: Array2::zeros((0, 0)),
+        }
+    }
+    /// update the touched rows with a sparse optimizer
+    pub fn update_sparse(&mut self, optimizer: &mut dyn SparseOptimizerBase<T>) {
+        optimizer.update_rows(&mut self.weight, &self.dw_rows, &self.dw);
+    }
+}
+
+impl<T: 'static> LayerBase<T> for Embedding<T>
+where
+    T: CrateFloat,
+{
+    type A = Array2<usize>;
+    type B = Array3<T>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        let (batch_size, time) = x.dim();
+        let mut dst: Self::B = Array3::zeros((batch_size, time, self.weight.ncols()));
+        for ((b, t), &id) in x.indexed_iter() {
+            dst.slice_mut(s![b, t, ..]).assign(&self.weight.row(id));
+        }
+        self.ids = x.clone();
+        dst
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        let mut rows: Vec<usize> = self.ids.iter().cloned().collect();
+        rows.sort_unstable();
+        rows.dedup();
+        let mut dw: Array2<T> = Array2::zeros((rows.len(), self.weight.ncols()));
+        for ((b, t), id) in self.ids.indexed_iter() {
+            let row: usize = rows.binary_search(id).unwrap();
+            dw.row_mut(row)
+                .scaled_add(cast_t2u(1.0), &dx.slice(s![b, t, ..]));
+        }
+        self.dw = dw;
+        self.dw_rows = rows;
+        Array2::zeros(self.ids.raw_dim())
+    }
+    fn update(&mut self, lr: T) {
+        for (&row, dw) in self.dw_rows.iter().zip(self.dw.outer_iter()) {
+            self.weight.row_mut(row).scaled_add(-lr, &dw);
+        }
+    }
+    fn print_detail(&self) {
+        println!("embedding layer.");
+        println!("weight shape: {:?}", self.weight.shape());
+    }
+    fn print_parameters(&self) {
+        println!("weight: {:?}", self.weight);
+        println!("dw rows: {:?}", self.dw_rows);
+        println!("dw: {:?}", self.dw);
+    }
+}
@@ -4,7 +4,33 @@
 
 #![allow(unused_variables)]
 
+use super::super::error::CrateError;
 use super::super::util::CrateFloat;
+use ndarray::prelude::*;
+
+/// flattened views of a learnable parameter and its gradient for `LayerBase::parameters`
+pub type ParamGrad<'a, T> = (ArrayViewMut1<'a, T>, ArrayView1<'a, T>);
+
+/// flatten a learnable parameter and its gradient, which are of the standard layout
+pub(crate) fn param_grad<'a, T, D>(
+    param: &'a mut Array<T, D>,
+    grad: &'a Array<T, D>,
+) -> ParamGrad<'a, T>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    let len: usize = param.len();
+    (
+        param
+            .view_mut()
+            .into_shape(len)
+            .expect("parameters must be of the standard layout"),
+        grad.view()
+            .into_shape(len)
+            .expect("gradients must be of the standard layout"),
+    )
+}
 
 /// arbitrary-D layer trait
 pub trait LayerBase<T: CrateFloat> {
@@ -15,6 +41,11 @@ pub trait LayerBase<T: CrateFloat> {
     fn update(&mut self, lr: T) {
         return;
     }
+    /// learnable parameters with their gradients, which models update with their optimizers
+    /// in place of `update`
+    fn parameters(&mut self) -> Vec<ParamGrad<'_, T>> {
+        Vec::new()
+    }
     fn set_trainable(&mut self, _flag: bool) {
         return;
     }
@@ -26,11 +57,29 @@ pub trait LayerBase<T: CrateFloat> {
     }
 }
 
+/// multi-input layer trait
+///
+/// `backward` returns the gradients for the inputs in the order they were given to `forward`.
+pub trait MergeLayerBase<T: CrateFloat> {
+    type A;
+    fn forward(&mut self, xs: &[&Self::A]) -> Self::A;
+    fn backward(&mut self, dx: &Self::A) -> Vec<Self::A>;
+    fn print_detail(&self) {}
+}
+
 /// Arbitrary-D loss layer trait
 pub trait LossLayerBase<T: CrateFloat> {
     type A;
     fn forward(&mut self, x: &Self::A, t: &Self::A) -> T;
     fn backward(&mut self, _dx: T) -> Self::A;
+    /// loss for sparse labels, i.e. class indices of samples on the batch axis
+    ///
+    /// `backward` works as usual after this.
+    fn forward_sparse(&mut self, x: &Self::A, t: &Array1<usize>) -> Result<T, CrateError> {
+        Err(CrateError::ConfigError(
+            "this loss layer does not take sparse labels.".to_string(),
+        ))
+    }
     fn update(&mut self, lr: T) {
         return;
     }
@@ -0,0 +1,390 @@
+//! merge
+//!
+//! Merge layers and skip connections
+//!
+//! Merge layers take several arrays of the same dimensionality and return one,
+//! and split the gradient into one for each input on backward.
+//! `Residual` and `DenseConnection` wrap a chain of layers into a single-input layer
+//! with a skip connection, so that they can be used wherever a `LayerBase` is expected.
+
+use super::super::util::*;
+use super::layer_base::{LayerBase, MergeLayerBase, ParamGrad};
+use ndarray::{concatenate, prelude::*, RemoveAxis, Slice};
+use std::marker::PhantomData;
+
+/// boxed layer used as a branch of skip connections
+pub type BranchLayer<T, D> = Box<dyn LayerBase<T, A = Array<T, D>, B = Array<T, D>>>;
+
+// >>>>>>>>>>>>> Add layer >>>>>>>>>>>>>
+
+/// Arbitrary-D element-wise sum of inputs
+pub struct Add<T: CrateFloat, D> {
+    num_inputs: usize,
+    _phantom: PhantomData<(T, D)>,
+}
+
+impl<T: 'static, D> Add<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    pub fn new() -> Self {
+        Self {
+            num_inputs: 0,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: 'static, D> Default for Add<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: 'static, D> MergeLayerBase<T> for Add<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    type A = Array<T, D>;
+    fn forward(&mut self, xs: &[&Self::A]) -> Self::A {
+        self.num_inputs = xs.len();
+        let mut dst: Self::A = xs[0].clone();
+        for &x in xs[1..].iter() {
+            dst += x;
+        }
+        dst
+    }
+    fn backward(&mut self, dx: &Self::A) -> Vec<Self::A> {
+        vec![dx.clone(); self.num_inputs]
+    }
+    fn print_detail(&self) {
+        println!("add layer.");
+    }
+}
+
+pub type Add2<T> = Add<T, Ix2>;
+pub type Add3<T> = Add<T, Ix3>;
+pub type Add4<T> = Add<T, Ix4>;
+pub type Add5<T> = Add<T, Ix5>;
+pub type Add6<T> = Add<T, Ix6>;
+pub type AddD<T> = Add<T, IxDyn>;
+
+// <<<<<<<<<<<<< Add layer <<<<<<<<<<<<<
+
+// >>>>>>>>>>>>> Multiply layer >>>>>>>>>>>>>
+
+/// Arbitrary-D element-wise product of inputs
+pub struct Multiply<T: CrateFloat, D> {
+    xs: Vec<Array<T, D>>,
+}
+
+impl<T: 'static, D> Multiply<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    pub fn new() -> Self {
+        Self { xs: Vec::new() }
+    }
+}
+
+impl<T: 'static, D> Default for Multiply<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: 'static, D> MergeLayerBase<T> for Multiply<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    type A = Array<T, D>;
+    fn forward(&mut self, xs: &[&Self::A]) -> Self::A {
+        self.xs = xs.iter().map(|&x| x.clone()).collect();
+        let mut dst: Self::A = xs[0].clone();
+        for &x in xs[1..].iter() {
+            dst *= x;
+        }
+        dst
+    }
+    fn backward(&mut self, dx: &Self::A) -> Vec<Self::A> {
+        (0..self.xs.len())
+            .map(|i| {
+                let mut dst: Self::A = dx.clone();
+                for (_, x) in self.xs.iter().enumerate().filter(|(j, _)| *j != i) {
+                    dst *= x;
+                }
+                dst
+            })
+            .collect()
+    }
+    fn print_detail(&self) {
+        println!("multiply layer.");
+    }
+}
+
+pub type Multiply2<T> = Multiply<T, Ix2>;
+pub type Multiply3<T> = Multiply<T, Ix3>;
+pub type Multiply4<T> = Multiply<T, Ix4>;
+pub type Multiply5<T> = Multiply<T, Ix5>;
+pub type Multiply6<T> = Multiply<T, Ix6>;
+pub type MultiplyD<T> = Multiply<T, IxDyn>;
+
+// <<<<<<<<<<<<< Multiply layer <<<<<<<<<<<<<
+
+// >>>>>>>>>>>>> Concatenate layer >>>>>>>>>>>>>
+
+/// Arbitrary-D concatenation of inputs along an axis, e.g. the channel axis
+pub struct Concatenate<T: CrateFloat, D> {
+    axis: usize,
+    sizes: Vec<usize>,
+    _phantom: PhantomData<(T, D)>,
+}
+
+impl<T: 'static, D> Concatenate<T, D>
+where
+    T: CrateFloat,
+    D: Dimension + RemoveAxis,
+{
+    pub fn new(axis: usize) -> Self {
+        Self {
+            axis,
+            sizes: Vec::new(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: 'static, D> MergeLayerBase<T> for Concatenate<T, D>
+where
+    T: CrateFloat,
+    D: Dimension + RemoveAxis,
+{
+    type A = Array<T, D>;
+    fn forward(&mut self, xs: &[&Self::A]) -> Self::A {
+        self.sizes = xs.iter().map(|x| x.len_of(Axis(self.axis))).collect();
+        let views: Vec<ArrayView<T, D>> = xs.iter().map(|x| x.view()).collect();
+        concatenate(Axis(self.axis), &views).unwrap()
+    }
+    fn backward(&mut self, dx: &Self::A) -> Vec<Self::A> {
+        let mut start: usize = 0;
+        self.sizes
+            .iter()
+            .map(|&size| {
+                let dst = dx
+                    .slice_axis(Axis(self.axis), Slice::from(start..start + size))
+                    .to_owned();
+                start += size;
+                dst
+            })
+            .collect()
+    }
+    fn print_detail(&self) {
+        println!("concatenate layer.");
+        println!("axis: {}", self.axis);
+    }
+}
+
+pub type Concatenate2<T> = Concatenate<T, Ix2>;
+pub type Concatenate3<T> = Concatenate<T, Ix3>;
+pub type Concatenate4<T> = Concatenate<T, Ix4>;
+pub type Concatenate5<T> = Concatenate<T, Ix5>;
+pub type Concatenate6<T> = Concatenate<T, Ix6>;
+pub type ConcatenateD<T> = Concatenate<T, IxDyn>;
+
+// <<<<<<<<<<<<< Concatenate layer <<<<<<<<<<<<<
+
+/// forward a chain of layers
+fn forward_chain<T, D>(layers: &mut [BranchLayer<T, D>], x: &Array<T, D>) -> Array<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    layers
+        .iter_mut()
+        .fold(x.clone(), |x, layer| layer.forward(&x))
+}
+
+/// backward a chain of layers
+fn backward_chain<T, D>(layers: &mut [BranchLayer<T, D>], dx: &Array<T, D>) -> Array<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    layers
+        .iter_mut()
+        .rev()
+        .fold(dx.clone(), |dx, layer| layer.backward(&dx))
+}
+
+// >>>>>>>>>>>>> Residual layer >>>>>>>>>>>>>
+
+/// Residual block `shortcut(x) + body(x)`
+///
+/// The body is a chain of layers, and the shortcut is the identity unless a projection,
+/// e.g. a 1x1 convolution, is given by `with_shortcut`.
+///
+/// See https://arxiv.org/abs/1512.03385 in detail
+pub struct Residual<T: CrateFloat, D> {
+    pub body: Vec<BranchLayer<T, D>>,
+    pub shortcut: Option<BranchLayer<T, D>>,
+    add: Add<T, D>,
+}
+
+impl<T: 'static, D> Residual<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    pub fn new(body: Vec<BranchLayer<T, D>>) -> Self {
+        Self {
+            body,
+            shortcut: None,
+            add: Add::new(),
+        }
+    }
+    pub fn with_shortcut(mut self, shortcut: BranchLayer<T, D>) -> Self {
+        self.shortcut = Some(shortcut);
+        self
+    }
+}
+
+impl<T: 'static, D> LayerBase<T> for Residual<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    type A = Array<T, D>;
+    type B = Array<T, D>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        let skip: Self::B = match self.shortcut.as_mut() {
+            Some(shortcut) => shortcut.forward(x),
+            None => x.clone(),
+        };
+        let y: Self::B = forward_chain(&mut self.body, x);
+        self.add.forward(&[&skip, &y])
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        let grads = self.add.backward(dx);
+        let dskip: Self::A = match self.shortcut.as_mut() {
+            Some(shortcut) => shortcut.backward(&grads[0]),
+            None => grads[0].clone(),
+        };
+        dskip + backward_chain(&mut self.body, &grads[1])
+    }
+    fn update(&mut self, lr: T) {
+        for layer in self.body.iter_mut().chain(self.shortcut.iter_mut()) {
+            layer.update(lr);
+        }
+    }
+    fn parameters(&mut self) -> Vec<ParamGrad<'_, T>> {
+        self.body
+            .iter_mut()
+            .chain(self.shortcut.iter_mut())
+            .flat_map(|layer| layer.parameters())
+            .collect()
+    }
+    fn set_trainable(&mut self, flag: bool) {
+        for layer in self.body.iter_mut().chain(self.shortcut.iter_mut()) {
+            layer.set_trainable(flag);
+        }
+    }
+    fn print_detail(&self) {
+        println!("residual block.");
+        for layer in self.body.iter() {
+            layer.print_detail();
+        }
+        if let Some(shortcut) = self.shortcut.as_ref() {
+            println!("shortcut:");
+            shortcut.print_detail();
+        }
+    }
+    fn print_parameters(&self) {
+        for layer in self.body.iter().chain(self.shortcut.iter()) {
+            layer.print_parameters();
+        }
+    }
+}
+
+// <<<<<<<<<<<<< Residual layer <<<<<<<<<<<<<
+
+// >>>>>>>>>>>>> DenseConnection layer >>>>>>>>>>>>>
+
+/// Dense connection concatenating `x` and `body(x)` along an axis, e.g. the channel axis
+///
+/// See https://arxiv.org/abs/1608.06993 in detail
+pub struct DenseConnection<T: CrateFloat, D> {
+    pub body: Vec<BranchLayer<T, D>>,
+    concatenate: Concatenate<T, D>,
+}
+
+impl<T: 'static, D> DenseConnection<T, D>
+where
+    T: CrateFloat,
+    D: Dimension + RemoveAxis,
+{
+    pub fn new(body: Vec<BranchLayer<T, D>>, axis: usize) -> Self {
+        Self {
+            body,
+            concatenate: Concatenate::new(axis),
+        }
+    }
+}
+
+impl<T: 'static, D> LayerBase<T> for DenseConnection<T, D>
+where
+    T: CrateFloat,
+    D: Dimension + RemoveAxis,
+{
+    type A = Array<T, D>;
+    type B = Array<T, D>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        let y: Self::B = forward_chain(&mut self.body, x);
+        self.concatenate.forward(&[x, &y])
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        let grads = self.concatenate.backward(dx);
+        backward_chain(&mut self.body, &grads[1]) + &grads[0]
+    }
+    fn update(&mut self, lr: T) {
+        for layer in self.body.iter_mut() {
+            layer.update(lr);
+        }
+    }
+    fn parameters(&mut self) -> Vec<ParamGrad<'_, T>> {
+        self.body
+            .iter_mut()
+            .flat_map(|layer| layer.parameters())
+            .collect()
+    }
+    fn set_trainable(&mut self, flag: bool) {
+        for layer in self.body.iter_mut() {
+            layer.set_trainable(flag);
+        }
+    }
+    fn print_detail(&self) {
+        println!("dense connection.");
+        self.concatenate.print_detail();
+        for layer in self.body.iter() {
+            layer.print_detail();
+        }
+    }
+    fn print_parameters(&self) {
+        for layer in self.body.iter() {
+            layer.print_parameters();
+        }
+    }
+}
+
+// <<<<<<<<<<<<< DenseConnection layer <<<<<<<<<<<<<
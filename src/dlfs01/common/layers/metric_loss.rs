@@ -0,0 +1,344 @@
+//! metric_loss
+//!
+//! Loss layers for metric learning
+//!
+//! These layers take embeddings of (batch, features) arrays, e.g. outputs of an `MLPClassifier`
+//! backbone without a softmax head, and have their own `forward` and `backward` because they take
+//! pairs or triplets of embeddings. Losses are averaged over the batch.
+
+use super::super::error::CrateError;
+use super::super::util::*;
+use ndarray::prelude::*;
+
+const EPS: f64 = 1E-12;
+
+/// Euclidean norm of each row
+fn row_norms<T: CrateFloat>(x: &Array2<T>) -> Array1<T> {
+    x.map_axis(Axis(1), |row| row.dot(&row).sqrt())
+}
+
+/// each row of `x` divided by `norms`, or zero for rows of zero norm
+fn normalize_rows<T: CrateFloat>(x: &Array2<T>, norms: &Array1<T>) -> Array2<T> {
+    let eps: T = cast_t2u(EPS);
+    let mut dst: Array2<T> = x.clone();
+    for (mut row, &norm) in dst.outer_iter_mut().zip(norms.iter()) {
+        if norm > eps {
+            row /= norm;
+        } else {
+            row.fill(cast_t2u(0.0));
+        }
+    }
+    dst
+}
+
+fn check_margin<T: CrateFloat>(margin: T) -> Result<(), CrateError> {
+    if margin.is_nan() || margin <= cast_t2u(0.0) {
+        return Err(CrateError::ConfigError(format!(
+            "the margin must be positive, found {}.",
+            margin
+        )));
+    }
+    Ok(())
+}
+
+// >>>>>>>>>>>>> ContrastiveLoss layer >>>>>>>>>>>>>
+
+/// Contrastive loss of pairs `y d^2 / 2 + (1 - y) max(0, margin - d)^2 / 2`
+///
+/// `d` is the Euclidean distance of a pair and `y` is 1 for similar pairs and 0 for dissimilar ones.
+///
+/// See http://yann.lecun.com/exdb/publis/pdf/hadsell-chopra-lecun-06.pdf in detail
+pub struct ContrastiveLoss<T: CrateFloat> {
+    margin: T,
+    diff: Array2<T>,
+    distances: Array1<T>,
+    target: Array1<T>,
+}
+
+impl<T: 'static> ContrastiveLoss<T>
+where
+    T: CrateFloat,
+{
+    pub fn new(margin: T) -> Result<Self, CrateError> {
+        check_margin(margin)?;
+        Ok(Self {
+            margin,
+            diff: Array2::zeros((0, 0)),
+            distances: Array1::zeros(0),
+            target: Array1::zeros(0),
+        })
+    }
+    pub fn forward(&mut self, x1: &Array2<T>, x2: &Array2<T>, t: &Array1<T>) -> T {
+        let (zero, half, one): (T, T, T) = (cast_t2u(0.0), cast_t2u(0.5), cast_t2u(1.0));
+        self.diff = x1 - x2;
+        self.distances = row_norms(&self.diff);
+        self.target = t.clone();
+        let loss: T = self
+            .distances
+            .iter()
+            .zip(t.iter())
+            .fold(zero, |m, (&d, &y)| {
+                let gap: T = (self.margin - d).max(zero);
+                m + half * (y * d * d + (one - y) * gap * gap)
+            });
+        loss / cast_t2u(t.len())
+    }
+    /// gradients with respect to the first and the second embeddings
+    pub fn backward(&mut self, dout: T) -> (Array2<T>, Array2<T>) {
+        let (zero, one): (T, T) = (cast_t2u(0.0), cast_t2u(1.0));
+        let scale: T = dout / cast_t2u(self.target.len());
+        let mut dx1: Array2<T> = self.diff.clone();
+        for ((mut row, &d), &y) in dx1
+            .outer_iter_mut()
+            .zip(self.distances.iter())
+            .zip(self.target.iter())
+        {
+            // d(d^2 / 2) = diff and d(max(0, margin - d)^2 / 2) = -(margin - d) diff / d
+            let dissimilar: T = if d > cast_t2u(EPS) && d < self.margin {
+                -(self.margin - d) / d
+            } else {
+                zero
+            };
+            row *= (y + (one - y) * dissimilar) * scale;
+        }
+        let dx2: Array2<T> = dx1.mapv(|v| -v);
+        (dx1, dx2)
+    }
+    pub fn print_detail(&self) {
+        println!("contrastive loss layer.");
+        println!("margin: {}", self.margin);
+    }
+}
+
+// <<<<<<<<<<<<< ContrastiveLoss layer <<<<<<<<<<<<<
+
+// >>>>>>>>>>>>> TripletLoss layer >>>>>>>>>>>>>
+
+/// Triplet loss `max(0, d(a, p) - d(a, n) + margin)` of anchors, positives and negatives
+///
+/// See https://arxiv.org/abs/1503.03832 in detail
+pub struct TripletLoss<T: CrateFloat> {
+    margin: T,
+    /// unit vectors from positives and negatives to anchors
+    unit_ap: Array2<T>,
+    unit_an: Array2<T>,
+    active: Vec<bool>,
+}
+
+impl<T: 'static> TripletLoss<T>
+where
+    T: CrateFloat,
+{
+    pub fn new(margin: T) -> Result<Self, CrateError> {
+        check_margin(margin)?;
+        Ok(Self {
+            margin,
+            unit_ap: Array2::zeros((0, 0)),
+            unit_an: Array2::zeros((0, 0)),
+            active: Vec::new(),
+        })
+    }
+    pub fn forward(&mut self, anchor: &Array2<T>, positive: &Array2<T>, negative: &Array2<T>) -> T {
+        let zero: T = cast_t2u(0.0);
+        let ap: Array2<T> = anchor - positive;
+        let an: Array2<T> = anchor - negative;
+        let d_ap: Array1<T> = row_norms(&ap);
+        let d_an: Array1<T> = row_norms(&an);
+        self.unit_ap = normalize_rows(&ap, &d_ap);
+        self.unit_an = normalize_rows(&an, &d_an);
+        let losses: Array1<T> = (&d_ap - &d_an).mapv(|v| (v + self.margin).max(zero));
+        self.active = losses.iter().map(|&v| v > zero).collect();
+        losses.sum() / cast_t2u(losses.len())
+    }
+    /// gradients with respect to anchors, positives and negatives
+    pub fn backward(&mut self, dout: T) -> (Array2<T>, Array2<T>, Array2<T>) {
+        let scale: T = dout / cast_t2u(self.active.len());
+        let mut dp: Array2<T> = &self.unit_ap * -scale;
+        let mut dn: Array2<T> = &self.unit_an * scale;
+        for ((mut p, mut n), &active) in dp
+            .outer_iter_mut()
+            .zip(dn.outer_iter_mut())
+            .zip(self.active.iter())
+        {
+            if !active {
+                p.fill(cast_t2u(0.0));
+                n.fill(cast_t2u(0.0));
+            }
+        }
+        let da: Array2<T> = (&dp + &dn).mapv(|v| -v);
+        (da, dp, dn)
+    }
+    pub fn print_detail(&self) {
+        println!("triplet loss layer.");
+        println!("margin: {}", self.margin);
+    }
+}
+
+// <<<<<<<<<<<<< TripletLoss layer <<<<<<<<<<<<<
+
+// >>>>>>>>>>>>> BatchHardTripletLoss layer >>>>>>>>>>>>>
+
+/// Triplet loss with batch-hard mining
+///
+/// For each anchor in a batch, the farthest sample of the same label and the nearest sample of
+/// another label are chosen as the positive and the negative. Anchors without either are skipped.
+///
+/// See https://arxiv.org/abs/1703.07737 in detail
+pub struct BatchHardTripletLoss<T: CrateFloat> {
+    triplet: TripletLoss<T>,
+    /// indices of anchors, positives and negatives in the batch
+    triplets: Vec<[usize; 3]>,
+    batch_size: usize,
+}
+
+impl<T: 'static> BatchHardTripletLoss<T>
+where
+    T: CrateFloat,
+{
+    pub fn new(margin: T) -> Result<Self, CrateError> {
+        Ok(Self {
+            triplet: TripletLoss::new(margin)?,
+            triplets: Vec::new(),
+            batch_size: 0,
+        })
+    }
+    /// triplets mined in the last forward
+    pub fn get_triplets(&self) -> Vec<[usize; 3]> {
+        self.triplets.clone()
+    }
+    pub fn forward(&mut self, x: &Array2<T>, labels: &Array1<usize>) -> T {
+        self.batch_size = x.len_of(Axis(0));
+        let norms: Array1<T> = x.map_axis(Axis(1), |row| row.dot(&row));
+        let gram: Array2<T> = x.dot(&x.t());
+        let mut distances: Array2<T> = Array2::zeros(gram.raw_dim());
+        for ((i, j), d) in distances.indexed_iter_mut() {
+            *d = norms[i] + norms[j] - gram[[i, j]] * cast_t2u(2.0);
+        }
+        self.triplets = (0..self.batch_size)
+            .filter_map(|i| {
+                let row = distances.row(i);
+                let positive = (0..self.batch_size)
+                    .filter(|&j| j != i && labels[j] == labels[i])
+                    .max_by(|&j, &k| row[j].partial_cmp(&row[k]).unwrap());
+                let negative = (0..self.batch_size)
+                    .filter(|&k| labels[k] != labels[i])
+                    .min_by(|&j, &k| row[j].partial_cmp(&row[k]).unwrap());
+                Some([i, positive?, negative?])
+            })
+            .collect();
+        if self.triplets.is_empty() {
+            return cast_t2u(0.0);
+        }
+        let select = |jj: usize| -> Array2<T> {
+            let indices: Vec<usize> = self.triplets.iter().map(|v| v[jj]).collect();
+            x.select(Axis(0), &indices)
+        };
+        self.triplet.forward(&select(0), &select(1), &select(2))
+    }
+    /// gradient with respect to the batch of embeddings
+    pub fn backward(&mut self, dout: T) -> Array2<T> {
+        let mut dx: Array2<T> = Array2::zeros((self.batch_size, self.triplet.unit_ap.ncols()));
+        if self.triplets.is_empty() {
+            return dx;
+        }
+        let grads = self.triplet.backward(dout);
+        for (ii, triplet) in self.triplets.iter().enumerate() {
+            for (&index, grad) in triplet.iter().zip([&grads.0, &grads.1, &grads.2]) {
+                let mut row = dx.row_mut(index);
+                row += &grad.row(ii);
+            }
+        }
+        dx
+    }
+    pub fn print_detail(&self) {
+        println!("batch-hard triplet loss layer.");
+        println!("margin: {}", self.triplet.margin);
+    }
+}
+
+// <<<<<<<<<<<<< BatchHardTripletLoss layer <<<<<<<<<<<<<
+
+// >>>>>>>>>>>>> CosineEmbeddingLoss layer >>>>>>>>>>>>>
+
+/// Cosine embedding loss of pairs, `1 - cos` for similar pairs and `max(0, cos - margin)` for
+/// dissimilar ones
+///
+/// Targets are 1 for similar pairs and -1 for dissimilar ones, and the margin is in [-1, 1).
+pub struct CosineEmbeddingLoss<T: CrateFloat> {
+    margin: T,
+    /// gradients of the cosines with respect to the first and the second embeddings
+    dcos1: Array2<T>,
+    dcos2: Array2<T>,
+    /// gradients of the losses with respect to the cosines
+    dloss: Array1<T>,
+}
+
+impl<T: 'static> CosineEmbeddingLoss<T>
+where
+    T: CrateFloat,
+{
+    pub fn new(margin: T) -> Result<Self, CrateError> {
+        if !(margin >= cast_t2u(-1.0) && margin < cast_t2u(1.0)) {
+            return Err(CrateError::ConfigError(format!(
+                "the margin must be in [-1, 1), found {}.",
+                margin
+            )));
+        }
+        Ok(Self {
+            margin,
+            dcos1: Array2::zeros((0, 0)),
+            dcos2: Array2::zeros((0, 0)),
+            dloss: Array1::zeros(0),
+        })
+    }
+    pub fn forward(&mut self, x1: &Array2<T>, x2: &Array2<T>, t: &Array1<T>) -> T {
+        let (zero, one): (T, T) = (cast_t2u(0.0), cast_t2u(1.0));
+        let u1: Array2<T> = normalize_rows(x1, &row_norms(x1));
+        let u2: Array2<T> = normalize_rows(x2, &row_norms(x2));
+        let cosines: Array1<T> = (&u1 * &u2).sum_axis(Axis(1));
+        // d(cos)/dx1 = (u2 - cos u1) / |x1|
+        let scaled = |u: &Array2<T>, v: &Array2<T>, x: &Array2<T>| -> Array2<T> {
+            let mut dst: Array2<T> = v - &(u * &cosines.view().insert_axis(Axis(1)));
+            let norms: Array1<T> = row_norms(x);
+            for (mut row, &norm) in dst.outer_iter_mut().zip(norms.iter()) {
+                if norm > cast_t2u(EPS) {
+                    row /= norm;
+                } else {
+                    row.fill(zero);
+                }
+            }
+            dst
+        };
+        self.dcos1 = scaled(&u1, &u2, x1);
+        self.dcos2 = scaled(&u2, &u1, x2);
+        let mut loss: T = zero;
+        self.dloss = cosines
+            .iter()
+            .zip(t.iter())
+            .map(|(&cos, &y)| {
+                if y > zero {
+                    loss += one - cos;
+                    -one
+                } else if cos > self.margin {
+                    loss += cos - self.margin;
+                    one
+                } else {
+                    zero
+                }
+            })
+            .collect();
+        loss / cast_t2u(t.len())
+    }
+    /// gradients with respect to the first and the second embeddings
+    pub fn backward(&mut self, dout: T) -> (Array2<T>, Array2<T>) {
+        let scale: Array2<T> =
+            (&self.dloss * (dout / cast_t2u(self.dloss.len()))).insert_axis(Axis(1));
+        (&self.dcos1 * &scale, &self.dcos2 * &scale)
+    }
+    pub fn print_detail(&self) {
+        println!("cosine embedding loss layer.");
+        println!("margin: {}", self.margin);
+    }
+}
+
+// <<<<<<<<<<<<< CosineEmbeddingLoss layer <<<<<<<<<<<<<
@@ -4,26 +4,65 @@
 
 pub mod activation;
 pub mod affine;
+pub mod attention;
 pub mod batch_normalization;
 pub mod convolution;
 pub mod dropout;
+pub mod embedding;
 pub mod layer_base;
+pub mod merge;
+pub mod metric_loss;
+pub mod normalization;
 pub mod pooling;
+pub mod recurrent;
+pub mod regression_loss;
+pub mod reshape;
+pub mod sigmoid_with_loss;
 pub mod softmax_with_loss;
 
-use super::util::CrateFloat;
+use super::error::CrateError;
+use super::util::{cast_t2u, CrateFloat};
 pub use activation::{
-    Identity, ReLU, ReLU2, ReLU3, ReLU4, ReLU5, ReLU6, ReLUD, Sigmoid, Sigmoid2, Sigmoid3,
-    Sigmoid4, Sigmoid5, SigmoidD, Softmax, Softmax2, Softmax3, Softmax4, Softmax5, Softmax6,
-    SoftmaxD,
+    Identity, LeakyReLU, LeakyReLU1, LeakyReLU2, LeakyReLU3, LeakyReLU4, LeakyReLU5, LeakyReLU6,
+    LeakyReLUD, LogSoftmax, LogSoftmax2, LogSoftmax3, LogSoftmax4, LogSoftmax5, LogSoftmax6,
+    LogSoftmaxD, Mish, Mish1, Mish2, Mish3, Mish4, Mish5, Mish6, MishD, PReLU, PReLU1, PReLU2,
+    PReLU3, PReLU4, PReLU5, PReLU6, PReLUD, ReLU, ReLU2, ReLU3, ReLU4, ReLU5, ReLU6, ReLUD,
+    Sigmoid, Sigmoid2, Sigmoid3, Sigmoid4, Sigmoid5, SigmoidD, Softmax, Softmax2, Softmax3,
+    Softmax4, Softmax5, Softmax6, SoftmaxD, Softplus, Softplus1, Softplus2, Softplus3, Softplus4,
+    Softplus5, Softplus6, SoftplusD, Swish, Swish1, Swish2, Swish3, Swish4, Swish5, Swish6, SwishD,
+    Tanh, Tanh1, Tanh2, Tanh3, Tanh4, Tanh5, Tanh6, TanhD, ELU, ELU1, ELU2, ELU3, ELU4, ELU5, ELU6,
+    ELUD, GELU, GELU1, GELU2, GELU3, GELU4, GELU5, GELU6, GELUD, SELU, SELU1, SELU2, SELU3, SELU4,
+    SELU5, SELU6, SELUD,
 };
 pub use affine::Affine;
+pub use attention::{MultiHeadAttention, TransformerEncoderBlock};
 pub use batch_normalization::{call_batch_norm_layer, BatchNormalization, UseBatchNormEnum};
-pub use convolution::Convolution;
-pub use dropout::{call_dropout_layer, DropOut, UseDropoutEnum};
-pub use layer_base::{LayerBase, LossLayerBase};
+pub use convolution::{
+    Conv1d, Conv3d, ConvTranspose2d, Convolution, ConvolutionNd, DepthwiseSeparableConv,
+};
+pub use dropout::{
+    call_dropout_layer, AlphaDropout, DropConnect, DropOut, SpatialDropout, UseDropoutEnum,
+};
+pub use embedding::Embedding;
+pub use layer_base::{LayerBase, LossLayerBase, MergeLayerBase, ParamGrad};
+pub use merge::{Add, BranchLayer, Concatenate, DenseConnection, Multiply, Residual};
+pub use metric_loss::{BatchHardTripletLoss, ContrastiveLoss, CosineEmbeddingLoss, TripletLoss};
 use ndarray::{prelude::*, RemoveAxis};
-pub use pooling::{MaxPooling, MeanPooling, MinPooling};
+pub use normalization::{
+    call_normalization_layer, GroupNormalization, InstanceNormalization, LayerNormalization,
+    NormalizationEnum, NormalizationLayer,
+};
+pub use pooling::{
+    AdaptiveAvgPool, GlobalAveragePooling, GlobalMaxPooling, MaxPooling, MaxPooling4, MeanPooling,
+    MinPooling, Upsample, UpsampleModeEnum,
+};
+pub use recurrent::{GRU, LSTM, RNN};
+pub use regression_loss::{Huber, LogCosh, MeanAbsoluteError, MeanSquaredError, ReductionEnum};
+pub use reshape::{Flatten, IntoDyn, Permute, Reshape, Squeeze, Unsqueeze};
+pub use sigmoid_with_loss::{
+    SigmoidWithBCELoss, SigmoidWithBCELoss2, SigmoidWithBCELoss3, SigmoidWithBCELoss4,
+    SigmoidWithBCELoss5, SigmoidWithBCELoss6, SigmoidWithBCELossD,
+};
 pub use softmax_with_loss::{
     SoftmaxWithLoss, SoftmaxWithLoss2, SoftmaxWithLoss3, SoftmaxWithLoss4, SoftmaxWithLoss5,
     SoftmaxWithLoss6, SoftmaxWithLossD,
@@ -31,8 +70,9 @@ pub use softmax_with_loss::{
 use std::fmt::Display;
 
 /// Enum of basic layers
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub enum BasicLayerEnum {
+    #[default]
     Affine = 0,
     Convolution = 1,
 }
@@ -47,28 +87,59 @@ impl Display for BasicLayerEnum {
 }
 
 /// Enum of activators
+///
+/// Parameters of activators are given as values of variants, e.g. `{"LeakyReLU": 0.01}`.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
-pub enum ActivatorEnum {
-    Identity = 0,
-    ReLU = 1,
-    Sigmoid = 2,
-    Softmax = 3,
+pub enum ActivatorEnum<T: CrateFloat> {
+    Identity,
+    ReLU,
+    Sigmoid,
+    Softmax,
+    LogSoftmax,
+    /// slope for negative inputs
+    LeakyReLU(T),
+    /// initial value of the learnable slope for negative inputs
+    PReLU(T),
+    /// saturation value for negative inputs
+    ELU(T),
+    SELU,
+    GELU,
+    /// beta of `x * sigmoid(beta * x)`
+    Swish(T),
+    SiLU,
+    Mish,
+    Tanh,
+    Softplus,
 }
 
-impl Display for ActivatorEnum {
+impl<T> Display for ActivatorEnum<T>
+where
+    T: CrateFloat,
+{
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ActivatorEnum::Identity => write!(f, "Identity"),
             ActivatorEnum::ReLU => write!(f, "ReLU"),
             ActivatorEnum::Sigmoid => write!(f, "Sigmoid"),
             ActivatorEnum::Softmax => write!(f, "Softmax"),
+            ActivatorEnum::LogSoftmax => write!(f, "LogSoftmax"),
+            ActivatorEnum::LeakyReLU(alpha) => write!(f, "LeakyReLU (alpha: {})", alpha),
+            ActivatorEnum::PReLU(alpha) => write!(f, "PReLU (initial alpha: {})", alpha),
+            ActivatorEnum::ELU(alpha) => write!(f, "ELU (alpha: {})", alpha),
+            ActivatorEnum::SELU => write!(f, "SELU"),
+            ActivatorEnum::GELU => write!(f, "GELU"),
+            ActivatorEnum::Swish(beta) => write!(f, "Swish (beta: {})", beta),
+            ActivatorEnum::SiLU => write!(f, "SiLU"),
+            ActivatorEnum::Mish => write!(f, "Mish"),
+            ActivatorEnum::Tanh => write!(f, "Tanh"),
+            ActivatorEnum::Softplus => write!(f, "Softplus"),
         }
     }
 }
 
 /// generate an activator
 pub fn call_activator<T: 'static, D: 'static, Sh>(
-    name: ActivatorEnum,
+    name: ActivatorEnum<T>,
     shape: Sh,
     batch_axis: usize,
 ) -> Box<dyn LayerBase<T, A = Array<T, D>, B = Array<T, D>>>
@@ -78,9 +149,139 @@ where
     Sh: ShapeBuilder<Dim = D>,
 {
     match name {
-        ActivatorEnum::Identity => return Box::new(Identity::new(shape)),
-        ActivatorEnum::ReLU => return Box::new(ReLU::new(shape)),
-        ActivatorEnum::Sigmoid => return Box::new(Sigmoid::new(shape)),
-        ActivatorEnum::Softmax => return Box::new(Softmax::new(shape, batch_axis)),
+        ActivatorEnum::Identity => Box::new(Identity::new(shape)),
+        ActivatorEnum::ReLU => Box::new(ReLU::new(shape)),
+        ActivatorEnum::Sigmoid => Box::new(Sigmoid::new(shape)),
+        ActivatorEnum::Softmax => Box::new(Softmax::new(shape, batch_axis)),
+        ActivatorEnum::LogSoftmax => Box::new(LogSoftmax::new(shape, batch_axis)),
+        ActivatorEnum::LeakyReLU(alpha) => Box::new(LeakyReLU::new(shape, alpha)),
+        ActivatorEnum::PReLU(alpha) => Box::new(PReLU::new(shape, batch_axis, alpha)),
+        ActivatorEnum::ELU(alpha) => Box::new(ELU::new(shape, alpha)),
+        ActivatorEnum::SELU => Box::new(SELU::new(shape)),
+        ActivatorEnum::GELU => Box::new(GELU::new(shape)),
+        ActivatorEnum::Swish(beta) => Box::new(Swish::new(shape, beta)),
+        ActivatorEnum::SiLU => Box::new(Swish::new(shape, cast_t2u(1.0))),
+        ActivatorEnum::Mish => Box::new(Mish::new(shape)),
+        ActivatorEnum::Tanh => Box::new(Tanh::new(shape)),
+        ActivatorEnum::Softplus => Box::new(Softplus::new(shape)),
+    }
+}
+
+/// Parameters of the focal loss
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FocalParameters<T: CrateFloat> {
+    pub gamma: T,
+    pub alpha: T,
+}
+
+/// Enum of loss layers
+///
+/// Options are given as named fields, which can be omitted,
+/// e.g. `{"SoftmaxWithLoss": {"class_weights": [1.0, 3.0], "label_smoothing": 0.1}}`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum LossEnum<T: CrateFloat> {
+    SoftmaxWithLoss {
+        /// weights of classes, or empty for equal weights
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        class_weights: Vec<T>,
+        /// epsilon of label smoothing
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        label_smoothing: Option<T>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        focal: Option<FocalParameters<T>>,
+    },
+    SigmoidWithBCELoss {
+        /// weights of positive targets of classes, or empty for equal weights
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pos_weight: Vec<T>,
+    },
+    /// mean squared error for regression
+    MeanSquaredError,
+}
+
+impl<T> Default for LossEnum<T>
+where
+    T: CrateFloat,
+{
+    fn default() -> Self {
+        LossEnum::SoftmaxWithLoss {
+            class_weights: Vec::new(),
+            label_smoothing: None,
+            focal: None,
+        }
+    }
+}
+
+impl<T> Display for LossEnum<T>
+where
+    T: CrateFloat,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LossEnum::SoftmaxWithLoss {
+                class_weights,
+                label_smoothing,
+                focal,
+            } => {
+                write!(f, "SoftmaxWithLoss")?;
+                if !class_weights.is_empty() {
+                    write!(f, " (class weights: {:?})", class_weights)?;
+                }
+                if let Some(epsilon) = label_smoothing {
+                    write!(f, " (label smoothing: {})", epsilon)?;
+                }
+                if let Some(focal) = focal {
+                    write!(f, " (focal gamma: {}, alpha: {})", focal.gamma, focal.alpha)?;
+                }
+                Ok(())
+            }
+            LossEnum::SigmoidWithBCELoss { pos_weight } if pos_weight.is_empty() => {
+                write!(f, "SigmoidWithBCELoss")
+            }
+            LossEnum::SigmoidWithBCELoss { pos_weight } => {
+                write!(f, "SigmoidWithBCELoss (pos_weight: {:?})", pos_weight)
+            }
+            LossEnum::MeanSquaredError => write!(f, "MeanSquaredError"),
+        }
+    }
+}
+
+/// generate a loss layer
+pub fn call_loss_layer<T, D, Sh>(
+    loss_enum: LossEnum<T>,
+    shape: Sh,
+    batch_axis: usize,
+) -> Result<Box<dyn LossLayerBase<T, A = Array<T, D>>>, CrateError>
+where
+    T: 'static + CrateFloat,
+    D: 'static + Dimension + RemoveAxis,
+    Sh: ShapeBuilder<Dim = D>,
+{
+    match loss_enum {
+        LossEnum::SoftmaxWithLoss {
+            class_weights,
+            label_smoothing,
+            focal,
+        } => {
+            let mut layer = SoftmaxWithLoss::new(shape, batch_axis);
+            if !class_weights.is_empty() {
+                layer = layer.with_class_weights(Array1::from(class_weights))?;
+            }
+            if let Some(epsilon) = label_smoothing {
+                layer = layer.with_label_smoothing(epsilon)?;
+            }
+            if let Some(focal) = focal {
+                layer = layer.with_focal(focal.gamma, focal.alpha)?;
+            }
+            Ok(Box::new(layer))
+        }
+        LossEnum::SigmoidWithBCELoss { pos_weight } => {
+            let mut layer = SigmoidWithBCELoss::new();
+            if !pos_weight.is_empty() {
+                layer = layer.with_pos_weight(Array1::from(pos_weight))?;
+            }
+            Ok(Box::new(layer))
+        }
+        LossEnum::MeanSquaredError => Ok(Box::new(MeanSquaredError::new(ReductionEnum::Mean))),
     }
 }
@@ -0,0 +1,502 @@
+//! normalization
+//!
+//! Normalization layers computing statistics within each sample
+//!
+//! Unlike BatchNormalization, the statistics do not depend on the other samples in a batch,
+//! so the layers behave the same in training and in inference.
+
+use super::super::error::CrateError;
+use super::super::util::*;
+use super::batch_normalization::{BatchNormalization, UseBatchNormEnum};
+use super::layer_base::{param_grad, LayerBase, ParamGrad};
+use ndarray::{prelude::*, RemoveAxis, Slice, Zip};
+use std::fmt::Display;
+use std::ops::{Deref, DerefMut};
+
+const EPS: f64 = 1E-8;
+
+/// Enum of normalization layers
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum NormalizationEnum<T: CrateFloat> {
+    /// momentum of the running statistics
+    BatchNorm(T),
+    LayerNorm,
+    /// number of groups of channels
+    GroupNorm(usize),
+    InstanceNorm,
+    #[default]
+    None,
+}
+
+impl<T> Display for NormalizationEnum<T>
+where
+    T: CrateFloat,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NormalizationEnum::BatchNorm(momentum) => {
+                write!(f, "BatchNorm (momentum: {})", momentum)
+            }
+            NormalizationEnum::LayerNorm => write!(f, "LayerNorm"),
+            NormalizationEnum::GroupNorm(groups) => write!(f, "GroupNorm (groups: {})", groups),
+            NormalizationEnum::InstanceNorm => write!(f, "InstanceNorm"),
+            NormalizationEnum::None => write!(f, "None"),
+        }
+    }
+}
+
+impl<T> From<UseBatchNormEnum<T>> for NormalizationEnum<T>
+where
+    T: CrateFloat,
+{
+    fn from(use_batch_norm: UseBatchNormEnum<T>) -> Self {
+        match use_batch_norm {
+            UseBatchNormEnum::Use(momentum) => NormalizationEnum::BatchNorm(momentum),
+            UseBatchNormEnum::None => NormalizationEnum::None,
+        }
+    }
+}
+
+/// Normalization layer generated by `call_normalization_layer`
+pub type NormalizationLayer<T, D> = Box<dyn LayerBase<T, A = Array<T, D>, B = Array<T, D>>>;
+
+/// generate a normalization layer, or `None` for `NormalizationEnum::None`
+///
+/// `channel_axis` is used by GroupNorm and InstanceNorm.
+pub fn call_normalization_layer<T, D, Sh>(
+    normalization: NormalizationEnum<T>,
+    shape: Sh,
+    batch_axis: usize,
+    channel_axis: usize,
+) -> Result<Option<NormalizationLayer<T, D>>, CrateError>
+where
+    T: 'static + CrateFloat,
+    D: 'static + Dimension + RemoveAxis,
+    Sh: ShapeBuilder<Dim = D>,
+{
+    match normalization {
+        NormalizationEnum::BatchNorm(momentum) => Ok(Some(Box::new(BatchNormalization::new(
+            momentum, batch_axis, shape,
+        )))),
+        NormalizationEnum::LayerNorm => {
+            Ok(Some(Box::new(LayerNormalization::new(batch_axis, shape))))
+        }
+        NormalizationEnum::GroupNorm(groups) => Ok(Some(Box::new(GroupNormalization::new(
+            groups,
+            batch_axis,
+            channel_axis,
+            shape,
+        )?))),
+        NormalizationEnum::InstanceNorm => Ok(Some(Box::new(InstanceNormalization::new(
+            batch_axis,
+            channel_axis,
+            shape,
+        )?))),
+        NormalizationEnum::None => Ok(None),
+    }
+}
+
+/// normalize values in place and return their standard deviation
+fn normalize_in_place<T, E>(mut x: ArrayViewMut<T, E>, eps: T) -> T
+where
+    T: CrateFloat,
+    E: Dimension,
+{
+    let n: T = cast_t2u(x.len());
+    let mean: T = x.sum() / n;
+    let var: T = x.fold(cast_t2u(0.0), |acc: T, &v| acc + (v - mean) * (v - mean)) / n;
+    let std: T = (var + eps).sqrt();
+    x.mapv_inplace(|v| (v - mean) / std);
+    std
+}
+
+/// convert the gradient with respect to normalized values into the one with respect to inputs
+fn normalize_backward_in_place<T, E>(mut dxn: ArrayViewMut<T, E>, xn: ArrayView<T, E>, std: T)
+where
+    T: CrateFloat,
+    E: Dimension,
+{
+    let n: T = cast_t2u(dxn.len());
+    let mean_dxn: T = dxn.sum() / n;
+    let mean_dxn_xn: T = Zip::from(&dxn)
+        .and(&xn)
+        .fold(cast_t2u(0.0), |acc: T, &d, &v| acc + d * v)
+        / n;
+    Zip::from(&mut dxn)
+        .and(&xn)
+        .for_each(|d, &v| *d = (*d - mean_dxn - v * mean_dxn_xn) / std);
+}
+
+// >>>>>>>>>>>>> LayerNormalization layer >>>>>>>>>>>>>
+
+/// LayerNormalization
+///
+/// Each sample is normalized over all of the axes except the batch axis,
+/// and gamma and beta are given for each element of a sample.
+///
+/// See https://arxiv.org/abs/1607.06450 in detail
+#[derive(Clone, Debug)]
+pub struct LayerNormalization<T: CrateFloat, D: Dimension + RemoveAxis> {
+    batch_axis: usize,
+    pub gamma: Array<T, D::Smaller>,
+    pub beta: Array<T, D::Smaller>,
+    pub dgamma: Array<T, D::Smaller>,
+    pub dbeta: Array<T, D::Smaller>,
+    xn: Array<T, D>,
+    std: Vec<T>,
+    eps: T,
+}
+
+impl<T: 'static, D> LayerNormalization<T, D>
+where
+    T: CrateFloat,
+    D: Dimension + RemoveAxis,
+{
+    pub fn new<Sh>(batch_axis: usize, shape: Sh) -> Self
+    where
+        Sh: ShapeBuilder<Dim = D>,
+    {
+        let xn: Array<T, D> = Array::<T, D>::zeros(shape);
+        let zeros: Array<T, D::Smaller> =
+            Array::<T, D::Smaller>::zeros(xn.raw_dim().remove_axis(Axis(batch_axis)));
+        Self {
+            batch_axis,
+            gamma: Array::ones(zeros.raw_dim()),
+            beta: zeros.clone(),
+            dgamma: zeros.clone(),
+            dbeta: zeros,
+            xn,
+            std: Vec::new(),
+            eps: cast_t2u(EPS),
+        }
+    }
+}
+
+impl<T: 'static, D: 'static> LayerBase<T> for LayerNormalization<T, D>
+where
+    T: CrateFloat,
+    D: Dimension + RemoveAxis,
+{
+    type A = Array<T, D>;
+    type B = Array<T, D>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        let eps: T = self.eps;
+        let mut xn: Self::B = x.clone();
+        self.std = xn
+            .axis_iter_mut(Axis(self.batch_axis))
+            .map(|sample| normalize_in_place(sample, eps))
+            .collect();
+        self.xn = xn.clone();
+        let mut y: Self::B = xn;
+        for mut sample in y.axis_iter_mut(Axis(self.batch_axis)) {
+            Zip::from(&mut sample)
+                .and(&self.gamma)
+                .and(&self.beta)
+                .for_each(|v, &gamma, &beta| *v = *v * gamma + beta);
+        }
+        y
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        self.dbeta = dx.sum_axis(Axis(self.batch_axis));
+        self.dgamma = (dx * &self.xn).sum_axis(Axis(self.batch_axis));
+        let mut dxn: Self::A = dx.clone();
+        for ((mut d, xn), &std) in dxn
+            .axis_iter_mut(Axis(self.batch_axis))
+            .zip(self.xn.axis_iter(Axis(self.batch_axis)))
+            .zip(self.std.iter())
+        {
+            Zip::from(&mut d)
+                .and(&self.gamma)
+                .for_each(|v, &gamma| *v *= gamma);
+            normalize_backward_in_place(d, xn, std);
+        }
+        dxn
+    }
+    fn update(&mut self, lr: T) {
+        self.gamma.scaled_add(-lr, &self.dgamma);
+        self.beta.scaled_add(-lr, &self.dbeta);
+    }
+    fn parameters(&mut self) -> Vec<ParamGrad<'_, T>> {
+        vec![
+            param_grad(&mut self.gamma, &self.dgamma),
+            param_grad(&mut self.beta, &self.dbeta),
+        ]
+    }
+    fn print_detail(&self) {
+        println!("layer normalization layer.");
+        println!("gamma shape: {:?}", self.gamma.shape());
+        println!("beta shape: {:?}", self.beta.shape());
+    }
+    fn print_parameters(&self) {
+        println!("gamma: {:?}", self.gamma);
+        println!("beta: {:?}", self.beta);
+        println!("dgamma: {:?}", self.dgamma);
+        println!("dbeta: {:?}", self.dbeta);
+    }
+}
+
+pub type LayerNormalization2<T> = LayerNormalization<T, Ix2>;
+pub type LayerNormalization3<T> = LayerNormalization<T, Ix3>;
+pub type LayerNormalization4<T> = LayerNormalization<T, Ix4>;
+pub type LayerNormalization5<T> = LayerNormalization<T, Ix5>;
+pub type LayerNormalization6<T> = LayerNormalization<T, Ix6>;
+pub type LayerNormalizationD<T> = LayerNormalization<T, IxDyn>;
+
+// <<<<<<<<<<<<< LayerNormalization layer <<<<<<<<<<<<<
+
+// >>>>>>>>>>>>> GroupNormalization layer >>>>>>>>>>>>>
+
+/// GroupNormalization
+///
+/// Channels are divided into groups, and each group of each sample is normalized
+/// over its channels and the remaining axes. Gamma and beta are given for each channel.
+///
+/// See https://arxiv.org/abs/1803.08494 in detail
+#[derive(Clone, Debug)]
+pub struct GroupNormalization<T: CrateFloat, D: Dimension + RemoveAxis> {
+    groups: usize,
+    batch_axis: usize,
+    channel_axis: usize,
+    pub gamma: Array1<T>,
+    pub beta: Array1<T>,
+    pub dgamma: Array1<T>,
+    pub dbeta: Array1<T>,
+    xn: Array<T, D>,
+    std: Array2<T>,
+    eps: T,
+}
+
+impl<T: 'static, D> GroupNormalization<T, D>
+where
+    T: CrateFloat,
+    D: Dimension + RemoveAxis,
+{
+    pub fn new<Sh>(
+        groups: usize,
+        batch_axis: usize,
+        channel_axis: usize,
+        shape: Sh,
+    ) -> Result<Self, CrateError>
+    where
+        Sh: ShapeBuilder<Dim = D>,
+    {
+        let xn: Array<T, D> = Array::<T, D>::zeros(shape);
+        if batch_axis >= xn.ndim() || channel_axis >= xn.ndim() || batch_axis == channel_axis {
+            return Err(CrateError::ShapeError(format!(
+                "the batch axis {} and the channel axis {} must be different axes of {:?}.",
+                batch_axis,
+                channel_axis,
+                xn.shape()
+            )));
+        }
+        let channels: usize = xn.len_of(Axis(channel_axis));
+        if groups == 0 || !channels.is_multiple_of(groups) {
+            return Err(CrateError::ConfigError(format!(
+                "the number of groups must divide the number of channels {}, found {}.",
+                channels, groups
+            )));
+        }
+        Ok(Self {
+            groups,
+            batch_axis,
+            channel_axis,
+            gamma: Array1::ones(channels),
+            beta: Array1::zeros(channels),
+            dgamma: Array1::zeros(channels),
+            dbeta: Array1::zeros(channels),
+            xn,
+            std: Array2::zeros((0, groups)),
+            eps: cast_t2u(EPS),
+        })
+    }
+    /// channel axis within a sample, i.e., after the batch axis is removed
+    fn sample_channel_axis(&self) -> Axis {
+        if self.channel_axis > self.batch_axis {
+            Axis(self.channel_axis - 1)
+        } else {
+            Axis(self.channel_axis)
+        }
+    }
+    /// channels of the `group`-th group
+    fn group_slice(&self, group: usize) -> Slice {
+        let size: usize = self.gamma.len() / self.groups;
+        Slice::from(group * size..(group + 1) * size)
+    }
+}
+
+impl<T: 'static, D: 'static> LayerBase<T> for GroupNormalization<T, D>
+where
+    T: CrateFloat,
+    D: Dimension + RemoveAxis,
+{
+    type A = Array<T, D>;
+    type B = Array<T, D>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        let axis: Axis = self.sample_channel_axis();
+        let mut xn: Self::B = x.clone();
+        let mut std: Array2<T> = Array2::zeros((x.len_of(Axis(self.batch_axis)), self.groups));
+        for (mut sample, mut std) in xn
+            .axis_iter_mut(Axis(self.batch_axis))
+            .zip(std.outer_iter_mut())
+        {
+            for (group, s) in std.iter_mut().enumerate() {
+                let group: Slice = self.group_slice(group);
+                *s = normalize_in_place(sample.slice_axis_mut(axis, group), self.eps);
+            }
+        }
+        self.xn = xn.clone();
+        self.std = std;
+        let mut y: Self::B = xn;
+        for (c, mut channel) in y.axis_iter_mut(Axis(self.channel_axis)).enumerate() {
+            let (gamma, beta) = (self.gamma[c], self.beta[c]);
+            channel.mapv_inplace(|v| v * gamma + beta);
+        }
+        y
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        let axis: Axis = self.sample_channel_axis();
+        let mut dxn: Self::A = dx.clone();
+        for (c, (mut d, xn)) in dxn
+            .axis_iter_mut(Axis(self.channel_axis))
+            .zip(self.xn.axis_iter(Axis(self.channel_axis)))
+            .enumerate()
+        {
+            self.dbeta[c] = d.sum();
+            self.dgamma[c] = Zip::from(&d)
+                .and(&xn)
+                .fold(cast_t2u(0.0), |acc: T, &v, &w| acc + v * w);
+            let gamma: T = self.gamma[c];
+            d.mapv_inplace(|v| v * gamma);
+        }
+        for ((mut d, xn), std) in dxn
+            .axis_iter_mut(Axis(self.batch_axis))
+            .zip(self.xn.axis_iter(Axis(self.batch_axis)))
+            .zip(self.std.outer_iter())
+        {
+            for (group, &s) in std.iter().enumerate() {
+                let group: Slice = self.group_slice(group);
+                normalize_backward_in_place(
+                    d.slice_axis_mut(axis, group),
+                    xn.slice_axis(axis, group),
+                    s,
+                );
+            }
+        }
+        dxn
+    }
+    fn update(&mut self, lr: T) {
+        self.gamma.scaled_add(-lr, &self.dgamma);
+        self.beta.scaled_add(-lr, &self.dbeta);
+    }
+    fn parameters(&mut self) -> Vec<ParamGrad<'_, T>> {
+        vec![
+            param_grad(&mut self.gamma, &self.dgamma),
+            param_grad(&mut self.beta, &self.dbeta),
+        ]
+    }
+    fn print_detail(&self) {
+        println!("group normalization layer.");
+        println!("groups: {}", self.groups);
+        println!("gamma shape: {:?}", self.gamma.shape());
+        println!("beta shape: {:?}", self.beta.shape());
+    }
+    fn print_parameters(&self) {
+        println!("gamma: {:?}", self.gamma);
+        println!("beta: {:?}", self.beta);
+        println!("dgamma: {:?}", self.dgamma);
+        println!("dbeta: {:?}", self.dbeta);
+    }
+}
+
+pub type GroupNormalization2<T> = GroupNormalization<T, Ix2>;
+pub type GroupNormalization3<T> = GroupNormalization<T, Ix3>;
+pub type GroupNormalization4<T> = GroupNormalization<T, Ix4>;
+pub type GroupNormalization5<T> = GroupNormalization<T, Ix5>;
+pub type GroupNormalization6<T> = GroupNormalization<T, Ix6>;
+pub type GroupNormalizationD<T> = GroupNormalization<T, IxDyn>;
+
+// <<<<<<<<<<<<< GroupNormalization layer <<<<<<<<<<<<<
+
+// >>>>>>>>>>>>> InstanceNormalization layer >>>>>>>>>>>>>
+
+/// InstanceNormalization
+///
+/// GroupNormalization with a group for each channel, i.e., each channel of each sample
+/// is normalized over the remaining axes. The parameters are accessed through `Deref`.
+///
+/// See https://arxiv.org/abs/1607.08022 in detail
+#[derive(Clone, Debug)]
+pub struct InstanceNormalization<T: CrateFloat, D: Dimension + RemoveAxis> {
+    group_norm: GroupNormalization<T, D>,
+}
+
+impl<T: 'static, D> InstanceNormalization<T, D>
+where
+    T: CrateFloat,
+    D: Dimension + RemoveAxis,
+{
+    pub fn new<Sh>(batch_axis: usize, channel_axis: usize, shape: Sh) -> Result<Self, CrateError>
+    where
+        Sh: ShapeBuilder<Dim = D>,
+    {
+        let x: Array<T, D> = Array::<T, D>::zeros(shape);
+        let channels: usize = if channel_axis < x.ndim() {
+            x.len_of(Axis(channel_axis))
+        } else {
+            1
+        };
+        Ok(Self {
+            group_norm: GroupNormalization::new(channels, batch_axis, channel_axis, x.raw_dim())?,
+        })
+    }
+}
+
+impl<T: CrateFloat, D: Dimension + RemoveAxis> Deref for InstanceNormalization<T, D> {
+    type Target = GroupNormalization<T, D>;
+    fn deref(&self) -> &Self::Target {
+        &self.group_norm
+    }
+}
+
+impl<T: CrateFloat, D: Dimension + RemoveAxis> DerefMut for InstanceNormalization<T, D> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.group_norm
+    }
+}
+
+impl<T: 'static, D: 'static> LayerBase<T> for InstanceNormalization<T, D>
+where
+    T: CrateFloat,
+    D: Dimension + RemoveAxis,
+{
+    type A = Array<T, D>;
+    type B = Array<T, D>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        self.group_norm.forward(x)
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        self.group_norm.backward(dx)
+    }
+    fn update(&mut self, lr: T) {
+        self.group_norm.update(lr);
+    }
+    fn parameters(&mut self) -> Vec<ParamGrad<'_, T>> {
+        self.group_norm.parameters()
+    }
+    fn print_detail(&self) {
+        println!("instance normalization layer.");
+        println!("gamma shape: {:?}", self.gamma.shape());
+        println!("beta shape: {:?}", self.beta.shape());
+    }
+    fn print_parameters(&self) {
+        self.group_norm.print_parameters();
+    }
+}
+
+pub type InstanceNormalization3<T> = InstanceNormalization<T, Ix3>;
+pub type InstanceNormalization4<T> = InstanceNormalization<T, Ix4>;
+pub type InstanceNormalization5<T> = InstanceNormalization<T, Ix5>;
+pub type InstanceNormalization6<T> = InstanceNormalization<T, Ix6>;
+pub type InstanceNormalizationD<T> = InstanceNormalization<T, IxDyn>;
+
+// <<<<<<<<<<<<< InstanceNormalization layer <<<<<<<<<<<<<
@@ -6,24 +6,115 @@
 #![allow(unused_variables)]
 #![allow(unused_imports)]
 
-use super::super::util::CrateFloat;
+use super::super::im2col::*;
+use super::super::util::{cast_t2u, CrateFloat};
+use super::layer_base::LayerBase;
 use ndarray::prelude::*;
+use std::fmt::Display;
 
 /// MaxPooling
+///
+/// Images to pool must be "channel-first".
 pub struct MaxPooling<T: CrateFloat, D> {
     pool_height: usize,
     pool_width: usize,
     stride: usize,
     pad_size: usize,
     x: Array<T, D>,
-    arg: Array<T, D>,
+    arg: Array1<usize>,
 }
 
-impl<T, D> MaxPooling<T, D>
+pub type MaxPooling4<T> = MaxPooling<T, Ix4>;
+
+impl<T> MaxPooling4<T>
 where
     T: CrateFloat,
-    D: Dimension,
 {
+    pub fn new(pool_height: usize, pool_width: usize, stride: usize, pad_size: usize) -> Self {
+        Self {
+            pool_height,
+            pool_width,
+            stride,
+            pad_size,
+            x: Array4::zeros((1, 1, 1, 1)),
+            arg: Array1::zeros(1),
+        }
+    }
+}
+
+impl<T: 'static> LayerBase<T> for MaxPooling4<T>
+where
+    T: CrateFloat,
+{
+    type A = Array4<T>;
+    type B = Array4<T>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        let (n, c, h, w) = x.dim();
+        let output_h: usize = output_size(h, self.pool_height, self.stride, self.pad_size);
+        let output_w: usize = output_size(w, self.pool_width, self.stride, self.pad_size);
+        // padded elements must never be chosen as the maximum
+        let img: Array4<T> = pad_images(x, self.pad_size, T::neg_infinity());
+        let col: Array2<T> = im2col(
+            &img,
+            (self.pool_height, self.pool_width),
+            (self.stride, self.stride),
+            0,
+        )
+        .into_shape((
+            n * output_h * output_w * c,
+            self.pool_height * self.pool_width,
+        ))
+        .unwrap();
+        let mut dst: Array1<T> = Array1::zeros(col.len_of(Axis(0)));
+        self.arg = Array1::zeros(col.len_of(Axis(0)));
+        for ((view, d), a) in col
+            .axis_iter(Axis(0))
+            .zip(dst.iter_mut())
+            .zip(self.arg.iter_mut())
+        {
+            for (ii, &v) in view.iter().enumerate() {
+                if v > view[*a] {
+                    *a = ii;
+                }
+            }
+            *d = view[*a];
+        }
+        self.x = x.clone();
+        dst.into_shape((n, output_h, output_w, c))
+            .unwrap()
+            .permuted_axes([0, 3, 1, 2])
+            .as_standard_layout()
+            .into_owned()
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        let (n, c, output_h, output_w) = dx.dim();
+        let pool_size: usize = self.pool_height * self.pool_width;
+        let dout = dx.view().permuted_axes([0, 2, 3, 1]);
+        let mut dmax: Array2<T> = Array2::zeros((dx.len(), pool_size));
+        for ((mut view, &d), &a) in dmax
+            .axis_iter_mut(Axis(0))
+            .zip(dout.iter())
+            .zip(self.arg.iter())
+        {
+            view[a] = d;
+        }
+        let dcol: Array2<T> = dmax
+            .into_shape((n * output_h * output_w, c * pool_size))
+            .unwrap();
+        col2im(
+            &dcol,
+            self.x.dim(),
+            (self.pool_height, self.pool_width),
+            (self.stride, self.stride),
+            self.pad_size,
+        )
+    }
+    fn print_detail(&self) {
+        println!("max pooling layer.");
+        println!("pool shape: ({}, {})", self.pool_height, self.pool_width);
+        println!("stride size: {}", self.stride);
+        println!("pad size: {}", self.pad_size);
+    }
 }
 
 /// MinPooling
@@ -59,3 +150,317 @@ where
     D: Dimension,
 {
 }
+
+/// apply `left.dot(image).dot(&right.t())` to each (height, width) image of (N, C, H, W) arrays
+///
+/// Layers whose outputs are linear combinations along height and width separately,
+/// e.g. adaptive average pooling and upsampling, are given by such matrices,
+/// and their backward applies the transposed matrices.
+fn apply_separable<T>(x: &Array4<T>, left: &Array2<T>, right: &Array2<T>) -> Array4<T>
+where
+    T: CrateFloat,
+{
+    let (n, c, _, _) = x.dim();
+    let mut dst: Array4<T> = Array4::zeros((n, c, left.nrows(), right.nrows()));
+    for ni in 0..n {
+        for ci in 0..c {
+            dst.slice_mut(s![ni, ci, .., ..])
+                .assign(&left.dot(&x.slice(s![ni, ci, .., ..])).dot(&right.t()));
+        }
+    }
+    dst
+}
+
+/// GlobalAveragePooling
+///
+/// (N, C, H, W) feature maps are averaged into (N, C) arrays.
+pub struct GlobalAveragePooling<T: CrateFloat> {
+    data_shape: (usize, usize),
+    _phantom: std::marker::PhantomData<T>,
+}
+
+impl<T> GlobalAveragePooling<T>
+where
+    T: CrateFloat,
+{
+    pub fn new() -> Self {
+        Self {
+            data_shape: (0, 0),
+            _phantom: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<T> Default for GlobalAveragePooling<T>
+where
+    T: CrateFloat,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: 'static> LayerBase<T> for GlobalAveragePooling<T>
+where
+    T: CrateFloat,
+{
+    type A = Array4<T>;
+    type B = Array2<T>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        let (_, _, h, w) = x.dim();
+        self.data_shape = (h, w);
+        let area: T = cast_t2u(h * w);
+        x.sum_axis(Axis(3)).sum_axis(Axis(2)).mapv(|v| v / area)
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        let (n, c) = dx.dim();
+        let (h, w) = self.data_shape;
+        let area: T = cast_t2u(h * w);
+        dx.mapv(|v| v / area)
+            .into_shape((n, c, 1, 1))
+            .unwrap()
+            .broadcast((n, c, h, w))
+            .unwrap()
+            .to_owned()
+    }
+    fn print_detail(&self) {
+        println!("global average pooling layer.");
+    }
+}
+
+/// GlobalMaxPooling
+///
+/// The maximum of each of (N, C, H, W) feature maps is taken into (N, C) arrays.
+pub struct GlobalMaxPooling<T: CrateFloat> {
+    data_shape: (usize, usize),
+    arg: Array2<usize>,
+    _phantom: std::marker::PhantomData<T>,
+}
+
+impl<T> GlobalMaxPooling<T>
+where
+    T: CrateFloat,
+{
+    pub fn new() -> Self {
+        Self {
+            data_shape: (0, 0),
+            arg: Array2::zeros((0, 0)),
+            _phantom: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<T> Default for GlobalMaxPooling<T>
+where
+    T: CrateFloat,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: 'static> LayerBase<T> for GlobalMaxPooling<T>
+where
+    T: CrateFloat,
+{
+    type A = Array4<T>;
+    type B = Array2<T>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        let (n, c, h, w) = x.dim();
+        self.data_shape = (h, w);
+        let x = x.as_standard_layout();
+        let maps = x.to_shape((n, c, h * w)).unwrap();
+        let mut dst: Array2<T> = Array2::zeros((n, c));
+        self.arg = Array2::zeros((n, c));
+        for ((map, d), a) in maps
+            .lanes(Axis(2))
+            .into_iter()
+            .zip(dst.iter_mut())
+            .zip(self.arg.iter_mut())
+        {
+            for (ii, &v) in map.iter().enumerate() {
+                if v > map[*a] {
+                    *a = ii;
+                }
+            }
+            *d = map[*a];
+        }
+        dst
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        let (n, c) = dx.dim();
+        let (h, w) = self.data_shape;
+        let mut dst: Array3<T> = Array3::zeros((n, c, h * w));
+        for (((ni, ci), &d), &a) in dx.indexed_iter().zip(self.arg.iter()) {
+            dst[[ni, ci, a]] = d;
+        }
+        dst.into_shape((n, c, h, w)).unwrap()
+    }
+    fn print_detail(&self) {
+        println!("global max pooling layer.");
+    }
+}
+
+/// matrix averaging `input_size` values into `output_size` bins
+///
+/// Bin `i` covers `[floor(i * input_size / output_size), ceil((i + 1) * input_size / output_size))`,
+/// so bins may overlap when the sizes are not divisible.
+fn adaptive_average_matrix<T>(input_size: usize, output_size: usize) -> Array2<T>
+where
+    T: CrateFloat,
+{
+    let mut dst: Array2<T> = Array2::zeros((output_size, input_size));
+    for (i, mut row) in dst.outer_iter_mut().enumerate() {
+        let start: usize = i * input_size / output_size;
+        let end: usize = ((i + 1) * input_size).div_ceil(output_size);
+        let len: T = cast_t2u(end - start);
+        row.slice_mut(s![start..end])
+            .fill(cast_t2u::<f64, T>(1.0) / len);
+    }
+    dst
+}
+
+/// AdaptiveAvgPool
+///
+/// (N, C, H, W) feature maps are averaged into (N, C, OH, OW) ones of a fixed output size
+/// whatever the input size is.
+pub struct AdaptiveAvgPool<T: CrateFloat> {
+    output_shape: (usize, usize),
+    left: Array2<T>,
+    right: Array2<T>,
+}
+
+impl<T> AdaptiveAvgPool<T>
+where
+    T: CrateFloat,
+{
+    /// `output_shape` is (height, width).
+    pub fn new(output_shape: (usize, usize)) -> Self {
+        Self {
+            output_shape,
+            left: Array2::zeros((output_shape.0, 0)),
+            right: Array2::zeros((output_shape.1, 0)),
+        }
+    }
+}
+
+impl<T: 'static> LayerBase<T> for AdaptiveAvgPool<T>
+where
+    T: CrateFloat,
+{
+    type A = Array4<T>;
+    type B = Array4<T>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        let (_, _, h, w) = x.dim();
+        if self.left.ncols() != h || self.right.ncols() != w {
+            self.left = adaptive_average_matrix(h, self.output_shape.0);
+            self.right = adaptive_average_matrix(w, self.output_shape.1);
+        }
+        apply_separable(x, &self.left, &self.right)
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        apply_separable(dx, &self.left.t().to_owned(), &self.right.t().to_owned())
+    }
+    fn print_detail(&self) {
+        println!("adaptive average pooling layer.");
+        println!(
+            "output shape: ({}, {})",
+            self.output_shape.0, self.output_shape.1
+        );
+    }
+}
+
+/// Enum of upsampling modes
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum UpsampleModeEnum {
+    /// copy the nearest value
+    #[default]
+    Nearest,
+    /// interpolate the two nearest values along each axis, with the centers of pixels aligned
+    Bilinear,
+}
+
+impl Display for UpsampleModeEnum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UpsampleModeEnum::Nearest => write!(f, "Nearest"),
+            UpsampleModeEnum::Bilinear => write!(f, "Bilinear"),
+        }
+    }
+}
+
+/// matrix upsampling `input_size` values by `scale`
+fn upsample_matrix<T>(input_size: usize, scale: usize, mode: &UpsampleModeEnum) -> Array2<T>
+where
+    T: CrateFloat,
+{
+    let mut dst: Array2<T> = Array2::zeros((input_size * scale, input_size));
+    if input_size == 0 {
+        // an empty axis stays empty, and has no neighbors to interpolate
+        return dst;
+    }
+    for (i, mut row) in dst.outer_iter_mut().enumerate() {
+        match mode {
+            UpsampleModeEnum::Nearest => row[i / scale] = cast_t2u(1.0),
+            UpsampleModeEnum::Bilinear => {
+                // center of the output pixel in the input coordinates
+                let src: f64 = ((i as f64 + 0.5) / scale as f64 - 0.5).max(0.0);
+                let i0: usize = (src.floor() as usize).min(input_size - 1);
+                let i1: usize = (i0 + 1).min(input_size - 1);
+                let lambda: f64 = src - i0 as f64;
+                row[i0] += cast_t2u(1.0 - lambda);
+                row[i1] += cast_t2u(lambda);
+            }
+        }
+    }
+    dst
+}
+
+/// Upsample
+///
+/// (N, C, H, W) feature maps are enlarged into (N, C, H * SH, W * SW) ones by integer scales.
+pub struct Upsample<T: CrateFloat> {
+    scales: (usize, usize),
+    mode: UpsampleModeEnum,
+    left: Array2<T>,
+    right: Array2<T>,
+}
+
+impl<T> Upsample<T>
+where
+    T: CrateFloat,
+{
+    /// `scales` is (height, width).
+    pub fn new(scales: (usize, usize), mode: UpsampleModeEnum) -> Self {
+        Self {
+            scales,
+            mode,
+            left: Array2::zeros((0, 0)),
+            right: Array2::zeros((0, 0)),
+        }
+    }
+}
+
+impl<T: 'static> LayerBase<T> for Upsample<T>
+where
+    T: CrateFloat,
+{
+    type A = Array4<T>;
+    type B = Array4<T>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        let (_, _, h, w) = x.dim();
+        if self.left.ncols() != h || self.right.ncols() != w {
+            self.left = upsample_matrix(h, self.scales.0, &self.mode);
+            self.right = upsample_matrix(w, self.scales.1, &self.mode);
+        }
+        apply_separable(x, &self.left, &self.right)
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        apply_separable(dx, &self.left.t().to_owned(), &self.right.t().to_owned())
+    }
+    fn print_detail(&self) {
+        println!("upsampling layer.");
+        println!("scales: ({}, {})", self.scales.0, self.scales.1);
+        println!("mode: {}", self.mode);
+    }
+}
@@ -0,0 +1,621 @@
+//! recurrent
+//!
+//! Recurrent layers
+//!
+//! Inputs are (batch, time, features) arrays. The outputs are the hidden states of every
+//! time step if `return_sequences` is set, or else (batch, 1, hidden) arrays of the last state,
+//! which can be fed to `Affine` through `index_axis(Axis(1), 0)`.
+//!
+//! Gradients are propagated through the whole sequence unless the number of steps of
+//! truncated BPTT is given by `set_bptt_steps`. A stateful layer carries its last state over
+//! to the next batch without propagating gradients into the previous batch.
+
+use super::super::param_initializers::weight_init::{initialize_weight, WeightInitEnum};
+use super::super::util::*;
+use super::layer_base::LayerBase;
+use ndarray::{concatenate, prelude::*};
+
+fn sigmoid<T: CrateFloat>(v: T) -> T {
+    let one: T = cast_t2u(1.0);
+    one / (one + T::exp(-v))
+}
+
+/// whether gradients through the hidden state are cut before time step `t`
+fn is_truncated(t: usize, bptt_steps: Option<usize>) -> bool {
+    match bptt_steps {
+        Some(steps) => t > 0 && t.is_multiple_of(steps),
+        None => false,
+    }
+}
+
+/// gradient given to the hidden state of time step `t` from the output
+fn output_grad<T>(dx: &Array3<T>, t: usize, time: usize, return_sequences: bool) -> Array2<T>
+where
+    T: CrateFloat,
+{
+    if return_sequences {
+        dx.index_axis(Axis(1), t).to_owned()
+    } else if t + 1 == time {
+        dx.index_axis(Axis(1), 0).to_owned()
+    } else {
+        Array2::zeros((dx.len_of(Axis(0)), dx.len_of(Axis(2))))
+    }
+}
+
+/// outputs selected from the hidden states of time steps 1..=time
+fn output_of<T>(hs: &Array3<T>, return_sequences: bool) -> Array3<T>
+where
+    T: CrateFloat,
+{
+    let time: usize = hs.len_of(Axis(1)) - 1;
+    if return_sequences {
+        hs.slice(s![.., 1.., ..]).to_owned()
+    } else {
+        hs.slice(s![.., time..=time, ..]).to_owned()
+    }
+}
+
+/// initial state of a batch: the carried state of a stateful layer, or zeros
+fn initial_state<T>(state: &Option<Array2<T>>, batch_size: usize, hidden_size: usize) -> Array2<T>
+where
+    T: CrateFloat,
+{
+    match state {
+        Some(state) if state.dim() == (batch_size, hidden_size) => state.clone(),
+        _ => Array2::zeros((batch_size, hidden_size)),
+    }
+}
+
+// >>>>>>>>>>>>> RNN layer >>>>>>>>>>>>>
+
+/// Vanilla RNN layer, `h_t = tanh(x_t W_x + h_{t-1} W_h + b)`
+pub struct RNN<T: CrateFloat> {
+    pub weight_x: Array2<T>,
+    pub weight_h: Array2<T>,
+    pub bias: Array1<T>,
+    pub dwx: Array2<T>,
+    pub dwh: Array2<T>,
+    pub db: Array1<T>,
+    return_sequences: bool,
+    stateful: bool,
+    bptt_steps: Option<usize>,
+    state: Option<Array2<T>>,
+    xs: Array3<T>,
+    hs: Array3<T>,
+}
+
+impl<T: 'static> RNN<T>
+where
+    T: CrateFloat,
+{
+    pub fn new(
+        input_size: usize,
+        hidden_size: usize,
+        return_sequences: bool,
+        weight_init: WeightInitEnum,
+        weight_init_std: T,
+    ) -> Self {
+        Self::from(
+            &initialize_weight(
+                weight_init.clone(),
+                weight_init_std,
+                (input_size, hidden_size),
+            ),
+            &initialize_weight(weight_init, weight_init_std, (hidden_size, hidden_size)),
+            &Array1::zeros(hidden_size),
+            return_sequences,
+        )
+    }
+    pub fn from(
+        weight_x: &Array2<T>,
+        weight_h: &Array2<T>,
+        bias: &Array1<T>,
+        return_sequences: bool,
+    ) -> Self {
+        Self {
+            dwx: Array2::zeros(weight_x.raw_dim()),
+            dwh: Array2::zeros(weight_h.raw_dim()),
+            db: Array1::zeros(bias.raw_dim()),
+            weight_x: weight_x.clone(),
+            weight_h: weight_h.clone(),
+            bias: bias.clone(),
+            return_sequences,
+            stateful: false,
+            bptt_steps: None,
+            state: None,
+            xs: Array3::zeros((0, 0, 0)),
+            hs: Array3::zeros((0, 0, 0)),
+        }
+    }
+    /// carry the last state over to the next batch
+    pub fn set_stateful(&mut self, flag: bool) {
+        self.stateful = flag;
+        self.state = None;
+    }
+    /// number of time steps of truncated BPTT, or `None` for full BPTT
+    pub fn set_bptt_steps(&mut self, bptt_steps: Option<usize>) {
+        self.bptt_steps = bptt_steps;
+    }
+    /// forget the carried state
+    pub fn reset_state(&mut self) {
+        self.state = None;
+    }
+}
+
+impl<T: 'static> LayerBase<T> for RNN<T>
+where
+    T: CrateFloat,
+{
+    type A = Array3<T>;
+    type B = Array3<T>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        let (batch_size, time, _) = x.dim();
+        let hidden_size: usize = self.weight_h.nrows();
+        let mut hs: Array3<T> = Array3::zeros((batch_size, time + 1, hidden_size));
+        hs.index_axis_mut(Axis(1), 0)
+            .assign(&initial_state(&self.state, batch_size, hidden_size));
+        for t in 0..time {
+            let a: Array2<T> = x.index_axis(Axis(1), t).dot(&self.weight_x)
+                + hs.index_axis(Axis(1), t).dot(&self.weight_h)
+                + &self.bias;
+            hs.index_axis_mut(Axis(1), t + 1)
+                .assign(&a.map(|&v| v.tanh()));
+        }
+        if self.stateful {
+            self.state = Some(hs.index_axis(Axis(1), time).to_owned());
+        }
+        self.xs = x.clone();
+        self.hs = hs;
+        output_of(&self.hs, self.return_sequences)
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        let one: T = cast_t2u(1.0);
+        let (batch_size, time, _) = self.xs.dim();
+        let mut dst: Self::A = Array3::zeros(self.xs.raw_dim());
+        let mut dh_next: Array2<T> = Array2::zeros((batch_size, self.weight_h.nrows()));
+        self.dwx.fill(cast_t2u(0.0));
+        self.dwh.fill(cast_t2u(0.0));
+        self.db.fill(cast_t2u(0.0));
+        for t in (0..time).rev() {
+            let dh: Array2<T> = output_grad(dx, t, time, self.return_sequences) + &dh_next;
+            let h: ArrayView2<T> = self.hs.index_axis(Axis(1), t + 1);
+            let da: Array2<T> = dh * &h.map(|&v| one - v * v);
+            self.dwx += &self.xs.index_axis(Axis(1), t).t().dot(&da);
+            self.dwh += &self.hs.index_axis(Axis(1), t).t().dot(&da);
+            self.db += &da.sum_axis(Axis(0));
+            dst.index_axis_mut(Axis(1), t)
+                .assign(&da.dot(&self.weight_x.t()));
+            dh_next = da.dot(&self.weight_h.t());
+            if is_truncated(t, self.bptt_steps) {
+                dh_next.fill(cast_t2u(0.0));
+            }
+        }
+        dst
+    }
+    fn update(&mut self, lr: T) {
+        self.weight_x.scaled_add(-lr, &self.dwx);
+        self.weight_h.scaled_add(-lr, &self.dwh);
+        self.bias.scaled_add(-lr, &self.db);
+    }
+    fn print_detail(&self) {
+        println!("RNN layer.");
+        println!("weight_x shape: {:?}", self.weight_x.shape());
+        println!("weight_h shape: {:?}", self.weight_h.shape());
+        println!("return sequences: {}", self.return_sequences);
+    }
+    fn print_parameters(&self) {
+        println!("weight_x: {:?}", self.weight_x);
+        println!("weight_h: {:?}", self.weight_h);
+        println!("bias: {:?}", self.bias);
+        println!("dwx: {:?}", self.dwx);
+        println!("dwh: {:?}", self.dwh);
+        println!("db: {:?}", self.db);
+    }
+}
+
+// <<<<<<<<<<<<< RNN layer <<<<<<<<<<<<<
+
+// >>>>>>>>>>>>> LSTM layer >>>>>>>>>>>>>
+
+/// LSTM layer
+///
+/// The weights are the concatenation of the forget gate, the cell candidate,
+/// the input gate and the output gate in this order.
+///
+/// See https://www.bioinf.jku.at/publications/older/2604.pdf in detail
+pub struct LSTM<T: CrateFloat> {
+    pub weight_x: Array2<T>,
+    pub weight_h: Array2<T>,
+    pub bias: Array1<T>,
+    pub dwx: Array2<T>,
+    pub dwh: Array2<T>,
+    pub db: Array1<T>,
+    return_sequences: bool,
+    stateful: bool,
+    bptt_steps: Option<usize>,
+    state: Option<Array2<T>>,
+    cell_state: Option<Array2<T>>,
+    xs: Array3<T>,
+    hs: Array3<T>,
+    cs: Array3<T>,
+    gates: Array3<T>,
+}
+
+impl<T: 'static> LSTM<T>
+where
+    T: CrateFloat,
+{
+    pub fn new(
+        input_size: usize,
+        hidden_size: usize,
+        return_sequences: bool,
+        weight_init: WeightInitEnum,
+        weight_init_std: T,
+    ) -> Self {
+        Self::from(
+            &initialize_weight(
+                weight_init.clone(),
+                weight_init_std,
+                (input_size, 4 * hidden_size),
+            ),
+            &initialize_weight(weight_init, weight_init_std, (hidden_size, 4 * hidden_size)),
+            &Array1::zeros(4 * hidden_size),
+            return_sequences,
+        )
+    }
+    pub fn from(
+        weight_x: &Array2<T>,
+        weight_h: &Array2<T>,
+        bias: &Array1<T>,
+        return_sequences: bool,
+    ) -> Self {
+        Self {
+            dwx: Array2::zeros(weight_x.raw_dim()),
+            dwh: Array2::zeros(weight_h.raw_dim()),
+            db: Array1::zeros(bias.raw_dim()),
+            weight_x: weight_x.clone(),
+            weight_h: weight_h.clone(),
+            bias: bias.clone(),
+            return_sequences,
+            stateful: false,
+            bptt_steps: None,
+            state: None,
+            cell_state: None,
+            xs: Array3::zeros((0, 0, 0)),
+            hs: Array3::zeros((0, 0, 0)),
+            cs: Array3::zeros((0, 0, 0)),
+            gates: Array3::zeros((0, 0, 0)),
+        }
+    }
+    /// carry the last state over to the next batch
+    pub fn set_stateful(&mut self, flag: bool) {
+        self.stateful = flag;
+        self.reset_state();
+    }
+    /// number of time steps of truncated BPTT, or `None` for full BPTT
+    pub fn set_bptt_steps(&mut self, bptt_steps: Option<usize>) {
+        self.bptt_steps = bptt_steps;
+    }
+    /// forget the carried hidden and cell states
+    pub fn reset_state(&mut self) {
+        self.state = None;
+        self.cell_state = None;
+    }
+}
+
+impl<T: 'static> LayerBase<T> for LSTM<T>
+where
+    T: CrateFloat,
+{
+    type A = Array3<T>;
+    type B = Array3<T>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        let (batch_size, time, _) = x.dim();
+        let hidden_size: usize = self.weight_h.nrows();
+        let mut hs: Array3<T> = Array3::zeros((batch_size, time + 1, hidden_size));
+        let mut cs: Array3<T> = Array3::zeros((batch_size, time + 1, hidden_size));
+        let mut gates: Array3<T> = Array3::zeros((batch_size, time, 4 * hidden_size));
+        hs.index_axis_mut(Axis(1), 0)
+            .assign(&initial_state(&self.state, batch_size, hidden_size));
+        cs.index_axis_mut(Axis(1), 0).assign(&initial_state(
+            &self.cell_state,
+            batch_size,
+            hidden_size,
+        ));
+        for t in 0..time {
+            let mut a: Array2<T> = x.index_axis(Axis(1), t).dot(&self.weight_x)
+                + hs.index_axis(Axis(1), t).dot(&self.weight_h)
+                + &self.bias;
+            for (ii, mut column) in a.axis_iter_mut(Axis(1)).enumerate() {
+                if ii / hidden_size == 1 {
+                    column.mapv_inplace(|v| v.tanh());
+                } else {
+                    column.mapv_inplace(sigmoid);
+                }
+            }
+            let f = a.slice(s![.., ..hidden_size]);
+            let g = a.slice(s![.., hidden_size..2 * hidden_size]);
+            let i = a.slice(s![.., 2 * hidden_size..3 * hidden_size]);
+            let o = a.slice(s![.., 3 * hidden_size..]);
+            let c: Array2<T> = &f * &cs.index_axis(Axis(1), t) + &g * &i;
+            hs.index_axis_mut(Axis(1), t + 1)
+                .assign(&(&o * &c.map(|&v| v.tanh())));
+            cs.index_axis_mut(Axis(1), t + 1).assign(&c);
+            gates.index_axis_mut(Axis(1), t).assign(&a);
+        }
+        if self.stateful {
+            self.state = Some(hs.index_axis(Axis(1), time).to_owned());
+            self.cell_state = Some(cs.index_axis(Axis(1), time).to_owned());
+        }
+        self.xs = x.clone();
+        self.hs = hs;
+        self.cs = cs;
+        self.gates = gates;
+        output_of(&self.hs, self.return_sequences)
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        let one: T = cast_t2u(1.0);
+        let (batch_size, time, _) = self.xs.dim();
+        let hidden_size: usize = self.weight_h.nrows();
+        let mut dst: Self::A = Array3::zeros(self.xs.raw_dim());
+        let mut dh_next: Array2<T> = Array2::zeros((batch_size, hidden_size));
+        let mut dc_next: Array2<T> = Array2::zeros((batch_size, hidden_size));
+        self.dwx.fill(cast_t2u(0.0));
+        self.dwh.fill(cast_t2u(0.0));
+        self.db.fill(cast_t2u(0.0));
+        for t in (0..time).rev() {
+            let dh: Array2<T> = output_grad(dx, t, time, self.return_sequences) + &dh_next;
+            let gates: ArrayView2<T> = self.gates.index_axis(Axis(1), t);
+            let f = gates.slice(s![.., ..hidden_size]);
+            let g = gates.slice(s![.., hidden_size..2 * hidden_size]);
+            let i = gates.slice(s![.., 2 * hidden_size..3 * hidden_size]);
+            let o = gates.slice(s![.., 3 * hidden_size..]);
+            let c_prev: ArrayView2<T> = self.cs.index_axis(Axis(1), t);
+            let tanh_c: Array2<T> = self.cs.index_axis(Axis(1), t + 1).map(|&v| v.tanh());
+            let ds: Array2<T> = dc_next + &(&dh * &o * &tanh_c.map(|&v| one - v * v));
+            let df: Array2<T> = &ds * &c_prev * &f.map(|&v| v * (one - v));
+            let dg: Array2<T> = &ds * &i * &g.map(|&v| one - v * v);
+            let di: Array2<T> = &ds * &g * &i.map(|&v| v * (one - v));
+            let d_o: Array2<T> = &dh * &tanh_c * &o.map(|&v| v * (one - v));
+            let da: Array2<T> =
+                concatenate(Axis(1), &[df.view(), dg.view(), di.view(), d_o.view()]).unwrap();
+            self.dwx += &self.xs.index_axis(Axis(1), t).t().dot(&da);
+            self.dwh += &self.hs.index_axis(Axis(1), t).t().dot(&da);
+            self.db += &da.sum_axis(Axis(0));
+            dst.index_axis_mut(Axis(1), t)
+                .assign(&da.dot(&self.weight_x.t()));
+            dh_next = da.dot(&self.weight_h.t());
+            dc_next = ds * f;
+            if is_truncated(t, self.bptt_steps) {
+                dh_next.fill(cast_t2u(0.0));
+                dc_next.fill(cast_t2u(0.0));
+            }
+        }
+        dst
+    }
+    fn update(&mut self, lr: T) {
+        self.weight_x.scaled_add(-lr, &self.dwx);
+        self.weight_h.scaled_add(-lr, &self.dwh);
+        self.bias.scaled_add(-lr, &self.db);
+    }
+    fn print_detail(&self) {
+        println!("LSTM layer.");
+        println!("weight_x shape: {:?}", self.weight_x.shape());
+        println!("weight_h shape: {:?}", self.weight_h.shape());
+        println!("return sequences: {}", self.return_sequences);
+    }
+    fn print_parameters(&self) {
+        println!("weight_x: {:?}", self.weight_x);
+        println!("weight_h: {:?}", self.weight_h);
+        println!("bias: {:?}", self.bias);
+        println!("dwx: {:?}", self.dwx);
+        println!("dwh: {:?}", self.dwh);
+        println!("db: {:?}", self.db);
+    }
+}
+
+// <<<<<<<<<<<<< LSTM layer <<<<<<<<<<<<<
+
+// >>>>>>>>>>>>> GRU layer >>>>>>>>>>>>>
+
+/// GRU layer
+///
+/// The weights are the concatenation of the update gate, the reset gate
+/// and the candidate state in this order, and the new state is `(1 - z) * h + z * h_hat`.
+///
+/// See https://arxiv.org/abs/1406.1078 in detail
+pub struct GRU<T: CrateFloat> {
+    pub weight_x: Array2<T>,
+    pub weight_h: Array2<T>,
+    pub bias: Array1<T>,
+    pub dwx: Array2<T>,
+    pub dwh: Array2<T>,
+    pub db: Array1<T>,
+    return_sequences: bool,
+    stateful: bool,
+    bptt_steps: Option<usize>,
+    state: Option<Array2<T>>,
+    xs: Array3<T>,
+    hs: Array3<T>,
+    gates: Array3<T>,
+}
+
+impl<T: 'static> GRU<T>
+where
+    T: CrateFloat,
+{
+    pub fn new(
+        input_size: usize,
+        hidden_size: usize,
+        return_sequences: bool,
+        weight_init: WeightInitEnum,
+        weight_init_std: T,
+    ) -> Self {
+        Self::from(
+            &initialize_weight(
+                weight_init.clone(),
+                weight_init_std,
+                (input_size, 3 * hidden_size),
+            ),
+            &initialize_weight(weight_init, weight_init_std, (hidden_size, 3 * hidden_size)),
+            &Array1::zeros(3 * hidden_size),
+            return_sequences,
+        )
+    }
+    pub fn from(
+        weight_x: &Array2<T>,
+        weight_h: &Array2<T>,
+        bias: &Array1<T>,
+        return_sequences: bool,
+    ) -> Self {
+        Self {
+            dwx: Array2::zeros(weight_x.raw_dim()),
+            dwh: Array2::zeros(weight_h.raw_dim()),
+            db: Array1::zeros(bias.raw_dim()),
+            weight_x: weight_x.clone(),
+            weight_h: weight_h.clone(),
+            bias: bias.clone(),
+            return_sequences,
+            stateful: false,
+            bptt_steps: None,
+            state: None,
+            xs: Array3::zeros((0, 0, 0)),
+            hs: Array3::zeros((0, 0, 0)),
+            gates: Array3::zeros((0, 0, 0)),
+        }
+    }
+    /// carry the last state over to the next batch
+    pub fn set_stateful(&mut self, flag: bool) {
+        self.stateful = flag;
+        self.state = None;
+    }
+    /// number of time steps of truncated BPTT, or `None` for full BPTT
+    pub fn set_bptt_steps(&mut self, bptt_steps: Option<usize>) {
+        self.bptt_steps = bptt_steps;
+    }
+    /// forget the carried state
+    pub fn reset_state(&mut self) {
+        self.state = None;
+    }
+}
+
+impl<T: 'static> LayerBase<T> for GRU<T>
+where
+    T: CrateFloat,
+{
+    type A = Array3<T>;
+    type B = Array3<T>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        let one: T = cast_t2u(1.0);
+        let (batch_size, time, _) = x.dim();
+        let hidden_size: usize = self.weight_h.nrows();
+        let zr = s![.., ..2 * hidden_size];
+        let candidate = s![.., 2 * hidden_size..];
+        let mut hs: Array3<T> = Array3::zeros((batch_size, time + 1, hidden_size));
+        let mut gates: Array3<T> = Array3::zeros((batch_size, time, 3 * hidden_size));
+        hs.index_axis_mut(Axis(1), 0)
+            .assign(&initial_state(&self.state, batch_size, hidden_size));
+        for t in 0..time {
+            let xt: ArrayView2<T> = x.index_axis(Axis(1), t);
+            let h: ArrayView2<T> = hs.index_axis(Axis(1), t);
+            let a: Array2<T> = xt.dot(&self.weight_x.slice(zr))
+                + h.dot(&self.weight_h.slice(zr))
+                + self.bias.slice(s![..2 * hidden_size]);
+            let a: Array2<T> = a.map(|&v| sigmoid(v));
+            let z = a.slice(s![.., ..hidden_size]);
+            let r = a.slice(s![.., hidden_size..]);
+            let h_hat: Array2<T> = (xt.dot(&self.weight_x.slice(candidate))
+                + (&r * &h).dot(&self.weight_h.slice(candidate))
+                + self.bias.slice(s![2 * hidden_size..]))
+            .map(|&v| v.tanh());
+            let h_next: Array2<T> = z.map(|&v| one - v) * h + &(&z * &h_hat);
+            gates
+                .index_axis_mut(Axis(1), t)
+                .assign(&concatenate(Axis(1), &[a.view(), h_hat.view()]).unwrap());
+            hs.index_axis_mut(Axis(1), t + 1).assign(&h_next);
+        }
+        if self.stateful {
+            self.state = Some(hs.index_axis(Axis(1), time).to_owned());
+        }
+        self.xs = x.clone();
+        self.hs = hs;
+        self.gates = gates;
+        output_of(&self.hs, self.return_sequences)
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        let one: T = cast_t2u(1.0);
+        let (batch_size, time, _) = self.xs.dim();
+        let hidden_size: usize = self.weight_h.nrows();
+        let zr = s![.., ..2 * hidden_size];
+        let candidate = s![.., 2 * hidden_size..];
+        let mut dst: Self::A = Array3::zeros(self.xs.raw_dim());
+        let mut dh_next: Array2<T> = Array2::zeros((batch_size, hidden_size));
+        self.dwx.fill(cast_t2u(0.0));
+        self.dwh.fill(cast_t2u(0.0));
+        self.db.fill(cast_t2u(0.0));
+        for t in (0..time).rev() {
+            let dh: Array2<T> = output_grad(dx, t, time, self.return_sequences) + &dh_next;
+            let xt: ArrayView2<T> = self.xs.index_axis(Axis(1), t);
+            let h: ArrayView2<T> = self.hs.index_axis(Axis(1), t);
+            let gates: ArrayView2<T> = self.gates.index_axis(Axis(1), t);
+            let z = gates.slice(s![.., ..hidden_size]);
+            let r = gates.slice(s![.., hidden_size..2 * hidden_size]);
+            let h_hat = gates.slice(s![.., 2 * hidden_size..]);
+            let rh: Array2<T> = &r * &h;
+
+            let da_hat: Array2<T> = &dh * &z * &h_hat.map(|&v| one - v * v);
+            let drh: Array2<T> = da_hat.dot(&self.weight_h.slice(candidate).t());
+            let dz: Array2<T> = &dh * &(&h_hat - &h) * &z.map(|&v| v * (one - v));
+            let dr: Array2<T> = &drh * &h * &r.map(|&v| v * (one - v));
+            let da_zr: Array2<T> = concatenate(Axis(1), &[dz.view(), dr.view()]).unwrap();
+
+            self.dwx.slice_mut(zr).scaled_add(one, &xt.t().dot(&da_zr));
+            self.dwx
+                .slice_mut(candidate)
+                .scaled_add(one, &xt.t().dot(&da_hat));
+            self.dwh.slice_mut(zr).scaled_add(one, &h.t().dot(&da_zr));
+            self.dwh
+                .slice_mut(candidate)
+                .scaled_add(one, &rh.t().dot(&da_hat));
+            self.db
+                .slice_mut(s![..2 * hidden_size])
+                .scaled_add(one, &da_zr.sum_axis(Axis(0)));
+            self.db
+                .slice_mut(s![2 * hidden_size..])
+                .scaled_add(one, &da_hat.sum_axis(Axis(0)));
+
+            dst.index_axis_mut(Axis(1), t).assign(
+                &(da_zr.dot(&self.weight_x.slice(zr).t())
+                    + da_hat.dot(&self.weight_x.slice(candidate).t())),
+            );
+            dh_next = &dh * &z.map(|&v| one - v)
+                + &(&drh * &r)
+                + &da_zr.dot(&self.weight_h.slice(zr).t());
+            if is_truncated(t, self.bptt_steps) {
+                dh_next.fill(cast_t2u(0.0));
+            }
+        }
+        dst
+    }
+    fn update(&mut self, lr: T) {
+        self.weight_x.scaled_add(-lr, &self.dwx);
+        self.weight_h.scaled_add(-lr, &self.dwh);
+        self.bias.scaled_add(-lr, &self.db);
+    }
+    fn print_detail(&self) {
+        println!("GRU layer.");
+        println!("weight_x shape: {:?}", self.weight_x.shape());
+        println!("weight_h shape: {:?}", self.weight_h.shape());
+        println!("return sequences: {}", self.return_sequences);
+    }
+    fn print_parameters(&self) {
+        println!("weight_x: {:?}", self.weight_x);
+        println!("weight_h: {:?}", self.weight_h);
+        println!("bias: {:?}", self.bias);
+        println!("dwx: {:?}", self.dwx);
+        println!("dwh: {:?}", self.dwh);
+        println!("db: {:?}", self.db);
+    }
+}
+
+// <<<<<<<<<<<<< GRU layer <<<<<<<<<<<<<
@@ -0,0 +1,338 @@
+//! regression_loss
+//!
+//! Loss layers for regression
+//!
+//! The loss of each sample is the mean of the element-wise losses over all axes but the batch axis 0,
+//! and the losses of samples are reduced as given by `ReductionEnum`.
+//! These layers have no activation, so `get_output` returns the predictions as they are.
+
+use super::super::error::CrateError;
+use super::super::util::*;
+use super::layer_base::LossLayerBase;
+use ndarray::prelude::*;
+use std::fmt::Display;
+
+/// Enum for the reduction of per-sample losses
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum ReductionEnum {
+    /// mean over samples
+    #[default]
+    Mean,
+    /// sum over samples
+    Sum,
+    /// no reduction: per-sample losses are given by `get_sample_losses`,
+    /// `forward` returns their sum and `backward` returns the gradient of each sample's own loss
+    None,
+}
+
+impl Display for ReductionEnum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReductionEnum::Mean => write!(f, "Mean"),
+            ReductionEnum::Sum => write!(f, "Sum"),
+            ReductionEnum::None => write!(f, "None"),
+        }
+    }
+}
+
+/// common part of loss layers defined by element-wise functions of `x - t`
+struct ElementwiseLoss<T: CrateFloat, D> {
+    reduction: ReductionEnum,
+    output: Array<T, D>,
+    diff: Array<T, D>,
+    sample_losses: Array1<T>,
+}
+
+impl<T: 'static, D> ElementwiseLoss<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    fn new(reduction: ReductionEnum) -> Self {
+        let ndim: usize = D::NDIM.unwrap_or(2);
+        Self {
+            reduction,
+            output: Array::zeros(D::zeros(ndim)),
+            diff: Array::zeros(D::zeros(ndim)),
+            sample_losses: Array1::zeros(0),
+        }
+    }
+    /// number of elements of each sample
+    fn sample_size(&self) -> usize {
+        self.diff
+            .len()
+            .checked_div(self.diff.len_of(Axis(0)))
+            .unwrap_or(0)
+    }
+    fn forward<F>(&mut self, x: &Array<T, D>, t: &Array<T, D>, loss: F) -> T
+    where
+        F: Fn(T) -> T,
+    {
+        self.output = x.clone();
+        self.diff = x - t;
+        let batch_size: usize = x.len_of(Axis(0));
+        let sample_size: T = cast_t2u(self.sample_size());
+        self.sample_losses = self
+            .diff
+            .mapv(loss)
+            .as_standard_layout()
+            .into_owned()
+            .into_shape((batch_size, self.sample_size()))
+            .unwrap()
+            .sum_axis(Axis(1))
+            / sample_size;
+        match self.reduction {
+            ReductionEnum::Mean => self.sample_losses.sum() / cast_t2u(batch_size),
+            ReductionEnum::Sum | ReductionEnum::None => self.sample_losses.sum(),
+        }
+    }
+    fn backward<G>(&self, dout: T, grad: G) -> Array<T, D>
+    where
+        G: Fn(T) -> T,
+    {
+        let num_elements: usize = match self.reduction {
+            ReductionEnum::Mean => self.diff.len(),
+            ReductionEnum::Sum | ReductionEnum::None => self.sample_size(),
+        };
+        let scale: T = dout / cast_t2u(num_elements);
+        self.diff.mapv(|v| grad(v) * scale)
+    }
+}
+
+// >>>>>>>>>>>>> MeanSquaredError layer >>>>>>>>>>>>>
+
+/// Arbitrary-D mean squared error `(x - t)^2`
+pub struct MeanSquaredError<T: CrateFloat, D> {
+    core: ElementwiseLoss<T, D>,
+}
+
+impl<T: 'static, D> MeanSquaredError<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    pub fn new(reduction: ReductionEnum) -> Self {
+        Self {
+            core: ElementwiseLoss::new(reduction),
+        }
+    }
+    /// losses of samples in the last forward
+    pub fn get_sample_losses(&self) -> Array1<T> {
+        self.core.sample_losses.clone()
+    }
+}
+
+impl<T: 'static, D> LossLayerBase<T> for MeanSquaredError<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    type A = Array<T, D>;
+    fn forward(&mut self, x: &Self::A, t: &Self::A) -> T {
+        self.core.forward(x, t, |d| d * d)
+    }
+    fn backward(&mut self, dout: T) -> Self::A {
+        let two: T = cast_t2u(2.0);
+        self.core.backward(dout, |d| two * d)
+    }
+    fn print_detail(&self) {
+        println!("mean squared error layer.");
+        println!("reduction: {}", self.core.reduction);
+    }
+    fn get_output(&self) -> Self::A {
+        self.core.output.clone()
+    }
+}
+
+pub type MeanSquaredError2<T> = MeanSquaredError<T, Ix2>;
+pub type MeanSquaredError3<T> = MeanSquaredError<T, Ix3>;
+pub type MeanSquaredError4<T> = MeanSquaredError<T, Ix4>;
+pub type MeanSquaredError5<T> = MeanSquaredError<T, Ix5>;
+pub type MeanSquaredError6<T> = MeanSquaredError<T, Ix6>;
+pub type MeanSquaredErrorD<T> = MeanSquaredError<T, IxDyn>;
+
+// <<<<<<<<<<<<< MeanSquaredError layer <<<<<<<<<<<<<
+
+// >>>>>>>>>>>>> MeanAbsoluteError layer >>>>>>>>>>>>>
+
+/// Arbitrary-D mean absolute error `|x - t|`
+///
+/// The gradient at `x == t` is 0.
+pub struct MeanAbsoluteError<T: CrateFloat, D> {
+    core: ElementwiseLoss<T, D>,
+}
+
+impl<T: 'static, D> MeanAbsoluteError<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    pub fn new(reduction: ReductionEnum) -> Self {
+        Self {
+            core: ElementwiseLoss::new(reduction),
+        }
+    }
+    /// losses of samples in the last forward
+    pub fn get_sample_losses(&self) -> Array1<T> {
+        self.core.sample_losses.clone()
+    }
+}
+
+impl<T: 'static, D> LossLayerBase<T> for MeanAbsoluteError<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    type A = Array<T, D>;
+    fn forward(&mut self, x: &Self::A, t: &Self::A) -> T {
+        self.core.forward(x, t, |d| d.abs())
+    }
+    fn backward(&mut self, dout: T) -> Self::A {
+        let zero: T = cast_t2u(0.0);
+        self.core
+            .backward(dout, |d| if d == zero { zero } else { d.signum() })
+    }
+    fn print_detail(&self) {
+        println!("mean absolute error layer.");
+        println!("reduction: {}", self.core.reduction);
+    }
+    fn get_output(&self) -> Self::A {
+        self.core.output.clone()
+    }
+}
+
+pub type MeanAbsoluteError2<T> = MeanAbsoluteError<T, Ix2>;
+pub type MeanAbsoluteError3<T> = MeanAbsoluteError<T, Ix3>;
+pub type MeanAbsoluteError4<T> = MeanAbsoluteError<T, Ix4>;
+pub type MeanAbsoluteError5<T> = MeanAbsoluteError<T, Ix5>;
+pub type MeanAbsoluteError6<T> = MeanAbsoluteError<T, Ix6>;
+pub type MeanAbsoluteErrorD<T> = MeanAbsoluteError<T, IxDyn>;
+
+// <<<<<<<<<<<<< MeanAbsoluteError layer <<<<<<<<<<<<<
+
+// >>>>>>>>>>>>> Huber layer >>>>>>>>>>>>>
+
+/// Arbitrary-D Huber loss, quadratic for `|x - t| <= delta` and linear beyond
+pub struct Huber<T: CrateFloat, D> {
+    delta: T,
+    core: ElementwiseLoss<T, D>,
+}
+
+impl<T: 'static, D> Huber<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    pub fn new(delta: T, reduction: ReductionEnum) -> Result<Self, CrateError> {
+        if delta.is_nan() || delta <= cast_t2u(0.0) {
+            return Err(CrateError::ConfigError(format!(
+                "delta of the Huber loss must be positive, found {}.",
+                delta
+            )));
+        }
+        Ok(Self {
+            delta,
+            core: ElementwiseLoss::new(reduction),
+        })
+    }
+    /// losses of samples in the last forward
+    pub fn get_sample_losses(&self) -> Array1<T> {
+        self.core.sample_losses.clone()
+    }
+}
+
+impl<T: 'static, D> LossLayerBase<T> for Huber<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    type A = Array<T, D>;
+    fn forward(&mut self, x: &Self::A, t: &Self::A) -> T {
+        let (delta, half): (T, T) = (self.delta, cast_t2u(0.5));
+        self.core.forward(x, t, |d| {
+            if d.abs() <= delta {
+                half * d * d
+            } else {
+                delta * (d.abs() - half * delta)
+            }
+        })
+    }
+    fn backward(&mut self, dout: T) -> Self::A {
+        let delta: T = self.delta;
+        self.core.backward(dout, |d| d.max(-delta).min(delta))
+    }
+    fn print_detail(&self) {
+        println!("huber loss layer.");
+        println!("delta: {}", self.delta);
+        println!("reduction: {}", self.core.reduction);
+    }
+    fn get_output(&self) -> Self::A {
+        self.core.output.clone()
+    }
+}
+
+pub type Huber2<T> = Huber<T, Ix2>;
+pub type Huber3<T> = Huber<T, Ix3>;
+pub type Huber4<T> = Huber<T, Ix4>;
+pub type Huber5<T> = Huber<T, Ix5>;
+pub type Huber6<T> = Huber<T, Ix6>;
+pub type HuberD<T> = Huber<T, IxDyn>;
+
+// <<<<<<<<<<<<< Huber layer <<<<<<<<<<<<<
+
+// >>>>>>>>>>>>> LogCosh layer >>>>>>>>>>>>>
+
+/// Arbitrary-D log-cosh loss `log(cosh(x - t))`
+///
+/// It is computed as `|d| + log(1 + exp(-2|d|)) - log(2)` so as not to overflow for large errors.
+pub struct LogCosh<T: CrateFloat, D> {
+    core: ElementwiseLoss<T, D>,
+}
+
+impl<T: 'static, D> LogCosh<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    pub fn new(reduction: ReductionEnum) -> Self {
+        Self {
+            core: ElementwiseLoss::new(reduction),
+        }
+    }
+    /// losses of samples in the last forward
+    pub fn get_sample_losses(&self) -> Array1<T> {
+        self.core.sample_losses.clone()
+    }
+}
+
+impl<T: 'static, D> LossLayerBase<T> for LogCosh<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    type A = Array<T, D>;
+    fn forward(&mut self, x: &Self::A, t: &Self::A) -> T {
+        let (two, ln2): (T, T) = (cast_t2u(2.0), cast_t2u(std::f64::consts::LN_2));
+        self.core
+            .forward(x, t, |d| d.abs() + (-two * d.abs()).exp().ln_1p() - ln2)
+    }
+    fn backward(&mut self, dout: T) -> Self::A {
+        self.core.backward(dout, |d| d.tanh())
+    }
+    fn print_detail(&self) {
+        println!("log-cosh loss layer.");
+        println!("reduction: {}", self.core.reduction);
+    }
+    fn get_output(&self) -> Self::A {
+        self.core.output.clone()
+    }
+}
+
+pub type LogCosh2<T> = LogCosh<T, Ix2>;
+pub type LogCosh3<T> = LogCosh<T, Ix3>;
+pub type LogCosh4<T> = LogCosh<T, Ix4>;
+pub type LogCosh5<T> = LogCosh<T, Ix5>;
+pub type LogCosh6<T> = LogCosh<T, Ix6>;
+pub type LogCoshD<T> = LogCosh<T, IxDyn>;
+
+// <<<<<<<<<<<<< LogCosh layer <<<<<<<<<<<<<
@@ -0,0 +1,377 @@
+//! reshape
+//!
+//! Shape adapter layers
+//!
+//! These layers have no parameters and only change the shape or the dimensionality of arrays,
+//! e.g. `Flatten4` connects (batch, channel, height, width) feature maps to `Affine`.
+//! The batch axis is axis 0 unless the layer moves it explicitly.
+
+use super::super::util::*;
+use super::layer_base::LayerBase;
+use ndarray::{prelude::*, RemoveAxis};
+use std::marker::PhantomData;
+
+// >>>>>>>>>>>>> Flatten layer >>>>>>>>>>>>>
+
+/// Arbitrary-D layer flattening each sample into a row of a (batch, features) array
+pub struct Flatten<T: CrateFloat, D> {
+    shape: D,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: 'static, D> Flatten<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    pub fn new() -> Self {
+        Self {
+            shape: D::zeros(D::NDIM.unwrap_or(0)),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: 'static, D> Default for Flatten<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: 'static, D> LayerBase<T> for Flatten<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    type A = Array<T, D>;
+    type B = Array2<T>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        self.shape = x.raw_dim();
+        let batch_size = x.len_of(Axis(0));
+        let features = x.len().checked_div(batch_size).unwrap_or(0);
+        x.as_standard_layout()
+            .into_owned()
+            .into_shape((batch_size, features))
+            .unwrap()
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        dx.as_standard_layout()
+            .into_owned()
+            .into_shape(self.shape.clone())
+            .unwrap()
+    }
+    fn print_detail(&self) {
+        println!("flatten layer.");
+    }
+}
+
+pub type Flatten3<T> = Flatten<T, Ix3>;
+pub type Flatten4<T> = Flatten<T, Ix4>;
+pub type Flatten5<T> = Flatten<T, Ix5>;
+pub type Flatten6<T> = Flatten<T, Ix6>;
+pub type FlattenD<T> = Flatten<T, IxDyn>;
+
+// <<<<<<<<<<<<< Flatten layer <<<<<<<<<<<<<
+
+// >>>>>>>>>>>>> Reshape layer >>>>>>>>>>>>>
+
+/// Layer reshaping arrays of dimension `D1` into arrays of dimension `D2`
+///
+/// The size of axis 0 of `shape` is ignored and replaced with the batch size of inputs.
+pub struct Reshape<T: CrateFloat, D1, D2> {
+    shape: D2,
+    input_shape: D1,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: 'static, D1, D2> Reshape<T, D1, D2>
+where
+    T: CrateFloat,
+    D1: Dimension,
+    D2: Dimension,
+{
+    pub fn new<Sh>(shape: Sh) -> Self
+    where
+        Sh: ShapeBuilder<Dim = D2>,
+    {
+        Self {
+            shape: shape.into_shape().raw_dim().clone(),
+            input_shape: D1::zeros(D1::NDIM.unwrap_or(0)),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: 'static, D1, D2> LayerBase<T> for Reshape<T, D1, D2>
+where
+    T: CrateFloat,
+    D1: Dimension,
+    D2: Dimension,
+{
+    type A = Array<T, D1>;
+    type B = Array<T, D2>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        self.input_shape = x.raw_dim();
+        let mut shape: D2 = self.shape.clone();
+        shape[0] = x.len_of(Axis(0));
+        x.as_standard_layout()
+            .into_owned()
+            .into_shape(shape)
+            .unwrap()
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        dx.as_standard_layout()
+            .into_owned()
+            .into_shape(self.input_shape.clone())
+            .unwrap()
+    }
+    fn print_detail(&self) {
+        println!("reshape layer.");
+        println!("shape: {:?}", self.shape.slice());
+    }
+}
+
+pub type ReshapeD<T> = Reshape<T, IxDyn, IxDyn>;
+
+// <<<<<<<<<<<<< Reshape layer <<<<<<<<<<<<<
+
+// >>>>>>>>>>>>> Permute layer >>>>>>>>>>>>>
+
+/// Arbitrary-D layer permuting axes, where axis `i` of outputs is axis `axes[i]` of inputs
+pub struct Permute<T: CrateFloat, D> {
+    axes: Vec<usize>,
+    inverse: Vec<usize>,
+    _phantom: PhantomData<(T, D)>,
+}
+
+impl<T: 'static, D> Permute<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    pub fn new(axes: &[usize]) -> Self {
+        let mut inverse: Vec<usize> = vec![0; axes.len()];
+        for (i, &axis) in axes.iter().enumerate() {
+            inverse[axis] = i;
+        }
+        Self {
+            axes: axes.to_vec(),
+            inverse,
+            _phantom: PhantomData,
+        }
+    }
+    /// permutation swapping two axes of `ndim`-D arrays
+    pub fn transpose(ndim: usize, axis1: usize, axis2: usize) -> Self {
+        let mut axes: Vec<usize> = (0..ndim).collect();
+        axes.swap(axis1, axis2);
+        Self::new(&axes)
+    }
+}
+
+/// permute axes of an array and return it in the standard layout
+fn permuted<T, D>(x: &Array<T, D>, axes: &[usize]) -> Array<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    let mut perm: D = D::zeros(x.ndim());
+    for (p, &axis) in perm.slice_mut().iter_mut().zip(axes.iter()) {
+        *p = axis;
+    }
+    x.view()
+        .permuted_axes(perm)
+        .as_standard_layout()
+        .into_owned()
+}
+
+impl<T: 'static, D> LayerBase<T> for Permute<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    type A = Array<T, D>;
+    type B = Array<T, D>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        permuted(x, &self.axes)
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        permuted(dx, &self.inverse)
+    }
+    fn print_detail(&self) {
+        println!("permute layer.");
+        println!("axes: {:?}", self.axes);
+    }
+}
+
+pub type Permute2<T> = Permute<T, Ix2>;
+pub type Permute3<T> = Permute<T, Ix3>;
+pub type Permute4<T> = Permute<T, Ix4>;
+pub type Permute5<T> = Permute<T, Ix5>;
+pub type Permute6<T> = Permute<T, Ix6>;
+pub type PermuteD<T> = Permute<T, IxDyn>;
+
+// <<<<<<<<<<<<< Permute layer <<<<<<<<<<<<<
+
+// >>>>>>>>>>>>> Squeeze layer >>>>>>>>>>>>>
+
+/// Arbitrary-D layer removing an axis of length 1
+pub struct Squeeze<T: CrateFloat, D> {
+    axis: usize,
+    _phantom: PhantomData<(T, D)>,
+}
+
+impl<T: 'static, D> Squeeze<T, D>
+where
+    T: CrateFloat,
+    D: Dimension + RemoveAxis,
+{
+    pub fn new(axis: usize) -> Self {
+        Self {
+            axis,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: 'static, D> LayerBase<T> for Squeeze<T, D>
+where
+    T: CrateFloat,
+    D: Dimension + RemoveAxis,
+{
+    type A = Array<T, D>;
+    type B = Array<T, D::Smaller>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        assert_eq!(
+            x.len_of(Axis(self.axis)),
+            1,
+            "squeezed axis must be of length 1"
+        );
+        x.index_axis(Axis(self.axis), 0).to_owned()
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        dx.clone()
+            .insert_axis(Axis(self.axis))
+            .into_dimensionality::<D>()
+            .unwrap()
+    }
+    fn print_detail(&self) {
+        println!("squeeze layer.");
+        println!("axis: {}", self.axis);
+    }
+}
+
+pub type Squeeze2<T> = Squeeze<T, Ix2>;
+pub type Squeeze3<T> = Squeeze<T, Ix3>;
+pub type Squeeze4<T> = Squeeze<T, Ix4>;
+pub type Squeeze5<T> = Squeeze<T, Ix5>;
+pub type Squeeze6<T> = Squeeze<T, Ix6>;
+pub type SqueezeD<T> = Squeeze<T, IxDyn>;
+
+// <<<<<<<<<<<<< Squeeze layer <<<<<<<<<<<<<
+
+// >>>>>>>>>>>>> Unsqueeze layer >>>>>>>>>>>>>
+
+/// Arbitrary-D layer inserting an axis of length 1
+pub struct Unsqueeze<T: CrateFloat, D> {
+    axis: usize,
+    _phantom: PhantomData<(T, D)>,
+}
+
+impl<T: 'static, D> Unsqueeze<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    pub fn new(axis: usize) -> Self {
+        Self {
+            axis,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: 'static, D> LayerBase<T> for Unsqueeze<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    type A = Array<T, D>;
+    type B = Array<T, D::Larger>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        x.clone().insert_axis(Axis(self.axis))
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        dx.view()
+            .into_dyn()
+            .index_axis_move(Axis(self.axis), 0)
+            .to_owned()
+            .into_dimensionality::<D>()
+            .unwrap()
+    }
+    fn print_detail(&self) {
+        println!("unsqueeze layer.");
+        println!("axis: {}", self.axis);
+    }
+}
+
+pub type Unsqueeze1<T> = Unsqueeze<T, Ix1>;
+pub type Unsqueeze2<T> = Unsqueeze<T, Ix2>;
+pub type Unsqueeze3<T> = Unsqueeze<T, Ix3>;
+pub type Unsqueeze4<T> = Unsqueeze<T, Ix4>;
+pub type Unsqueeze5<T> = Unsqueeze<T, Ix5>;
+pub type UnsqueezeD<T> = Unsqueeze<T, IxDyn>;
+
+// <<<<<<<<<<<<< Unsqueeze layer <<<<<<<<<<<<<
+
+// >>>>>>>>>>>>> IntoDyn layer >>>>>>>>>>>>>
+
+/// Layer converting fixed-dimension arrays into `IxDyn` ones for generic pipelines
+pub struct IntoDyn<T: CrateFloat, D> {
+    _phantom: PhantomData<(T, D)>,
+}
+
+impl<T: 'static, D> IntoDyn<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    pub fn new() -> Self {
+        Self {
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: 'static, D> Default for IntoDyn<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: 'static, D> LayerBase<T> for IntoDyn<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    type A = Array<T, D>;
+    type B = ArrayD<T>;
+    fn forward(&mut self, x: &Self::A) -> Self::B {
+        x.clone().into_dyn()
+    }
+    fn backward(&mut self, dx: &Self::B) -> Self::A {
+        dx.clone().into_dimensionality::<D>().unwrap()
+    }
+    fn print_detail(&self) {
+        println!("into-dyn layer.");
+    }
+}
+
+// <<<<<<<<<<<<< IntoDyn layer <<<<<<<<<<<<<
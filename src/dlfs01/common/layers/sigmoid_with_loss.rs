@@ -0,0 +1,126 @@
+//! sigmoid_with_loss
+//!
+//! custom layer: combination of sigmoid and binary cross-entropy loss
+
+use super::super::error::CrateError;
+use super::super::util::*;
+use super::layer_base::*;
+use ndarray::{prelude::*, Zip};
+
+/// Arbitrary-D sigmoid-with-binary-cross-entropy layer for binary and multi-label classification
+///
+/// Each output is an independent sigmoid, and targets are multi-hot arrays of 0 and 1.
+/// The loss is summed over outputs and averaged over the batch axis 0, and is computed in the
+/// log-sum-exp form `(1 - t) x + w (log(1 + exp(-|x|)) + max(-x, 0))` with `w = 1 + (pos_weight - 1) t`
+/// so that it is finite for any logits.
+pub struct SigmoidWithBCELoss<T: CrateFloat, D> {
+    output: Array<T, D>,
+    target: Array<T, D>,
+    pos_weight: Option<Array1<T>>,
+}
+
+impl<T: 'static, D> SigmoidWithBCELoss<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    pub fn new() -> Self {
+        let ndim: usize = D::NDIM.unwrap_or(2);
+        let zeros = Array::<T, D>::zeros(D::zeros(ndim));
+        Self {
+            output: zeros.clone(),
+            target: zeros,
+            pos_weight: None,
+        }
+    }
+    /// weights of positive targets for each class on the last axis, e.g. `#negatives / #positives`
+    pub fn with_pos_weight(mut self, pos_weight: Array1<T>) -> Result<Self, CrateError> {
+        if let Some(w) = pos_weight
+            .iter()
+            .find(|w| !w.is_finite() || **w < cast_t2u(0.0))
+        {
+            return Err(CrateError::ConfigError(format!(
+                "positive weights must be non-negative, found {}.",
+                w
+            )));
+        }
+        self.pos_weight = Some(pos_weight);
+        Ok(self)
+    }
+    /// `1 + (pos_weight - 1) t` for each element
+    fn log_weight(&self) -> Array<T, D> {
+        let one: T = cast_t2u(1.0);
+        match self.pos_weight.as_ref() {
+            Some(pos_weight) => {
+                let pos_weight = pos_weight
+                    .broadcast(self.target.raw_dim())
+                    .expect("the length of pos_weight must be the number of classes");
+                let mut dst: Array<T, D> = self.target.clone();
+                Zip::from(&mut dst)
+                    .and(&pos_weight)
+                    .for_each(|t, &w| *t = one + (w - one) * *t);
+                dst
+            }
+            None => Array::from_elem(self.target.raw_dim(), one),
+        }
+    }
+}
+
+impl<T: 'static, D> Default for SigmoidWithBCELoss<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: 'static, D> LossLayerBase<T> for SigmoidWithBCELoss<T, D>
+where
+    T: CrateFloat,
+    D: Dimension,
+{
+    type A = Array<T, D>;
+    fn forward(&mut self, x: &Self::A, t: &Self::A) -> T {
+        let (zero, one): (T, T) = (cast_t2u(0.0), cast_t2u(1.0));
+        let batch_size: T = cast_t2u(x.len_of(Axis(0)));
+        self.target = t.clone();
+        self.output = x.mapv(|v| one / (one + (-v).exp()));
+        let mut loss: T = zero;
+        Zip::from(x)
+            .and(t)
+            .and(&self.log_weight())
+            .for_each(|&x, &t, &w| {
+                loss += (one - t) * x + w * ((-x.abs()).exp().ln_1p() + (-x).max(zero));
+            });
+        loss / batch_size
+    }
+    fn backward(&mut self, dout: T) -> Self::A {
+        let one: T = cast_t2u(1.0);
+        let batch_size: T = cast_t2u(self.target.len_of(Axis(0)));
+        let scale: T = dout / batch_size;
+        let mut dst: Self::A = self.log_weight();
+        Zip::from(&mut dst)
+            .and(&self.output)
+            .and(&self.target)
+            .for_each(|d, &y, &t| *d = ((one - t) - *d * (one - y)) * scale);
+        dst
+    }
+    fn print_detail(&self) {
+        println!("sigmoid-with-BCE-loss layer.");
+        if let Some(pos_weight) = self.pos_weight.as_ref() {
+            println!("pos_weight: {}", pos_weight);
+        }
+    }
+    fn get_output(&self) -> Self::A {
+        self.output.clone()
+    }
+}
+
+pub type SigmoidWithBCELoss2<T> = SigmoidWithBCELoss<T, Ix2>;
+pub type SigmoidWithBCELoss3<T> = SigmoidWithBCELoss<T, Ix3>;
+pub type SigmoidWithBCELoss4<T> = SigmoidWithBCELoss<T, Ix4>;
+pub type SigmoidWithBCELoss5<T> = SigmoidWithBCELoss<T, Ix5>;
+pub type SigmoidWithBCELoss6<T> = SigmoidWithBCELoss<T, Ix6>;
+pub type SigmoidWithBCELossD<T> = SigmoidWithBCELoss<T, IxDyn>;
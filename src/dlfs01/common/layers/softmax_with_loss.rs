@@ -2,23 +2,36 @@
 //!
 //! custom layer: combination of softmax and loss
 
+use super::super::error::CrateError;
 use super::super::util::*;
+use super::activation::log_softmax_inplace;
 use super::layer_base::*;
 // use itertools::multizip;
-use ndarray::{prelude::*, RemoveAxis};
-use std::f64::consts::E;
-
-const EPS: f64 = 1E-8;
+use ndarray::{prelude::*, RemoveAxis, Zip};
 
 /// Arbitrary-D softmax-with-loss layer
+///
+/// The cross-entropy is computed from the log-softmax by the log-sum-exp,
+/// so that the loss and its gradient are finite for any finite logits.
+///
+/// The cross-entropy loss can be modified by the following options, which can be combined.
+/// - `with_class_weights`: weights of classes for imbalanced datasets
+/// - `with_label_smoothing`: targets `t` are replaced by `(1 - epsilon) t + epsilon / K` for `K` classes
+/// - `with_focal`: focal loss `-alpha (1 - p)^gamma log(p)` down-weighting well-classified samples,
+///   see https://arxiv.org/abs/1708.02002 in detail
 pub struct SoftmaxWithLoss<T: CrateFloat, D> {
     output: Array<T, D>,
+    log_output: Array<T, D>,
     axis: usize,
     target: Array<T, D>,
+    /// class indices given to `forward_sparse` instead of `target`
+    labels: Option<Array1<usize>>,
     loss: T,
-    eps: T,
-    e: T,
     zero: T,
+    class_weights: Option<Array1<T>>,
+    label_smoothing: T,
+    /// gamma and alpha of the focal loss
+    focal: Option<(T, T)>,
 }
 
 impl<T: 'static, D> SoftmaxWithLoss<T, D>
@@ -33,13 +46,112 @@ where
         let zeros = Array::<T, D>::zeros(shape);
         Self {
             output: zeros.clone(),
+            log_output: zeros.clone(),
             axis,
             target: zeros,
+            labels: None,
             loss: cast_t2u(0.0),
-            eps: cast_t2u(EPS),
-            e: cast_t2u(E),
             zero: cast_t2u(0.0),
+            class_weights: None,
+            label_smoothing: cast_t2u(0.0),
+            focal: None,
+        }
+    }
+    /// weights of classes, which are broadcast over each sample
+    pub fn with_class_weights(mut self, class_weights: Array1<T>) -> Result<Self, CrateError> {
+        if let Some(w) = class_weights
+            .iter()
+            .find(|w| !w.is_finite() || **w < self.zero)
+        {
+            return Err(CrateError::ConfigError(format!(
+                "class weights must be non-negative, found {}.",
+                w
+            )));
+        }
+        self.class_weights = Some(class_weights);
+        Ok(self)
+    }
+    pub fn with_label_smoothing(mut self, epsilon: T) -> Result<Self, CrateError> {
+        if !(epsilon >= self.zero && epsilon < cast_t2u(1.0)) {
+            return Err(CrateError::ConfigError(format!(
+                "the label smoothing epsilon must be in [0, 1), found {}.",
+                epsilon
+            )));
+        }
+        self.label_smoothing = epsilon;
+        Ok(self)
+    }
+    pub fn with_focal(mut self, gamma: T, alpha: T) -> Result<Self, CrateError> {
+        if gamma.is_nan() || gamma < self.zero || alpha.is_nan() || alpha <= self.zero {
+            return Err(CrateError::ConfigError(format!(
+                "the focal loss needs gamma >= 0 and alpha > 0, found gamma {} and alpha {}.",
+                gamma, alpha
+            )));
+        }
+        self.focal = Some((gamma, alpha));
+        Ok(self)
+    }
+    fn is_plain(&self) -> bool {
+        self.class_weights.is_none() && self.label_smoothing == self.zero && self.focal.is_none()
+    }
+    fn set_outputs(&mut self, x: &Array<T, D>)
+    where
+        D: RemoveAxis,
+    {
+        self.log_output = x.clone();
+        for view in self.log_output.axis_iter_mut(Axis(self.axis)) {
+            log_softmax_inplace(view);
+        }
+        self.output = self.log_output.mapv(T::exp);
+    }
+}
+
+impl<T: 'static, D> SoftmaxWithLoss<T, D>
+where
+    T: CrateFloat,
+    D: Dimension + RemoveAxis,
+{
+    /// loss summed over samples and its gradient with the options applied
+    fn modified_loss(&self) -> (T, Array<T, D>) {
+        let one: T = cast_t2u(1.0);
+        let (gamma, alpha): (T, T) = self.focal.unwrap_or((self.zero, one));
+        let epsilon: T = self.label_smoothing;
+        let mut loss: T = self.zero;
+        let mut grad: Array<T, D> = Array::zeros(self.output.raw_dim());
+        for (((p, log_p), t), mut g) in self
+            .output
+            .axis_iter(Axis(self.axis))
+            .zip(self.log_output.axis_iter(Axis(self.axis)))
+            .zip(self.target.axis_iter(Axis(self.axis)))
+            .zip(grad.axis_iter_mut(Axis(self.axis)))
+        {
+            let num_classes: T = cast_t2u(p.len());
+            // coefficients of -log(p) in the cross entropy
+            let mut c = t.mapv(|t| ((one - epsilon) * t + epsilon / num_classes) * alpha);
+            if let Some(class_weights) = self.class_weights.as_ref() {
+                c *= &class_weights
+                    .broadcast(c.raw_dim())
+                    .expect("the length of class weights must be the number of classes");
+            }
+            // h = p * dloss/dp, so that dloss/dx = h - p sum(h)
+            Zip::from(&mut g)
+                .and(&p)
+                .and(&log_p)
+                .and(&c)
+                .for_each(|h, &p, &log_p, &c| {
+                    let q: T = one - p;
+                    let focal_term: T = if gamma == self.zero || q <= self.zero {
+                        self.zero
+                    } else {
+                        gamma * q.powf(gamma - one) * p * log_p
+                    };
+                    loss -= c * q.powf(gamma) * log_p;
+                    *h = -c * (q.powf(gamma) - focal_term);
+                });
+            let h_sum: T = g.sum();
+            Zip::from(&mut g).and(&p).for_each(|h, &p| *h -= p * h_sum);
         }
+        (loss, grad)
     }
 }
 
@@ -50,28 +162,80 @@ where
 {
     type A = Array<T, D>;
     fn forward(&mut self, x: &Self::A, t: &Self::A) -> T {
-        let batch_size: T = cast_t2u(x.len_of(Axis(0)));
+        let batch_size: T = cast_t2u(x.len_of(Axis(self.axis)));
 
-        self.output = x.clone();
-        for mut view in self.output.axis_iter_mut(Axis(self.axis)) {
-            let v_max = view.fold(self.zero / self.zero, |m, &v| v.max(m));
-            view.mapv_inplace(|v| T::exp(v - v_max));
-            let v_exp_sum = view.sum();
-            view.mapv_inplace(|v| v / v_exp_sum);
-        }
+        self.set_outputs(x);
         // self.output = dst.clone();
         self.target = t.clone();
+        self.labels = None;
+        if !self.is_plain() {
+            self.loss = self.modified_loss().0;
+            return self.loss / batch_size;
+        }
         self.loss = self
-            .output
+            .log_output
             .iter()
             .zip(t.iter())
-            .fold(self.zero, |m, (o_, t_)| {
-                m - *t_ * (*o_ + self.eps).log(self.e)
-            });
+            .fold(self.zero, |m, (log_p, t_)| m - *t_ * *log_p);
         self.loss / batch_size
     }
+    /// The cross-entropy is `-log p` at the labels, and the gradient is `p - 1` there.
+    /// Labels are expanded into one-hot targets only with the options of the loss.
+    fn forward_sparse(&mut self, x: &Self::A, t: &Array1<usize>) -> Result<T, CrateError> {
+        if x.ndim() != 2 || x.len_of(Axis(self.axis)) != t.len() {
+            return Err(CrateError::ShapeError(format!(
+                "sparse labels of {} samples do not match inputs of {:?}.",
+                t.len(),
+                x.shape()
+            )));
+        }
+        let num_classes: usize = x.len_of(Axis(1 - self.axis));
+        if let Some(label) = t.iter().find(|&&label| label >= num_classes) {
+            return Err(CrateError::ShapeError(format!(
+                "label {} is out of {} classes.",
+                label, num_classes
+            )));
+        }
+        if !self.is_plain() {
+            let mut one_hot: Array2<T> = to_one_hot(t, num_classes)?;
+            if self.axis == 1 {
+                one_hot = one_hot.reversed_axes();
+            }
+            let one_hot: Self::A = one_hot
+                .into_dimensionality()
+                .expect("inputs are two-dimensional");
+            return Ok(self.forward(x, &one_hot));
+        }
+        self.set_outputs(x);
+        let log_output: ArrayView2<T> = self
+            .log_output
+            .view()
+            .into_dimensionality()
+            .expect("inputs are two-dimensional");
+        self.loss = log_output
+            .axis_iter(Axis(self.axis))
+            .zip(t.iter())
+            .fold(self.zero, |m, (log_p, &label)| m - log_p[label]);
+        self.labels = Some(t.clone());
+        Ok(self.loss / cast_t2u(t.len()))
+    }
     fn backward(&mut self, _dx: T) -> Self::A {
+        if let Some(labels) = self.labels.as_ref() {
+            let batch_size: T = cast_t2u(labels.len());
+            let mut grad: Self::A = self.output.clone();
+            let mut grad2: ArrayViewMut2<T> = grad
+                .view_mut()
+                .into_dimensionality()
+                .expect("inputs are two-dimensional");
+            for (mut g, &label) in grad2.axis_iter_mut(Axis(self.axis)).zip(labels.iter()) {
+                g[label] -= cast_t2u(1.0);
+            }
+            return grad / batch_size;
+        }
         let batch_size: T = cast_t2u(self.target.len_of(Axis(self.axis)));
+        if !self.is_plain() {
+            return self.modified_loss().1 / batch_size;
+        }
         (self.output.clone() - &self.target) / batch_size
         //     let mut dst = Array::<T, D>::zeros(self.target.raw_dim());
         //     for (t, d, o) in multizip((self.target.iter(), dst.iter_mut(), self.output.iter())) {
@@ -81,6 +245,15 @@ where
     }
     fn print_detail(&self) {
         println!("softmax-with-loss layer.");
+        if let Some(class_weights) = self.class_weights.as_ref() {
+            println!("class weights: {}", class_weights);
+        }
+        if self.label_smoothing != self.zero {
+            println!("label smoothing: {}", self.label_smoothing);
+        }
+        if let Some((gamma, alpha)) = self.focal {
+            println!("focal loss: gamma {}, alpha {}", gamma, alpha);
+        }
     }
     fn get_output(&self) -> Self::A {
         self.output.clone()
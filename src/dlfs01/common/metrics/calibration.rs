@@ -0,0 +1,117 @@
+//! calibration
+//!
+//! Calibration diagnostics for classifiers
+//!
+//! The confidence of a sample is its highest probability, and the prediction is correct
+//! if the class of that probability is the label.
+
+use super::super::error::CrateError;
+use super::super::util::*;
+use ndarray::prelude::*;
+
+/// Data of a reliability diagram over equal-width bins of confidences in [0, 1]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReliabilityDiagram<T: CrateFloat> {
+    /// edges of bins, one more than the number of bins
+    pub bin_edges: Array1<T>,
+    /// mean confidence of samples in each bin, or 0 for an empty bin
+    pub confidences: Array1<T>,
+    /// accuracy of samples in each bin, or 0 for an empty bin
+    pub accuracies: Array1<T>,
+    /// number of samples in each bin
+    pub counts: Array1<usize>,
+}
+
+impl<T> ReliabilityDiagram<T>
+where
+    T: CrateFloat,
+{
+    /// expected calibration error, i.e. the gap between accuracies and confidences weighted by counts
+    pub fn expected_calibration_error(&self) -> T {
+        let total: usize = self.counts.sum();
+        if total == 0 {
+            return cast_t2u(0.0);
+        }
+        self.gaps()
+            .iter()
+            .zip(self.counts.iter())
+            .fold(cast_t2u(0.0), |m: T, (&gap, &count)| {
+                m + gap * cast_t2u(count)
+            })
+            / cast_t2u(total)
+    }
+    /// maximum calibration error, i.e. the largest gap between accuracies and confidences of bins
+    pub fn maximum_calibration_error(&self) -> T {
+        self.gaps()
+            .iter()
+            .zip(self.counts.iter())
+            .filter(|(_, &count)| count > 0)
+            .fold(cast_t2u(0.0), |m: T, (&gap, _)| m.max(gap))
+    }
+    fn gaps(&self) -> Array1<T> {
+        (&self.accuracies - &self.confidences).mapv(|v| v.abs())
+    }
+}
+
+/// reliability diagram of probabilities of (batch, classes) for labels of class indices
+pub fn reliability_diagram<T: CrateFloat>(
+    prob: &Array2<T>,
+    labels: &Array1<usize>,
+    num_bins: usize,
+) -> Result<ReliabilityDiagram<T>, CrateError> {
+    if num_bins == 0 {
+        return Err(CrateError::ConfigError(
+            "the number of bins must be positive.".to_string(),
+        ));
+    }
+    if prob.nrows() != labels.len() {
+        return Err(CrateError::ShapeError(format!(
+            "{} samples of probabilities do not match {} labels.",
+            prob.nrows(),
+            labels.len()
+        )));
+    }
+    let predictions: Array1<usize> = to_class_indices(prob);
+    let mut confidences: Array1<T> = Array1::zeros(num_bins);
+    let mut accuracies: Array1<T> = Array1::zeros(num_bins);
+    let mut counts: Array1<usize> = Array1::zeros(num_bins);
+    for ((row, &prediction), &label) in prob.outer_iter().zip(predictions.iter()).zip(labels.iter())
+    {
+        let confidence: T = row[prediction];
+        let bin: usize =
+            cast_t2u::<T, usize>((confidence * cast_t2u(num_bins)).floor().max(cast_t2u(0.0)))
+                .min(num_bins - 1);
+        confidences[bin] += confidence;
+        if prediction == label {
+            accuracies[bin] += cast_t2u(1.0);
+        }
+        counts[bin] += 1;
+    }
+    for ((confidence, accuracy), &count) in confidences
+        .iter_mut()
+        .zip(accuracies.iter_mut())
+        .zip(counts.iter())
+    {
+        if count > 0 {
+            *confidence /= cast_t2u(count);
+            *accuracy /= cast_t2u(count);
+        }
+    }
+    Ok(ReliabilityDiagram {
+        bin_edges: Array1::linspace(cast_t2u(0.0), cast_t2u(1.0), num_bins + 1),
+        confidences,
+        accuracies,
+        counts,
+    })
+}
+
+/// expected calibration error of probabilities over equal-width bins
+///
+/// See `ReliabilityDiagram::expected_calibration_error` in detail.
+pub fn expected_calibration_error<T: CrateFloat>(
+    prob: &Array2<T>,
+    labels: &Array1<usize>,
+    num_bins: usize,
+) -> Result<T, CrateError> {
+    Ok(reliability_diagram(prob, labels, num_bins)?.expected_calibration_error())
+}
@@ -0,0 +1,399 @@
+//! classification
+//!
+//! Metrics for classifiers
+//!
+//! Scores are (batch, classes) arrays such as outputs of `predict_prob`, and labels are class indices.
+//! `ClassificationMetricEnum` takes the softmax of scores which are not probabilities
+//! for the log-loss and the AUCs, so logits can be given as well.
+
+use super::super::error::CrateError;
+use super::super::layers::{LayerBase, Softmax};
+use super::super::util::*;
+use ndarray::{prelude::*, Zip};
+use std::cmp::Ordering;
+use std::fmt::Display;
+
+const LOG_LOSS_EPS: f64 = 1E-15;
+/// tolerance of the sum of probabilities of a sample
+const PROB_SUM_TOLERANCE: f64 = 1E-6;
+
+/// Enum of averages of metrics over classes
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum AverageEnum {
+    /// unweighted mean of the metrics of classes
+    #[default]
+    Macro,
+    /// metric of the counts summed over classes
+    Micro,
+}
+
+impl Display for AverageEnum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AverageEnum::Macro => write!(f, "Macro"),
+            AverageEnum::Micro => write!(f, "Micro"),
+        }
+    }
+}
+
+/// Enum of classification metrics, which can be logged by `Trainer`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ClassificationMetricEnum {
+    Accuracy,
+    Precision(AverageEnum),
+    Recall(AverageEnum),
+    F1(AverageEnum),
+    /// number of the highest scores among which the label is looked for
+    TopK(usize),
+    LogLoss,
+    RocAuc(AverageEnum),
+    PrAuc(AverageEnum),
+}
+
+impl Display for ClassificationMetricEnum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClassificationMetricEnum::Accuracy => write!(f, "Accuracy"),
+            ClassificationMetricEnum::Precision(average) => write!(f, "Precision ({})", average),
+            ClassificationMetricEnum::Recall(average) => write!(f, "Recall ({})", average),
+            ClassificationMetricEnum::F1(average) => write!(f, "F1 ({})", average),
+            ClassificationMetricEnum::TopK(k) => write!(f, "Top-{} accuracy", k),
+            ClassificationMetricEnum::LogLoss => write!(f, "Log-loss"),
+            ClassificationMetricEnum::RocAuc(average) => write!(f, "ROC-AUC ({})", average),
+            ClassificationMetricEnum::PrAuc(average) => write!(f, "PR-AUC ({})", average),
+        }
+    }
+}
+
+impl ClassificationMetricEnum {
+    /// compute the metric of scores for labels
+    pub fn compute<T: CrateFloat>(
+        &self,
+        scores: &Array2<T>,
+        labels: &Array1<usize>,
+    ) -> Result<T, CrateError> {
+        let num_classes: usize = scores.ncols();
+        match self {
+            ClassificationMetricEnum::Accuracy => accuracy(scores, labels),
+            ClassificationMetricEnum::Precision(average) => {
+                let cm = confusion_matrix(labels, &argmax_rows(scores), num_classes)?;
+                Ok(precision(&cm, average))
+            }
+            ClassificationMetricEnum::Recall(average) => {
+                let cm = confusion_matrix(labels, &argmax_rows(scores), num_classes)?;
+                Ok(recall(&cm, average))
+            }
+            ClassificationMetricEnum::F1(average) => {
+                let cm = confusion_matrix(labels, &argmax_rows(scores), num_classes)?;
+                Ok(f1_score(&cm, average))
+            }
+            ClassificationMetricEnum::TopK(k) => top_k_accuracy(scores, labels, *k),
+            ClassificationMetricEnum::LogLoss => log_loss(&to_probabilities(scores), labels),
+            ClassificationMetricEnum::RocAuc(average) => {
+                roc_auc(&to_probabilities(scores), labels, average)
+            }
+            ClassificationMetricEnum::PrAuc(average) => {
+                pr_auc(&to_probabilities(scores), labels, average)
+            }
+        }
+    }
+}
+
+fn check_labels<T>(scores: &Array2<T>, labels: &Array1<usize>) -> Result<(), CrateError> {
+    if scores.nrows() != labels.len() {
+        return Err(CrateError::ShapeError(format!(
+            "{} samples of scores do not match {} labels.",
+            scores.nrows(),
+            labels.len()
+        )));
+    }
+    if let Some(label) = labels.iter().find(|&&label| label >= scores.ncols()) {
+        return Err(CrateError::ShapeError(format!(
+            "label {} is out of {} classes.",
+            label,
+            scores.ncols()
+        )));
+    }
+    Ok(())
+}
+
+/// scores as they are if they are probabilities of classes, and their softmax otherwise
+pub fn to_probabilities<T: 'static + CrateFloat>(scores: &Array2<T>) -> Array2<T> {
+    let zero: T = cast_t2u(0.0);
+    let one: T = cast_t2u(1.0);
+    let is_prob: bool = scores.iter().all(|&p| p >= zero && p <= one)
+        && scores
+            .sum_axis(Axis(1))
+            .iter()
+            .all(|&sum| (sum - one).abs() <= cast_t2u(PROB_SUM_TOLERANCE));
+    if is_prob {
+        scores.clone()
+    } else {
+        Softmax::new(scores.raw_dim(), 0).forward(scores)
+    }
+}
+
+/// index of the highest score of each sample
+fn argmax_rows<T: CrateFloat>(scores: &Array2<T>) -> Array1<usize> {
+    to_class_indices(scores)
+}
+
+/// ratio with zero for a zero denominator
+fn ratio<T: CrateFloat>(numerator: usize, denominator: usize) -> T {
+    if denominator == 0 {
+        cast_t2u(0.0)
+    } else {
+        cast_t2u::<usize, T>(numerator) / cast_t2u(denominator)
+    }
+}
+
+/// fraction of samples whose highest score is of the label
+pub fn accuracy<T: CrateFloat>(
+    scores: &Array2<T>,
+    labels: &Array1<usize>,
+) -> Result<T, CrateError> {
+    check_labels(scores, labels)?;
+    let num_correct: usize = argmax_rows(scores)
+        .iter()
+        .zip(labels.iter())
+        .filter(|(y, t)| y == t)
+        .count();
+    Ok(ratio(num_correct, labels.len()))
+}
+
+/// fraction of samples whose label is among the `k` highest scores
+///
+/// Ties with the `k`-th score count in favor of the label.
+pub fn top_k_accuracy<T: CrateFloat>(
+    scores: &Array2<T>,
+    labels: &Array1<usize>,
+    k: usize,
+) -> Result<T, CrateError> {
+    check_labels(scores, labels)?;
+    if k == 0 {
+        return Err(CrateError::ConfigError(
+            "k of the top-k accuracy must be positive.".to_string(),
+        ));
+    }
+    let num_correct: usize = scores
+        .outer_iter()
+        .zip(labels.iter())
+        .filter(|(row, &label)| row.iter().filter(|&&v| v > row[label]).count() < k)
+        .count();
+    Ok(ratio(num_correct, labels.len()))
+}
+
+/// mean negative log-likelihood of labels for probabilities, which are clipped at 1e-15
+pub fn log_loss<T: CrateFloat>(prob: &Array2<T>, labels: &Array1<usize>) -> Result<T, CrateError> {
+    check_labels(prob, labels)?;
+    let eps: T = cast_t2u(LOG_LOSS_EPS);
+    let loss: T = prob
+        .outer_iter()
+        .zip(labels.iter())
+        .fold(cast_t2u(0.0), |m, (row, &label)| {
+            m - row[label].max(eps).ln()
+        });
+    Ok(loss / cast_t2u(labels.len()))
+}
+
+/// confusion matrix whose rows are true classes and columns are predicted classes
+pub fn confusion_matrix(
+    labels: &Array1<usize>,
+    predictions: &Array1<usize>,
+    num_classes: usize,
+) -> Result<Array2<usize>, CrateError> {
+    if labels.len() != predictions.len() {
+        return Err(CrateError::ShapeError(format!(
+            "{} labels do not match {} predictions.",
+            labels.len(),
+            predictions.len()
+        )));
+    }
+    let mut dst: Array2<usize> = Array2::zeros((num_classes, num_classes));
+    for (&t, &y) in labels.iter().zip(predictions.iter()) {
+        if t >= num_classes || y >= num_classes {
+            return Err(CrateError::ShapeError(format!(
+                "class {} is out of {} classes.",
+                t.max(y),
+                num_classes
+            )));
+        }
+        dst[[t, y]] += 1;
+    }
+    Ok(dst)
+}
+
+/// precision, recall and F1 score of each class from a confusion matrix
+///
+/// Scores with a zero denominator are 0.
+pub fn per_class_scores<T: CrateFloat>(cm: &Array2<usize>) -> (Array1<T>, Array1<T>, Array1<T>) {
+    let two: T = cast_t2u(2.0);
+    let true_positives: Array1<usize> = cm.diag().to_owned();
+    let num_predicted: Array1<usize> = cm.sum_axis(Axis(0));
+    let num_actual: Array1<usize> = cm.sum_axis(Axis(1));
+    let precision: Array1<T> = Zip::from(&true_positives)
+        .and(&num_predicted)
+        .map_collect(|&tp, &n| ratio(tp, n));
+    let recall: Array1<T> = Zip::from(&true_positives)
+        .and(&num_actual)
+        .map_collect(|&tp, &n| ratio(tp, n));
+    let f1: Array1<T> = Zip::from(&precision).and(&recall).map_collect(|&p, &r| {
+        if p + r > cast_t2u(0.0) {
+            two * p * r / (p + r)
+        } else {
+            cast_t2u(0.0)
+        }
+    });
+    (precision, recall, f1)
+}
+
+fn mean<T: CrateFloat>(values: &Array1<T>) -> T {
+    values.mean().unwrap_or_else(|| cast_t2u(0.0))
+}
+
+/// precision averaged over classes
+pub fn precision<T: CrateFloat>(cm: &Array2<usize>, average: &AverageEnum) -> T {
+    match average {
+        AverageEnum::Macro => mean(&per_class_scores(cm).0),
+        AverageEnum::Micro => ratio(cm.diag().sum(), cm.sum()),
+    }
+}
+
+/// recall averaged over classes
+pub fn recall<T: CrateFloat>(cm: &Array2<usize>, average: &AverageEnum) -> T {
+    match average {
+        AverageEnum::Macro => mean(&per_class_scores(cm).1),
+        AverageEnum::Micro => ratio(cm.diag().sum(), cm.sum()),
+    }
+}
+
+/// F1 score averaged over classes
+///
+/// The macro average is the mean of F1 scores of classes, not the F1 score of macro precision and recall.
+pub fn f1_score<T: CrateFloat>(cm: &Array2<usize>, average: &AverageEnum) -> T {
+    match average {
+        AverageEnum::Macro => mean(&per_class_scores(cm).2),
+        // micro precision and recall are the same for single-label classification
+        AverageEnum::Micro => ratio(cm.diag().sum(), cm.sum()),
+    }
+}
+
+/// pairs of scores and flags of positives sorted by descending scores
+fn sorted_pairs<T: CrateFloat>(pairs: &mut [(T, bool)]) {
+    pairs.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+}
+
+/// lengths of runs of equal scores in sorted pairs
+fn tie_groups<T: CrateFloat>(pairs: &[(T, bool)]) -> Vec<usize> {
+    let mut groups: Vec<usize> = Vec::new();
+    let mut start: usize = 0;
+    for ii in 1..=pairs.len() {
+        if ii == pairs.len() || pairs[ii].0 != pairs[start].0 {
+            groups.push(ii - start);
+            start = ii;
+        }
+    }
+    groups
+}
+
+/// area under the ROC curve, or None without positives or negatives
+///
+/// This is the probability that a positive scores higher than a negative, counting ties as half.
+fn binary_roc_auc<T: CrateFloat>(mut pairs: Vec<(T, bool)>) -> Option<T> {
+    sorted_pairs(&mut pairs);
+    let num_positives: usize = pairs.iter().filter(|v| v.1).count();
+    let num_negatives: usize = pairs.len() - num_positives;
+    if num_positives == 0 || num_negatives == 0 {
+        return None;
+    }
+    let half: T = cast_t2u(0.5);
+    // count pairs of a positive and a lower negative, going from the lowest scores
+    let mut area: T = cast_t2u(0.0);
+    let mut negatives_below: usize = 0;
+    let mut end: usize = pairs.len();
+    for size in tie_groups(&pairs).into_iter().rev() {
+        let group = &pairs[end - size..end];
+        let positives: usize = group.iter().filter(|v| v.1).count();
+        let negatives: usize = size - positives;
+        area += cast_t2u::<usize, T>(positives)
+            * (cast_t2u::<usize, T>(negatives_below) + half * cast_t2u(negatives));
+        negatives_below += negatives;
+        end -= size;
+    }
+    Some(area / cast_t2u(num_positives * num_negatives))
+}
+
+/// average precision, i.e. precisions weighted by increments of recall, or None without positives
+fn binary_average_precision<T: CrateFloat>(mut pairs: Vec<(T, bool)>) -> Option<T> {
+    sorted_pairs(&mut pairs);
+    let num_positives: usize = pairs.iter().filter(|v| v.1).count();
+    if num_positives == 0 {
+        return None;
+    }
+    let mut area: T = cast_t2u(0.0);
+    let (mut true_positives, mut start): (usize, usize) = (0, 0);
+    for size in tie_groups(&pairs) {
+        let positives: usize = pairs[start..start + size].iter().filter(|v| v.1).count();
+        true_positives += positives;
+        start += size;
+        area += ratio::<T>(positives, num_positives) * ratio(true_positives, start);
+    }
+    Some(area)
+}
+
+/// one-vs-rest binary metric averaged over classes
+///
+/// Classes where the metric is undefined are left out of the macro average,
+/// and the result is NaN if it is undefined for all classes.
+fn one_vs_rest<T, F>(
+    scores: &Array2<T>,
+    labels: &Array1<usize>,
+    average: &AverageEnum,
+    metric: F,
+) -> Result<T, CrateError>
+where
+    T: CrateFloat,
+    F: Fn(Vec<(T, bool)>) -> Option<T>,
+{
+    check_labels(scores, labels)?;
+    let pairs = |k: usize| -> Vec<(T, bool)> {
+        scores
+            .column(k)
+            .iter()
+            .zip(labels.iter())
+            .map(|(&v, &label)| (v, label == k))
+            .collect()
+    };
+    let value: Option<T> = match average {
+        AverageEnum::Macro => {
+            let values: Vec<T> = (0..scores.ncols())
+                .filter_map(|k| metric(pairs(k)))
+                .collect();
+            if values.is_empty() {
+                None
+            } else {
+                Some(mean(&Array1::from(values)))
+            }
+        }
+        AverageEnum::Micro => metric((0..scores.ncols()).flat_map(pairs).collect()),
+    };
+    Ok(value.unwrap_or_else(T::nan))
+}
+
+/// one-vs-rest area under the ROC curve
+pub fn roc_auc<T: CrateFloat>(
+    scores: &Array2<T>,
+    labels: &Array1<usize>,
+    average: &AverageEnum,
+) -> Result<T, CrateError> {
+    one_vs_rest(scores, labels, average, binary_roc_auc)
+}
+
+/// one-vs-rest area under the precision-recall curve as the average precision
+pub fn pr_auc<T: CrateFloat>(
+    scores: &Array2<T>,
+    labels: &Array1<usize>,
+    average: &AverageEnum,
+) -> Result<T, CrateError> {
+    one_vs_rest(scores, labels, average, binary_average_precision)
+}
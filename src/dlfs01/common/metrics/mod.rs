@@ -0,0 +1,17 @@
+//! metrics
+//!
+//! Metrics for evaluating models
+
+pub mod calibration;
+pub mod classification;
+pub mod regression;
+
+pub use calibration::{expected_calibration_error, reliability_diagram, ReliabilityDiagram};
+pub use classification::{
+    accuracy, confusion_matrix, f1_score, log_loss, per_class_scores, pr_auc, precision, recall,
+    roc_auc, to_probabilities, top_k_accuracy, AverageEnum, ClassificationMetricEnum,
+};
+pub use regression::{
+    explained_variance_score, mean_absolute_error, mean_squared_error, r2_score,
+    root_mean_squared_error,
+};
@@ -0,0 +1,81 @@
+//! regression
+//!
+//! Metrics for regressors
+//!
+//! Predictions and targets are (batch, outputs) arrays, and metrics of outputs are averaged uniformly.
+
+use super::super::error::CrateError;
+use super::super::util::*;
+use ndarray::{prelude::*, Zip};
+
+fn check_shapes<T>(y: &Array2<T>, t: &Array2<T>) -> Result<(), CrateError> {
+    if y.shape() != t.shape() {
+        return Err(CrateError::ShapeError(format!(
+            "predictions of {:?} do not match targets of {:?}.",
+            y.shape(),
+            t.shape()
+        )));
+    }
+    if y.nrows() == 0 {
+        return Err(CrateError::ShapeError("no samples are given.".to_string()));
+    }
+    Ok(())
+}
+
+/// mean over samples and outputs
+fn mean<T: CrateFloat>(x: &Array2<T>) -> T {
+    x.sum() / cast_t2u(x.len())
+}
+
+/// variance of each output over samples
+fn variances<T: CrateFloat>(x: &Array2<T>) -> Array1<T> {
+    x.var_axis(Axis(0), cast_t2u(0.0))
+}
+
+pub fn mean_squared_error<T: CrateFloat>(y: &Array2<T>, t: &Array2<T>) -> Result<T, CrateError> {
+    check_shapes(y, t)?;
+    Ok(mean(&(y - t).mapv(|v| v * v)))
+}
+
+pub fn root_mean_squared_error<T: CrateFloat>(
+    y: &Array2<T>,
+    t: &Array2<T>,
+) -> Result<T, CrateError> {
+    Ok(mean_squared_error(y, t)?.sqrt())
+}
+
+pub fn mean_absolute_error<T: CrateFloat>(y: &Array2<T>, t: &Array2<T>) -> Result<T, CrateError> {
+    check_shapes(y, t)?;
+    Ok(mean(&(y - t).mapv(|v| v.abs())))
+}
+
+/// `1 - a / b` of each output, which is 1 for a perfect fit of constant targets and 0 otherwise
+fn one_minus_ratio<T: CrateFloat>(a: &Array1<T>, b: &Array1<T>) -> T {
+    let (zero, one): (T, T) = (cast_t2u(0.0), cast_t2u(1.0));
+    let scores: Array1<T> = Zip::from(a).and(b).map_collect(|&a, &b| {
+        if b > zero {
+            one - a / b
+        } else if a > zero {
+            zero
+        } else {
+            one
+        }
+    });
+    scores.sum() / cast_t2u(scores.len())
+}
+
+/// coefficient of determination `1 - sum((t - y)^2) / sum((t - mean(t))^2)`
+pub fn r2_score<T: CrateFloat>(y: &Array2<T>, t: &Array2<T>) -> Result<T, CrateError> {
+    check_shapes(y, t)?;
+    let residuals: Array1<T> = (t - y).mapv(|v| v * v).mean_axis(Axis(0)).unwrap();
+    Ok(one_minus_ratio(&residuals, &variances(t)))
+}
+
+/// explained variance `1 - var(t - y) / var(t)`, which is the R^2 score ignoring biases of predictions
+pub fn explained_variance_score<T: CrateFloat>(
+    y: &Array2<T>,
+    t: &Array2<T>,
+) -> Result<T, CrateError> {
+    check_shapes(y, t)?;
+    Ok(one_minus_ratio(&variances(&(t - y)), &variances(t)))
+}
@@ -5,11 +5,15 @@
 // pub mod functions;
 pub mod choice;
 pub mod choice_ndarray;
+pub mod error;
 pub mod gradient;
+pub mod im2col;
 pub mod layers;
 pub mod loss_function;
 pub mod math;
+pub mod metrics;
 pub mod models;
+pub mod onnx;
 pub mod operators;
 pub mod optimizers;
 pub mod param_initializers;
@@ -0,0 +1,245 @@
+//! calibration
+//!
+//! Post-hoc calibration of classifiers
+//!
+//! Logits are transformed before the softmax so that probabilities match the accuracy,
+//! by parameters fitted to minimize the negative log-likelihood on a validation set.
+//! See https://arxiv.org/abs/1706.04599 in detail.
+
+use super::super::error::CrateError;
+use super::super::layers::{LayerBase, LogSoftmax};
+use super::super::util::*;
+use ndarray::prelude::*;
+use std::fmt::Display;
+
+/// maximum number of iterations in fitting
+const MAX_ITER: usize = 500;
+/// tolerance of the squared norm of the gradient in fitting
+const TOLERANCE: f64 = 1E-12;
+
+/// Enum of calibrations of logits
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum CalibrationEnum<T: CrateFloat> {
+    #[default]
+    None,
+    /// temperature dividing logits
+    Temperature(T),
+    /// scale and bias of each class
+    Vector { weights: Vec<T>, biases: Vec<T> },
+    /// linear transform `W z + b` of logits, where `weights` are rows of `W`
+    Matrix {
+        weights: Vec<Vec<T>>,
+        biases: Vec<T>,
+    },
+}
+
+impl<T> Display for CalibrationEnum<T>
+where
+    T: CrateFloat,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CalibrationEnum::None => write!(f, "None"),
+            CalibrationEnum::Temperature(temperature) => {
+                write!(f, "Temperature ({})", temperature)
+            }
+            CalibrationEnum::Vector { weights, biases } => {
+                write!(f, "Vector (weights: {:?}, biases: {:?})", weights, biases)
+            }
+            CalibrationEnum::Matrix { weights, biases } => {
+                write!(f, "Matrix (weights: {:?}, biases: {:?})", weights, biases)
+            }
+        }
+    }
+}
+
+impl<T: 'static> CalibrationEnum<T>
+where
+    T: CrateFloat,
+{
+    /// calibrated logits of (batch, classes)
+    pub fn apply(&self, logits: &Array2<T>) -> Array2<T> {
+        match self {
+            CalibrationEnum::None => logits.clone(),
+            CalibrationEnum::Temperature(temperature) => logits / *temperature,
+            CalibrationEnum::Vector { weights, biases } => {
+                logits * &aview1(weights) + aview1(biases)
+            }
+            CalibrationEnum::Matrix { weights, biases } => {
+                logits.dot(&matrix_from_rows(weights).t()) + aview1(biases)
+            }
+        }
+    }
+}
+
+/// Enum of methods of calibration
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CalibrationMethodEnum {
+    /// temperature scaling with a single parameter, which keeps predictions
+    Temperature,
+    /// vector scaling with a scale and a bias of each class
+    Vector,
+    /// matrix scaling with a linear transform of logits
+    Matrix,
+}
+
+impl Display for CalibrationMethodEnum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CalibrationMethodEnum::Temperature => write!(f, "Temperature"),
+            CalibrationMethodEnum::Vector => write!(f, "Vector"),
+            CalibrationMethodEnum::Matrix => write!(f, "Matrix"),
+        }
+    }
+}
+
+fn matrix_from_rows<T: CrateFloat>(rows: &[Vec<T>]) -> Array2<T> {
+    let ncols: usize = rows.first().map_or(0, |row| row.len());
+    Array2::from_shape_fn((rows.len(), ncols), |(ii, jj)| rows[ii][jj])
+}
+
+/// mean negative log-likelihood of labels for logits and its gradient with respect to the logits
+fn nll_and_grad<T: 'static + CrateFloat>(
+    logits: &Array2<T>,
+    labels: &Array1<usize>,
+) -> (T, Array2<T>) {
+    let batch_size: T = cast_t2u(labels.len());
+    let log_prob: Array2<T> = LogSoftmax::new(logits.raw_dim(), 0).forward(logits);
+    let mut grad: Array2<T> = log_prob.mapv(T::exp);
+    let mut loss: T = cast_t2u(0.0);
+    for ((mut row, log_p), &label) in grad
+        .outer_iter_mut()
+        .zip(log_prob.outer_iter())
+        .zip(labels.iter())
+    {
+        loss -= log_p[label];
+        row[label] -= cast_t2u(1.0);
+    }
+    (loss / batch_size, grad / batch_size)
+}
+
+/// minimize a smooth convex function by gradient descent with a backtracking line search
+fn minimize<T, F>(mut params: Array1<T>, f: F) -> Array1<T>
+where
+    T: CrateFloat,
+    F: Fn(&Array1<T>) -> (T, Array1<T>),
+{
+    let half: T = cast_t2u(0.5);
+    let mut step: T = cast_t2u(1.0);
+    for _ in 0..MAX_ITER {
+        let (loss, grad) = f(&params);
+        let grad_norm2: T = grad.dot(&grad);
+        if grad_norm2 <= cast_t2u(TOLERANCE) {
+            break;
+        }
+        loop {
+            let candidate: Array1<T> = &params - &(&grad * step);
+            if f(&candidate).0 <= loss - half * step * grad_norm2 {
+                params = candidate;
+                step = step + step;
+                break;
+            }
+            step *= half;
+            if step < cast_t2u(TOLERANCE) {
+                return params;
+            }
+        }
+    }
+    params
+}
+
+fn check_labels<T>(logits: &Array2<T>, labels: &Array1<usize>) -> Result<(), CrateError> {
+    if logits.nrows() != labels.len() || logits.nrows() == 0 {
+        return Err(CrateError::ShapeError(format!(
+            "{} samples of logits do not match {} labels.",
+            logits.nrows(),
+            labels.len()
+        )));
+    }
+    if let Some(label) = labels.iter().find(|&&label| label >= logits.ncols()) {
+        return Err(CrateError::ShapeError(format!(
+            "label {} is out of {} classes.",
+            label,
+            logits.ncols()
+        )));
+    }
+    Ok(())
+}
+
+/// fit a calibration of logits of (batch, classes) for labels of class indices
+pub fn fit_calibration<T: 'static + CrateFloat>(
+    logits: &Array2<T>,
+    labels: &Array1<usize>,
+    method: &CalibrationMethodEnum,
+) -> Result<CalibrationEnum<T>, CrateError> {
+    check_labels(logits, labels)?;
+    let num_classes: usize = logits.ncols();
+    match method {
+        CalibrationMethodEnum::Temperature => {
+            // the loss is convex in the inverse of the temperature
+            let params: Array1<T> = minimize(Array1::ones(1), |params: &Array1<T>| {
+                let (loss, grad) = nll_and_grad(&(logits * params[0]), labels);
+                (loss, array![(&grad * logits).sum()])
+            });
+            if params[0].is_nan() || params[0] <= cast_t2u(0.0) {
+                return Err(CrateError::ConfigError(format!(
+                    "the fitted inverse temperature must be positive, found {}.",
+                    params[0]
+                )));
+            }
+            Ok(CalibrationEnum::Temperature(params[0].recip()))
+        }
+        CalibrationMethodEnum::Vector => {
+            let mut initial: Array1<T> = Array1::zeros(2 * num_classes);
+            initial.slice_mut(s![..num_classes]).fill(cast_t2u(1.0));
+            let params = minimize(initial, |params| {
+                let (weights, biases) = params.view().split_at(Axis(0), num_classes);
+                let (loss, grad) = nll_and_grad(&(logits * &weights + biases), labels);
+                let dweights: Array1<T> = (&grad * logits).sum_axis(Axis(0));
+                let dbiases: Array1<T> = grad.sum_axis(Axis(0));
+                (loss, ndarray::concatenate![Axis(0), dweights, dbiases])
+            });
+            Ok(CalibrationEnum::Vector {
+                weights: params.slice(s![..num_classes]).to_vec(),
+                biases: params.slice(s![num_classes..]).to_vec(),
+            })
+        }
+        CalibrationMethodEnum::Matrix => {
+            let num_weights: usize = num_classes * num_classes;
+            let initial: Array1<T> = ndarray::concatenate![
+                Axis(0),
+                Array2::<T>::eye(num_classes)
+                    .into_shape(num_weights)
+                    .unwrap(),
+                Array1::<T>::zeros(num_classes)
+            ];
+            let params = minimize(initial, |params| {
+                let weights = params
+                    .slice(s![..num_weights])
+                    .into_shape((num_classes, num_classes))
+                    .unwrap();
+                let biases = params.slice(s![num_weights..]);
+                let (loss, grad) = nll_and_grad(&(logits.dot(&weights.t()) + biases), labels);
+                let dweights: Array2<T> = grad.t().dot(logits);
+                let dbiases: Array1<T> = grad.sum_axis(Axis(0));
+                (
+                    loss,
+                    ndarray::concatenate![
+                        Axis(0),
+                        dweights.into_shape(num_weights).unwrap(),
+                        dbiases
+                    ],
+                )
+            });
+            Ok(CalibrationEnum::Matrix {
+                weights: params
+                    .slice(s![..num_weights])
+                    .exact_chunks(num_classes)
+                    .into_iter()
+                    .map(|row| row.to_vec())
+                    .collect(),
+                biases: params.slice(s![num_weights..]).to_vec(),
+            })
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! migration
+//!
+//! Migrations of model schemes saved with older format versions
+//!
+//! Each migration upgrades a scheme by one format version, and schemes are upgraded
+//! step by step up to `CURRENT_FORMAT_VERSION` before deserialization.
+
+use super::model_params::SchemeError;
+use serde_json::{Map, Value};
+
+/// format version of model schemes written by this crate
+pub const CURRENT_FORMAT_VERSION: u32 = 3;
+
+type Migration = fn(&mut Map<String, Value>) -> Result<(), SchemeError>;
+
+/// migrations indexed by the format version they upgrade from
+const MIGRATIONS: [Migration; CURRENT_FORMAT_VERSION as usize] =
+    [migrate_v0_to_v1, migrate_v1_to_v2, migrate_v2_to_v3];
+
+/// upgrade a scheme to `CURRENT_FORMAT_VERSION`
+pub fn migrate(mut scheme: Value) -> Result<Value, SchemeError> {
+    let fields: &mut Map<String, Value> =
+        scheme
+            .as_object_mut()
+            .ok_or_else(|| SchemeError::FieldError {
+                path: ".".to_string(),
+                message: "a scheme must be a map of fields.".to_string(),
+            })?;
+    let version: u32 = format_version(fields)?;
+    if version > CURRENT_FORMAT_VERSION {
+        return Err(SchemeError::VersionError {
+            found: version,
+            supported: CURRENT_FORMAT_VERSION,
+        });
+    }
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        migration(fields)?;
+        fields.insert("format_version".to_string(), Value::from(from as u32 + 1));
+    }
+    Ok(scheme)
+}
+
+/// format version of a scheme; schemes without the field are version 0
+fn format_version(fields: &Map<String, Value>) -> Result<u32, SchemeError> {
+    match fields.get("format_version") {
+        None => Ok(0),
+        Some(value) => value
+            .as_u64()
+            .filter(|&v| v <= u32::MAX as u64)
+            .map(|v| v as u32)
+            .ok_or_else(|| SchemeError::FieldError {
+                path: "format_version".to_string(),
+                message: format!("expected a non-negative integer, found `{}`.", value),
+            }),
+    }
+}
+
+/// version 0: schemes saved before `format_version` was introduced
+///
+/// The fields are unchanged, so only the version is set.
+fn migrate_v0_to_v1(_fields: &mut Map<String, Value>) -> Result<(), SchemeError> {
+    Ok(())
+}
+
+/// version 1: hyperparameters of optimizers were serialized as tuples
+///
+/// e.g. `{"Adam": [0.001, 0.9, 0.999]}` is converted into
+/// `{"Adam": {"lr": 0.001, "beta1": 0.9, "beta2": 0.999}}`.
+fn migrate_v1_to_v2(fields: &mut Map<String, Value>) -> Result<(), SchemeError> {
+    let optimizer: &mut Map<String, Value> = match fields.get_mut("optimizer_enum") {
+        Some(Value::Object(optimizer)) => optimizer,
+        // invalid values are reported by deserialization
+        _ => return Ok(()),
+    };
+    for (name, hyperparameters) in optimizer.iter_mut() {
+        let keys: &[&str] = match name.as_str() {
+            "Momentum" | "Nesterov" => &["lr", "momentum"],
+            "RMSprop" => &["lr", "decay_rate"],
+            "Adam" => &["lr", "beta1", "beta2"],
+            _ => &["lr"],
+        };
+        let values: Vec<Value> = match &*hyperparameters {
+            Value::Object(_) => continue,
+            Value::Array(values) => values.clone(),
+            value => vec![value.clone()],
+        };
+        if values.len() != keys.len() {
+            return Err(SchemeError::FieldError {
+                path: format!("optimizer_enum.{}", name),
+                message: format!(
+                    "expected {} hyperparameters, found {}.",
+                    keys.len(),
+                    values.len()
+                ),
+            });
+        }
+        *hyperparameters =
+            Value::Object(keys.iter().map(|key| key.to_string()).zip(values).collect());
+    }
+    Ok(())
+}
+
+/// version 2: batch normalization was the only normalization of hidden layers
+///
+/// e.g. `"use_batch_norm": {"Use": 0.9}` is converted into `"normalization": {"BatchNorm": 0.9}`
+/// both in the global fields and in each of `layers`.
+fn migrate_v2_to_v3(fields: &mut Map<String, Value>) -> Result<(), SchemeError> {
+    rename_batch_norm(fields);
+    if let Some(Value::Array(layers)) = fields.get_mut("layers") {
+        for layer in layers.iter_mut() {
+            if let Value::Object(layer) = layer {
+                rename_batch_norm(layer);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn rename_batch_norm(fields: &mut Map<String, Value>) {
+    let value: Value = match fields.remove("use_batch_norm") {
+        Some(value) => value,
+        None => return,
+    };
+    let value: Value = match value {
+        Value::Object(mut use_batch_norm) => match use_batch_norm.remove("Use") {
+            Some(momentum) => serde_json::json!({ "BatchNorm": momentum }),
+            // invalid values are reported by deserialization
+            None => Value::Object(use_batch_norm),
+        },
+        value => value,
+    };
+    fields.insert("normalization".to_string(), value);
+}
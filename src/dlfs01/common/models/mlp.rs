@@ -1,6 +1,6 @@
 //! mlp
 //!
-//! Multi-layer perceptron model
+//! Multi-layer perceptron models
 //!
 //! This model can be used only with datasets composed of 1D data.
 
@@ -11,27 +11,39 @@
 use ndarray::prelude::*;
 use ndarray_stats::QuantileExt;
 use serde::Deserialize;
-use std::io::{self, ErrorKind};
+use std::io;
 use std::path::Path;
 
 use crate::dlfs01::common::regularizers::{call_regularizer, RegularizerBase, RegularizerEnum};
 
+use super::super::error::CrateError;
+use super::super::metrics;
 use super::super::optimizers::*;
 use super::super::param_initializers::weight_init::WeightInitEnum;
 use super::super::util::*;
-use super::model_base::ModelBase;
-use super::model_params::ModelParameters;
+use super::calibration::{fit_calibration, CalibrationEnum, CalibrationMethodEnum};
+use super::model_base::{multi_hot_accuracy, ModelBase, SparseModelBase};
+use super::model_params::{LayerParameters, ModelParameters};
 use super::{super::layers::*, ModelEnum};
 
+/// threshold of probabilities in `accuracy` for multi-hot targets of `SigmoidWithBCELoss`
+const MULTI_LABEL_THRESHOLD: f64 = 0.5;
+
 /// MLP classifier
 pub struct MLPClassifier<T: 'static + CrateFloat> {
     affine_layers: Vec<Affine<T>>,
-    batch_norm_layers: Vec<BatchNormalization<T, Ix2>>,
+    batch_norm_layers: Vec<Option<BatchNormalization<T, Ix2>>>,
+    /// normalization layers other than BatchNormalization
+    normalization_layers: Vec<Option<NormalizationLayer<T, Ix2>>>,
     activators: Vec<Box<dyn LayerBase<T, A = Array2<T>, B = Array2<T>>>>,
-    dropout_layers: Vec<DropOut<T, Ix2>>,
+    dropout_layers: Vec<Option<DropOut<T, Ix2>>>,
     loss_layer: Box<dyn LossLayerBase<T, A = Array2<T>>>,
     optimizer_weight: Box<dyn OptimizerBase<Src = Array2<T>>>,
     optimizer_bias: Box<dyn OptimizerBase<Src = Array1<T>>>,
+    /// optimizers of parameters of normalization layers in the order of `LayerBase::parameters`
+    normalization_optimizers: Vec<Box<dyn OptimizerBase<Src = Array1<T>>>>,
+    /// optimizers of parameters of activators in the order of `LayerBase::parameters`
+    activator_optimizers: Vec<Box<dyn OptimizerBase<Src = Array1<T>>>>,
     regularizer_enum: RegularizerEnum<T>,
     regularizer: Box<dyn RegularizerBase<T, A = Array2<T>>>,
     current_regularizer_value: T,
@@ -49,16 +61,15 @@ where
         input_size: usize,
         hidden_sizes: &[usize],
         output_size: usize,
-        activator_enums: &[ActivatorEnum],
+        activator_enums: &[ActivatorEnum<T>],
         optimizer_enum: OptimizerEnum<T>,
-        use_batch_norm: UseBatchNormEnum<T>,
+        normalization: NormalizationEnum<T>,
         use_dropout: UseDropoutEnum<T>,
         regularizer_enum: RegularizerEnum<T>,
         batch_axis: usize,
         weight_init_enum: WeightInitEnum,
         weight_init_std: T,
-    ) -> Self {
-        assert_eq!(hidden_sizes.len(), activator_enums.len());
+    ) -> Result<Self, CrateError> {
         let params: ModelParameters<T> = ModelParameters::from(
             ModelEnum::MLPClassifier,
             input_size,
@@ -67,98 +78,117 @@ where
             batch_axis,
             activator_enums.to_vec(),
             optimizer_enum,
-            use_batch_norm,
+            normalization,
             use_dropout,
             regularizer_enum,
             weight_init_enum,
             weight_init_std,
         );
-        match Self::from(params) {
-            Ok(x) => x,
-            Err(err) => panic!("{}", err.to_string()),
-        }
+        Self::from(params)
     }
-    pub fn from(params: ModelParameters<T>) -> Result<Self, io::Error> {
+    pub fn from(params: ModelParameters<T>) -> Result<Self, CrateError> {
         if params.model_enum != ModelEnum::MLPClassifier {
-            return Err(io::Error::new(
-                ErrorKind::InvalidData,
-                "The model type specified by the input is not `MLPClassifier`.",
+            return Err(CrateError::ConfigError(
+                "The model type specified by the input is not `MLPClassifier`.".to_string(),
             ));
         }
+        Self::build(params)
+    }
+    /// build the layers of an MLP whose model type is checked by the caller
+    fn build(params: ModelParameters<T>) -> Result<Self, CrateError> {
+        params.validate()?;
         let params_clone = params.clone();
-        let nbr_of_hidden_layers: usize = params.hidden_sizes.len();
+        let hidden_layers: Vec<LayerParameters<T>> = params.hidden_layers();
+        let nbr_of_hidden_layers: usize = hidden_layers.len();
         let mut affine_layers: Vec<Affine<T>> = Vec::new();
         let mut activators: Vec<Box<dyn LayerBase<T, A = Array2<T>, B = Array2<T>>>> = Vec::new();
-        let mut batch_norm_layers: Vec<BatchNormalization<T, Ix2>> = Vec::new();
-        let mut dropout_layers: Vec<DropOut<T, Ix2>> = Vec::new();
-        for ii in 0..nbr_of_hidden_layers {
-            if ii == 0 {
-                affine_layers.push(Affine::new(
-                    (params.input_size, params.hidden_sizes[ii]),
-                    params.weight_init_enum.clone(),
-                    params.weight_init_std,
-                ));
-            } else {
-                affine_layers.push(Affine::new(
-                    (params.hidden_sizes[ii - 1], params.hidden_sizes[ii]),
-                    params.weight_init_enum.clone(),
-                    params.weight_init_std,
-                ));
-            }
-            batch_norm_layers.push(call_batch_norm_layer(
-                params.use_batch_norm.clone(),
-                (params.hidden_sizes[ii], params.hidden_sizes[ii]),
-                params.batch_axis,
+        let mut batch_norm_layers: Vec<Option<BatchNormalization<T, Ix2>>> = Vec::new();
+        let mut normalization_layers: Vec<Option<NormalizationLayer<T, Ix2>>> = Vec::new();
+        let mut dropout_layers: Vec<Option<DropOut<T, Ix2>>> = Vec::new();
+        let mut input_size: usize = params.input_size;
+        for (ii, layer) in hidden_layers.into_iter().enumerate() {
+            affine_layers.push(Affine::new(
+                (input_size, layer.size),
+                params.weight_init_enum.clone(),
+                params.weight_init_std,
             ));
+            // BatchNormalization is updated through its fields, and the others by `parameters`
+            match layer.normalization {
+                NormalizationEnum::BatchNorm(momentum) => {
+                    batch_norm_layers.push(Some(BatchNormalization::new(
+                        momentum,
+                        params.batch_axis,
+                        (layer.size, layer.size),
+                    )));
+                    normalization_layers.push(None);
+                }
+                normalization => {
+                    batch_norm_layers.push(None);
+                    normalization_layers.push(call_normalization_layer(
+                        normalization,
+                        (layer.size, layer.size),
+                        params.batch_axis,
+                        1 - params.batch_axis,
+                    )?);
+                }
+            }
             activators.push(call_activator(
-                params.activator_enums[ii].clone(),
-                (params.hidden_sizes[ii], params.hidden_sizes[ii]),
+                layer.activator_enum,
+                (layer.size, layer.size),
                 params.batch_axis,
             ));
-            dropout_layers.push(call_dropout_layer(
-                params.use_dropout.clone(),
-                (params.hidden_sizes[ii], params.hidden_sizes[ii]),
-            ));
+            dropout_layers.push(match layer.use_dropout {
+                UseDropoutEnum::None => None,
+                UseDropoutEnum::UseWith {
+                    ratio,
+                    inverted,
+                    seed,
+                } => Some(call_dropout_layer(
+                    UseDropoutEnum::UseWith {
+                        ratio,
+                        inverted,
+                        seed: seed.map(|v| v.wrapping_add(ii as u64)),
+                    },
+                    (layer.size, layer.size),
+                )?),
+                use_dropout => Some(call_dropout_layer(use_dropout, (layer.size, layer.size))?),
+            });
+            input_size = layer.size;
         }
         affine_layers.push(Affine::new(
-            (
-                params.hidden_sizes[nbr_of_hidden_layers - 1],
-                params.output_size,
-            ),
+            (input_size, params.output_size),
             params.weight_init_enum,
             params.weight_init_std,
         ));
-        // batch_norm_layers.push(call_batch_norm_layer(
-        //     params.use_batch_norm.clone(),
-        //     (params.output_size, params.output_size),
-        //     params.batch_axis,
-        // ));
-        // dropout_layers.push(call_dropout_layer(
-        //     params.use_dropout.clone(),
-        //     (params.output_size, params.output_size),
-        // ));
-        let loss_layer = Box::new(SoftmaxWithLoss2::new(
+        let loss_layer = call_loss_layer(
+            params.loss_enum.clone(),
             (params.output_size, params.output_size),
             params.batch_axis,
-        ));
+        )?;
         let optimizer_weight = call_optimizer(
             params.optimizer_enum.clone(),
             (params.output_size, params.output_size),
-        );
-        let optimizer_bias = call_optimizer(
-            params.optimizer_enum,
-            params.hidden_sizes[params.hidden_sizes.len() - 1],
-        );
+        )?;
+        let optimizer_bias = call_optimizer(params.optimizer_enum.clone(), input_size)?;
+        let normalization_optimizers = call_parameter_optimizers(
+            &params.optimizer_enum,
+            normalization_layers.iter_mut().flatten(),
+        )?;
+        let activator_optimizers =
+            call_parameter_optimizers(&params.optimizer_enum, activators.iter_mut())?;
         let regularizer = call_regularizer(params.regularizer_enum.clone());
         let nbr_of_affine_layers: usize = affine_layers.len();
         Ok(Self {
             affine_layers,
             batch_norm_layers,
+            normalization_layers,
             activators,
             dropout_layers,
             loss_layer,
             optimizer_weight,
             optimizer_bias,
+            normalization_optimizers,
+            activator_optimizers,
             regularizer_enum: params.regularizer_enum,
             regularizer,
             current_regularizer_value: cast_t2u(0.0),
@@ -168,12 +198,213 @@ where
             params: params_clone,
         })
     }
-    pub fn read_scheme_from_json(src: &Path) -> Result<Self, io::Error>
+    pub fn read_scheme_from_json(src: &Path) -> Result<Self, CrateError>
     where
         T: for<'de> Deserialize<'de>,
     {
-        let params: ModelParameters<T> = ModelParameters::from_json(src)?;
-        Self::from(params)
+        Self::from(ModelParameters::from_json(src)?)
+    }
+    /// read a scheme in the format given by the extension of `src` (json, toml, yaml or yml)
+    pub fn read_scheme(src: &Path) -> Result<Self, CrateError>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        Self::from(ModelParameters::from_file(src)?)
+    }
+    /// exact-match accuracy of multi-hot targets, where the outputs are taken as independent sigmoids
+    ///
+    /// See `multi_hot_accuracy` in detail.
+    pub fn multi_label_accuracy(&mut self, x: &Array2<T>, t: &Array2<T>, threshold: T) -> T {
+        let one: T = cast_t2u(1.0);
+        let prob: Array2<T> = self.predict_logits(x).mapv(|v| one / (one + (-v).exp()));
+        multi_hot_accuracy(&prob, t, threshold)
+    }
+    /// outputs of the last Affine layer without the softmax or the calibration
+    pub fn predict_logits(&mut self, x: &Array2<T>) -> Array2<T> {
+        // The first layer set
+        let mut y: Array2<T> = self.affine_layers[0].forward(x);
+        y = self.forward_hidden(0, y);
+        // Hidden layer sets
+        for ii in 1..self.nbr_of_hidden_layers {
+            y = self.affine_layers[ii].forward(&y);
+            y = self.forward_hidden(ii, y);
+        }
+        // The last Affine layer
+        self.affine_layers[self.nbr_of_affine_layers - 1].forward(&y)
+    }
+    /// fit a calibration of the softmax outputs to a validation set of class indices
+    ///
+    /// The layers are kept as they are, and the fitted calibration is stored in the parameters
+    /// so that it is written with the scheme. Call `set_trainable(false)` beforehand if the model
+    /// has layers behaving differently in training such as DropOut.
+    /// Calibration is only for the batch axis 0.
+    pub fn calibrate(
+        &mut self,
+        x: &Array2<T>,
+        t: &Array1<usize>,
+        method: CalibrationMethodEnum,
+    ) -> Result<(), CrateError> {
+        if !matches!(self.params.loss_enum, LossEnum::SoftmaxWithLoss { .. }) {
+            return Err(CrateError::ConfigError(format!(
+                "calibration of softmax outputs needs `SoftmaxWithLoss`, found `{}`.",
+                self.params.loss_enum
+            )));
+        }
+        if self.params.batch_axis != 0 {
+            return Err(CrateError::ConfigError(format!(
+                "calibration needs logits of (batch, classes), found the batch axis {}.",
+                self.params.batch_axis
+            )));
+        }
+        let logits: Array2<T> = self.predict_logits(x);
+        self.params.calibration = fit_calibration(&logits, t, &method)?;
+        Ok(())
+    }
+    pub fn get_calibration(&self) -> &CalibrationEnum<T> {
+        &self.params.calibration
+    }
+    /// replace the calibration, which is checked as in the scheme
+    pub fn set_calibration(&mut self, calibration: CalibrationEnum<T>) -> Result<(), CrateError> {
+        let mut params: ModelParameters<T> = self.params.clone();
+        params.calibration = calibration;
+        params.validate()?;
+        self.params = params;
+        Ok(())
+    }
+    /// update parameters with the gradient of a loss with respect to the outputs `predict_logits(x)`
+    ///
+    /// This is for losses computed outside the model such as metric-learning losses on embeddings.
+    /// The outputs must be of the last `predict_logits`, so pairs or triplets should be forwarded as one
+    /// batch. Regularization is applied as in `update`.
+    pub fn update_with_output_grad(&mut self, dout: &Array2<T>) {
+        self.backward_from_output(dout.clone());
+        self.step();
+    }
+    /// forward through the layers following the `ii`-th Affine layer
+    fn forward_hidden(&mut self, ii: usize, x: Array2<T>) -> Array2<T> {
+        let mut y: Array2<T> = x;
+        if let Some(layer) = self.batch_norm_layers[ii].as_mut() {
+            y = layer.forward(&y);
+        }
+        if let Some(layer) = self.normalization_layers[ii].as_mut() {
+            y = layer.forward(&y);
+        }
+        y = self.activators[ii].forward(&y);
+        if let Some(layer) = self.dropout_layers[ii].as_mut() {
+            y = layer.forward(&y);
+        }
+        y
+    }
+    /// total loss with the regularizer value added to the current loss
+    fn add_regularizer_value(&mut self) -> T {
+        if self.regularizer_enum != RegularizerEnum::None {
+            self.current_regularizer_value = cast_t2u(0.0);
+            for layer in self.affine_layers.iter() {
+                self.current_regularizer_value += self.regularizer.forward(&layer.weight);
+            }
+            self.current_loss + self.current_regularizer_value
+        } else {
+            self.current_loss
+        }
+    }
+    /// backward from the gradient of the outputs of the last Affine layer
+    fn backward_from_output(&mut self, dout: Array2<T>) {
+        let mut _dx: Array2<T> = dout;
+        for ii in 0..self.nbr_of_hidden_layers {
+            _dx = self.affine_layers[self.nbr_of_affine_layers - 1 - ii].backward(&_dx);
+            if let Some(layer) = self.dropout_layers[self.nbr_of_hidden_layers - 1 - ii].as_mut() {
+                _dx = layer.backward(&_dx);
+            }
+            _dx = self.activators[self.nbr_of_hidden_layers - 1 - ii].backward(&_dx);
+            if let Some(layer) =
+                self.normalization_layers[self.nbr_of_hidden_layers - 1 - ii].as_mut()
+            {
+                _dx = layer.backward(&_dx);
+            }
+            if let Some(layer) = self.batch_norm_layers[self.nbr_of_hidden_layers - 1 - ii].as_mut()
+            {
+                _dx = layer.backward(&_dx);
+            }
+        }
+        _dx = self.affine_layers[0].backward(&_dx);
+        if self.regularizer_enum != RegularizerEnum::None {
+            for layer in self.affine_layers.iter_mut() {
+                layer.dw = self.regularizer.backward(&layer.weight) + &layer.dw;
+            }
+        }
+    }
+    /// print the details of layers
+    fn print_layers(&self) {
+        for ii in 0..self.nbr_of_hidden_layers {
+            self.affine_layers[ii].print_detail();
+            if let Some(layer) = self.batch_norm_layers[ii].as_ref() {
+                layer.print_detail();
+            }
+            if let Some(layer) = self.normalization_layers[ii].as_ref() {
+                layer.print_detail();
+            }
+            self.activators[ii].print_detail();
+            if let Some(layer) = self.dropout_layers[ii].as_ref() {
+                layer.print_detail();
+            }
+        }
+        self.affine_layers[self.nbr_of_affine_layers - 1].print_detail();
+        self.loss_layer.print_detail();
+    }
+    /// update parameters with the gradients of the last backward
+    fn step(&mut self) {
+        for layer in self.affine_layers.iter_mut() {
+            self.optimizer_weight
+                .update(&mut layer.weight, &mut layer.dw);
+            self.optimizer_bias.update(&mut layer.bias, &mut layer.db);
+        }
+        for layer in self.batch_norm_layers.iter_mut().flatten() {
+            self.optimizer_bias
+                .update(&mut layer.gamma, &mut layer.dgamma);
+            self.optimizer_bias
+                .update(&mut layer.beta, &mut layer.dbeta);
+        }
+        // learnable normalizations other than BatchNormalization
+        update_parameters(
+            &mut self.normalization_optimizers,
+            self.normalization_layers.iter_mut().flatten(),
+        );
+        // learnable activators such as PReLU
+        update_parameters(&mut self.activator_optimizers, self.activators.iter_mut());
+    }
+}
+
+type Layer2<'a, T> = &'a mut Box<dyn LayerBase<T, A = Array2<T>, B = Array2<T>>>;
+
+/// an optimizer for each parameter of the layers
+fn call_parameter_optimizers<'a, T: 'static + CrateFloat>(
+    optimizer_enum: &OptimizerEnum<T>,
+    layers: impl Iterator<Item = Layer2<'a, T>>,
+) -> Result<Vec<Box<dyn OptimizerBase<Src = Array1<T>>>>, CrateError> {
+    let mut optimizers: Vec<Box<dyn OptimizerBase<Src = Array1<T>>>> = Vec::new();
+    for layer in layers {
+        for (param, _) in layer.parameters() {
+            optimizers.push(call_optimizer(optimizer_enum.clone(), param.len())?);
+        }
+    }
+    Ok(optimizers)
+}
+
+/// update parameters of the layers with the optimizers given by `call_parameter_optimizers`
+fn update_parameters<'a, T: 'static + CrateFloat>(
+    optimizers: &mut [Box<dyn OptimizerBase<Src = Array1<T>>>],
+    layers: impl Iterator<Item = Layer2<'a, T>>,
+) {
+    let mut optimizers = optimizers.iter_mut();
+    for layer in layers {
+        for (mut param, grad) in layer.parameters() {
+            let optimizer = optimizers
+                .next()
+                .expect("an optimizer must be given for each parameter");
+            let mut updated: Array1<T> = param.to_owned();
+            optimizer.update(&mut updated, &grad.to_owned());
+            param.assign(&updated);
+        }
     }
 }
 
@@ -185,29 +416,18 @@ where
 
     type B = Array2<T>;
 
+    /// probabilities of classes, i.e. the softmax of the calibrated logits,
+    /// or independent sigmoids of the logits with `SigmoidWithBCELoss`
     fn predict_prob(&mut self, x: &Self::A) -> Self::B {
-        // The first layer set
-        let mut y: Self::B = self.affine_layers[0].forward(x);
-        if self.params.use_batch_norm != UseBatchNormEnum::None {
-            y = self.batch_norm_layers[0].forward(&y);
-        }
-        y = self.activators[0].forward(&y);
-        if self.params.use_dropout != UseDropoutEnum::None {
-            y = self.dropout_layers[0].forward(&y);
-        }
-        // Hidden layer sets
-        for ii in 1..self.nbr_of_hidden_layers {
-            y = self.affine_layers[ii].forward(&y);
-            if self.params.use_batch_norm != UseBatchNormEnum::None {
-                y = self.batch_norm_layers[ii + 1].forward(&y);
-            }
-            y = self.activators[ii].forward(&y);
-            if self.params.use_dropout != UseDropoutEnum::None {
-                y = self.dropout_layers[ii].forward(&y);
+        let logits: Self::B = self.predict_logits(x);
+        match self.params.loss_enum {
+            LossEnum::SigmoidWithBCELoss { .. } => {
+                let one: T = cast_t2u(1.0);
+                logits.mapv(|v| one / (one + (-v).exp()))
             }
+            _ => Softmax::new(logits.raw_dim(), self.params.batch_axis)
+                .forward(&self.params.calibration.apply(&logits)),
         }
-        // The last Affine layer
-        self.affine_layers[self.nbr_of_affine_layers - 1].forward(&y)
     }
 
     fn predict(&mut self, x: &Self::A) -> Self::B {
@@ -222,30 +442,18 @@ where
     }
 
     fn loss(&mut self, x: &Self::A, t: &Self::B) -> T {
-        let y: Self::B = self.predict_prob(&x);
+        let y: Self::B = self.predict_logits(&x);
         self.current_loss = self.loss_layer.forward(&y, &t);
-        if self.regularizer_enum != RegularizerEnum::None {
-            self.current_regularizer_value = cast_t2u(0.0);
-            for layer in self.affine_layers.iter() {
-                self.current_regularizer_value += self.regularizer.forward(&layer.weight);
-            }
-            self.current_loss + self.current_regularizer_value
-        } else {
-            self.current_loss
-        }
+        self.add_regularizer_value()
     }
 
+    /// exact-match accuracy of multi-hot targets with `SigmoidWithBCELoss`, and that of the argmax otherwise
     fn accuracy(&mut self, x: &Self::A, t: &Self::B) -> T {
-        let y: Self::B = self.predict(&x);
-        let mut acc: f32 = 0.0;
-        for (view1, view2) in y.axis_iter(Axis(0)).zip(t.axis_iter(Axis(0))) {
-            let y_argmax = view1.argmax().unwrap();
-            let t_argmax = view2.argmax().unwrap();
-            if y_argmax == t_argmax {
-                acc += 1.0;
-            }
+        if let LossEnum::SigmoidWithBCELoss { .. } = self.params.loss_enum {
+            return self.multi_label_accuracy(x, t, cast_t2u(MULTI_LABEL_THRESHOLD));
         }
-        cast_t2u(acc / t.len_of(Axis(0)) as f32)
+        self.accuracy_sparse(x, &to_class_indices(t))
+            .expect("targets must have a column for each output")
     }
 
     fn gradient(&mut self, x: &Self::A, t: &Self::B) {
@@ -254,69 +462,27 @@ where
 
         // backward
         let _dx: T = cast_t2u(1.0);
-        let mut _dx: Self::B = self.loss_layer.backward(_dx);
-        for ii in 0..self.nbr_of_hidden_layers {
-            _dx = self.affine_layers[self.nbr_of_affine_layers - 1 - ii].backward(&_dx);
-            if self.params.use_dropout != UseDropoutEnum::None {
-                _dx = self.dropout_layers[self.nbr_of_hidden_layers - 1 - ii].backward(&_dx);
-            }
-            _dx = self.activators[self.nbr_of_hidden_layers - 1 - ii].backward(&_dx);
-            if self.params.use_batch_norm != UseBatchNormEnum::None {
-                _dx = self.batch_norm_layers[self.nbr_of_hidden_layers - 1 - ii].backward(&_dx);
-            }
-        }
-        _dx = self.affine_layers[0].backward(&_dx);
-        if self.regularizer_enum != RegularizerEnum::None {
-            for layer in self.affine_layers.iter_mut() {
-                layer.dw = self.regularizer.backward(&layer.weight) + &layer.dw;
-            }
-        }
+        let _dx: Self::B = self.loss_layer.backward(_dx);
+        self.backward_from_output(_dx);
     }
 
     fn update(&mut self, x: &Self::A, t: &Self::B) {
         self.gradient(&x, &t);
-        for layer in self.affine_layers.iter_mut() {
-            self.optimizer_weight
-                .update(&mut layer.weight, &mut layer.dw);
-            self.optimizer_bias.update(&mut layer.bias, &mut layer.db);
-        }
-        if self.params.use_batch_norm != UseBatchNormEnum::None {
-            for layer in self.batch_norm_layers.iter_mut() {
-                self.optimizer_bias
-                    .update(&mut layer.gamma, &mut layer.dgamma);
-                self.optimizer_bias
-                    .update(&mut layer.beta, &mut layer.dbeta);
-            }
-        }
+        self.step();
     }
 
     fn set_trainable(&mut self, flag: bool) {
-        if self.params.use_batch_norm != UseBatchNormEnum::None {
-            for layer in self.batch_norm_layers.iter_mut() {
-                layer.set_trainable(flag);
-            }
+        for layer in self.batch_norm_layers.iter_mut().flatten() {
+            layer.set_trainable(flag);
         }
-        if self.params.use_dropout != UseDropoutEnum::None {
-            for layer in self.dropout_layers.iter_mut() {
-                layer.set_trainable(flag);
-            }
+        for layer in self.dropout_layers.iter_mut().flatten() {
+            layer.set_trainable(flag);
         }
     }
 
     fn print_detail(&self) {
         println!("MLP classifier.");
-        for ii in 0..self.nbr_of_hidden_layers {
-            self.affine_layers[ii].print_detail();
-            if self.params.use_batch_norm != UseBatchNormEnum::None {
-                self.batch_norm_layers[ii].print_detail();
-            }
-            self.activators[ii].print_detail();
-            if self.params.use_dropout != UseDropoutEnum::None {
-                self.dropout_layers[ii].print_detail();
-            }
-        }
-        self.affine_layers[self.nbr_of_affine_layers - 1].print_detail();
-        self.loss_layer.print_detail();
+        self.print_layers();
     }
 
     fn print_parameters(&self) {
@@ -327,11 +493,35 @@ where
         }
         println!("Layer {}:", self.affine_layers.len() - 1);
         self.affine_layers[self.affine_layers.len() - 1].print_parameters();
-        if self.params.use_batch_norm != UseBatchNormEnum::None {
+        if self.batch_norm_layers.iter().any(|v| v.is_some()) {
             println!("BatchNormalization layers:");
-            for ii in 0..self.batch_norm_layers.len() - 1 {
-                println!("Layer {}:", ii);
-                self.batch_norm_layers[ii].print_parameters();
+            for (ii, layer) in self.batch_norm_layers.iter().enumerate() {
+                if let Some(layer) = layer {
+                    println!("Layer {}:", ii);
+                    layer.print_parameters();
+                }
+            }
+        }
+        if self.normalization_layers.iter().any(|v| v.is_some()) {
+            println!("Normalization layers:");
+            for (ii, layer) in self.normalization_layers.iter().enumerate() {
+                if let Some(layer) = layer {
+                    println!("Layer {}:", ii);
+                    layer.print_parameters();
+                }
+            }
+        }
+        let hidden_layers: Vec<LayerParameters<T>> = self.params.hidden_layers();
+        if hidden_layers
+            .iter()
+            .any(|v| matches!(v.activator_enum, ActivatorEnum::PReLU(_)))
+        {
+            println!("PReLU layers:");
+            for (ii, layer) in hidden_layers.iter().enumerate() {
+                if let ActivatorEnum::PReLU(_) = layer.activator_enum {
+                    println!("Layer {}:", ii);
+                    self.activators[ii].print_parameters();
+                }
             }
         }
     }
@@ -348,4 +538,173 @@ where
         self.params.to_json(dst)?;
         Ok(())
     }
+
+    fn write_scheme(&self, dst: &Path) -> Result<(), io::Error> {
+        self.params.to_file(dst)
+    }
+}
+
+/// Sparse labels are given to the loss layer as they are with `SoftmaxWithLoss`,
+/// and expanded into one-hot targets batch by batch otherwise.
+impl<T: 'static> SparseModelBase<T> for MLPClassifier<T>
+where
+    T: CrateFloat,
+{
+    fn loss_sparse(&mut self, x: &Self::A, t: &Array1<usize>) -> Result<T, CrateError> {
+        if !matches!(self.params.loss_enum, LossEnum::SoftmaxWithLoss { .. }) {
+            let t: Self::B = to_one_hot(t, self.params.output_size)?;
+            return Ok(self.loss(x, &t));
+        }
+        let y: Self::B = self.predict_logits(x);
+        self.current_loss = self.loss_layer.forward_sparse(&y, t)?;
+        Ok(self.add_regularizer_value())
+    }
+
+    fn accuracy_sparse(&mut self, x: &Self::A, t: &Array1<usize>) -> Result<T, CrateError> {
+        let y: Self::B = self.predict_prob(x);
+        metrics::accuracy(&y, t)
+    }
+
+    fn update_sparse(&mut self, x: &Self::A, t: &Array1<usize>) -> Result<(), CrateError> {
+        self.loss_sparse(x, t)?;
+        let dout: Self::B = self.loss_layer.backward(cast_t2u(1.0));
+        self.backward_from_output(dout);
+        self.step();
+        Ok(())
+    }
+}
+
+/// MLP regressor
+///
+/// This model has the same layers as `MLPClassifier` with the linear outputs of the last Affine
+/// layer as predictions, and is trained with the mean squared error.
+pub struct MLPRegressor<T: 'static + CrateFloat> {
+    mlp: MLPClassifier<T>,
+}
+
+impl<T: 'static> MLPRegressor<T>
+where
+    T: CrateFloat,
+{
+    /// arguments are the same as those of `MLPClassifier::new`
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        input_size: usize,
+        hidden_sizes: &[usize],
+        output_size: usize,
+        activator_enums: &[ActivatorEnum<T>],
+        optimizer_enum: OptimizerEnum<T>,
+        normalization: NormalizationEnum<T>,
+        use_dropout: UseDropoutEnum<T>,
+        regularizer_enum: RegularizerEnum<T>,
+        batch_axis: usize,
+        weight_init_enum: WeightInitEnum,
+        weight_init_std: T,
+    ) -> Result<Self, CrateError> {
+        let mut params: ModelParameters<T> = ModelParameters::from(
+            ModelEnum::MLPRegressor,
+            input_size,
+            hidden_sizes.to_vec(),
+            output_size,
+            batch_axis,
+            activator_enums.to_vec(),
+            optimizer_enum,
+            normalization,
+            use_dropout,
+            regularizer_enum,
+            weight_init_enum,
+            weight_init_std,
+        );
+        params.loss_enum = LossEnum::MeanSquaredError;
+        Self::from(params)
+    }
+    pub fn from(params: ModelParameters<T>) -> Result<Self, CrateError> {
+        if params.model_enum != ModelEnum::MLPRegressor {
+            return Err(CrateError::ConfigError(
+                "The model type specified by the input is not `MLPRegressor`.".to_string(),
+            ));
+        }
+        Ok(Self {
+            mlp: MLPClassifier::build(params)?,
+        })
+    }
+    pub fn read_scheme_from_json(src: &Path) -> Result<Self, CrateError>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        Self::from(ModelParameters::from_json(src)?)
+    }
+    /// read a scheme in the format given by the extension of `src` (json, toml, yaml or yml)
+    pub fn read_scheme(src: &Path) -> Result<Self, CrateError>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        Self::from(ModelParameters::from_file(src)?)
+    }
+}
+
+impl<T: 'static> ModelBase<T> for MLPRegressor<T>
+where
+    T: CrateFloat,
+{
+    type A = Array2<T>;
+
+    type B = Array2<T>;
+
+    fn predict_prob(&mut self, x: &Self::A) -> Self::B {
+        self.mlp.predict_logits(x)
+    }
+
+    fn predict(&mut self, x: &Self::A) -> Self::B {
+        self.mlp.predict_logits(x)
+    }
+
+    fn loss(&mut self, x: &Self::A, t: &Self::B) -> T {
+        self.mlp.loss(x, t)
+    }
+
+    /// coefficient of determination R^2 in place of the accuracy, so that `Trainer` logs it
+    ///
+    /// It is NaN for targets not matching the outputs and for empty batches.
+    fn accuracy(&mut self, x: &Self::A, t: &Self::B) -> T {
+        let y: Self::B = self.predict(x);
+        metrics::r2_score(&y, t).unwrap_or_else(|_| T::nan())
+    }
+
+    fn gradient(&mut self, x: &Self::A, t: &Self::B) {
+        self.mlp.gradient(x, t)
+    }
+
+    fn update(&mut self, x: &Self::A, t: &Self::B) {
+        self.mlp.update(x, t)
+    }
+
+    fn set_trainable(&mut self, flag: bool) {
+        self.mlp.set_trainable(flag)
+    }
+
+    fn print_detail(&self) {
+        println!("MLP regressor.");
+        self.mlp.print_layers();
+    }
+
+    fn print_parameters(&self) {
+        self.mlp.print_parameters()
+    }
+
+    fn get_current_loss(&self) -> T {
+        self.mlp.get_current_loss()
+    }
+
+    fn get_output(&self) -> Self::B {
+        self.mlp.get_output()
+    }
+
+    fn write_scheme_to_json(&self, dst: &Path) -> Result<(), io::Error> {
+        self.mlp.write_scheme_to_json(dst)
+    }
+
+    fn write_scheme(&self, dst: &Path) -> Result<(), io::Error> {
+        self.mlp.write_scheme(dst)
+    }
 }
@@ -2,14 +2,18 @@
 //!
 //! models
 
+pub mod calibration;
+pub mod migration;
 pub mod mlp;
 pub mod model_base;
 pub mod model_params;
 pub mod sequential;
+pub mod validation;
 
-pub use mlp::MLPClassifier;
-pub use model_base::ModelBase;
-pub use model_params::ModelParameters;
+pub use calibration::{fit_calibration, CalibrationEnum, CalibrationMethodEnum};
+pub use mlp::{MLPClassifier, MLPRegressor};
+pub use model_base::{multi_hot_accuracy, ModelBase, SparseModelBase};
+pub use model_params::{LayerParameters, ModelParameters, SchemeError, SchemeIssue};
 // pub use sequential::Sequential;
 
 use std::fmt::Display;
@@ -19,6 +23,17 @@ use std::fmt::Display;
 pub enum ModelEnum {
     None = -1,
     MLPClassifier = 0,
+    MLPRegressor = 1,
+}
+
+impl ModelEnum {
+    /// rank of the input data including the batch axis
+    pub fn get_input_ndim(&self) -> Option<usize> {
+        match self {
+            ModelEnum::None => None,
+            ModelEnum::MLPClassifier | ModelEnum::MLPRegressor => Some(2),
+        }
+    }
 }
 
 impl Display for ModelEnum {
@@ -26,6 +41,7 @@ impl Display for ModelEnum {
         match self {
             ModelEnum::None => write!(f, "None"),
             ModelEnum::MLPClassifier => write!(f, "MLPClassifier"),
+            ModelEnum::MLPRegressor => write!(f, "MLPRegressor"),
         }
     }
 }
@@ -4,7 +4,9 @@
 
 #![allow(unused_variables)]
 
+use super::super::error::CrateError;
 use super::super::util::*;
+use ndarray::prelude::*;
 use std::io;
 use std::path::Path;
 
@@ -32,4 +34,33 @@ pub trait ModelBase<T: CrateFloat> {
     }
     fn get_output(&self) -> Self::B;
     fn write_scheme_to_json(&self, dst: &Path) -> Result<(), io::Error>;
+    /// write the scheme in the format given by the extension of `dst` (json, toml, yaml or yml)
+    fn write_scheme(&self, dst: &Path) -> Result<(), io::Error>;
+}
+
+/// Trait of classifiers trained with sparse labels, i.e. class indices instead of one-hot targets
+///
+/// Labels out of the classes are reported as errors.
+pub trait SparseModelBase<T: CrateFloat>: ModelBase<T> {
+    fn loss_sparse(&mut self, x: &Self::A, t: &Array1<usize>) -> Result<T, CrateError>;
+    fn accuracy_sparse(&mut self, x: &Self::A, t: &Array1<usize>) -> Result<T, CrateError>;
+    fn update_sparse(&mut self, x: &Self::A, t: &Array1<usize>) -> Result<(), CrateError>;
+}
+
+/// exact-match accuracy of multi-hot targets
+///
+/// A sample is correct only if every output with a probability of at least `threshold` is a positive
+/// target and every other output is a negative one. Targets of 0.5 or more are positive.
+pub fn multi_hot_accuracy<T: CrateFloat>(prob: &Array2<T>, t: &Array2<T>, threshold: T) -> T {
+    let half: T = cast_t2u(0.5);
+    let num_correct: usize = prob
+        .outer_iter()
+        .zip(t.outer_iter())
+        .filter(|(p, t)| {
+            p.iter()
+                .zip(t.iter())
+                .all(|(&p, &t)| (p >= threshold) == (t >= half))
+        })
+        .count();
+    cast_t2u::<usize, T>(num_correct) / cast_t2u(t.len_of(Axis(0)))
 }
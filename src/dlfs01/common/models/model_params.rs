@@ -2,34 +2,118 @@
 //!
 //! Parameters for initialization of models
 
-use super::super::layers::{ActivatorEnum, UseBatchNormEnum, UseDropoutEnum};
+use super::super::layers::{
+    ActivatorEnum, BasicLayerEnum, LossEnum, NormalizationEnum, UseDropoutEnum,
+};
 use super::super::optimizers::OptimizerEnum;
 use super::super::param_initializers::WeightInitEnum;
 use super::super::regularizers::RegularizerEnum;
 use super::super::util::*;
+use super::calibration::CalibrationEnum;
+use super::migration::{migrate, CURRENT_FORMAT_VERSION};
 use super::ModelEnum;
 use serde::Deserialize;
-use serde_json;
+use serde_json::{self, Value};
 use std::fs::File;
 use std::io;
 use std::io::prelude::*;
+use std::io::ErrorKind;
 use std::{fmt::Display, path::Path};
+use thiserror::Error as ThisError;
+
+/// Errors in loading model schemes
+#[derive(ThisError, Debug)]
+pub enum SchemeError {
+    #[error("File IO error: {0}")]
+    FileIOError(#[from] io::Error),
+    #[error("failure in parsing the scheme: {0}")]
+    ParseError(String),
+    #[error("invalid field `{path}`: {message}")]
+    FieldError { path: String, message: String },
+    #[error("format version {found} is newer than the supported version {supported}.")]
+    VersionError { found: u32, supported: u32 },
+    #[error("{} problem(s) found in the scheme: {}", .0.len(), issues_to_string(.0))]
+    ValidationError(Vec<SchemeIssue>),
+}
+
+/// A problem in a scheme found by `ModelParameters::validate`
+#[derive(Clone, Debug, PartialEq)]
+pub struct SchemeIssue {
+    pub path: String,
+    pub message: String,
+}
+
+impl Display for SchemeIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "`{}`: {}", self.path, self.message)
+    }
+}
+
+fn issues_to_string(issues: &[SchemeIssue]) -> String {
+    issues
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Parameters of a hidden layer
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LayerParameters<T: CrateFloat> {
+    #[serde(default)]
+    pub layer_enum: BasicLayerEnum,
+    pub size: usize,
+    pub activator_enum: ActivatorEnum<T>,
+    #[serde(default)]
+    pub normalization: NormalizationEnum<T>,
+    #[serde(default)]
+    pub use_dropout: UseDropoutEnum<T>,
+}
+
+impl<T> Display for LayerParameters<T>
+where
+    T: CrateFloat,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} (size: {}, activator: {}, normalization: {}, dropout: {})",
+            self.layer_enum, self.size, self.activator_enum, self.normalization, self.use_dropout
+        )
+    }
+}
 
 /// Model parameters
+///
+/// Hidden layers are given either by `layers` or by `hidden_sizes` and `activator_enums`
+/// with `normalization` and `use_dropout` applied to every hidden layer.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ModelParameters<T: CrateFloat> {
+    pub format_version: u32,
     pub model_enum: ModelEnum,
     pub input_size: usize,
+    #[serde(default)]
     pub hidden_sizes: Vec<usize>,
     pub output_size: usize,
     pub batch_axis: usize,
-    pub activator_enums: Vec<ActivatorEnum>,
+    #[serde(default)]
+    pub activator_enums: Vec<ActivatorEnum<T>>,
     pub optimizer_enum: OptimizerEnum<T>,
-    pub use_batch_norm: UseBatchNormEnum<T>,
+    #[serde(default)]
+    pub normalization: NormalizationEnum<T>,
+    #[serde(default)]
     pub use_dropout: UseDropoutEnum<T>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub layers: Vec<LayerParameters<T>>,
     pub regularizer_enum: RegularizerEnum<T>,
     pub weight_init_enum: WeightInitEnum,
     pub weight_init_std: T,
+    /// `SoftmaxWithLoss` by default, or `MeanSquaredError` for `MLPRegressor`
+    #[serde(default)]
+    pub loss_enum: LossEnum<T>,
+    /// calibration of logits fitted after training, e.g. by `MLPClassifier::calibrate`
+    #[serde(default)]
+    pub calibration: CalibrationEnum<T>,
 }
 
 impl<T: 'static> ModelParameters<T>
@@ -38,6 +122,7 @@ where
 {
     pub fn new() -> Self {
         Self {
+            format_version: CURRENT_FORMAT_VERSION,
             model_enum: ModelEnum::None,
             input_size: 0,
             hidden_sizes: Vec::new(),
@@ -45,11 +130,14 @@ where
             batch_axis: 0,
             activator_enums: Vec::new(),
             optimizer_enum: OptimizerEnum::SGD(cast_t2u(0.01)),
-            use_batch_norm: UseBatchNormEnum::None,
+            normalization: NormalizationEnum::None,
             use_dropout: UseDropoutEnum::None,
+            layers: Vec::new(),
             regularizer_enum: RegularizerEnum::None,
             weight_init_enum: WeightInitEnum::Normal,
             weight_init_std: cast_t2u(0.0),
+            loss_enum: LossEnum::default(),
+            calibration: CalibrationEnum::None,
         }
     }
     pub fn from(
@@ -58,15 +146,16 @@ where
         hidden_sizes: Vec<usize>,
         output_size: usize,
         batch_axis: usize,
-        activator_enums: Vec<ActivatorEnum>,
+        activator_enums: Vec<ActivatorEnum<T>>,
         optimizer_enum: OptimizerEnum<T>,
-        use_batch_norm: UseBatchNormEnum<T>,
+        normalization: NormalizationEnum<T>,
         use_dropout: UseDropoutEnum<T>,
         regularizer_enum: RegularizerEnum<T>,
         weight_init_enum: WeightInitEnum,
         weight_init_std: T,
     ) -> Self {
         Self {
+            format_version: CURRENT_FORMAT_VERSION,
             model_enum,
             input_size,
             hidden_sizes,
@@ -74,28 +163,116 @@ where
             batch_axis,
             activator_enums,
             optimizer_enum,
-            use_batch_norm,
+            normalization,
             use_dropout,
+            layers: Vec::new(),
             regularizer_enum,
             weight_init_enum,
             weight_init_std,
+            loss_enum: LossEnum::default(),
+            calibration: CalibrationEnum::None,
+        }
+    }
+    /// hidden layers given by `layers`, or else by the global fields
+    pub fn hidden_layers(&self) -> Vec<LayerParameters<T>> {
+        if !self.layers.is_empty() {
+            return self.layers.clone();
         }
+        self.hidden_sizes
+            .iter()
+            .zip(self.activator_enums.iter())
+            .map(|(&size, activator_enum)| LayerParameters {
+                layer_enum: BasicLayerEnum::Affine,
+                size,
+                activator_enum: activator_enum.clone(),
+                normalization: self.normalization.clone(),
+                use_dropout: self.use_dropout.clone(),
+            })
+            .collect()
+    }
+    /// read a scheme in the format given by the extension of `src` (json, toml, yaml or yml)
+    pub fn from_file(src: &Path) -> Result<Self, SchemeError>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        match extension(src).as_str() {
+            "json" => Self::from_json(src),
+            "toml" => Self::from_toml(src),
+            "yaml" | "yml" => Self::from_yaml(src),
+            other => Err(SchemeError::ParseError(format!(
+                "unknown scheme format `{}`.",
+                other
+            ))),
+        }
+    }
+    pub fn from_json(src: &Path) -> Result<Self, SchemeError>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        let scheme: Value = serde_json::from_str(&read_scheme(src)?)
+            .map_err(|err| SchemeError::ParseError(err.to_string()))?;
+        Self::from_value(scheme)
+    }
+    pub fn from_toml(src: &Path) -> Result<Self, SchemeError>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        let scheme: toml::Value = toml::from_str(&read_scheme(src)?)
+            .map_err(|err| SchemeError::ParseError(err.to_string()))?;
+        let scheme: Value =
+            serde_json::to_value(scheme).map_err(|err| SchemeError::ParseError(err.to_string()))?;
+        Self::from_value(scheme)
     }
-    pub fn from_json(src: &Path) -> Result<Self, io::Error>
+    pub fn from_yaml(src: &Path) -> Result<Self, SchemeError>
     where
         T: for<'de> Deserialize<'de>,
     {
-        let mut file: File = File::open(src)?;
-        let mut buff: String = String::new();
-        let _ = file.read_to_string(&mut buff);
-        let dst: Self = serde_json::from_reader(buff.as_bytes())?;
-        Ok(dst)
+        let scheme: serde_yaml::Value = serde_yaml::from_str(&read_scheme(src)?)
+            .map_err(|err| SchemeError::ParseError(err.to_string()))?;
+        Self::from_value(yaml_to_json(scheme)?)
+    }
+    /// generate parameters from a scheme, upgrading it from an older format version if needed
+    pub fn from_value(scheme: Value) -> Result<Self, SchemeError>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        let mut scheme: Value = migrate(scheme)?;
+        insert_default_loss(&mut scheme);
+        serde_path_to_error::deserialize(scheme).map_err(|err| SchemeError::FieldError {
+            path: err.path().to_string(),
+            message: err.into_inner().to_string(),
+        })
+    }
+    /// write the scheme in the format given by the extension of `dst` (json, toml, yaml or yml)
+    pub fn to_file(&self, dst: &Path) -> Result<(), io::Error> {
+        match extension(dst).as_str() {
+            "json" => self.to_json(dst),
+            "toml" => self.to_toml(dst),
+            "yaml" | "yml" => self.to_yaml(dst),
+            other => Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("unknown scheme format `{}`.", other),
+            )),
+        }
     }
     pub fn to_json(&self, dst: &Path) -> Result<(), io::Error> {
-        let mut file: File = File::create(dst)?;
-        write!(file, "{}", serde_json::to_string(&self)?)?;
-        file.flush()?;
-        Ok(())
+        write_scheme(dst, &serde_json::to_string(&self.current())?)
+    }
+    pub fn to_toml(&self, dst: &Path) -> Result<(), io::Error> {
+        let scheme: String = toml::to_string(&self.current())
+            .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
+        write_scheme(dst, &scheme)
+    }
+    pub fn to_yaml(&self, dst: &Path) -> Result<(), io::Error> {
+        let scheme: String = serde_yaml::to_string(&self.current())
+            .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
+        write_scheme(dst, &scheme)
+    }
+    /// copy of the parameters stamped with the current format version
+    fn current(&self) -> Self {
+        let mut params: Self = self.clone();
+        params.format_version = CURRENT_FORMAT_VERSION;
+        params
     }
 }
 
@@ -105,6 +282,7 @@ where
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut output = String::new();
+        output += &format!("format version: {}", self.format_version);
         output += &format!("model type: {}", self.model_enum);
         output += &format!("size of the input layer: {}", self.input_size);
         output += &format!(
@@ -118,11 +296,14 @@ where
             vec_to_string(&self.activator_enums)
         );
         output += &format!("type of optimizer: {}", self.optimizer_enum);
-        output += &format!("batch normalization: {}", self.use_batch_norm);
+        output += &format!("normalization: {}", self.normalization);
         output += &format!("dropout: {}", self.use_dropout);
+        output += &format!("layers: {}", vec_to_string(&self.layers));
         output += &format!("regularizer: {}", self.regularizer_enum);
         output += &format!("weight init type: {}", self.weight_init_enum);
         output += &format!("weight init std: {}", self.weight_init_std);
+        output += &format!("loss: {}", self.loss_enum);
+        output += &format!("calibration: {}", self.calibration);
         write!(f, "{}", output)
     }
 }
@@ -137,3 +318,77 @@ where
     }
     dst + &"]"
 }
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .and_then(|v| v.to_str())
+        .unwrap_or("")
+        .to_lowercase()
+}
+
+fn read_scheme(src: &Path) -> Result<String, io::Error> {
+    let mut file: File = File::open(src)?;
+    let mut buff: String = String::new();
+    file.read_to_string(&mut buff)?;
+    Ok(buff)
+}
+
+fn write_scheme(dst: &Path, scheme: &str) -> Result<(), io::Error> {
+    let mut file: File = File::create(dst)?;
+    write!(file, "{}", scheme)?;
+    file.flush()?;
+    Ok(())
+}
+
+/// convert a YAML document into JSON
+///
+/// Enum variants written with tags, e.g. `!Adam {lr: 0.001, ...}`, become single-key maps
+/// as in JSON.
+fn yaml_to_json(src: serde_yaml::Value) -> Result<Value, SchemeError> {
+    use serde_yaml::Value as Yaml;
+    Ok(match src {
+        Yaml::Null => Value::Null,
+        Yaml::Bool(v) => Value::Bool(v),
+        Yaml::Number(v) => {
+            serde_json::to_value(v).map_err(|err| SchemeError::ParseError(err.to_string()))?
+        }
+        Yaml::String(v) => Value::String(v),
+        Yaml::Sequence(v) => {
+            Value::Array(v.into_iter().map(yaml_to_json).collect::<Result<_, _>>()?)
+        }
+        Yaml::Mapping(v) => {
+            let mut dst: serde_json::Map<String, Value> = serde_json::Map::new();
+            for (key, value) in v.into_iter() {
+                let key: String = match key {
+                    Yaml::String(key) => key,
+                    key => {
+                        return Err(SchemeError::ParseError(format!(
+                            "keys must be strings, found `{:?}`.",
+                            key
+                        )))
+                    }
+                };
+                dst.insert(key, yaml_to_json(value)?);
+            }
+            Value::Object(dst)
+        }
+        Yaml::Tagged(v) => {
+            let mut dst: serde_json::Map<String, Value> = serde_json::Map::new();
+            let name: String = v.tag.to_string().trim_start_matches('!').to_string();
+            dst.insert(name, yaml_to_json(v.value)?);
+            Value::Object(dst)
+        }
+    })
+}
+
+/// give `MLPRegressor` schemes without `loss_enum` the mean squared error,
+/// since the default of the field is `SoftmaxWithLoss` for classifiers
+fn insert_default_loss(scheme: &mut Value) {
+    if let Some(fields) = scheme.as_object_mut() {
+        if fields.get("model_enum") == Some(&Value::from("MLPRegressor"))
+            && !fields.contains_key("loss_enum")
+        {
+            fields.insert("loss_enum".to_string(), Value::from("MeanSquaredError"));
+        }
+    }
+}
@@ -0,0 +1,406 @@
+//! validation
+//!
+//! Validation of model parameters
+//!
+//! Every problem in a scheme is collected at once with the path of the offending field,
+//! written in the same notation as deserialization errors, e.g. `layers[1].use_dropout.Use`.
+
+use super::super::layers::{
+    ActivatorEnum, BasicLayerEnum, LossEnum, NormalizationEnum, UseDropoutEnum,
+};
+use super::super::optimizers::OptimizerEnum;
+use super::super::regularizers::RegularizerEnum;
+use super::super::util::*;
+use super::calibration::CalibrationEnum;
+use super::model_params::{ModelParameters, SchemeError, SchemeIssue};
+use super::ModelEnum;
+
+impl<T: 'static> ModelParameters<T>
+where
+    T: CrateFloat,
+{
+    /// check the parameters before building a model and report every problem found
+    pub fn validate(&self) -> Result<(), SchemeError> {
+        let mut issues: Vec<SchemeIssue> = Vec::new();
+        match self.model_enum.get_input_ndim() {
+            None => push(&mut issues, "model_enum", "a model type is required."),
+            Some(ndim) if self.batch_axis >= ndim => push(
+                &mut issues,
+                "batch_axis",
+                format!(
+                    "must be less than the input rank {}, found {}.",
+                    ndim, self.batch_axis
+                ),
+            ),
+            _ => (),
+        }
+        if self.input_size == 0 {
+            push(&mut issues, "input_size", "must be positive.");
+        }
+        if self.output_size == 0 {
+            push(&mut issues, "output_size", "must be positive.");
+        }
+        self.validate_hidden_layers(&mut issues);
+        validate_optimizer(&mut issues, &self.optimizer_enum);
+        match self.regularizer_enum {
+            RegularizerEnum::L1(decay_lambda) => {
+                check_non_negative(&mut issues, "regularizer_enum.L1", decay_lambda)
+            }
+            RegularizerEnum::L2(decay_lambda) => {
+                check_non_negative(&mut issues, "regularizer_enum.L2", decay_lambda)
+            }
+            RegularizerEnum::None => (),
+        }
+        check_non_negative(&mut issues, "weight_init_std", self.weight_init_std);
+        self.validate_loss(&mut issues);
+        self.validate_calibration(&mut issues);
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(SchemeError::ValidationError(issues))
+        }
+    }
+
+    fn validate_hidden_layers(&self, issues: &mut Vec<SchemeIssue>) {
+        if !self.layers.is_empty() {
+            for (ii, layer) in self.layers.iter().enumerate() {
+                let path: String = format!("layers[{}]", ii);
+                if self.is_mlp() && layer.layer_enum != BasicLayerEnum::Affine {
+                    push(
+                        issues,
+                        format!("{}.layer_enum", path),
+                        format!(
+                            "`{}` layers are not supported by `{}`.",
+                            layer.layer_enum, self.model_enum
+                        ),
+                    );
+                }
+                if layer.size == 0 {
+                    push(issues, format!("{}.size", path), "must be positive.");
+                }
+                validate_activator(
+                    issues,
+                    &format!("{}.activator_enum", path),
+                    &layer.activator_enum,
+                );
+                self.validate_normalization(
+                    issues,
+                    &format!("{}.normalization", path),
+                    &layer.normalization,
+                    &[layer.size],
+                );
+                validate_dropout(issues, &format!("{}.use_dropout", path), &layer.use_dropout);
+            }
+            return;
+        }
+        if self.hidden_sizes.is_empty() {
+            push(
+                issues,
+                "hidden_sizes",
+                "at least one hidden layer is required.",
+            );
+        }
+        if self.hidden_sizes.len() != self.activator_enums.len() {
+            push(
+                issues,
+                "activator_enums",
+                format!(
+                    "{} activators are given for {} hidden layers.",
+                    self.activator_enums.len(),
+                    self.hidden_sizes.len()
+                ),
+            );
+        }
+        for (ii, &size) in self.hidden_sizes.iter().enumerate() {
+            if size == 0 {
+                push(issues, format!("hidden_sizes[{}]", ii), "must be positive.");
+            }
+        }
+        for (ii, activator_enum) in self.activator_enums.iter().enumerate() {
+            validate_activator(issues, &format!("activator_enums[{}]", ii), activator_enum);
+        }
+        self.validate_normalization(
+            issues,
+            "normalization",
+            &self.normalization,
+            &self.hidden_sizes,
+        );
+        validate_dropout(issues, "use_dropout", &self.use_dropout);
+    }
+
+    /// whether the model is an MLP, which has only Affine layers
+    fn is_mlp(&self) -> bool {
+        matches!(
+            self.model_enum,
+            ModelEnum::MLPClassifier | ModelEnum::MLPRegressor
+        )
+    }
+
+    fn validate_loss(&self, issues: &mut Vec<SchemeIssue>) {
+        if self.model_enum == ModelEnum::MLPRegressor
+            && self.loss_enum != LossEnum::MeanSquaredError
+        {
+            push(
+                issues,
+                "loss_enum",
+                format!(
+                    "`{}` needs a regression loss such as `MeanSquaredError`, found `{}`.",
+                    self.model_enum, self.loss_enum
+                ),
+            );
+        }
+        match &self.loss_enum {
+            LossEnum::SoftmaxWithLoss {
+                class_weights,
+                label_smoothing,
+                focal,
+            } => {
+                let path: &str = "loss_enum.SoftmaxWithLoss";
+                self.validate_class_weights(
+                    issues,
+                    &format!("{}.class_weights", path),
+                    class_weights,
+                );
+                if let Some(epsilon) = *label_smoothing {
+                    check_ratio(issues, &format!("{}.label_smoothing", path), epsilon);
+                }
+                if let Some(focal) = focal {
+                    check_non_negative(issues, &format!("{}.focal.gamma", path), focal.gamma);
+                    check_positive(issues, &format!("{}.focal.alpha", path), focal.alpha);
+                }
+            }
+            LossEnum::SigmoidWithBCELoss { pos_weight } => self.validate_class_weights(
+                issues,
+                "loss_enum.SigmoidWithBCELoss.pos_weight",
+                pos_weight,
+            ),
+            LossEnum::MeanSquaredError => (),
+        }
+    }
+
+    fn validate_calibration(&self, issues: &mut Vec<SchemeIssue>) {
+        if self.calibration == CalibrationEnum::None {
+            return;
+        }
+        if !matches!(self.loss_enum, LossEnum::SoftmaxWithLoss { .. }) {
+            push(
+                issues,
+                "calibration",
+                format!(
+                    "calibration of softmax outputs needs `SoftmaxWithLoss`, found `{}`.",
+                    self.loss_enum
+                ),
+            );
+        }
+        if self.batch_axis != 0 {
+            push(
+                issues,
+                "calibration",
+                format!(
+                    "calibration needs logits of (batch, classes), found the batch axis {}.",
+                    self.batch_axis
+                ),
+            );
+        }
+        match &self.calibration {
+            CalibrationEnum::Temperature(temperature) => {
+                check_positive(issues, "calibration.Temperature", *temperature)
+            }
+            CalibrationEnum::Vector { weights, biases } => {
+                self.check_output_size(issues, "calibration.Vector.weights", weights.len());
+                self.check_output_size(issues, "calibration.Vector.biases", biases.len());
+            }
+            CalibrationEnum::Matrix { weights, biases } => {
+                self.check_output_size(issues, "calibration.Matrix.weights", weights.len());
+                for (ii, row) in weights.iter().enumerate() {
+                    self.check_output_size(
+                        issues,
+                        &format!("calibration.Matrix.weights[{}]", ii),
+                        row.len(),
+                    );
+                }
+                self.check_output_size(issues, "calibration.Matrix.biases", biases.len());
+            }
+            CalibrationEnum::None => (),
+        }
+    }
+
+    /// check the length of parameters given for each output
+    fn check_output_size(&self, issues: &mut Vec<SchemeIssue>, path: &str, len: usize) {
+        if len != self.output_size {
+            push(
+                issues,
+                path,
+                format!("{} values are given for {} outputs.", len, self.output_size),
+            );
+        }
+    }
+
+    /// check weights given for each output, where an empty list means equal weights
+    fn validate_class_weights(&self, issues: &mut Vec<SchemeIssue>, path: &str, weights: &[T]) {
+        if weights.is_empty() {
+            return;
+        }
+        if weights.len() != self.output_size {
+            push(
+                issues,
+                path,
+                format!(
+                    "{} weights are given for {} outputs.",
+                    weights.len(),
+                    self.output_size
+                ),
+            );
+        }
+        for (ii, &weight) in weights.iter().enumerate() {
+            check_non_negative(issues, &format!("{}[{}]", path, ii), weight);
+        }
+    }
+
+    /// check a normalization applied to hidden layers of the given sizes
+    fn validate_normalization(
+        &self,
+        issues: &mut Vec<SchemeIssue>,
+        path: &str,
+        normalization: &NormalizationEnum<T>,
+        sizes: &[usize],
+    ) {
+        match *normalization {
+            NormalizationEnum::BatchNorm(momentum) => {
+                check_ratio(issues, &format!("{}.BatchNorm", path), momentum)
+            }
+            NormalizationEnum::GroupNorm(groups) => {
+                if let Some(&size) = sizes.iter().find(|&&v| groups == 0 || v % groups != 0) {
+                    push(
+                        issues,
+                        format!("{}.GroupNorm", path),
+                        format!(
+                            "the number of groups must divide the layer size {}, found {}.",
+                            size, groups
+                        ),
+                    );
+                }
+            }
+            NormalizationEnum::InstanceNorm if self.is_mlp() => push(
+                issues,
+                path,
+                format!(
+                    "`{}` needs spatial axes, which `{}` does not have.",
+                    normalization, self.model_enum
+                ),
+            ),
+            _ => (),
+        }
+    }
+}
+
+fn validate_optimizer<T>(issues: &mut Vec<SchemeIssue>, optimizer_enum: &OptimizerEnum<T>)
+where
+    T: CrateFloat,
+{
+    match *optimizer_enum {
+        OptimizerEnum::SGD(lr) => check_positive(issues, "optimizer_enum.SGD.lr", lr),
+        OptimizerEnum::Momentum(lr, momentum) => {
+            check_positive(issues, "optimizer_enum.Momentum.lr", lr);
+            check_ratio(issues, "optimizer_enum.Momentum.momentum", momentum);
+        }
+        OptimizerEnum::Nesterov(lr, momentum) => {
+            check_positive(issues, "optimizer_enum.Nesterov.lr", lr);
+            check_ratio(issues, "optimizer_enum.Nesterov.momentum", momentum);
+        }
+        OptimizerEnum::AdaGrad(lr) => check_positive(issues, "optimizer_enum.AdaGrad.lr", lr),
+        OptimizerEnum::RMSprop(lr, decay_rate) => {
+            check_positive(issues, "optimizer_enum.RMSprop.lr", lr);
+            check_ratio(issues, "optimizer_enum.RMSprop.decay_rate", decay_rate);
+        }
+        OptimizerEnum::Adam(lr, beta1, beta2) => {
+            check_positive(issues, "optimizer_enum.Adam.lr", lr);
+            check_ratio(issues, "optimizer_enum.Adam.beta1", beta1);
+            check_ratio(issues, "optimizer_enum.Adam.beta2", beta2);
+        }
+        _ => push(
+            issues,
+            "optimizer_enum",
+            format!("`{}` is not implemented.", optimizer_enum),
+        ),
+    }
+}
+
+fn validate_activator<T>(
+    issues: &mut Vec<SchemeIssue>,
+    path: &str,
+    activator_enum: &ActivatorEnum<T>,
+) where
+    T: CrateFloat,
+{
+    match *activator_enum {
+        ActivatorEnum::LeakyReLU(alpha) => {
+            check_non_negative(issues, &format!("{}.LeakyReLU", path), alpha)
+        }
+        ActivatorEnum::PReLU(alpha) => {
+            check_non_negative(issues, &format!("{}.PReLU", path), alpha)
+        }
+        ActivatorEnum::ELU(alpha) => check_positive(issues, &format!("{}.ELU", path), alpha),
+        ActivatorEnum::Swish(beta) => check_positive(issues, &format!("{}.Swish", path), beta),
+        _ => (),
+    }
+}
+
+fn validate_dropout<T>(issues: &mut Vec<SchemeIssue>, path: &str, use_dropout: &UseDropoutEnum<T>)
+where
+    T: CrateFloat,
+{
+    let (path, ratio): (String, T) = match *use_dropout {
+        UseDropoutEnum::Use(ratio) => (format!("{}.Use", path), ratio),
+        UseDropoutEnum::UseWith { ratio, .. } => (format!("{}.UseWith.ratio", path), ratio),
+        UseDropoutEnum::None => return,
+    };
+    let zero: T = cast_t2u(0.0);
+    let one: T = cast_t2u(1.0);
+    if !(ratio >= zero && ratio <= one) {
+        push(issues, path, format!("must be in [0, 1], found {}.", ratio));
+    }
+}
+
+fn check_positive<T>(issues: &mut Vec<SchemeIssue>, path: &str, value: T)
+where
+    T: CrateFloat,
+{
+    if value.is_nan() || value <= cast_t2u(0.0) {
+        push(issues, path, format!("must be positive, found {}.", value));
+    }
+}
+
+fn check_non_negative<T>(issues: &mut Vec<SchemeIssue>, path: &str, value: T)
+where
+    T: CrateFloat,
+{
+    if value.is_nan() || value < cast_t2u(0.0) {
+        push(
+            issues,
+            path,
+            format!("must be non-negative, found {}.", value),
+        );
+    }
+}
+
+/// check a value in [0, 1) such as momentum and decay rates
+fn check_ratio<T>(issues: &mut Vec<SchemeIssue>, path: &str, value: T)
+where
+    T: CrateFloat,
+{
+    if !(value >= cast_t2u(0.0) && value < cast_t2u(1.0)) {
+        push(issues, path, format!("must be in [0, 1), found {}.", value));
+    }
+}
+
+fn push<P, M>(issues: &mut Vec<SchemeIssue>, path: P, message: M)
+where
+    P: Into<String>,
+    M: Into<String>,
+{
+    issues.push(SchemeIssue {
+        path: path.into(),
+        message: message.into(),
+    });
+}
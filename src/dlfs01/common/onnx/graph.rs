@@ -0,0 +1,690 @@
+//! graph
+//!
+//! Generic executor of ONNX graphs composed of layers of this crate
+
+use super::super::layers::activation::{ReLUD, SigmoidD, SoftmaxD};
+use super::super::layers::batch_normalization::BatchNormalizationD;
+use super::super::layers::convolution::Convolution3;
+use super::super::layers::{Affine, LayerBase, MaxPooling4};
+use super::super::util::*;
+use super::proto::*;
+use super::OnnxError;
+use ndarray::prelude::*;
+use prost::Message;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::Path;
+
+/// opset version assumed when a model does not declare the default domain
+const DEFAULT_OPSET_VERSION: i64 = 13;
+/// default `epsilon` of BatchNormalization
+const BATCH_NORM_EPS: f32 = 1E-5;
+
+/// Operator of a node, whose constant inputs are loaded into a layer
+enum Operator<T: CrateFloat> {
+    Affine(Affine<T>),
+    Add,
+    ReLU(ReLUD<T>),
+    Sigmoid(SigmoidD<T>),
+    Softmax {
+        layer: SoftmaxD<T>,
+        axis: i64,
+        coerce_2d: bool,
+    },
+    BatchNormalization(Box<BatchNormalizationD<T>>),
+    Convolution {
+        weight: Array4<T>,
+        bias: Array1<T>,
+        stride_sizes: (usize, usize),
+        pad_size: usize,
+        data_shape: (usize, usize, usize),
+        layer: Option<Box<Convolution3<T>>>,
+    },
+    MaxPooling(MaxPooling4<T>),
+    Flatten(i64),
+}
+
+/// Node of an ONNX graph
+struct Node<T: CrateFloat> {
+    name: String,
+    op_type: String,
+    inputs: Vec<String>,
+    output: String,
+    operator: Operator<T>,
+}
+
+/// ONNX model for inference
+pub struct OnnxModel<T: CrateFloat> {
+    name: String,
+    opset_version: i64,
+    nodes: Vec<Node<T>>,
+    initializers: HashMap<String, ArrayD<T>>,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+}
+
+impl<T: 'static> OnnxModel<T>
+where
+    T: CrateFloat,
+{
+    pub fn from_proto(model: &ModelProto) -> Result<Self, OnnxError> {
+        let graph: &GraphProto = model
+            .graph
+            .as_ref()
+            .ok_or_else(|| OnnxError::GraphError("The model has no graph.".to_string()))?;
+        let opset_version: i64 = model
+            .opset_import
+            .iter()
+            .find(|v| v.domain.is_empty() || v.domain == "ai.onnx")
+            .map(|v| v.version)
+            .unwrap_or(DEFAULT_OPSET_VERSION);
+        let mut initializers: HashMap<String, ArrayD<T>> = HashMap::new();
+        for tensor in graph.initializer.iter() {
+            initializers.insert(tensor.name.clone(), tensor_to_array(tensor)?);
+        }
+        let mut nodes: Vec<Node<T>> = Vec::new();
+        for node in graph.node.iter() {
+            nodes.push(Node::from_proto(node, &initializers, opset_version)?);
+        }
+        // graphs with IR version < 4 also list initializers as inputs
+        let inputs: Vec<String> = graph
+            .input
+            .iter()
+            .filter(|v| !initializers.contains_key(&v.name))
+            .map(|v| v.name.clone())
+            .collect();
+        let outputs: Vec<String> = graph.output.iter().map(|v| v.name.clone()).collect();
+        Ok(Self {
+            name: graph.name.clone(),
+            opset_version,
+            nodes,
+            initializers,
+            inputs,
+            outputs,
+        })
+    }
+    pub fn from_bytes(src: &[u8]) -> Result<Self, OnnxError> {
+        let model: ModelProto = ModelProto::decode(src)?;
+        Self::from_proto(&model)
+    }
+    pub fn from_file(src: &Path) -> Result<Self, OnnxError> {
+        let mut file: File = File::open(src)?;
+        let mut buff: Vec<u8> = Vec::new();
+        file.read_to_end(&mut buff)?;
+        Self::from_bytes(&buff)
+    }
+    /// run inference with arrays bound to the graph inputs in order
+    pub fn run(&mut self, inputs: &[ArrayD<T>]) -> Result<Vec<ArrayD<T>>, OnnxError> {
+        if inputs.len() != self.inputs.len() {
+            return Err(OnnxError::GraphError(format!(
+                "{} inputs are required but {} are given.",
+                self.inputs.len(),
+                inputs.len()
+            )));
+        }
+        let mut values: HashMap<String, ArrayD<T>> = HashMap::new();
+        for (name, x) in self.inputs.iter().zip(inputs.iter()) {
+            values.insert(name.clone(), x.clone());
+        }
+        let initializers: &HashMap<String, ArrayD<T>> = &self.initializers;
+        for node in self.nodes.iter_mut() {
+            let mut xs: Vec<&ArrayD<T>> = Vec::new();
+            for name in node.inputs.iter() {
+                let x = values
+                    .get(name)
+                    .or_else(|| initializers.get(name))
+                    .ok_or_else(|| OnnxError::TensorNotFoundError(name.clone()))?;
+                xs.push(x);
+            }
+            let y: ArrayD<T> = node.forward(&xs)?;
+            values.insert(node.output.clone(), y);
+        }
+        let mut dst: Vec<ArrayD<T>> = Vec::new();
+        for name in self.outputs.iter() {
+            let y = values
+                .remove(name)
+                .or_else(|| self.initializers.get(name).cloned())
+                .ok_or_else(|| OnnxError::TensorNotFoundError(name.clone()))?;
+            dst.push(y);
+        }
+        Ok(dst)
+    }
+    /// run inference on a graph with a single input and a single output
+    pub fn predict(&mut self, x: &ArrayD<T>) -> Result<ArrayD<T>, OnnxError> {
+        if self.inputs.len() != 1 || self.outputs.len() != 1 {
+            return Err(OnnxError::GraphError(format!(
+                "`predict` requires a graph with 1 input and 1 output, but it has {} and {}.",
+                self.inputs.len(),
+                self.outputs.len()
+            )));
+        }
+        Ok(self.run(std::slice::from_ref(x))?.remove(0))
+    }
+    pub fn get_input_names(&self) -> &[String] {
+        &self.inputs
+    }
+    pub fn get_output_names(&self) -> &[String] {
+        &self.outputs
+    }
+    pub fn print_detail(&self) {
+        println!("ONNX model `{}`.", self.name);
+        println!("opset version: {}", self.opset_version);
+        println!("inputs: {:?}", self.inputs);
+        println!("outputs: {:?}", self.outputs);
+        for node in self.nodes.iter() {
+            println!("node `{}` ({}):", node.name, node.op_type);
+            match &node.operator {
+                Operator::Affine(layer) => layer.print_detail(),
+                Operator::Add => println!("element-wise addition."),
+                Operator::ReLU(layer) => layer.print_detail(),
+                Operator::Sigmoid(layer) => layer.print_detail(),
+                Operator::Softmax { layer, .. } => layer.print_detail(),
+                Operator::BatchNormalization(layer) => layer.print_detail(),
+                Operator::Convolution {
+                    layer: Some(layer), ..
+                } => layer.print_detail(),
+                Operator::Convolution { weight, .. } => {
+                    println!("2D convolution layer.");
+                    println!("weight shape: {:?}", weight.shape());
+                }
+                Operator::MaxPooling(layer) => layer.print_detail(),
+                Operator::Flatten(axis) => println!("flatten (axis: {}).", axis),
+            }
+        }
+    }
+}
+
+impl<T: 'static> Node<T>
+where
+    T: CrateFloat,
+{
+    fn from_proto(
+        node: &NodeProto,
+        initializers: &HashMap<String, ArrayD<T>>,
+        opset_version: i64,
+    ) -> Result<Self, OnnxError> {
+        let output: String =
+            node.output.first().cloned().ok_or_else(|| {
+                OnnxError::GraphError(format!("Node `{}` has no output.", node.name))
+            })?;
+        let name: String = if node.name.is_empty() {
+            output.clone()
+        } else {
+            node.name.clone()
+        };
+        if !(node.domain.is_empty() || node.domain == "ai.onnx") {
+            return Err(OnnxError::UnsupportedOperatorError {
+                node: name,
+                op_type: format!("{}.{}", node.domain, node.op_type),
+            });
+        }
+        if node.output.iter().skip(1).any(|v| !v.is_empty()) {
+            return Err(unsupported_attribute(
+                &name,
+                "output",
+                "only the first output is supported.",
+            ));
+        }
+        let input =
+            |idx: usize| -> Option<&String> { node.input.get(idx).filter(|v| !v.is_empty()) };
+        let constant = |idx: usize| -> Result<&ArrayD<T>, OnnxError> {
+            let tensor: &String = input(idx).ok_or_else(|| OnnxError::InputError {
+                node: name.clone(),
+                reason: format!("input {} is missing.", idx),
+            })?;
+            initializers
+                .get(tensor)
+                .ok_or_else(|| OnnxError::InputError {
+                    node: name.clone(),
+                    reason: format!("input `{}` must be an initializer.", tensor),
+                })
+        };
+        let first_input: String = input(0).cloned().ok_or_else(|| OnnxError::InputError {
+            node: name.clone(),
+            reason: "input 0 is missing.".to_string(),
+        })?;
+        let operator: Operator<T> = match node.op_type.as_str() {
+            "Gemm" => {
+                if get_int(node, "transA", 0) != 0 {
+                    return Err(unsupported_attribute(&name, "transA", "must be 0."));
+                }
+                let alpha: T = cast_t2u(get_float(node, "alpha", 1.0));
+                let beta: T = cast_t2u(get_float(node, "beta", 1.0));
+                let mut weight: Array2<T> = to_2d(&name, constant(1)?)?;
+                if get_int(node, "transB", 0) != 0 {
+                    weight = weight.t().to_owned();
+                }
+                let bias: Array1<T> = match input(2) {
+                    Some(_) => {
+                        let c: &ArrayD<T> = constant(2)?;
+                        if c.len() != weight.len_of(Axis(1)) {
+                            return Err(OnnxError::InputError {
+                                node: name,
+                                reason: format!(
+                                    "bias with shape {:?} cannot be applied per output.",
+                                    c.shape()
+                                ),
+                            });
+                        }
+                        c.iter().map(|&v| v * beta).collect()
+                    }
+                    None => Array1::zeros(weight.len_of(Axis(1))),
+                };
+                Operator::Affine(Affine::from(&(weight * alpha), &bias))
+            }
+            "MatMul" => {
+                let weight: Array2<T> = to_2d(&name, constant(1)?)?;
+                let bias: Array1<T> = Array1::zeros(weight.len_of(Axis(1)));
+                Operator::Affine(Affine::from(&weight, &bias))
+            }
+            "Add" => {
+                let second: String = input(1).cloned().ok_or_else(|| OnnxError::InputError {
+                    node: name.clone(),
+                    reason: "input 1 is missing.".to_string(),
+                })?;
+                return Ok(Self {
+                    name,
+                    op_type: node.op_type.clone(),
+                    inputs: vec![first_input, second],
+                    output,
+                    operator: Operator::Add,
+                });
+            }
+            "Relu" => Operator::ReLU(ReLUD::new(IxDyn(&[1]))),
+            "Sigmoid" => Operator::Sigmoid(SigmoidD::new(IxDyn(&[1]))),
+            "Softmax" => {
+                // inputs are coerced into 2D arrays before opset 13
+                let coerce_2d: bool = opset_version < 13;
+                let default_axis: i64 = if coerce_2d { 1 } else { -1 };
+                Operator::Softmax {
+                    layer: SoftmaxD::new(IxDyn(&[1, 1]), 0),
+                    axis: get_int(node, "axis", default_axis),
+                    coerce_2d,
+                }
+            }
+            "BatchNormalization" => {
+                if get_int(node, "training_mode", 0) != 0 {
+                    return Err(unsupported_attribute(&name, "training_mode", "must be 0."));
+                }
+                let eps: T = cast_t2u(get_float(node, "epsilon", BATCH_NORM_EPS));
+                let momentum: T = cast_t2u(get_float(node, "momentum", 0.9));
+                let mut layer: BatchNormalizationD<T> = BatchNormalizationD::from(
+                    constant(1)?,
+                    constant(2)?,
+                    constant(3)?,
+                    constant(4)?,
+                    momentum,
+                    0,
+                    eps,
+                );
+                layer.set_trainable(false);
+                Operator::BatchNormalization(Box::new(layer))
+            }
+            "Conv" => {
+                if get_int(node, "group", 1) != 1 {
+                    return Err(unsupported_attribute(&name, "group", "must be 1."));
+                }
+                check_spatial_attributes(node, &name)?;
+                let weight: Array4<T> =
+                    constant(1)?
+                        .clone()
+                        .into_dimensionality::<Ix4>()
+                        .map_err(|_| OnnxError::InputError {
+                            node: name.clone(),
+                            reason: "the weight must be a 4D tensor.".to_string(),
+                        })?;
+                let bias: Array1<T> = match input(2) {
+                    Some(_) => constant(2)?.iter().cloned().collect(),
+                    None => Array1::zeros(weight.len_of(Axis(0))),
+                };
+                Operator::Convolution {
+                    stride_sizes: get_strides(node, &name)?,
+                    pad_size: get_pad_size(node, &name)?,
+                    data_shape: (0, 0, 0),
+                    layer: None,
+                    weight,
+                    bias,
+                }
+            }
+            "MaxPool" => {
+                check_spatial_attributes(node, &name)?;
+                if get_int(node, "ceil_mode", 0) != 0 {
+                    return Err(unsupported_attribute(&name, "ceil_mode", "must be 0."));
+                }
+                if get_int(node, "storage_order", 0) != 0 {
+                    return Err(unsupported_attribute(&name, "storage_order", "must be 0."));
+                }
+                let kernel_shape: (usize, usize) = get_kernel_shape(node, &name)?;
+                let stride_sizes: (usize, usize) = get_strides(node, &name)?;
+                if stride_sizes.0 != stride_sizes.1 {
+                    return Err(unsupported_attribute(
+                        &name,
+                        "strides",
+                        "strides must be the same along the both axes.",
+                    ));
+                }
+                Operator::MaxPooling(MaxPooling4::new(
+                    kernel_shape.0,
+                    kernel_shape.1,
+                    stride_sizes.0,
+                    get_pad_size(node, &name)?,
+                ))
+            }
+            "Flatten" => Operator::Flatten(get_int(node, "axis", 1)),
+            _ => {
+                return Err(OnnxError::UnsupportedOperatorError {
+                    node: name,
+                    op_type: node.op_type.clone(),
+                })
+            }
+        };
+        Ok(Self {
+            name,
+            op_type: node.op_type.clone(),
+            inputs: vec![first_input],
+            output,
+            operator,
+        })
+    }
+
+    fn forward(&mut self, xs: &[&ArrayD<T>]) -> Result<ArrayD<T>, OnnxError> {
+        let x: &ArrayD<T> = xs[0];
+        let ndim: usize = x.ndim();
+        match &mut self.operator {
+            Operator::Affine(layer) => {
+                let x: Array2<T> = to_2d(&self.name, x)?;
+                Ok(layer.forward(&x).into_dyn())
+            }
+            Operator::Add => {
+                let shape: Vec<usize> =
+                    broadcast_shape(x.shape(), xs[1].shape()).ok_or_else(|| {
+                        OnnxError::InputError {
+                            node: self.name.clone(),
+                            reason: format!(
+                                "shapes {:?} and {:?} cannot be broadcast.",
+                                x.shape(),
+                                xs[1].shape()
+                            ),
+                        }
+                    })?;
+                Ok(&x.broadcast(shape.clone()).unwrap() + &xs[1].broadcast(shape).unwrap())
+            }
+            Operator::ReLU(layer) => Ok(layer.forward(x)),
+            Operator::Sigmoid(layer) => Ok(layer.forward(x)),
+            Operator::Softmax {
+                layer,
+                axis,
+                coerce_2d,
+            } => {
+                let axis: usize = normalize_axis(&self.name, *axis, ndim)?;
+                if !*coerce_2d && axis + 1 != ndim {
+                    return Err(unsupported_attribute(
+                        &self.name,
+                        "axis",
+                        "only softmax along the last axis is supported.",
+                    ));
+                }
+                let outer: usize = x.shape()[..axis].iter().product();
+                let x_2d: ArrayD<T> = x
+                    .to_shape(IxDyn(&[outer, x.len() / outer.max(1)]))?
+                    .into_owned();
+                Ok(layer.forward(&x_2d).into_shape(x.raw_dim())?)
+            }
+            Operator::BatchNormalization(layer) => {
+                if ndim < 2 {
+                    return Err(OnnxError::InputError {
+                        node: self.name.clone(),
+                        reason: "the input must have the channel axis.".to_string(),
+                    });
+                }
+                // move the channel axis to the last to broadcast per-channel parameters
+                let mut axes: Vec<usize> = (0..ndim).collect();
+                axes.remove(1);
+                axes.push(1);
+                let mut inverse_axes: Vec<usize> = (1..ndim - 1).collect();
+                inverse_axes.insert(0, 0);
+                inverse_axes.insert(1, ndim - 1);
+                let y: ArrayD<T> = layer.forward(
+                    &x.view()
+                        .permuted_axes(axes)
+                        .as_standard_layout()
+                        .into_owned(),
+                );
+                Ok(y.permuted_axes(inverse_axes)
+                    .as_standard_layout()
+                    .into_owned())
+            }
+            Operator::Convolution {
+                weight,
+                bias,
+                stride_sizes,
+                pad_size,
+                data_shape,
+                layer,
+            } => {
+                let x: Array4<T> = to_4d(&self.name, x)?;
+                let (_, c, h, w) = x.dim();
+                let weight_c: usize = weight.shape()[1];
+                if c != weight_c {
+                    return Err(OnnxError::InputError {
+                        node: self.name.clone(),
+                        reason: format!("{} channels are required but {} are given.", weight_c, c),
+                    });
+                }
+                if layer.is_none() || *data_shape != (c, h, w) {
+                    let conv =
+                        match Convolution3::from(weight, bias, (c, h, w), *stride_sizes, *pad_size)
+                        {
+                            Ok(conv) => conv,
+                            Err(err) => {
+                                return Err(OnnxError::InputError {
+                                    node: self.name.clone(),
+                                    reason: err.to_string(),
+                                })
+                            }
+                        };
+                    *data_shape = (c, h, w);
+                    *layer = Some(Box::new(conv));
+                }
+                Ok(layer.as_mut().unwrap().forward(&x).into_dyn())
+            }
+            Operator::MaxPooling(layer) => {
+                let x: Array4<T> = to_4d(&self.name, x)?;
+                Ok(layer.forward(&x).into_dyn())
+            }
+            Operator::Flatten(axis) => {
+                let axis: usize = if *axis < 0 {
+                    normalize_axis(&self.name, *axis, ndim)?
+                } else {
+                    usize::min(*axis as usize, ndim)
+                };
+                let outer: usize = x.shape()[..axis].iter().product();
+                Ok(x.to_shape(IxDyn(&[outer, x.len() / outer.max(1)]))?
+                    .into_owned())
+            }
+        }
+    }
+}
+
+/// convert a TensorProto into an array
+fn tensor_to_array<T>(tensor: &TensorProto) -> Result<ArrayD<T>, OnnxError>
+where
+    T: CrateFloat,
+{
+    let shape: Vec<usize> = tensor.dims.iter().map(|&v| v as usize).collect();
+    let data: Vec<T> = match tensor.data_type {
+        DATA_TYPE_FLOAT if !tensor.raw_data.is_empty() => tensor
+            .raw_data
+            .chunks_exact(4)
+            .map(|v| cast_t2u(f32::from_le_bytes(v.try_into().unwrap())))
+            .collect(),
+        DATA_TYPE_FLOAT => tensor.float_data.iter().map(|&v| cast_t2u(v)).collect(),
+        DATA_TYPE_DOUBLE if !tensor.raw_data.is_empty() => tensor
+            .raw_data
+            .chunks_exact(8)
+            .map(|v| cast_t2u(f64::from_le_bytes(v.try_into().unwrap())))
+            .collect(),
+        DATA_TYPE_DOUBLE => tensor.double_data.iter().map(|&v| cast_t2u(v)).collect(),
+        DATA_TYPE_INT64 if !tensor.raw_data.is_empty() => tensor
+            .raw_data
+            .chunks_exact(8)
+            .map(|v| cast_t2u(i64::from_le_bytes(v.try_into().unwrap())))
+            .collect(),
+        DATA_TYPE_INT64 => tensor.int64_data.iter().map(|&v| cast_t2u(v)).collect(),
+        _ => {
+            return Err(OnnxError::UnsupportedDataTypeError {
+                tensor: tensor.name.clone(),
+                data_type: tensor.data_type,
+            })
+        }
+    };
+    Ok(ArrayD::from_shape_vec(IxDyn(&shape), data)?)
+}
+
+fn to_2d<T>(node: &str, x: &ArrayD<T>) -> Result<Array2<T>, OnnxError>
+where
+    T: CrateFloat,
+{
+    x.view()
+        .into_dimensionality::<Ix2>()
+        .map(|v| v.to_owned())
+        .map_err(|_| OnnxError::InputError {
+            node: node.to_string(),
+            reason: format!("a 2D array is required but the shape is {:?}.", x.shape()),
+        })
+}
+
+fn to_4d<T>(node: &str, x: &ArrayD<T>) -> Result<Array4<T>, OnnxError>
+where
+    T: CrateFloat,
+{
+    x.view()
+        .into_dimensionality::<Ix4>()
+        .map(|v| v.to_owned())
+        .map_err(|_| OnnxError::InputError {
+            node: node.to_string(),
+            reason: format!("a 4D array is required but the shape is {:?}.", x.shape()),
+        })
+}
+
+/// shape of the result of broadcasting two arrays with each other
+fn broadcast_shape(a: &[usize], b: &[usize]) -> Option<Vec<usize>> {
+    let ndim: usize = usize::max(a.len(), b.len());
+    let mut dst: Vec<usize> = vec![1; ndim];
+    for ii in 0..ndim {
+        let va: usize = if ii < ndim - a.len() {
+            1
+        } else {
+            a[ii + a.len() - ndim]
+        };
+        let vb: usize = if ii < ndim - b.len() {
+            1
+        } else {
+            b[ii + b.len() - ndim]
+        };
+        dst[ii] = match (va, vb) {
+            (x, y) if x == y => x,
+            (1, y) => y,
+            (x, 1) => x,
+            _ => return None,
+        };
+    }
+    Some(dst)
+}
+
+fn normalize_axis(node: &str, axis: i64, ndim: usize) -> Result<usize, OnnxError> {
+    let dst: i64 = if axis < 0 { axis + ndim as i64 } else { axis };
+    if dst < 0 || dst >= ndim as i64 {
+        return Err(unsupported_attribute(
+            node,
+            "axis",
+            &format!("{} is out of range for a {}D input.", axis, ndim),
+        ));
+    }
+    Ok(dst as usize)
+}
+
+fn unsupported_attribute(node: &str, attribute: &str, reason: &str) -> OnnxError {
+    OnnxError::UnsupportedAttributeError {
+        node: node.to_string(),
+        attribute: attribute.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+fn get_attribute<'a>(node: &'a NodeProto, name: &str) -> Option<&'a AttributeProto> {
+    node.attribute.iter().find(|v| v.name == name)
+}
+
+fn get_int(node: &NodeProto, name: &str, default: i64) -> i64 {
+    get_attribute(node, name).map(|v| v.i).unwrap_or(default)
+}
+
+fn get_float(node: &NodeProto, name: &str, default: f32) -> f32 {
+    get_attribute(node, name).map(|v| v.f).unwrap_or(default)
+}
+
+fn get_ints(node: &NodeProto, name: &str) -> Vec<i64> {
+    get_attribute(node, name)
+        .map(|v| v.ints.clone())
+        .unwrap_or_default()
+}
+
+/// validate attributes of Conv and MaxPool that this crate does not support
+fn check_spatial_attributes(node: &NodeProto, name: &str) -> Result<(), OnnxError> {
+    if let Some(attribute) = get_attribute(node, "auto_pad") {
+        let auto_pad = String::from_utf8_lossy(&attribute.s);
+        if !(auto_pad.is_empty() || auto_pad == "NOTSET") {
+            return Err(unsupported_attribute(name, "auto_pad", "must be NOTSET."));
+        }
+    }
+    if get_ints(node, "dilations").iter().any(|&v| v != 1) {
+        return Err(unsupported_attribute(name, "dilations", "must be 1."));
+    }
+    Ok(())
+}
+
+fn get_strides(node: &NodeProto, name: &str) -> Result<(usize, usize), OnnxError> {
+    match get_ints(node, "strides").as_slice() {
+        [] => Ok((1, 1)),
+        [sh, sw] if *sh > 0 && *sw > 0 => Ok((*sh as usize, *sw as usize)),
+        _ => Err(unsupported_attribute(
+            name,
+            "strides",
+            "two positive strides are required.",
+        )),
+    }
+}
+
+fn get_kernel_shape(node: &NodeProto, name: &str) -> Result<(usize, usize), OnnxError> {
+    match get_ints(node, "kernel_shape").as_slice() {
+        [kh, kw] if *kh > 0 && *kw > 0 => Ok((*kh as usize, *kw as usize)),
+        [_, _] => Err(unsupported_attribute(
+            name,
+            "kernel_shape",
+            "the kernel sizes must be positive.",
+        )),
+        _ => Err(unsupported_attribute(
+            name,
+            "kernel_shape",
+            "only 2D pooling is supported.",
+        )),
+    }
+}
+
+fn get_pad_size(node: &NodeProto, name: &str) -> Result<usize, OnnxError> {
+    let pads: Vec<i64> = get_ints(node, "pads");
+    if pads.is_empty() {
+        return Ok(0);
+    }
+    if pads.len() != 4 || pads.iter().any(|&v| v != pads[0] || v < 0) {
+        return Err(unsupported_attribute(
+            name,
+            "pads",
+            "only the same padding on all the sides is supported.",
+        ));
+    }
+    Ok(pads[0] as usize)
+}
@@ -0,0 +1,42 @@
+//! onnx
+//!
+//! Import of ONNX models for inference
+//!
+//! Supported operators are Gemm, MatMul, Add, Relu, Sigmoid, Softmax, BatchNormalization,
+//! Conv, MaxPool and Flatten. Each node is loaded into a layer of this crate and
+//! the graph is executed by a generic executor on `ndarray` inputs.
+
+pub mod graph;
+pub mod proto;
+
+pub use graph::OnnxModel;
+
+use std::io;
+use thiserror::Error as ThisError;
+
+/// Errors in loading and running ONNX models
+#[derive(ThisError, Debug)]
+pub enum OnnxError {
+    #[error("File IO error: {0}")]
+    FileIOError(#[from] io::Error),
+    #[error("failure in decoding the ONNX model: {0}")]
+    DecodeError(#[from] prost::DecodeError),
+    #[error("Invalid graph: {0}")]
+    GraphError(String),
+    #[error("Tensor `{0}` is not found in the graph.")]
+    TensorNotFoundError(String),
+    #[error("Unsupported operator `{op_type}` at node `{node}`.")]
+    UnsupportedOperatorError { node: String, op_type: String },
+    #[error("Unsupported attribute `{attribute}` at node `{node}`: {reason}")]
+    UnsupportedAttributeError {
+        node: String,
+        attribute: String,
+        reason: String,
+    },
+    #[error("Unsupported data type {data_type} of tensor `{tensor}`.")]
+    UnsupportedDataTypeError { tensor: String, data_type: i32 },
+    #[error("Invalid input at node `{node}`: {reason}")]
+    InputError { node: String, reason: String },
+    #[error("failure in reshaping array.")]
+    ShapeError(#[from] ndarray::ShapeError),
+}
@@ -5,14 +5,19 @@
 pub mod optimizer;
 pub mod optimizer_base;
 
+use super::error::CrateError;
 use super::util::CrateFloat;
-use ndarray::{Array, Dimension, ShapeBuilder};
+use ndarray::{Array, Dimension, Ix2, ShapeBuilder};
 pub use optimizer::*;
 pub use optimizer_base::*;
 use std::fmt::Display;
 
 /// Enum of optimizers
+///
+/// Hyperparameters are serialized with their names,
+/// e.g. `{"Adam": {"lr": 0.001, "beta1": 0.9, "beta2": 0.999}}`.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(from = "OptimizerFields<T>", into = "OptimizerFields<T>")]
 pub enum OptimizerEnum<T: CrateFloat> {
     SGD(T),
     Momentum(T, T),
@@ -35,6 +40,119 @@ pub enum OptimizerEnum<T: CrateFloat> {
     AdaBelief(T),
 }
 
+/// Serialized form of `OptimizerEnum` with named hyperparameters
+#[derive(Clone, Serialize, Deserialize)]
+#[allow(clippy::upper_case_acronyms)]
+enum OptimizerFields<T: CrateFloat> {
+    SGD { lr: T },
+    Momentum { lr: T, momentum: T },
+    Nesterov { lr: T, momentum: T },
+    AdaGrad { lr: T },
+    RMSprop { lr: T, decay_rate: T },
+    AdaDelta { lr: T },
+    Adam { lr: T, beta1: T, beta2: T },
+    RMSpropGraves { lr: T },
+    SMORMS3 { lr: T },
+    AdaMax { lr: T },
+    Nadam { lr: T },
+    Eve { lr: T },
+    Santa { lr: T },
+    GDByGD { lr: T },
+    AdaSecant { lr: T },
+    AMSGrad { lr: T },
+    AdaBound { lr: T },
+    AMSBound { lr: T },
+    AdaBelief { lr: T },
+}
+
+impl<T> From<OptimizerFields<T>> for OptimizerEnum<T>
+where
+    T: CrateFloat,
+{
+    fn from(src: OptimizerFields<T>) -> Self {
+        match src {
+            OptimizerFields::SGD { lr } => OptimizerEnum::SGD(lr),
+            OptimizerFields::Momentum { lr, momentum } => OptimizerEnum::Momentum(lr, momentum),
+            OptimizerFields::Nesterov { lr, momentum } => OptimizerEnum::Nesterov(lr, momentum),
+            OptimizerFields::AdaGrad { lr } => OptimizerEnum::AdaGrad(lr),
+            OptimizerFields::RMSprop { lr, decay_rate } => OptimizerEnum::RMSprop(lr, decay_rate),
+            OptimizerFields::AdaDelta { lr } => OptimizerEnum::AdaDelta(lr),
+            OptimizerFields::Adam { lr, beta1, beta2 } => OptimizerEnum::Adam(lr, beta1, beta2),
+            OptimizerFields::RMSpropGraves { lr } => OptimizerEnum::RMSpropGraves(lr),
+            OptimizerFields::SMORMS3 { lr } => OptimizerEnum::SMORMS3(lr),
+            OptimizerFields::AdaMax { lr } => OptimizerEnum::AdaMax(lr),
+            OptimizerFields::Nadam { lr } => OptimizerEnum::Nadam(lr),
+            OptimizerFields::Eve { lr } => OptimizerEnum::Eve(lr),
+            OptimizerFields::Santa { lr } => OptimizerEnum::Santa(lr),
+            OptimizerFields::GDByGD { lr } => OptimizerEnum::GDByGD(lr),
+            OptimizerFields::AdaSecant { lr } => OptimizerEnum::AdaSecant(lr),
+            OptimizerFields::AMSGrad { lr } => OptimizerEnum::AMSGrad(lr),
+            OptimizerFields::AdaBound { lr } => OptimizerEnum::AdaBound(lr),
+            OptimizerFields::AMSBound { lr } => OptimizerEnum::AMSBound(lr),
+            OptimizerFields::AdaBelief { lr } => OptimizerEnum::AdaBelief(lr),
+        }
+    }
+}
+
+impl<T> From<OptimizerEnum<T>> for OptimizerFields<T>
+where
+    T: CrateFloat,
+{
+    fn from(src: OptimizerEnum<T>) -> Self {
+        match src {
+            OptimizerEnum::SGD(lr) => OptimizerFields::SGD { lr },
+            OptimizerEnum::Momentum(lr, momentum) => OptimizerFields::Momentum { lr, momentum },
+            OptimizerEnum::Nesterov(lr, momentum) => OptimizerFields::Nesterov { lr, momentum },
+            OptimizerEnum::AdaGrad(lr) => OptimizerFields::AdaGrad { lr },
+            OptimizerEnum::RMSprop(lr, decay_rate) => OptimizerFields::RMSprop { lr, decay_rate },
+            OptimizerEnum::AdaDelta(lr) => OptimizerFields::AdaDelta { lr },
+            OptimizerEnum::Adam(lr, beta1, beta2) => OptimizerFields::Adam { lr, beta1, beta2 },
+            OptimizerEnum::RMSpropGraves(lr) => OptimizerFields::RMSpropGraves { lr },
+            OptimizerEnum::SMORMS3(lr) => OptimizerFields::SMORMS3 { lr },
+            OptimizerEnum::AdaMax(lr) => OptimizerFields::AdaMax { lr },
+            OptimizerEnum::Nadam(lr) => OptimizerFields::Nadam { lr },
+            OptimizerEnum::Eve(lr) => OptimizerFields::Eve { lr },
+            OptimizerEnum::Santa(lr) => OptimizerFields::Santa { lr },
+            OptimizerEnum::GDByGD(lr) => OptimizerFields::GDByGD { lr },
+            OptimizerEnum::AdaSecant(lr) => OptimizerFields::AdaSecant { lr },
+            OptimizerEnum::AMSGrad(lr) => OptimizerFields::AMSGrad { lr },
+            OptimizerEnum::AdaBound(lr) => OptimizerFields::AdaBound { lr },
+            OptimizerEnum::AMSBound(lr) => OptimizerFields::AMSBound { lr },
+            OptimizerEnum::AdaBelief(lr) => OptimizerFields::AdaBelief { lr },
+        }
+    }
+}
+
+impl<T> OptimizerEnum<T>
+where
+    T: CrateFloat,
+{
+    /// learning rate
+    pub fn get_lr(&self) -> T {
+        match *self {
+            OptimizerEnum::SGD(lr)
+            | OptimizerEnum::Momentum(lr, _)
+            | OptimizerEnum::Nesterov(lr, _)
+            | OptimizerEnum::AdaGrad(lr)
+            | OptimizerEnum::RMSprop(lr, _)
+            | OptimizerEnum::AdaDelta(lr)
+            | OptimizerEnum::Adam(lr, _, _)
+            | OptimizerEnum::RMSpropGraves(lr)
+            | OptimizerEnum::SMORMS3(lr)
+            | OptimizerEnum::AdaMax(lr)
+            | OptimizerEnum::Nadam(lr)
+            | OptimizerEnum::Eve(lr)
+            | OptimizerEnum::Santa(lr)
+            | OptimizerEnum::GDByGD(lr)
+            | OptimizerEnum::AdaSecant(lr)
+            | OptimizerEnum::AMSGrad(lr)
+            | OptimizerEnum::AdaBound(lr)
+            | OptimizerEnum::AMSBound(lr)
+            | OptimizerEnum::AdaBelief(lr) => lr,
+        }
+    }
+}
+
 impl<T> Display for OptimizerEnum<T>
 where
     T: CrateFloat,
@@ -76,28 +194,20 @@ where
 pub fn call_optimizer<T: 'static, D: 'static, Sh>(
     optimizer_enum: OptimizerEnum<T>,
     shape: Sh,
-) -> Box<dyn OptimizerBase<Src = Array<T, D>>>
+) -> Result<Box<dyn OptimizerBase<Src = Array<T, D>>>, CrateError>
 where
     T: CrateFloat,
     D: Dimension,
     Sh: ShapeBuilder<Dim = D>,
 {
     match optimizer_enum {
-        OptimizerEnum::SGD(lr) => return Box::new(SGD::new(lr)),
-        OptimizerEnum::Momentum(lr, momentum) => {
-            return Box::new(Momentum::new(lr, momentum, shape))
-        }
-        OptimizerEnum::Nesterov(lr, momentum) => {
-            return Box::new(Nesterov::new(lr, momentum, shape))
-        }
-        OptimizerEnum::AdaGrad(lr) => return Box::new(AdaGrad::new(lr, shape)),
-        OptimizerEnum::RMSprop(lr, decay_rate) => {
-            return Box::new(RMSprop::new(lr, decay_rate, shape))
-        }
+        OptimizerEnum::SGD(lr) => Ok(Box::new(SGD::new(lr))),
+        OptimizerEnum::Momentum(lr, momentum) => Ok(Box::new(Momentum::new(lr, momentum, shape))),
+        OptimizerEnum::Nesterov(lr, momentum) => Ok(Box::new(Nesterov::new(lr, momentum, shape))),
+        OptimizerEnum::AdaGrad(lr) => Ok(Box::new(AdaGrad::new(lr, shape))),
+        OptimizerEnum::RMSprop(lr, decay_rate) => Ok(Box::new(RMSprop::new(lr, decay_rate, shape))),
         // OptimizerEnum::AdaDelta(lr) => return Box::new(AdaDelta::new(lr, shape)),
-        OptimizerEnum::Adam(lr, beta1, beta2) => {
-            return Box::new(Adam::new(lr, beta1, beta2, shape))
-        }
+        OptimizerEnum::Adam(lr, beta1, beta2) => Ok(Box::new(Adam::new(lr, beta1, beta2, shape))),
         // OptimizerEnum::RMSpropGraves(lr) => return Box::new(RMSpropGraves::new(lr, shape)),
         // OptimizerEnum::SMORMS3(lr) => return Box::new(SMORMS3::new(lr, shape)),
         // OptimizerEnum::AdaMax(lr) => return Box::new(AdaMax::new(lr, shape)),
@@ -110,6 +220,28 @@ where
         // OptimizerEnum::AdaBound(lr) => return Box::new(AdaBound::new(lr, shape)),
         // OptimizerEnum::AMSBound(lr) => return Box::new(AMSBound::new(lr, shape)),
         // OptimizerEnum::AdaBelief(lr) => return Box::new(AdaBelief::new(lr, shape)),
-        _ => panic!("Invalid optimizer name: {}", optimizer_enum),
+        _ => Err(CrateError::ConfigError(format!(
+            "optimizer `{}` is not implemented.",
+            optimizer_enum
+        ))),
+    }
+}
+
+/// generate an optimizer with the sparse update path, which is given for SGD and Adam
+pub fn call_sparse_optimizer<T, Sh>(
+    optimizer_enum: OptimizerEnum<T>,
+    shape: Sh,
+) -> Result<Box<dyn SparseOptimizerBase<T>>, CrateError>
+where
+    T: 'static + CrateFloat,
+    Sh: ShapeBuilder<Dim = Ix2>,
+{
+    match optimizer_enum {
+        OptimizerEnum::SGD(lr) => Ok(Box::new(SGD::<T, Ix2>::new(lr))),
+        OptimizerEnum::Adam(lr, beta1, beta2) => Ok(Box::new(Adam::new(lr, beta1, beta2, shape))),
+        _ => Err(CrateError::ConfigError(format!(
+            "optimizer `{}` does not support sparse updates.",
+            optimizer_enum
+        ))),
     }
 }
@@ -10,7 +10,7 @@ use std::marker::PhantomData;
 
 use crate::prelude::*;
 
-use super::optimizer_base::OptimizerBase;
+use super::optimizer_base::{OptimizerBase, SparseOptimizerBase};
 use itertools::multizip;
 use ndarray::prelude::*;
 
@@ -49,6 +49,17 @@ where
     }
 }
 
+impl<T> SparseOptimizerBase<T> for SGD<T, Ix2>
+where
+    T: CrateFloat,
+{
+    fn update_rows(&mut self, param: &mut Array2<T>, rows: &[usize], grads: &Array2<T>) {
+        for (&row, g) in rows.iter().zip(grads.outer_iter()) {
+            param.row_mut(row).scaled_add(-self.lr, &g);
+        }
+    }
+}
+
 /// momentum
 pub struct Momentum<T: CrateFloat, D> {
     lr: T,
@@ -315,6 +326,34 @@ where
     }
 }
 
+/// lazy Adam: the moments are updated only for the given rows
+impl<T> SparseOptimizerBase<T> for Adam<T, Ix2>
+where
+    T: CrateFloat,
+{
+    fn update_rows(&mut self, param: &mut Array2<T>, rows: &[usize], grads: &Array2<T>) {
+        if self.param.shape() != param.shape() {
+            self.param = Array2::zeros(param.raw_dim());
+            self.momentum = Array2::zeros(param.raw_dim());
+        }
+        self.iter += self.one;
+        let lr_t: T = self.lr * (self.one - self.beta2.powf(self.iter)).sqrt()
+            / (self.one - self.beta1.powf(self.iter));
+        for (&row, grads) in rows.iter().zip(grads.outer_iter()) {
+            for (v, m, p, g) in multizip((
+                self.param.row_mut(row),
+                self.momentum.row_mut(row),
+                param.row_mut(row),
+                grads.iter(),
+            )) {
+                *m += self.one_minus_beta1 * (*g - *m);
+                *v += self.one_minus_beta2 * (*g * *g - *v);
+                *p -= lr_t * *m / ((*v).sqrt() + self.eps);
+            }
+        }
+    }
+}
+
 /// RMSpropGraves
 ///
 /// See https://arxiv.org/abs/1308.0850 in detail
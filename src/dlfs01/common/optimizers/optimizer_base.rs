@@ -2,9 +2,19 @@
 //!
 //! base traits for optimizers
 
-// use ndarray::prelude::*;
+use super::super::util::CrateFloat;
+use ndarray::prelude::*;
 
 pub trait OptimizerBase {
     type Src;
     fn update(&mut self, param: &mut Self::Src, grads: &Self::Src);
 }
+
+/// optimizer trait for parameters whose gradients are given only for some rows,
+/// e.g. weights of `Embedding`
+///
+/// `grads` has a row for each of `rows`, and the other rows of `param` and of the optimizer
+/// state are left untouched.
+pub trait SparseOptimizerBase<T: CrateFloat> {
+    fn update_rows(&mut self, param: &mut Array2<T>, rows: &[usize], grads: &Array2<T>);
+}
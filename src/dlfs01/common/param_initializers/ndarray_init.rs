@@ -2,6 +2,7 @@
 //!
 //! Initializer of ndarray
 
+use super::super::error::CrateError;
 use super::super::util::*;
 use ndarray::prelude::*;
 use ndarray_rand::rand_distr::*;
@@ -60,12 +61,35 @@ pub fn initialize_randomized_ndarray<T, D, Sh>(
     name: DistributionEnum,
     shape: Sh,
     params: &[T],
-) -> Array<T, D>
+) -> Result<Array<T, D>, CrateError>
 where
     T: CrateFloat,
     D: Dimension,
     Sh: ShapeBuilder<Dim = D>,
 {
+    let nbr_of_params: usize = match name {
+        DistributionEnum::Pert | DistributionEnum::Triangular => 3,
+        DistributionEnum::Beta
+        | DistributionEnum::Cauchy
+        | DistributionEnum::FisherF
+        | DistributionEnum::Gamma
+        | DistributionEnum::LogNormal
+        | DistributionEnum::Normal
+        | DistributionEnum::Pareto
+        | DistributionEnum::Uniform
+        | DistributionEnum::Weibull => 2,
+        _ => 1,
+    };
+    if params.len() < nbr_of_params {
+        return Err(invalid_params(
+            &name,
+            format!(
+                "{} parameters are required but {} are given.",
+                nbr_of_params,
+                params.len()
+            ),
+        ));
+    }
     let mut rng = rand::thread_rng();
     match name {
         // DistributionEnum::Bernoulli => {
@@ -73,19 +97,20 @@ where
         //     Array::zeros(shape).map(|_| cast_t2u(gen.sample(&mut rng)))
         // },
         DistributionEnum::Beta => {
-            let gen =
-                Beta::new(cast_t2u::<T, f64>(params[0]), cast_t2u::<T, f64>(params[1])).unwrap();
-            Array::<T, D>::zeros(shape).map(|_| cast_t2u(gen.sample(&mut rng)))
+            let gen = Beta::new(cast_t2u::<T, f64>(params[0]), cast_t2u::<T, f64>(params[1]))
+                .map_err(|err| invalid_params(&name, err))?;
+            Ok(Array::<T, D>::zeros(shape).map(|_| cast_t2u(gen.sample(&mut rng))))
         }
         // DistributionEnum::Binomial => Array::random(shape, Binomial::new(params[0], params[1])),
         DistributionEnum::Cauchy => {
-            let gen =
-                Cauchy::new(cast_t2u::<T, f64>(params[0]), cast_t2u::<T, f64>(params[1])).unwrap();
-            Array::<T, D>::zeros(shape).map(|_| cast_t2u(gen.sample(&mut rng)))
+            let gen = Cauchy::new(cast_t2u::<T, f64>(params[0]), cast_t2u::<T, f64>(params[1]))
+                .map_err(|err| invalid_params(&name, err))?;
+            Ok(Array::<T, D>::zeros(shape).map(|_| cast_t2u(gen.sample(&mut rng))))
         }
         DistributionEnum::ChiSquared => {
-            let gen = ChiSquared::new(cast_t2u::<T, f64>(params[0])).unwrap();
-            Array::<T, D>::zeros(shape).map(|_| cast_t2u(gen.sample(&mut rng)))
+            let gen = ChiSquared::new(cast_t2u::<T, f64>(params[0]))
+                .map_err(|err| invalid_params(&name, err))?;
+            Ok(Array::<T, D>::zeros(shape).map(|_| cast_t2u(gen.sample(&mut rng))))
         }
         // DistributionEnum::Dirichlet => {
         //     let gen = Dirichlet::new(cast_t2u::<T, f64>(params[0]), cast_t2u::<T, f64>(params[1]))
@@ -93,33 +118,34 @@ where
         //     Array::<T, D>::zeros(shape).map(|_| cast_t2u(gen.sample(&mut rng)))
         // },
         DistributionEnum::Exp => {
-            let gen = Exp::new(cast_t2u::<T, f64>(params[0])).unwrap();
-            Array::<T, D>::zeros(shape).map(|_| cast_t2u(gen.sample(&mut rng)))
+            let gen = Exp::new(cast_t2u::<T, f64>(params[0]))
+                .map_err(|err| invalid_params(&name, err))?;
+            Ok(Array::<T, D>::zeros(shape).map(|_| cast_t2u(gen.sample(&mut rng))))
         }
         DistributionEnum::FisherF => {
-            let gen =
-                FisherF::new(cast_t2u::<T, f64>(params[0]), cast_t2u::<T, f64>(params[1])).unwrap();
-            Array::<T, D>::zeros(shape).map(|_| cast_t2u(gen.sample(&mut rng)))
+            let gen = FisherF::new(cast_t2u::<T, f64>(params[0]), cast_t2u::<T, f64>(params[1]))
+                .map_err(|err| invalid_params(&name, err))?;
+            Ok(Array::<T, D>::zeros(shape).map(|_| cast_t2u(gen.sample(&mut rng))))
         }
         DistributionEnum::Gamma => {
-            let gen =
-                Gamma::new(cast_t2u::<T, f64>(params[0]), cast_t2u::<T, f64>(params[1])).unwrap();
-            Array::<T, D>::zeros(shape).map(|_| cast_t2u(gen.sample(&mut rng)))
+            let gen = Gamma::new(cast_t2u::<T, f64>(params[0]), cast_t2u::<T, f64>(params[1]))
+                .map_err(|err| invalid_params(&name, err))?;
+            Ok(Array::<T, D>::zeros(shape).map(|_| cast_t2u(gen.sample(&mut rng))))
         }
         DistributionEnum::LogNormal => {
             let gen = LogNormal::new(cast_t2u::<T, f64>(params[0]), cast_t2u::<T, f64>(params[1]))
-                .unwrap();
-            Array::<T, D>::zeros(shape).map(|_| cast_t2u(gen.sample(&mut rng)))
+                .map_err(|err| invalid_params(&name, err))?;
+            Ok(Array::<T, D>::zeros(shape).map(|_| cast_t2u(gen.sample(&mut rng))))
         }
         DistributionEnum::Normal => {
-            let gen =
-                Normal::new(cast_t2u::<T, f64>(params[0]), cast_t2u::<T, f64>(params[1])).unwrap();
-            Array::<T, D>::zeros(shape).map(|_| cast_t2u(gen.sample(&mut rng)))
+            let gen = Normal::new(cast_t2u::<T, f64>(params[0]), cast_t2u::<T, f64>(params[1]))
+                .map_err(|err| invalid_params(&name, err))?;
+            Ok(Array::<T, D>::zeros(shape).map(|_| cast_t2u(gen.sample(&mut rng))))
         }
         DistributionEnum::Pareto => {
-            let gen =
-                Pareto::new(cast_t2u::<T, f64>(params[0]), cast_t2u::<T, f64>(params[1])).unwrap();
-            Array::<T, D>::zeros(shape).map(|_| cast_t2u(gen.sample(&mut rng)))
+            let gen = Pareto::new(cast_t2u::<T, f64>(params[0]), cast_t2u::<T, f64>(params[1]))
+                .map_err(|err| invalid_params(&name, err))?;
+            Ok(Array::<T, D>::zeros(shape).map(|_| cast_t2u(gen.sample(&mut rng))))
         }
         DistributionEnum::Pert => {
             let gen = Pert::new(
@@ -127,16 +153,18 @@ where
                 cast_t2u::<T, f64>(params[1]),
                 cast_t2u::<T, f64>(params[2]),
             )
-            .unwrap();
-            Array::<T, D>::zeros(shape).map(|_| cast_t2u(gen.sample(&mut rng)))
+            .map_err(|err| invalid_params(&name, err))?;
+            Ok(Array::<T, D>::zeros(shape).map(|_| cast_t2u(gen.sample(&mut rng))))
         }
         DistributionEnum::Poisson => {
-            let gen = Poisson::new(cast_t2u::<T, f64>(params[0])).unwrap();
-            Array::<T, D>::zeros(shape).map(|_| cast_t2u::<f64, T>(gen.sample(&mut rng)))
+            let gen = Poisson::new(cast_t2u::<T, f64>(params[0]))
+                .map_err(|err| invalid_params(&name, err))?;
+            Ok(Array::<T, D>::zeros(shape).map(|_| cast_t2u::<f64, T>(gen.sample(&mut rng))))
         }
         DistributionEnum::StudentT => {
-            let gen = StudentT::new(cast_t2u::<T, f64>(params[0])).unwrap();
-            Array::<T, D>::zeros(shape).map(|_| cast_t2u(gen.sample(&mut rng)))
+            let gen = StudentT::new(cast_t2u::<T, f64>(params[0]))
+                .map_err(|err| invalid_params(&name, err))?;
+            Ok(Array::<T, D>::zeros(shape).map(|_| cast_t2u(gen.sample(&mut rng))))
         }
         DistributionEnum::Triangular => {
             let gen = Triangular::new(
@@ -144,18 +172,34 @@ where
                 cast_t2u::<T, f64>(params[1]),
                 cast_t2u::<T, f64>(params[2]),
             )
-            .unwrap();
-            Array::<T, D>::zeros(shape).map(|_| cast_t2u(gen.sample(&mut rng)))
+            .map_err(|err| invalid_params(&name, err))?;
+            Ok(Array::<T, D>::zeros(shape).map(|_| cast_t2u(gen.sample(&mut rng))))
         }
         DistributionEnum::Uniform => {
+            if params[0] >= params[1] {
+                return Err(invalid_params(
+                    &name,
+                    "the lower bound must be less than the upper one.",
+                ));
+            }
             let gen = Uniform::new(cast_t2u::<T, f64>(params[0]), cast_t2u::<T, f64>(params[1]));
-            Array::<T, D>::zeros(shape).map(|_| cast_t2u(gen.sample(&mut rng)))
+            Ok(Array::<T, D>::zeros(shape).map(|_| cast_t2u(gen.sample(&mut rng))))
         }
         DistributionEnum::Weibull => {
-            let gen =
-                Weibull::new(cast_t2u::<T, f64>(params[0]), cast_t2u::<T, f64>(params[1])).unwrap();
-            Array::<T, D>::zeros(shape).map(|_| cast_t2u(gen.sample(&mut rng)))
-        }
-        _ => panic!("Invalid distribution name: {}", name),
+            let gen = Weibull::new(cast_t2u::<T, f64>(params[0]), cast_t2u::<T, f64>(params[1]))
+                .map_err(|err| invalid_params(&name, err))?;
+            Ok(Array::<T, D>::zeros(shape).map(|_| cast_t2u(gen.sample(&mut rng))))
+        }
+        _ => Err(CrateError::ConfigError(format!(
+            "distribution `{}` is not implemented.",
+            name
+        ))),
     }
 }
+
+fn invalid_params<E>(name: &DistributionEnum, err: E) -> CrateError
+where
+    E: Display,
+{
+    CrateError::ConfigError(format!("invalid parameters of `{}`: {}", name, err))
+}
@@ -3,8 +3,9 @@
 //! Initializers of weights
 
 use super::super::util::*;
-use super::ndarray_init::*;
 use ndarray::prelude::*;
+use ndarray_rand::rand_distr::StandardNormal;
+use ndarray_rand::RandomExt;
 use std::fmt::Display;
 
 /// Enum of initializers of weights
@@ -39,11 +40,7 @@ where
     D: Dimension,
     Sh: ShapeBuilder<Dim = D>,
 {
-    let src: Array<T, D> = initialize_randomized_ndarray(
-        DistributionEnum::Normal,
-        shape,
-        &[cast_t2u(0.0), cast_t2u(1.0)],
-    );
+    let src: Array<T, D> = Array::<f64, D>::random(shape, StandardNormal).map(|&v| cast_t2u(v));
     let scale: T = match weight_init {
         WeightInitEnum::Normal => weight_init_std,
         WeightInitEnum::ReLU | WeightInitEnum::He => {
@@ -2,17 +2,29 @@
 //!
 //! Trainers for models
 
-use super::models::ModelBase;
+use super::error::CrateError;
+use super::metrics::ClassificationMetricEnum;
+use super::models::{ModelBase, SparseModelBase};
 use super::util::*;
 use ndarray::{prelude::*, RemoveAxis};
 use rand::prelude::*;
 use std::time::Instant;
 
+/// function updating a model with a batch
+type UpdateFn<M, X, Y> = fn(&mut M, &X, &Y) -> Result<(), CrateError>;
+/// function evaluating the accuracy of a model
+type AccuracyFn<T, M, X, Y> = fn(&mut M, &X, &Y) -> Result<T, CrateError>;
+/// function giving scores of a model as (batch, classes) and labels as class indices
+type ScoresFn<T, M, X, Y> = fn(&mut M, &X, &Y) -> Result<(Array2<T>, Array1<usize>), CrateError>;
+
 #[derive(Clone)]
 pub struct TrainResult<T: CrateFloat> {
     train_loss_list: Vec<T>,
     train_acc_list: Vec<T>,
     test_acc_list: Vec<T>,
+    metrics: Vec<ClassificationMetricEnum>,
+    train_metric_lists: Vec<Vec<T>>,
+    test_metric_lists: Vec<Vec<T>>,
 }
 
 impl<T> TrainResult<T>
@@ -24,6 +36,9 @@ where
             train_loss_list: Vec::new(),
             train_acc_list: Vec::new(),
             test_acc_list: Vec::new(),
+            metrics: Vec::new(),
+            train_metric_lists: Vec::new(),
+            test_metric_lists: Vec::new(),
         }
     }
     pub fn train_result(&self) -> (Vec<T>, Vec<T>, Vec<T>) {
@@ -33,13 +48,50 @@ where
             self.test_acc_list.clone(),
         )
     }
+    /// metrics logged per epoch with their values for training and test
+    pub fn metric_result(&self) -> Vec<(ClassificationMetricEnum, Vec<T>, Vec<T>)> {
+        self.metrics
+            .iter()
+            .zip(self.train_metric_lists.iter())
+            .zip(self.test_metric_lists.iter())
+            .map(|((metric, train), test)| (metric.clone(), train.clone(), test.clone()))
+            .collect()
+    }
 }
 
-pub struct Trainer<T: CrateFloat, D1: Dimension, D2: Dimension> {
+/// accuracy and metrics of a model for a dataset
+fn evaluate<T, M: ?Sized, X, Y>(
+    model: &mut M,
+    accuracy: AccuracyFn<T, M, X, Y>,
+    scores: ScoresFn<T, M, X, Y>,
+    metrics: &[ClassificationMetricEnum],
+    x: &X,
+    t: &Y,
+) -> Result<(T, Vec<T>), CrateError>
+where
+    T: CrateFloat,
+{
+    let acc: T = accuracy(model, x, t)?;
+    if metrics.is_empty() {
+        return Ok((acc, Vec::new()));
+    }
+    let (scores, labels) = scores(model, x, t)?;
+    let values: Vec<T> = metrics
+        .iter()
+        .map(|metric| metric.compute(&scores, &labels))
+        .collect::<Result<_, _>>()?;
+    Ok((acc, values))
+}
+
+/// Trainer of models
+///
+/// Targets are arrays of `T` such as one-hot labels by default,
+/// or class indices of `usize` for `train_sparse`.
+pub struct Trainer<T: CrateFloat, D1: Dimension, D2: Dimension, L = T> {
     x_train: Array<T, D1>,
-    t_train: Array<T, D2>,
+    t_train: Array<L, D2>,
     x_test: Array<T, D1>,
-    t_test: Array<T, D2>,
+    t_test: Array<L, D2>,
     epochs: usize,
     batch_size: usize,
     batch_axis: usize,
@@ -50,6 +102,9 @@ pub struct Trainer<T: CrateFloat, D1: Dimension, D2: Dimension> {
     train_loss_list: Vec<T>,
     train_acc_list: Vec<T>,
     test_acc_list: Vec<T>,
+    metrics: Vec<ClassificationMetricEnum>,
+    train_metric_lists: Vec<Vec<T>>,
+    test_metric_lists: Vec<Vec<T>>,
     iter_per_epoch: usize,
     train_size: usize,
     max_iter: usize,
@@ -59,17 +114,18 @@ pub struct Trainer<T: CrateFloat, D1: Dimension, D2: Dimension> {
     elapsed_time: f64,
 }
 
-impl<T, D1, D2> Trainer<T, D1, D2>
+impl<T, D1, D2, L> Trainer<T, D1, D2, L>
 where
     T: CrateFloat,
     D1: Dimension + RemoveAxis,
     D2: Dimension + RemoveAxis,
+    L: Clone,
 {
     pub fn new(
         x_train: Array<T, D1>,
-        t_train: Array<T, D2>,
+        t_train: Array<L, D2>,
         x_test: Array<T, D1>,
-        t_test: Array<T, D2>,
+        t_test: Array<L, D2>,
         batch_axis: usize,
         epochs: usize,
         batch_size: usize,
@@ -103,6 +159,9 @@ where
             train_loss_list: Vec::new(),
             train_acc_list: Vec::new(),
             test_acc_list: Vec::new(),
+            metrics: Vec::new(),
+            train_metric_lists: Vec::new(),
+            test_metric_lists: Vec::new(),
             iter_per_epoch,
             train_size,
             max_iter,
@@ -112,10 +171,24 @@ where
             elapsed_time: 0.0f64,
         }
     }
-    fn train_step(
+    /// classification metrics logged per epoch along with the accuracy
+    ///
+    /// Metrics are computed from `predict_prob` when `log_temporal_result` is set.
+    pub fn with_metrics(mut self, metrics: Vec<ClassificationMetricEnum>) -> Self {
+        self.train_metric_lists = vec![Vec::new(); metrics.len()];
+        self.test_metric_lists = vec![Vec::new(); metrics.len()];
+        self.metrics = metrics;
+        self
+    }
+    /// one iteration, where the model is accessed through the given functions
+    fn train_step<M: ?Sized>(
         &mut self,
-        model: &mut Box<dyn ModelBase<T, A = Array<T, D1>, B = Array<T, D2>>>,
-    ) {
+        model: &mut M,
+        update: UpdateFn<M, Array<T, D1>, Array<L, D2>>,
+        accuracy: AccuracyFn<T, M, Array<T, D1>, Array<L, D2>>,
+        scores: ScoresFn<T, M, Array<T, D1>, Array<L, D2>>,
+        current_loss: fn(&M) -> T,
+    ) -> Result<(), CrateError> {
         let mut rng = thread_rng();
         // choose indices
         let mut indices: Vec<usize> = vec![0usize; self.batch_size];
@@ -124,61 +197,89 @@ where
         }
         let x_batch = self.x_train.select(Axis(0), &indices);
         let t_batch = self.t_train.select(Axis(0), &indices);
-        model.update(&x_batch, &t_batch);
+        update(model, &x_batch, &t_batch)?;
         if self.current_iter % self.iter_per_epoch == 0 {
             self.current_epoch += 1;
             if self.log_temporal_result {
-                let train_acc: T;
-                let test_acc: T;
-                if self.nbr_of_samples_per_epoch > 0 {
-                    train_acc = model.accuracy(
-                        &self
-                            .x_train
-                            .select(Axis(self.batch_axis), &self.sample_indices),
-                        &self
-                            .t_train
-                            .select(Axis(self.batch_axis), &self.sample_indices),
-                    );
-                    test_acc = model.accuracy(
-                        &self
-                            .x_test
-                            .select(Axis(self.batch_axis), &self.sample_indices),
-                        &self
-                            .t_test
-                            .select(Axis(self.batch_axis), &self.sample_indices),
-                    );
+                let (train, test) = if self.nbr_of_samples_per_epoch > 0 {
+                    let select =
+                        |x: &Array<T, D1>| x.select(Axis(self.batch_axis), &self.sample_indices);
+                    let select_t =
+                        |t: &Array<L, D2>| t.select(Axis(self.batch_axis), &self.sample_indices);
+                    (
+                        evaluate(
+                            model,
+                            accuracy,
+                            scores,
+                            &self.metrics,
+                            &select(&self.x_train),
+                            &select_t(&self.t_train),
+                        )?,
+                        evaluate(
+                            model,
+                            accuracy,
+                            scores,
+                            &self.metrics,
+                            &select(&self.x_test),
+                            &select_t(&self.t_test),
+                        )?,
+                    )
                 } else {
-                    train_acc = model.accuracy(&self.x_train, &self.t_train);
-                    test_acc = model.accuracy(&self.x_test, &self.t_test);
+                    (
+                        evaluate(
+                            model,
+                            accuracy,
+                            scores,
+                            &self.metrics,
+                            &self.x_train,
+                            &self.t_train,
+                        )?,
+                        evaluate(
+                            model,
+                            accuracy,
+                            scores,
+                            &self.metrics,
+                            &self.x_test,
+                            &self.t_test,
+                        )?,
+                    )
+                };
+                self.train_acc_list.push(train.0);
+                self.test_acc_list.push(test.0);
+                for (ii, metric) in self.metrics.iter().enumerate() {
+                    self.train_metric_lists[ii].push(train.1[ii]);
+                    self.test_metric_lists[ii].push(test.1[ii]);
+                    if self.verbose > 0 {
+                        println!(
+                            "{} at epoch {}: train {}, test {}",
+                            metric, self.current_epoch, train.1[ii], test.1[ii]
+                        );
+                    }
                 }
-                self.train_acc_list.push(train_acc);
-                self.test_acc_list.push(test_acc);
             }
             if self.verbose > 0 {
                 println!(
                     "train loss at step {}: {}",
                     self.current_iter,
-                    model.get_current_loss()
+                    current_loss(model)
                 );
             }
         }
         self.current_iter += 1;
+        Ok(())
     }
-    pub fn train(&mut self, model: &mut Box<dyn ModelBase<T, A = Array<T, D1>, B = Array<T, D2>>>) {
-        model.set_trainable(true);
-        let start = Instant::now();
-        for _ in 0..self.max_iter {
-            self.train_step(model);
-        }
+    fn set_elapsed_time(&mut self, start: Instant) {
         let end = start.elapsed();
         self.elapsed_time = end.as_secs() as f64 + end.subsec_micros() as f64 * 1E-6;
-        model.set_trainable(false);
     }
     pub fn get_results(&self) -> TrainResult<T> {
         TrainResult {
             train_loss_list: self.train_loss_list.clone(),
             train_acc_list: self.train_acc_list.clone(),
             test_acc_list: self.test_acc_list.clone(),
+            metrics: self.metrics.clone(),
+            train_metric_lists: self.train_metric_lists.clone(),
+            test_metric_lists: self.test_metric_lists.clone(),
         }
     }
     pub fn get_elapsed_time(&self) -> f64 {
@@ -196,3 +297,75 @@ where
         println!("elapsed time: {}", self.elapsed_time);
     }
 }
+
+impl<T, D1, D2> Trainer<T, D1, D2>
+where
+    T: CrateFloat,
+    D1: Dimension + RemoveAxis,
+    D2: Dimension + RemoveAxis,
+{
+    /// train a model
+    ///
+    /// Metrics need targets of (batch, classes); they are rejected for other targets up front,
+    /// and failures while computing them stop the training with an error.
+    pub fn train(
+        &mut self,
+        model: &mut Box<dyn ModelBase<T, A = Array<T, D1>, B = Array<T, D2>>>,
+    ) -> Result<(), CrateError> {
+        if !self.metrics.is_empty() && D2::NDIM != Some(2) {
+            return Err(CrateError::ConfigError(
+                "metrics need targets of (batch, classes)".to_string(),
+            ));
+        }
+        model.set_trainable(true);
+        let start = Instant::now();
+        let result = (0..self.max_iter).try_for_each(|_| {
+            self.train_step(
+                model.as_mut(),
+                |m, x, t| {
+                    m.update(x, t);
+                    Ok(())
+                },
+                |m, x, t| Ok(m.accuracy(x, t)),
+                |m, x, t| {
+                    let scores: Array2<T> = m.predict_prob(x).into_dimensionality()?;
+                    let t: Array2<T> = t.to_owned().into_dimensionality()?;
+                    Ok((scores, to_class_indices(&t)))
+                },
+                |m| m.get_current_loss(),
+            )
+        });
+        self.set_elapsed_time(start);
+        model.set_trainable(false);
+        result
+    }
+}
+
+impl<T, D1> Trainer<T, D1, Ix1, usize>
+where
+    T: CrateFloat,
+    D1: Dimension + RemoveAxis,
+{
+    /// train a classifier with sparse labels, i.e. class indices of samples
+    ///
+    /// Labels out of the classes stop the training with an error.
+    pub fn train_sparse(
+        &mut self,
+        model: &mut Box<dyn SparseModelBase<T, A = Array<T, D1>, B = Array2<T>>>,
+    ) -> Result<(), CrateError> {
+        model.set_trainable(true);
+        let start = Instant::now();
+        let result = (0..self.max_iter).try_for_each(|_| {
+            self.train_step(
+                model.as_mut(),
+                |m, x, t| m.update_sparse(x, t),
+                |m, x, t| m.accuracy_sparse(x, t),
+                |m, x, t| Ok((m.predict_prob(x), t.clone())),
+                |m| m.get_current_loss(),
+            )
+        });
+        self.set_elapsed_time(start);
+        model.set_trainable(false);
+        result
+    }
+}
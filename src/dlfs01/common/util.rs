@@ -2,7 +2,8 @@
 //!
 //! utility functions
 
-use ndarray::ScalarOperand;
+use super::error::CrateError;
+use ndarray::{prelude::*, ScalarOperand};
 use ndarray_rand::rand_distr::uniform::SampleUniform;
 use num_traits::{Float, FromPrimitive, Num, NumCast};
 use serde::Serialize;
@@ -24,6 +25,7 @@ pub trait CrateFloat:
     + SampleUniform
     + ScalarOperand
     + Debug
+    + Default
     + Display
     + Serialize // + Deserialize<'static>
 {
@@ -42,6 +44,7 @@ impl<T> CrateFloat for T where
         + SampleUniform
         + ScalarOperand
         + Debug
+        + Default
         + Display
         + Serialize // + Deserialize<'static
 {
@@ -55,3 +58,40 @@ where
 {
     U::from(x).unwrap()
 }
+
+/// expand class indices into one-hot rows of `num_classes` columns
+pub fn to_one_hot<T>(labels: &Array1<usize>, num_classes: usize) -> Result<Array2<T>, CrateError>
+where
+    T: Num + Copy,
+{
+    let mut dst: Array2<T> = Array2::zeros((labels.len(), num_classes));
+    for (mut row, &label) in dst.outer_iter_mut().zip(labels.iter()) {
+        if label >= num_classes {
+            return Err(CrateError::ShapeError(format!(
+                "label {} is out of {} classes.",
+                label, num_classes
+            )));
+        }
+        row[label] = T::one();
+    }
+    Ok(dst)
+}
+
+/// class indices of one-hot rows, i.e. the index of the maximum of each row
+pub fn to_class_indices<T>(one_hot: &Array2<T>) -> Array1<usize>
+where
+    T: PartialOrd + Copy,
+{
+    one_hot
+        .outer_iter()
+        .map(|row| {
+            row.iter()
+                .enumerate()
+                .fold((0, None), |(arg, max), (ii, &v)| match max {
+                    Some(m) if v <= m => (arg, max),
+                    _ => (ii, Some(v)),
+                })
+                .0
+        })
+        .collect()
+}
@@ -7,6 +7,7 @@
 #![allow(unused_imports)]
 
 use crate::dlfs01::cast_t2u;
+use crate::dlfs01::common::util::to_one_hot;
 use ndarray::prelude::*;
 use num_traits::{Num, NumCast};
 use std::fs::File;
@@ -54,19 +55,29 @@ pub enum DataSetError {
     FileIOError(#[from] io::Error),
     #[error("failure in reshaping array.")]
     ShapeError(#[from] ndarray::ShapeError),
+    #[error("failure in sampling: {0}")]
+    SamplingError(String),
 }
 
+/// MNIST dataset
+///
+/// Labels are one-hot `Array2` by default, or class indices for `SparseMNISTDataSet`.
 #[derive(Clone)]
-pub struct MNISTDataSet<T, D> {
+pub struct MNISTDataSet<T, D, L = Array2<T>> {
     pub train_images: Array<T, D>,
-    pub train_labels: Array2<T>,
+    pub train_labels: L,
     pub test_images: Array<T, D>,
-    pub test_labels: Array2<T>,
+    pub test_labels: L,
 }
 
 pub type MNISTDataSet2<T> = MNISTDataSet<T, Ix2>;
 pub type MNISTDataSet4<T> = MNISTDataSet<T, Ix4>;
 
+/// MNIST dataset with labels given as class indices
+pub type SparseMNISTDataSet<T, D> = MNISTDataSet<T, D, Array1<usize>>;
+pub type SparseMNISTDataSet2<T> = SparseMNISTDataSet<T, Ix2>;
+pub type SparseMNISTDataSet4<T> = SparseMNISTDataSet<T, Ix4>;
+
 impl<D> MNISTDataSet<u8, D>
 where
     D: Dimension,
@@ -81,71 +92,78 @@ where
     }
 }
 
-impl MNISTDataSet2<u8> {
-    pub fn new(verbose: u8) -> Result<Self, DataSetError> {
-        if verbose > 0u8 {
-            println!("load images for training...");
+impl<D> SparseMNISTDataSet<u8, D>
+where
+    D: Dimension,
+{
+    pub fn to_f64(self) -> SparseMNISTDataSet<f64, D> {
+        SparseMNISTDataSet::<f64, D> {
+            train_images: self.train_images.map(|&v| v as f64 / IMG_MAX as f64),
+            train_labels: self.train_labels,
+            test_images: self.test_images.map(|&v| v as f64 / IMG_MAX as f64),
+            test_labels: self.test_labels,
         }
-        let file_path = Path::new(MNIST_DIR).join(KEY_FILE[0].1);
-        let train_images = load_images_2d(&file_path)?;
+    }
+}
 
-        if verbose > 0u8 {
-            println!("load labels for training...");
-        }
-        let file_path = Path::new(MNIST_DIR).join(KEY_FILE[1].1);
-        let train_labels = load_labels(&file_path)?;
+/// load images and labels for training and test with the given loaders
+fn load_data_set<D, L>(
+    verbose: u8,
+    load_images: fn(&Path) -> Result<Array<u8, D>, DataSetError>,
+    load_labels: fn(&Path) -> Result<L, DataSetError>,
+) -> Result<MNISTDataSet<u8, D, L>, DataSetError> {
+    if verbose > 0u8 {
+        println!("load images for training...");
+    }
+    let file_path = Path::new(MNIST_DIR).join(KEY_FILE[0].1);
+    let train_images = load_images(&file_path)?;
 
-        if verbose > 0u8 {
-            println!("load images for test...");
-        }
-        let file_path = Path::new(MNIST_DIR).join(KEY_FILE[2].1);
-        let test_images = load_images_2d(&file_path)?;
+    if verbose > 0u8 {
+        println!("load labels for training...");
+    }
+    let file_path = Path::new(MNIST_DIR).join(KEY_FILE[1].1);
+    let train_labels = load_labels(&file_path)?;
 
-        if verbose > 0u8 {
-            println!("load labels for test...");
-        }
-        let file_path = Path::new(MNIST_DIR).join(KEY_FILE[3].1);
-        let test_labels = load_labels(&file_path)?;
-        Ok(Self {
-            train_images,
-            train_labels,
-            test_images,
-            test_labels,
-        })
+    if verbose > 0u8 {
+        println!("load images for test...");
     }
+    let file_path = Path::new(MNIST_DIR).join(KEY_FILE[2].1);
+    let test_images = load_images(&file_path)?;
+
+    if verbose > 0u8 {
+        println!("load labels for test...");
+    }
+    let file_path = Path::new(MNIST_DIR).join(KEY_FILE[3].1);
+    let test_labels = load_labels(&file_path)?;
+    Ok(MNISTDataSet {
+        train_images,
+        train_labels,
+        test_images,
+        test_labels,
+    })
 }
 
-impl MNISTDataSet4<u8> {
+impl MNISTDataSet2<u8> {
     pub fn new(verbose: u8) -> Result<Self, DataSetError> {
-        if verbose > 0u8 {
-            println!("load images for training...");
-        }
-        let file_path = Path::new(MNIST_DIR).join(KEY_FILE[0].1);
-        let train_images = load_images_4d(&file_path)?;
+        load_data_set(verbose, load_images_2d, load_labels)
+    }
+}
 
-        if verbose > 0u8 {
-            println!("load labels for training...");
-        }
-        let file_path = Path::new(MNIST_DIR).join(KEY_FILE[1].1);
-        let train_labels = load_labels(&file_path)?;
+impl MNISTDataSet4<u8> {
+    pub fn new(verbose: u8) -> Result<Self, DataSetError> {
+        load_data_set(verbose, load_images_4d, load_labels)
+    }
+}
 
-        if verbose > 0u8 {
-            println!("load images for test...");
-        }
-        let file_path = Path::new(MNIST_DIR).join(KEY_FILE[2].1);
-        let test_images = load_images_4d(&file_path)?;
+impl SparseMNISTDataSet2<u8> {
+    pub fn new(verbose: u8) -> Result<Self, DataSetError> {
+        load_data_set(verbose, load_images_2d, load_label_indices)
+    }
+}
 
-        if verbose > 0u8 {
-            println!("load labels for test...");
-        }
-        let file_path = Path::new(MNIST_DIR).join(KEY_FILE[3].1);
-        let test_labels = load_labels(&file_path)?;
-        Ok(Self {
-            train_images,
-            train_labels,
-            test_images,
-            test_labels,
-        })
+impl SparseMNISTDataSet4<u8> {
+    pub fn new(verbose: u8) -> Result<Self, DataSetError> {
+        load_data_set(verbose, load_images_4d, load_label_indices)
     }
 }
 
@@ -171,21 +189,30 @@ pub fn load_images_2d(file_path: &Path) -> Result<Array2<u8>, DataSetError> {
     Ok(dst)
 }
 
+/// load labels as one-hot rows
 pub fn load_labels(file_path: &Path) -> Result<Array2<u8>, DataSetError> {
+    let labels = load_label_indices(file_path)?;
+    to_one_hot(&labels, NBR_CLASS).map_err(|_| DataSetError::IndexError)
+}
+
+/// load labels as class indices
+pub fn load_label_indices(file_path: &Path) -> Result<Array1<usize>, DataSetError> {
     let v = read_file(file_path)?;
-    assert!(v.len() > NBR_SKIP_BYTES_LABEL);
-    let v = v[NBR_SKIP_BYTES_LABEL..].to_vec();
-    let shape = (v.len(), NBR_CLASS);
-    let mut dst: Vec<u8> = Vec::new();
-    for c in v {
-        dst.append(&mut one_hot(c, NBR_CLASS)?);
+    if v.len() <= NBR_SKIP_BYTES_LABEL {
+        return Err(DataSetError::IndexError);
+    }
+    let labels: Array1<usize> = v[NBR_SKIP_BYTES_LABEL..]
+        .iter()
+        .map(|&c| c as usize)
+        .collect();
+    if labels.iter().any(|&c| c >= NBR_CLASS) {
+        return Err(DataSetError::IndexError);
     }
-    let x = Array2::from_shape_vec(shape, dst)?;
-    Ok(x)
+    Ok(labels)
 }
 
 pub fn one_hot(c: u8, size: usize) -> Result<Vec<u8>, DataSetError> {
-    if c as usize > size {
+    if c as usize >= size {
         return Err(DataSetError::IndexError);
     }
     let mut dst: Vec<u8> = vec![0u8; size];
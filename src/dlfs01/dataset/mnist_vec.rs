@@ -184,11 +184,13 @@ pub fn load_mnist(verbose: u8) -> Result<MNISTDataSet<f64>, DataSetError> {
 
 pub fn load_images(file_path: &Path) -> Result<Vec4d<u8>, DataSetError> {
     let v = read_file(file_path)?;
-    assert!(v.len() > NBR_SKIP_BYTES_IMAGE);
+    if v.len() <= NBR_SKIP_BYTES_IMAGE {
+        return Err(DataSetError::IndexError);
+    }
     let img_size: usize = IMG_DIM.0 * IMG_DIM.1 * IMG_DIM.2;
     let mut dst: Vec4d<u8> = Vec::new();
     let mut index: usize = NBR_SKIP_BYTES_IMAGE;
-    while index < v.len() {
+    while index + img_size <= v.len() {
         match reshape_1d3d(&v[index..(index + img_size)].to_vec(), IMG_DIM) {
             Ok(v) => dst.push(v),
             Err(err) => return Err(err),
@@ -218,7 +220,9 @@ pub fn reshape_1d3d(v: &Vec<u8>, shape: (usize, usize, usize)) -> Result<Vec3d<u
 
 pub fn load_labels(file_path: &Path) -> Result<Vec2d<u8>, DataSetError> {
     let v = read_file(file_path)?;
-    assert!(v.len() > NBR_SKIP_BYTES_LABEL);
+    if v.len() <= NBR_SKIP_BYTES_LABEL {
+        return Err(DataSetError::IndexError);
+    }
     let mut dst: Vec2d<u8> = Vec::new();
     for c in v[NBR_SKIP_BYTES_LABEL..].to_vec() {
         let v_: Vec<u8> = one_hot(c, NBR_CLASS)?;
@@ -228,7 +232,7 @@ pub fn load_labels(file_path: &Path) -> Result<Vec2d<u8>, DataSetError> {
 }
 
 pub fn one_hot(c: u8, size: usize) -> Result<Vec<u8>, DataSetError> {
-    if c as usize > size {
+    if c as usize >= size {
         return Err(DataSetError::IndexError);
     }
     let mut dst: Vec<u8> = vec![0u8; size];
@@ -4,3 +4,4 @@
 
 pub mod mnist;
 pub mod mnist_vec;
+pub mod sampling;
@@ -0,0 +1,148 @@
+//! sampling
+//!
+//! Sampling of pairs and triplets from labelled datasets for metric learning
+
+use super::mnist::{DataSetError, MNISTDataSet};
+use crate::dlfs01::common::util::to_class_indices;
+use ndarray::{prelude::*, RemoveAxis};
+use rand::prelude::*;
+use std::collections::BTreeMap;
+
+/// Labels which can be read as class indices
+pub trait ClassLabels {
+    fn class_indices(&self) -> Array1<usize>;
+}
+
+/// one-hot labels
+impl<T> ClassLabels for Array2<T>
+where
+    T: PartialOrd + Copy,
+{
+    fn class_indices(&self) -> Array1<usize> {
+        to_class_indices(self)
+    }
+}
+
+/// class indices
+impl ClassLabels for Array1<usize> {
+    fn class_indices(&self) -> Array1<usize> {
+        self.clone()
+    }
+}
+
+/// indices of samples for each class
+fn group_by_class(labels: &Array1<usize>) -> BTreeMap<usize, Vec<usize>> {
+    let mut groups: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for (ii, &label) in labels.iter().enumerate() {
+        groups.entry(label).or_default().push(ii);
+    }
+    groups
+}
+
+/// indices of samples belonging to classes of two or more samples, which can be anchors
+fn anchor_candidates(
+    labels: &Array1<usize>,
+    groups: &BTreeMap<usize, Vec<usize>>,
+) -> Result<Vec<usize>, DataSetError> {
+    if groups.len() < 2 {
+        return Err(DataSetError::SamplingError(
+            "two or more classes are needed.".to_string(),
+        ));
+    }
+    let candidates: Vec<usize> = (0..labels.len())
+        .filter(|&ii| groups[&labels[ii]].len() > 1)
+        .collect();
+    if candidates.is_empty() {
+        return Err(DataSetError::SamplingError(
+            "a class of two or more samples is needed.".to_string(),
+        ));
+    }
+    Ok(candidates)
+}
+
+/// a sample of the same class as `anchor` other than itself
+fn choose_positive<R: Rng>(anchor: usize, group: &[usize], rng: &mut R) -> usize {
+    let index: usize = rng.gen_range(0..group.len() - 1);
+    let position: usize = group.iter().position(|&ii| ii == anchor).unwrap();
+    group[if index < position { index } else { index + 1 }]
+}
+
+/// a sample of a class other than `label`
+fn choose_negative<R: Rng>(label: usize, labels: &Array1<usize>, rng: &mut R) -> usize {
+    loop {
+        let index: usize = rng.gen_range(0..labels.len());
+        if labels[index] != label {
+            return index;
+        }
+    }
+}
+
+/// Sample pairs of indices with flags of whether the two samples are of the same class
+///
+/// Similar and dissimilar pairs are sampled with the same probability.
+pub fn sample_pairs<R: Rng>(
+    labels: &Array1<usize>,
+    num_pairs: usize,
+    rng: &mut R,
+) -> Result<Vec<([usize; 2], bool)>, DataSetError> {
+    let groups = group_by_class(labels);
+    let candidates: Vec<usize> = anchor_candidates(labels, &groups)?;
+    Ok((0..num_pairs)
+        .map(|_| {
+            let anchor: usize = *candidates.choose(rng).unwrap();
+            let label: usize = labels[anchor];
+            if rng.gen_bool(0.5) {
+                (
+                    [anchor, choose_positive(anchor, &groups[&label], rng)],
+                    true,
+                )
+            } else {
+                ([anchor, choose_negative(label, labels, rng)], false)
+            }
+        })
+        .collect())
+}
+
+/// Sample triplets of indices of anchors, positives of the same class and negatives of other classes
+pub fn sample_triplets<R: Rng>(
+    labels: &Array1<usize>,
+    num_triplets: usize,
+    rng: &mut R,
+) -> Result<Vec<[usize; 3]>, DataSetError> {
+    let groups = group_by_class(labels);
+    let candidates: Vec<usize> = anchor_candidates(labels, &groups)?;
+    Ok((0..num_triplets)
+        .map(|_| {
+            let anchor: usize = *candidates.choose(rng).unwrap();
+            let label: usize = labels[anchor];
+            [
+                anchor,
+                choose_positive(anchor, &groups[&label], rng),
+                choose_negative(label, labels, rng),
+            ]
+        })
+        .collect())
+}
+
+impl<T, D, L> MNISTDataSet<T, D, L>
+where
+    T: Clone,
+    D: RemoveAxis,
+    L: ClassLabels,
+{
+    /// Sample triplets from the training set
+    ///
+    /// Returns images of anchors, positives and negatives.
+    pub fn sample_train_triplets<R: Rng>(
+        &self,
+        num_triplets: usize,
+        rng: &mut R,
+    ) -> Result<[Array<T, D>; 3], DataSetError> {
+        let triplets = sample_triplets(&self.train_labels.class_indices(), num_triplets, rng)?;
+        let select = |jj: usize| -> Array<T, D> {
+            let indices: Vec<usize> = triplets.iter().map(|v| v[jj]).collect();
+            self.train_images.select(Axis(0), &indices)
+        };
+        Ok([select(0), select(1), select(2)])
+    }
+}
@@ -60,32 +60,35 @@ pub fn main() {
         println!("No model scheme specified. A model is built with the default parameters...");
         // set activators and optimizers
         let hidden_sizes: [usize; 1] = [HIDDEN_SIZE];
-        let activator_enums: [ActivatorEnum; 1] = [ActivatorEnum::ReLU];
+        let activator_enums: [ActivatorEnum<FF>; 1] = [ActivatorEnum::ReLU];
         let optimizer_enum: OptimizerEnum<FF> = OptimizerEnum::SGD(0.1);
         // let optimizer_enum: OptimizerEnum<FF> = OptimizerEnum::AdaGrad(0.1);
         // let optimizer_enum: OptimizerEnum<FF> = OptimizerEnum::Momentum(0.01, 0.9);
         // let optimizer_enum: OptimizerEnum<FF> = OptimizerEnum::Nesterov(0.01, 0.9);
         // let optimizer_enum: OptimizerEnum<FF> = OptimizerEnum::RMSprop(0.01, 0.99);
         // let optimizer_enum: OptimizerEnum<FF> = OptimizerEnum::Adam(0.001, 0.9, 0.999);
-        let use_batch_norm: UseBatchNormEnum<FF> = UseBatchNormEnum::Use(0.9);
+        let normalization: NormalizationEnum<FF> = NormalizationEnum::BatchNorm(0.9);
         let use_dropout: UseDropoutEnum<FF> = UseDropoutEnum::None;
         let regularizer_enum: RegularizerEnum<FF> = RegularizerEnum::None;
         let weight_init: WeightInitEnum = WeightInitEnum::Normal;
         let weight_init_std: FF = 0.01;
 
-        model = Box::new(MLPClassifier::new(
-            input_size,
-            &hidden_sizes,
-            output_size,
-            &activator_enums,
-            optimizer_enum,
-            use_batch_norm,
-            use_dropout,
-            regularizer_enum,
-            batch_axis,
-            weight_init,
-            weight_init_std,
-        ));
+        model = Box::new(
+            MLPClassifier::new(
+                input_size,
+                &hidden_sizes,
+                output_size,
+                &activator_enums,
+                optimizer_enum,
+                normalization,
+                use_dropout,
+                regularizer_enum,
+                batch_axis,
+                weight_init,
+                weight_init_std,
+            )
+            .unwrap(),
+        );
     } else {
         model = match Path::new(&args[1]).canonicalize() {
             Ok(pb) => {
@@ -93,7 +96,7 @@ pub fn main() {
                     "load a scheme of a model from `{}`...",
                     pb.to_str().unwrap()
                 );
-                match MLPClassifier::<FF>::read_scheme_from_json(&pb) {
+                match MLPClassifier::<FF>::read_scheme(&pb) {
                     Ok(x) => Box::new(x),
                     Err(err) => panic!("Failure in loading the model: {}", err.to_string()),
                 }
@@ -125,7 +128,7 @@ pub fn main() {
 
     // train loop
     println!("start training...");
-    trainer.train(&mut model);
+    trainer.train(&mut model).unwrap();
     println!("training finished.");
     println!("{} sec elapsed to training.", trainer.get_elapsed_time());
 
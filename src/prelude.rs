@@ -10,12 +10,25 @@
 //! ```
 
 #[doc(no_inline)]
-pub use crate::dlfs01::common::layers::{call_activator, ActivatorEnum, BasicLayerEnum};
+pub use crate::dlfs01::common::error::CrateError;
+
+#[doc(no_inline)]
+pub use crate::dlfs01::common::layers::{
+    call_activator, call_loss_layer, ActivatorEnum, BasicLayerEnum, FocalParameters, LossEnum,
+};
 
 #[doc(no_inline)]
 pub use crate::dlfs01::common::layers::activation::{
-    ReLU, ReLU2, ReLU3, ReLU4, ReLU5, ReLU6, ReLUD, Sigmoid, Sigmoid2, Sigmoid3, Sigmoid4,
-    Sigmoid5, SigmoidD, Softmax, Softmax2, Softmax3, Softmax4, Softmax5, Softmax6, SoftmaxD,
+    LeakyReLU, LeakyReLU1, LeakyReLU2, LeakyReLU3, LeakyReLU4, LeakyReLU5, LeakyReLU6, LeakyReLUD,
+    LogSoftmax, LogSoftmax2, LogSoftmax3, LogSoftmax4, LogSoftmax5, LogSoftmax6, LogSoftmaxD, Mish,
+    Mish1, Mish2, Mish3, Mish4, Mish5, Mish6, MishD, PReLU, PReLU1, PReLU2, PReLU3, PReLU4, PReLU5,
+    PReLU6, PReLUD, ReLU, ReLU2, ReLU3, ReLU4, ReLU5, ReLU6, ReLUD, Sigmoid, Sigmoid2, Sigmoid3,
+    Sigmoid4, Sigmoid5, SigmoidD, Softmax, Softmax2, Softmax3, Softmax4, Softmax5, Softmax6,
+    SoftmaxD, Softplus, Softplus1, Softplus2, Softplus3, Softplus4, Softplus5, Softplus6,
+    SoftplusD, Swish, Swish1, Swish2, Swish3, Swish4, Swish5, Swish6, SwishD, Tanh, Tanh1, Tanh2,
+    Tanh3, Tanh4, Tanh5, Tanh6, TanhD, ELU, ELU1, ELU2, ELU3, ELU4, ELU5, ELU6, ELUD, GELU, GELU1,
+    GELU2, GELU3, GELU4, GELU5, GELU6, GELUD, SELU, SELU1, SELU2, SELU3, SELU4, SELU5, SELU6,
+    SELUD,
 };
 
 #[doc(no_inline)]
@@ -27,10 +40,76 @@ pub use crate::dlfs01::common::layers::batch_normalization::{
 };
 
 #[doc(no_inline)]
-pub use crate::dlfs01::common::layers::dropout::{call_dropout_layer, DropOut, UseDropoutEnum};
+pub use crate::dlfs01::common::layers::normalization::{
+    call_normalization_layer, GroupNormalization, GroupNormalization2, GroupNormalization3,
+    GroupNormalization4, GroupNormalization5, GroupNormalization6, GroupNormalizationD,
+    InstanceNormalization, InstanceNormalization3, InstanceNormalization4, InstanceNormalization5,
+    InstanceNormalization6, InstanceNormalizationD, LayerNormalization, LayerNormalization2,
+    LayerNormalization3, LayerNormalization4, LayerNormalization5, LayerNormalization6,
+    LayerNormalizationD, NormalizationEnum, NormalizationLayer,
+};
+
+#[doc(no_inline)]
+pub use crate::dlfs01::common::layers::pooling::{
+    AdaptiveAvgPool, GlobalAveragePooling, GlobalMaxPooling, MaxPooling, MaxPooling4, MeanPooling,
+    MinPooling, Upsample, UpsampleModeEnum,
+};
+
+#[doc(no_inline)]
+pub use crate::dlfs01::common::layers::embedding::Embedding;
+
+#[doc(no_inline)]
+pub use crate::dlfs01::common::layers::recurrent::{GRU, LSTM, RNN};
+
+#[doc(no_inline)]
+pub use crate::dlfs01::common::layers::regression_loss::{
+    Huber, Huber2, Huber3, Huber4, Huber5, Huber6, HuberD, LogCosh, LogCosh2, LogCosh3, LogCosh4,
+    LogCosh5, LogCosh6, LogCoshD, MeanAbsoluteError, MeanAbsoluteError2, MeanAbsoluteError3,
+    MeanAbsoluteError4, MeanAbsoluteError5, MeanAbsoluteError6, MeanAbsoluteErrorD,
+    MeanSquaredError, MeanSquaredError2, MeanSquaredError3, MeanSquaredError4, MeanSquaredError5,
+    MeanSquaredError6, MeanSquaredErrorD, ReductionEnum,
+};
+
+#[doc(no_inline)]
+pub use crate::dlfs01::common::layers::reshape::{
+    Flatten, Flatten3, Flatten4, Flatten5, Flatten6, FlattenD, IntoDyn, Permute, Permute2,
+    Permute3, Permute4, Permute5, Permute6, PermuteD, Reshape, ReshapeD, Squeeze, Squeeze2,
+    Squeeze3, Squeeze4, Squeeze5, Squeeze6, SqueezeD, Unsqueeze, Unsqueeze1, Unsqueeze2,
+    Unsqueeze3, Unsqueeze4, Unsqueeze5, UnsqueezeD,
+};
+
+#[doc(no_inline)]
+pub use crate::dlfs01::common::layers::attention::{MultiHeadAttention, TransformerEncoderBlock};
+
+#[doc(no_inline)]
+pub use crate::dlfs01::common::layers::dropout::{
+    call_dropout_layer, AlphaDropout, AlphaDropout2, AlphaDropout3, AlphaDropout4, AlphaDropoutD,
+    DropConnect, DropOut, SpatialDropout, SpatialDropout3, SpatialDropout4, SpatialDropout5,
+    SpatialDropoutD, UseDropoutEnum,
+};
+
+#[doc(no_inline)]
+pub use crate::dlfs01::common::layers::layer_base::{
+    LayerBase, LossLayerBase, MergeLayerBase, ParamGrad,
+};
 
 #[doc(no_inline)]
-pub use crate::dlfs01::common::layers::layer_base::{LayerBase, LossLayerBase};
+pub use crate::dlfs01::common::layers::merge::{
+    Add, Add2, Add3, Add4, Add5, Add6, AddD, BranchLayer, Concatenate, Concatenate2, Concatenate3,
+    Concatenate4, Concatenate5, Concatenate6, ConcatenateD, DenseConnection, Multiply, Multiply2,
+    Multiply3, Multiply4, Multiply5, Multiply6, MultiplyD, Residual,
+};
+
+#[doc(no_inline)]
+pub use crate::dlfs01::common::layers::metric_loss::{
+    BatchHardTripletLoss, ContrastiveLoss, CosineEmbeddingLoss, TripletLoss,
+};
+
+#[doc(no_inline)]
+pub use crate::dlfs01::common::layers::sigmoid_with_loss::{
+    SigmoidWithBCELoss, SigmoidWithBCELoss2, SigmoidWithBCELoss3, SigmoidWithBCELoss4,
+    SigmoidWithBCELoss5, SigmoidWithBCELoss6, SigmoidWithBCELossD,
+};
 
 #[doc(no_inline)]
 pub use crate::dlfs01::common::layers::softmax_with_loss::{
@@ -39,22 +118,42 @@ pub use crate::dlfs01::common::layers::softmax_with_loss::{
 };
 
 #[doc(no_inline)]
-pub use crate::dlfs01::common::models::mlp::MLPClassifier;
+pub use crate::dlfs01::common::metrics::{
+    accuracy, confusion_matrix, expected_calibration_error, explained_variance_score, f1_score,
+    log_loss, mean_absolute_error, mean_squared_error, per_class_scores, pr_auc, precision,
+    r2_score, recall, reliability_diagram, roc_auc, root_mean_squared_error, to_probabilities,
+    top_k_accuracy, AverageEnum, ClassificationMetricEnum, ReliabilityDiagram,
+};
+
+#[doc(no_inline)]
+pub use crate::dlfs01::common::models::calibration::{
+    fit_calibration, CalibrationEnum, CalibrationMethodEnum,
+};
+
+#[doc(no_inline)]
+pub use crate::dlfs01::common::models::mlp::{MLPClassifier, MLPRegressor};
 
 #[doc(no_inline)]
 pub use crate::dlfs01::common::models::ModelEnum;
 
 #[doc(no_inline)]
-pub use crate::dlfs01::common::models::model_base::ModelBase;
+pub use crate::dlfs01::common::models::model_base::{
+    multi_hot_accuracy, ModelBase, SparseModelBase,
+};
 
 #[doc(no_inline)]
-pub use crate::dlfs01::common::models::model_params::ModelParameters;
+pub use crate::dlfs01::common::models::model_params::{
+    LayerParameters, ModelParameters, SchemeError, SchemeIssue,
+};
+
+#[doc(no_inline)]
+pub use crate::dlfs01::common::onnx::{OnnxError, OnnxModel};
 
 #[doc(no_inline)]
-pub use crate::dlfs01::common::optimizers::{call_optimizer, OptimizerEnum};
+pub use crate::dlfs01::common::optimizers::{call_optimizer, call_sparse_optimizer, OptimizerEnum};
 
 #[doc(no_inline)]
-pub use crate::dlfs01::common::optimizers::optimizer_base::OptimizerBase;
+pub use crate::dlfs01::common::optimizers::optimizer_base::{OptimizerBase, SparseOptimizerBase};
 
 #[doc(no_inline)]
 pub use crate::dlfs01::common::optimizers::optimizer::{AdaGrad, Adam, Nesterov, RMSprop, SGD};
@@ -78,10 +177,13 @@ pub use crate::dlfs01::common::regularizers::{
 pub use crate::dlfs01::common::trainers::{TrainResult, Trainer};
 
 #[doc(no_inline)]
-pub use crate::dlfs01::common::util::{cast_t2u, CrateFloat};
+pub use crate::dlfs01::common::util::{cast_t2u, to_class_indices, to_one_hot, CrateFloat};
 
 // #[doc(no_inline)]
 // pub use crate::dlfs01::dataset::{DataSetError, MNISTDataSet, MNISTDataSetArray2};
 
 #[doc(no_inline)]
-pub use crate::dlfs01::dataset::mnist::{MNISTDataSet2, MNISTDataSet4};
+pub use crate::dlfs01::dataset::mnist::{DataSetError, MNISTDataSet2, MNISTDataSet4};
+
+#[doc(no_inline)]
+pub use crate::dlfs01::dataset::sampling::{sample_pairs, sample_triplets, ClassLabels};
@@ -8,6 +8,12 @@ use ndarray_rand::RandomExt;
 use ndarray_stats::QuantileExt;
 use rand::distributions::Uniform;
 use rand::prelude::*;
+use rs_deep::dlfs01::common::layers::convolution::{
+    Conv1d, Conv3d, ConvTranspose2d, Convolution2, Convolution3, DepthwiseSeparableConv,
+};
+use rs_deep::dlfs01::common::models::migration::CURRENT_FORMAT_VERSION;
+use rs_deep::dlfs01::common::onnx;
+use rs_deep::dlfs01::dataset::mnist::SparseMNISTDataSet2;
 use rs_deep::prelude::*;
 
 #[test]
@@ -102,7 +108,7 @@ pub fn test_mlp() {
     println!("< ch06 mlp sub module >");
     let weight_init: WeightInitEnum = WeightInitEnum::Normal;
     let weight_init_params: f32 = 1.0;
-    let use_batch_norm: UseBatchNormEnum<f32> = UseBatchNormEnum::None;
+    let normalization: NormalizationEnum<f32> = NormalizationEnum::None;
     let use_dropout: UseDropoutEnum<f32> = UseDropoutEnum::None;
     let mut net: MLPClassifier<f32> = MLPClassifier::new(
         2,
@@ -110,13 +116,14 @@ pub fn test_mlp() {
         3,
         &[ActivatorEnum::ReLU],
         OptimizerEnum::SGD(0.1),
-        use_batch_norm,
+        normalization,
         use_dropout,
         RegularizerEnum::None,
         0,
         weight_init,
         weight_init_params,
-    );
+    )
+    .unwrap();
     net.print_detail();
     let x: Array2<f32> = Array::from_shape_vec((1, 2), vec![0.6, 0.9]).unwrap();
     let t: Array2<f32> = Array::from_shape_vec((1, 3), vec![0.0, 0.0, 1.0]).unwrap();
@@ -182,3 +189,2655 @@ fn test_ops_different_shape() {
     assert_eq!(c, array![[0, 0, 0], [2, 3, 3], [2, 3, 3]]);
     assert!(c != array![[0, 0, 1], [2, 3, 4], [1, 2, 3]]);
 }
+
+fn onnx_tensor(name: &str, dims: &[i64], data: &[f32]) -> onnx::proto::TensorProto {
+    onnx::proto::TensorProto {
+        name: name.to_string(),
+        dims: dims.to_vec(),
+        data_type: onnx::proto::DATA_TYPE_FLOAT,
+        float_data: data.to_vec(),
+        ..Default::default()
+    }
+}
+
+fn onnx_node(
+    op_type: &str,
+    inputs: &[&str],
+    output: &str,
+    attribute: Vec<onnx::proto::AttributeProto>,
+) -> onnx::proto::NodeProto {
+    onnx::proto::NodeProto {
+        input: inputs.iter().map(|v| v.to_string()).collect(),
+        output: vec![output.to_string()],
+        name: output.to_string(),
+        op_type: op_type.to_string(),
+        attribute,
+        ..Default::default()
+    }
+}
+
+fn onnx_ints(name: &str, ints: &[i64]) -> onnx::proto::AttributeProto {
+    onnx::proto::AttributeProto {
+        name: name.to_string(),
+        ints: ints.to_vec(),
+        ..Default::default()
+    }
+}
+
+fn onnx_model(
+    nodes: Vec<onnx::proto::NodeProto>,
+    initializer: Vec<onnx::proto::TensorProto>,
+    output: &str,
+) -> Vec<u8> {
+    let value_info = |name: &str| onnx::proto::ValueInfoProto {
+        name: name.to_string(),
+        ..Default::default()
+    };
+    let model = onnx::proto::ModelProto {
+        ir_version: 8,
+        opset_import: vec![onnx::proto::OperatorSetIdProto {
+            domain: String::new(),
+            version: 13,
+        }],
+        graph: Some(onnx::proto::GraphProto {
+            node: nodes,
+            name: "test".to_string(),
+            initializer,
+            input: vec![value_info("x")],
+            output: vec![value_info(output)],
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    prost::Message::encode_to_vec(&model)
+}
+
+#[test]
+fn test_onnx_import_mlp() {
+    let src = onnx_model(
+        vec![
+            onnx_node("Gemm", &["x", "w1", "b1"], "h1", Vec::new()),
+            onnx_node("Relu", &["h1"], "a1", Vec::new()),
+            onnx_node("MatMul", &["a1", "w2"], "h2", Vec::new()),
+            onnx_node("Add", &["h2", "b2"], "h3", Vec::new()),
+            onnx_node("Softmax", &["h3"], "y", Vec::new()),
+        ],
+        vec![
+            onnx_tensor("w1", &[2, 3], &[1.0, -1.0, 0.5, 2.0, 1.0, -0.5]),
+            onnx_tensor("b1", &[3], &[0.0, 0.5, -1.0]),
+            onnx_tensor("w2", &[3, 2], &[1.0, 0.0, 0.0, 1.0, 1.0, 1.0]),
+            onnx_tensor("b2", &[2], &[0.1, -0.1]),
+        ],
+        "y",
+    );
+    let mut model: OnnxModel<f64> = OnnxModel::from_bytes(&src).unwrap();
+    model.print_detail();
+    let x: Array2<f64> = array![[1.0, 2.0], [-1.0, 0.5]];
+    let y = model.predict(&x.clone().into_dyn()).unwrap();
+
+    let w1: Array2<f64> = array![[1.0, -1.0, 0.5], [2.0, 1.0, -0.5]];
+    let w2: Array2<f64> = array![[1.0, 0.0], [0.0, 1.0], [1.0, 1.0]];
+    let h = (x.dot(&w1) + array![0.0, 0.5, -1.0]).mapv(|v| v.max(0.0));
+    let h = (h.dot(&w2) + array![0.1, -0.1]).mapv(f64::exp);
+    let expected = &h / &h.sum_axis(Axis(1)).insert_axis(Axis(1));
+    assert_eq!(y.shape(), &[2, 2]);
+    for (a, b) in y.iter().zip(expected.iter()) {
+        assert!((a - b).abs() < 1E-6);
+    }
+}
+
+#[test]
+fn test_onnx_import_cnn() {
+    let attribute = |name: &str, f: f32| onnx::proto::AttributeProto {
+        name: name.to_string(),
+        f,
+        ..Default::default()
+    };
+    let src = onnx_model(
+        vec![
+            onnx_node(
+                "Conv",
+                &["x", "w", "b"],
+                "c",
+                vec![onnx_ints("kernel_shape", &[2, 2])],
+            ),
+            onnx_node(
+                "BatchNormalization",
+                &["c", "scale", "shift", "mean", "var"],
+                "n",
+                vec![attribute("epsilon", 0.0)],
+            ),
+            onnx_node(
+                "MaxPool",
+                &["n"],
+                "p",
+                vec![onnx_ints("kernel_shape", &[2, 2])],
+            ),
+            onnx_node("Flatten", &["p"], "y", Vec::new()),
+        ],
+        vec![
+            onnx_tensor(
+                "w",
+                &[2, 1, 2, 2],
+                &[1.0, 1.0, 1.0, 1.0, 1.0, 0.0, 0.0, -1.0],
+            ),
+            onnx_tensor("b", &[2], &[0.5, 0.0]),
+            onnx_tensor("scale", &[2], &[2.0, 1.0]),
+            onnx_tensor("shift", &[2], &[0.0, 1.0]),
+            onnx_tensor("mean", &[2], &[1.0, 0.0]),
+            onnx_tensor("var", &[2], &[4.0, 1.0]),
+        ],
+        "y",
+    );
+    let mut model: OnnxModel<f32> = OnnxModel::from_bytes(&src).unwrap();
+    let x: Array4<f32> =
+        Array::from_shape_vec((1, 1, 3, 3), (1..10).map(|v| v as f32).collect()).unwrap();
+    let y = model.predict(&x.into_dyn()).unwrap();
+    // conv outputs: [[12.5, 16.5], [24.5, 28.5]] and [[-4, -4], [-4, -4]]
+    assert_eq!(y.shape(), &[1, 2]);
+    assert!((y[[0, 0]] - 27.5).abs() < 1E-5);
+    assert!((y[[0, 1]] + 3.0).abs() < 1E-5);
+}
+
+#[test]
+fn test_onnx_unsupported_operator() {
+    let src = onnx_model(
+        vec![onnx_node("LSTM", &["x"], "y", Vec::new())],
+        Vec::new(),
+        "y",
+    );
+    match OnnxModel::<f32>::from_bytes(&src) {
+        Err(OnnxError::UnsupportedOperatorError { op_type, .. }) => assert_eq!(op_type, "LSTM"),
+        _ => panic!("an unsupported operator must be reported."),
+    }
+    let src = onnx_model(
+        vec![onnx_node(
+            "Conv",
+            &["x", "w"],
+            "y",
+            vec![onnx_ints("dilations", &[2, 2])],
+        )],
+        vec![onnx_tensor("w", &[1, 1, 1, 1], &[1.0])],
+        "y",
+    );
+    match OnnxModel::<f32>::from_bytes(&src) {
+        Err(OnnxError::UnsupportedAttributeError { attribute, .. }) => {
+            assert_eq!(attribute, "dilations")
+        }
+        _ => panic!("an unsupported attribute must be reported."),
+    }
+    for kernel_shape in [[0, 2], [2, -1]].iter() {
+        let src = onnx_model(
+            vec![onnx_node(
+                "MaxPool",
+                &["x"],
+                "y",
+                vec![onnx_ints("kernel_shape", kernel_shape)],
+            )],
+            Vec::new(),
+            "y",
+        );
+        match OnnxModel::<f32>::from_bytes(&src) {
+            Err(OnnxError::UnsupportedAttributeError { attribute, .. }) => {
+                assert_eq!(attribute, "kernel_shape")
+            }
+            _ => panic!("non-positive kernel sizes must be reported."),
+        }
+    }
+    assert!(OnnxModel::<f32>::from_bytes(&[0xff, 0xff]).is_err());
+}
+
+#[test]
+fn test_scheme_migration() {
+    // a scheme saved before `format_version` was introduced
+    let legacy = serde_json::json!({
+        "model_enum": "MLPClassifier",
+        "input_size": 2,
+        "hidden_sizes": [10],
+        "output_size": 3,
+        "batch_axis": 0,
+        "activator_enums": ["ReLU"],
+        "optimizer_enum": {"Adam": [0.001, 0.9, 0.999]},
+        "use_batch_norm": "None",
+        "use_dropout": {"Use": 0.5},
+        "regularizer_enum": "None",
+        "weight_init_enum": "He",
+        "weight_init_std": 0.01
+    });
+    let params: ModelParameters<f64> = ModelParameters::from_value(legacy.clone()).unwrap();
+    assert_eq!(params.format_version, CURRENT_FORMAT_VERSION);
+    assert_eq!(
+        params.optimizer_enum,
+        OptimizerEnum::Adam(0.001, 0.9, 0.999)
+    );
+    let net: MLPClassifier<f64> = MLPClassifier::from(params).unwrap();
+    net.print_detail();
+
+    let mut broken = legacy.clone();
+    broken["optimizer_enum"] = serde_json::json!({"Adam": [0.001, "0.9", 0.999]});
+    match ModelParameters::<f64>::from_value(broken) {
+        Err(SchemeError::FieldError { path, .. }) => assert!(path.starts_with("optimizer_enum")),
+        _ => panic!("the offending field must be reported."),
+    }
+
+    let mut newer = legacy;
+    newer["format_version"] = serde_json::json!(CURRENT_FORMAT_VERSION + 1);
+    match ModelParameters::<f64>::from_value(newer) {
+        Err(SchemeError::VersionError { found, .. }) => {
+            assert_eq!(found, CURRENT_FORMAT_VERSION + 1)
+        }
+        _ => panic!("a scheme newer than the crate must be rejected."),
+    }
+}
+
+#[test]
+fn test_scheme_formats() {
+    let dir = std::env::temp_dir().join(format!("rs_deep_schemes_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let toml_src = dir.join("scheme.toml");
+    std::fs::write(
+        &toml_src,
+        r#"
+format_version = 2
+model_enum = "MLPClassifier"
+input_size = 4
+output_size = 3
+batch_axis = 0
+regularizer_enum = "None"
+weight_init_enum = "He"
+weight_init_std = 0.01
+
+[optimizer_enum.Adam]
+lr = 0.001
+beta1 = 0.9
+beta2 = 0.999
+
+[[layers]]
+size = 8
+activator_enum = "ReLU"
+use_batch_norm = { Use = 0.9 }
+
+[[layers]]
+size = 6
+activator_enum = "Sigmoid"
+use_dropout = { Use = 0.5 }
+"#,
+    )
+    .unwrap();
+    let params: ModelParameters<f64> = ModelParameters::from_file(&toml_src).unwrap();
+    assert_eq!(
+        params.optimizer_enum,
+        OptimizerEnum::Adam(0.001, 0.9, 0.999)
+    );
+    let layers = params.hidden_layers();
+    assert_eq!(layers.len(), 2);
+    assert_eq!(layers[0].normalization, NormalizationEnum::BatchNorm(0.9));
+    assert_eq!(layers[0].use_dropout, UseDropoutEnum::None);
+    assert_eq!(layers[1].use_dropout, UseDropoutEnum::Use(0.5));
+
+    let yaml_src = dir.join("scheme.yaml");
+    std::fs::write(
+        &yaml_src,
+        r#"
+format_version: 2
+model_enum: MLPClassifier
+input_size: 4
+output_size: 3
+batch_axis: 0
+regularizer_enum: None
+weight_init_enum: He
+weight_init_std: 0.01
+optimizer_enum: !Momentum
+  lr: 0.01
+  momentum: 0.9
+layers:
+  - size: 8
+    activator_enum: ReLU
+    use_batch_norm: !Use 0.9
+  - size: 6
+    activator_enum: Sigmoid
+    use_dropout: !Use 0.5
+"#,
+    )
+    .unwrap();
+    let mut net: MLPClassifier<f64> = MLPClassifier::read_scheme(&yaml_src).unwrap();
+    let x: Array2<f64> = Array::random((5, 4), Uniform::new(-1.0, 1.0));
+    assert_eq!(net.predict_prob(&x).shape(), &[5, 3]);
+
+    // every format reproduces the parameters it has written
+    for name in ["saved.json", "saved.toml", "saved.yml"].iter() {
+        let dst = dir.join(name);
+        net.write_scheme(&dst).unwrap();
+        let loaded: ModelParameters<f64> = ModelParameters::from_file(&dst).unwrap();
+        assert_eq!(loaded.optimizer_enum, OptimizerEnum::Momentum(0.01, 0.9));
+        assert_eq!(loaded.hidden_layers(), layers);
+    }
+
+    match ModelParameters::<f64>::from_file(&dir.join("scheme.xml")) {
+        Err(SchemeError::ParseError(_)) => (),
+        _ => panic!("unknown formats must be rejected."),
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_crate_errors() {
+    let new_mlp = |optimizer_enum, use_dropout| {
+        MLPClassifier::<f64>::new(
+            2,
+            &[10],
+            3,
+            &[ActivatorEnum::ReLU],
+            optimizer_enum,
+            NormalizationEnum::None,
+            use_dropout,
+            RegularizerEnum::None,
+            0,
+            WeightInitEnum::He,
+            0.01,
+        )
+    };
+    match new_mlp(OptimizerEnum::AdaBelief(0.01), UseDropoutEnum::None) {
+        Err(CrateError::SchemeError(SchemeError::ValidationError(_))) => (),
+        _ => panic!("unimplemented optimizers must be rejected."),
+    }
+    match new_mlp(OptimizerEnum::SGD(0.01), UseDropoutEnum::Use(1.5)) {
+        Err(CrateError::SchemeError(SchemeError::ValidationError(_))) => (),
+        _ => panic!("dropout ratios out of [0, 1] must be rejected."),
+    }
+    match DropOut::<f64, Ix2>::new(1.5, (2, 2)) {
+        Err(CrateError::ConfigError(_)) => (),
+        _ => panic!("dropout ratios out of [0, 1] must be rejected."),
+    }
+    match call_optimizer::<f64, Ix1, _>(OptimizerEnum::AMSGrad(0.01), 3) {
+        Err(CrateError::ConfigError(_)) => (),
+        _ => panic!("unimplemented optimizers must be rejected."),
+    }
+    match initialize_randomized_ndarray::<f64, Ix1, _>(DistributionEnum::Uniform, 3, &[1.0, 0.0]) {
+        Err(CrateError::ConfigError(_)) => (),
+        _ => panic!("invalid parameters of distributions must be rejected."),
+    }
+    match initialize_randomized_ndarray::<f64, Ix1, _>(DistributionEnum::Normal, 3, &[0.0]) {
+        Err(CrateError::ConfigError(_)) => (),
+        _ => panic!("missing parameters of distributions must be rejected."),
+    }
+
+    // (5 + 2 * 0 - 2) is not divisible by 2
+    let weight: Array4<f64> = Array::zeros((1, 1, 2, 2));
+    let bias: Array1<f64> = Array::zeros(1);
+    match Convolution3::from(&weight, &bias, (1, 5, 5), (2, 2), 0) {
+        Err(CrateError::ShapeError(_)) => (),
+        _ => panic!("strides incompatible with the input must be rejected."),
+    }
+    match Convolution3::from(&weight, &bias, (1, 1, 1), (1, 1), 0) {
+        Err(CrateError::ShapeError(_)) => (),
+        _ => panic!("filters larger than the input must be rejected."),
+    }
+    assert!(Convolution3::from(&weight, &bias, (1, 5, 5), (1, 1), 0).is_ok());
+
+    let labels = std::env::temp_dir().join(format!("rs_deep_labels_{}", std::process::id()));
+    std::fs::write(&labels, [0u8; 4]).unwrap();
+    let err: CrateError = rs_deep::dlfs01::dataset::mnist::load_labels(&labels)
+        .unwrap_err()
+        .into();
+    std::fs::remove_file(&labels).unwrap();
+    match err {
+        CrateError::DataSetError(DataSetError::IndexError) => (),
+        _ => panic!("truncated label files must be rejected."),
+    }
+}
+
+#[test]
+fn test_scheme_validation() {
+    let mut params: ModelParameters<f64> = ModelParameters::from(
+        ModelEnum::MLPClassifier,
+        2,
+        vec![10, 0],
+        3,
+        2,
+        vec![ActivatorEnum::ReLU],
+        OptimizerEnum::Momentum(-0.1, 1.0),
+        NormalizationEnum::None,
+        UseDropoutEnum::Use(1.5),
+        RegularizerEnum::None,
+        WeightInitEnum::He,
+        0.01,
+    );
+    let issues: Vec<SchemeIssue> = match params.validate() {
+        Err(SchemeError::ValidationError(issues)) => issues,
+        _ => panic!("invalid parameters must be rejected."),
+    };
+    let mut paths: Vec<&str> = issues.iter().map(|v| v.path.as_str()).collect();
+    paths.sort_unstable();
+    assert_eq!(
+        paths,
+        vec![
+            "activator_enums",
+            "batch_axis",
+            "hidden_sizes[1]",
+            "optimizer_enum.Momentum.lr",
+            "optimizer_enum.Momentum.momentum",
+            "use_dropout.Use",
+        ]
+    );
+
+    params.hidden_sizes = Vec::new();
+    params.activator_enums = Vec::new();
+    params.batch_axis = 0;
+    params.optimizer_enum = OptimizerEnum::Momentum(0.1, 0.9);
+    params.use_dropout = UseDropoutEnum::Use(0.5);
+    match params.validate() {
+        Err(SchemeError::ValidationError(issues)) => {
+            assert_eq!(issues.len(), 1);
+            assert_eq!(issues[0].path, "hidden_sizes");
+        }
+        _ => panic!("models without hidden layers must be rejected."),
+    }
+    match MLPClassifier::from(params.clone()) {
+        Err(CrateError::SchemeError(SchemeError::ValidationError(_))) => (),
+        _ => panic!("models must be validated before being built."),
+    }
+
+    params.layers = vec![
+        LayerParameters {
+            layer_enum: BasicLayerEnum::Affine,
+            size: 10,
+            activator_enum: ActivatorEnum::ReLU,
+            normalization: NormalizationEnum::BatchNorm(0.9),
+            use_dropout: UseDropoutEnum::None,
+        },
+        LayerParameters {
+            layer_enum: BasicLayerEnum::Convolution,
+            size: 10,
+            activator_enum: ActivatorEnum::ReLU,
+            normalization: NormalizationEnum::None,
+            use_dropout: UseDropoutEnum::Use(-0.5),
+        },
+    ];
+    match params.validate() {
+        Err(SchemeError::ValidationError(issues)) => {
+            let paths: Vec<&str> = issues.iter().map(|v| v.path.as_str()).collect();
+            assert_eq!(
+                paths,
+                vec!["layers[1].layer_enum", "layers[1].use_dropout.Use"]
+            );
+        }
+        _ => panic!("invalid layers must be rejected."),
+    }
+    params.layers.pop();
+    assert!(params.validate().is_ok());
+    assert!(MLPClassifier::from(params).is_ok());
+}
+
+/// numerical gradient of `sum(forward(x) * dy)` with respect to `x`
+fn numerical_layer_grad<D: Dimension>(
+    layer: &mut dyn LayerBase<f64, A = Array<f64, D>, B = Array<f64, D>>,
+    x: &Array<f64, D>,
+    dy: &Array<f64, D>,
+) -> Array<f64, D> {
+    let h = 1e-5;
+    let x = x.as_standard_layout().into_owned();
+    let mut grad = Array::<f64, D>::zeros(x.raw_dim());
+    for (ii, g) in grad.iter_mut().enumerate() {
+        let mut xp = x.clone();
+        xp.as_slice_mut().unwrap()[ii] += h;
+        let fp = (layer.forward(&xp) * dy).sum();
+        let mut xm = x.clone();
+        xm.as_slice_mut().unwrap()[ii] -= h;
+        let fm = (layer.forward(&xm) * dy).sum();
+        *g = (fp - fm) / (2.0 * h);
+    }
+    grad
+}
+
+#[test]
+fn test_activation_family() {
+    let x = array![[-1.5, -0.3, 0.2, 2.0], [0.7, -2.2, 1.1, -0.05]];
+    let dy = array![[0.5, -1.0, 0.3, 0.8], [-0.2, 0.6, 1.0, -0.7]];
+    let enums: [ActivatorEnum<f64>; 10] = [
+        ActivatorEnum::LeakyReLU(0.1),
+        ActivatorEnum::PReLU(0.25),
+        ActivatorEnum::ELU(1.0),
+        ActivatorEnum::SELU,
+        ActivatorEnum::GELU,
+        ActivatorEnum::Swish(1.5),
+        ActivatorEnum::SiLU,
+        ActivatorEnum::Mish,
+        ActivatorEnum::Tanh,
+        ActivatorEnum::Softplus,
+    ];
+    for activator_enum in enums.iter() {
+        let mut layer = call_activator(activator_enum.clone(), x.raw_dim(), 0);
+        let numerical = numerical_layer_grad(layer.as_mut(), &x, &dy);
+        layer.forward(&x);
+        let analytical = layer.backward(&dy);
+        let diff = (&analytical - &numerical).mapv(f64::abs);
+        assert!(
+            *diff.max().unwrap() < 1e-6,
+            "{}: {} vs {}",
+            activator_enum,
+            analytical,
+            numerical
+        );
+    }
+
+    let y = LeakyReLU2::<f64>::new((2, 4), 0.1).forward(&x);
+    assert!((y[[0, 0]] + 0.15).abs() < 1e-12);
+    assert_eq!(y[[0, 3]], 2.0);
+    let y = SELUD::<f64>::new(IxDyn(&[2, 4])).forward(&x.clone().into_dyn());
+    assert!((y[[0, 3]] - 2.0 * 1.0507009873554805).abs() < 1e-12);
+
+    // the slope of PReLU is learned per feature
+    let mut layer = PReLU2::<f64>::new((2, 4), 0, 0.25);
+    layer.forward(&x);
+    layer.backward(&dy);
+    assert_eq!(layer.dalpha.shape(), &[4]);
+    assert!((layer.dalpha[1] - (-0.3 * -1.0 + -2.2 * 0.6)).abs() < 1e-12);
+    let alpha = layer.alpha.clone();
+    layer.update(0.1);
+    assert_eq!(layer.alpha, &alpha - &(&layer.dalpha * 0.1));
+    // the batch axis can be the last one, where the slope is broadcast over columns
+    let mut transposed = PReLU2::<f64>::new((4, 2), 1, 0.25);
+    transposed.alpha = layer.alpha.clone();
+    let y = layer.forward(&x);
+    assert_eq!(transposed.forward(&x.t().to_owned()), y.t());
+    let dx = layer.backward(&dy);
+    assert_eq!(transposed.backward(&dy.t().to_owned()), dx.t());
+    assert_eq!(transposed.dalpha, layer.dalpha);
+    let mut layer = PReLU2::<f64>::new((4, 2), 1, 0.25);
+    let numerical = numerical_layer_grad(&mut layer, &x.t().to_owned(), &dy.t().to_owned());
+    layer.forward(&x.t().to_owned());
+    let analytical = layer.backward(&dy.t().to_owned());
+    assert!(*(&analytical - &numerical).mapv(f64::abs).max().unwrap() < 1e-6);
+    // models update the slope with their optimizers through the flattened parameters
+    let mut adam = Adam::new(0.01, 0.9, 0.999, 4);
+    let mut expected: Array1<f64> = layer.alpha.clone();
+    adam.update(&mut expected, &layer.dalpha);
+    let params = layer.parameters();
+    assert_eq!(params.len(), 1);
+    let mut adam = Adam::new(0.01, 0.9, 0.999, 4);
+    for (mut param, grad) in params {
+        let mut updated: Array1<f64> = param.to_owned();
+        adam.update(&mut updated, &grad.to_owned());
+        param.assign(&updated);
+    }
+    assert_eq!(layer.alpha, expected);
+
+    // parameters of activators are read from schemes
+    let value: ActivatorEnum<f64> = serde_json::from_str("{\"LeakyReLU\": 0.01}").unwrap();
+    assert_eq!(value, ActivatorEnum::LeakyReLU(0.01));
+    let value: ActivatorEnum<f64> = serde_json::from_str("\"ReLU\"").unwrap();
+    assert_eq!(value, ActivatorEnum::ReLU);
+    let mut params: ModelParameters<f64> = ModelParameters::from(
+        ModelEnum::MLPClassifier,
+        784,
+        vec![50, 50],
+        10,
+        0,
+        vec![ActivatorEnum::ELU(-1.0), ActivatorEnum::PReLU(0.25)],
+        OptimizerEnum::SGD(0.1),
+        NormalizationEnum::None,
+        UseDropoutEnum::None,
+        RegularizerEnum::None,
+        WeightInitEnum::He,
+        0.01,
+    );
+    match params.validate() {
+        Err(SchemeError::ValidationError(issues)) => {
+            assert_eq!(issues.len(), 1);
+            assert_eq!(issues[0].path, "activator_enums[0].ELU");
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+    params.activator_enums[0] = ActivatorEnum::Swish(1.0);
+    let mut model = MLPClassifier::from(params.clone()).unwrap();
+    let x = Array2::<f64>::from_elem((3, 784), -0.1);
+    let t = Array2::<f64>::from_shape_fn((3, 10), |(i, j)| if i == j { 1.0 } else { 0.0 });
+    model.update(&x, &t);
+    // PReLU is trained with the optimizer of the scheme
+    params.optimizer_enum = OptimizerEnum::Adam(0.01, 0.9, 0.999);
+    let mut model = MLPClassifier::from(params).unwrap();
+    let initial_loss = model.loss(&x, &t);
+    for _ in 0..20 {
+        model.update(&x, &t);
+    }
+    assert!(model.loss(&x, &t) < initial_loss);
+}
+
+#[test]
+fn test_normalization() {
+    let x: Array4<f64> = Array::random((3, 4, 2, 2), Uniform::new(-2.0, 2.0));
+    let dy: Array4<f64> = Array::random((3, 4, 2, 2), Uniform::new(-1.0, 1.0));
+    let layers: Vec<Box<dyn LayerBase<f64, A = Array4<f64>, B = Array4<f64>>>> = vec![
+        Box::new(LayerNormalization::new(0, x.raw_dim())),
+        Box::new(GroupNormalization::new(2, 0, 1, x.raw_dim()).unwrap()),
+        Box::new(InstanceNormalization::new(0, 1, x.raw_dim()).unwrap()),
+    ];
+    for mut layer in layers.into_iter() {
+        let numerical = numerical_layer_grad(layer.as_mut(), &x, &dy);
+        layer.forward(&x);
+        let analytical = layer.backward(&dy);
+        let diff = (&analytical - &numerical).mapv(f64::abs);
+        assert!(*diff.max().unwrap() < 1e-6);
+    }
+
+    // each group of each sample is normalized
+    let mut layer = GroupNormalization4::<f64>::new(2, 0, 1, x.raw_dim()).unwrap();
+    let y = layer.forward(&x);
+    let group = y.slice(s![1, 2..4, .., ..]);
+    assert!(group.mean().unwrap().abs() < 1e-9);
+    assert!((group.map(|v| v * v).mean().unwrap() - 1.0).abs() < 1e-6);
+    layer.backward(&dy);
+    assert_eq!(layer.dgamma.shape(), &[4]);
+    assert!((layer.dbeta[1] - dy.index_axis(Axis(1), 1).sum()).abs() < 1e-12);
+    let gamma = layer.gamma.clone();
+    layer.update(0.1);
+    assert_eq!(layer.gamma, &gamma - &(&layer.dgamma * 0.1));
+
+    // the batch axis does not have to be the first axis
+    let xt = x.clone().permuted_axes([1, 0, 2, 3]);
+    let mut layer = InstanceNormalization4::<f64>::new(1, 0, xt.raw_dim()).unwrap();
+    let y = layer.forward(&xt);
+    assert!(y.slice(s![2, 1, .., ..]).mean().unwrap().abs() < 1e-9);
+    let mut layer = LayerNormalization3::<f64>::new(2, (4, 5, 3));
+    let x3: Array3<f64> = Array::random((4, 5, 3), Uniform::new(-2.0, 2.0));
+    let y = layer.forward(&x3);
+    assert!(y.index_axis(Axis(2), 2).mean().unwrap().abs() < 1e-9);
+
+    match GroupNormalization4::<f64>::new(3, 0, 1, x.raw_dim()) {
+        Err(CrateError::ConfigError(_)) => (),
+        _ => panic!("groups must divide the channels."),
+    }
+    match call_normalization_layer::<f64, Ix4, _>(
+        NormalizationEnum::InstanceNorm,
+        x.raw_dim(),
+        0,
+        0,
+    ) {
+        Err(CrateError::ShapeError(_)) => (),
+        _ => panic!("the channel axis must differ from the batch axis."),
+    }
+    assert_eq!(
+        NormalizationEnum::from(UseBatchNormEnum::Use(0.9)),
+        NormalizationEnum::BatchNorm(0.9)
+    );
+
+    // schemes written with `use_batch_norm` are migrated
+    let legacy = serde_json::json!({
+        "format_version": 2,
+        "model_enum": "MLPClassifier",
+        "input_size": 4,
+        "output_size": 3,
+        "batch_axis": 0,
+        "optimizer_enum": {"SGD": {"lr": 0.1}},
+        "layers": [
+            {"size": 8, "activator_enum": "ReLU", "use_batch_norm": {"Use": 0.9}},
+            {"size": 6, "activator_enum": "ReLU", "use_batch_norm": "None"}
+        ],
+        "regularizer_enum": "None",
+        "weight_init_enum": "He",
+        "weight_init_std": 0.01
+    });
+    let mut params: ModelParameters<f64> = ModelParameters::from_value(legacy).unwrap();
+    assert_eq!(
+        params.layers[0].normalization,
+        NormalizationEnum::BatchNorm(0.9)
+    );
+    assert_eq!(params.layers[1].normalization, NormalizationEnum::None);
+
+    params.layers[0].normalization = NormalizationEnum::GroupNorm(3);
+    params.layers[1].normalization = NormalizationEnum::InstanceNorm;
+    match params.validate() {
+        Err(SchemeError::ValidationError(issues)) => {
+            let paths: Vec<&str> = issues.iter().map(|v| v.path.as_str()).collect();
+            assert_eq!(
+                paths,
+                vec![
+                    "layers[0].normalization.GroupNorm",
+                    "layers[1].normalization"
+                ]
+            );
+        }
+        _ => panic!("invalid normalizations must be rejected."),
+    }
+    params.layers[0].normalization = NormalizationEnum::GroupNorm(4);
+    params.layers[1].normalization = NormalizationEnum::LayerNorm;
+    let mut net = MLPClassifier::from(params.clone()).unwrap();
+    let x: Array2<f64> = Array::random((5, 4), Uniform::new(-1.0, 1.0));
+    let t = Array2::<f64>::from_shape_fn((5, 3), |(i, j)| if i % 3 == j { 1.0 } else { 0.0 });
+    let before = net.loss(&x, &t);
+    for _ in 0..20 {
+        net.update(&x, &t);
+    }
+    assert!(net.loss(&x, &t) < before);
+
+    // gamma and beta are exposed to the optimizer of a model
+    let mut layer = GroupNormalization2::<f64>::new(4, 0, 1, (5, 8)).unwrap();
+    layer.forward(&Array::random((5, 8), Uniform::new(-1.0, 1.0)));
+    layer.backward(&Array::random((5, 8), Uniform::new(-1.0, 1.0)));
+    let (dgamma, dbeta) = (layer.dgamma.clone(), layer.dbeta.clone());
+    let grads: Vec<Array1<f64>> = layer
+        .parameters()
+        .into_iter()
+        .map(|(_, grad)| grad.to_owned())
+        .collect();
+    assert_eq!(grads, vec![dgamma, dbeta]);
+    params.optimizer_enum = OptimizerEnum::Momentum(0.05, 0.9);
+    let mut net = MLPClassifier::from(params).unwrap();
+    let before = net.loss(&x, &t);
+    for _ in 0..20 {
+        net.update(&x, &t);
+    }
+    assert!(net.loss(&x, &t) < before);
+}
+
+/// numerical gradient of `sum(forward(x) * dy)` with respect to a parameter of a layer
+fn numerical_param_grad<L, D, E>(
+    layer: &mut L,
+    param: fn(&mut L) -> &mut Array<f64, E>,
+    x: &Array<f64, D>,
+    dy: &Array<f64, D>,
+) -> Array<f64, E>
+where
+    L: LayerBase<f64, A = Array<f64, D>, B = Array<f64, D>>,
+    D: Dimension,
+    E: Dimension,
+{
+    let h = 1e-5;
+    let shape = param(layer).raw_dim();
+    let mut grad = Array::<f64, E>::zeros(shape);
+    for (ii, g) in grad.iter_mut().enumerate() {
+        param(layer).as_slice_mut().unwrap()[ii] += h;
+        let fp = (layer.forward(x) * dy).sum();
+        param(layer).as_slice_mut().unwrap()[ii] -= 2.0 * h;
+        let fm = (layer.forward(x) * dy).sum();
+        param(layer).as_slice_mut().unwrap()[ii] += h;
+        *g = (fp - fm) / (2.0 * h);
+    }
+    grad
+}
+
+/// check the gradients of a recurrent layer with respect to inputs and weights
+macro_rules! check_recurrent_grads {
+    ($layer:expr, $x:expr, $dy:expr) => {{
+        let mut layer = $layer;
+        let numerical_dx = numerical_layer_grad(&mut layer, $x, $dy);
+        let numerical_dwx = numerical_param_grad(&mut layer, |v| &mut v.weight_x, $x, $dy);
+        let numerical_dwh = numerical_param_grad(&mut layer, |v| &mut v.weight_h, $x, $dy);
+        layer.forward($x);
+        let dx = layer.backward($dy);
+        assert!(*(&dx - &numerical_dx).mapv(f64::abs).max().unwrap() < 1e-6);
+        assert!(*(&layer.dwx - &numerical_dwx).mapv(f64::abs).max().unwrap() < 1e-6);
+        assert!(*(&layer.dwh - &numerical_dwh).mapv(f64::abs).max().unwrap() < 1e-6);
+        dx
+    }};
+}
+
+#[test]
+fn test_recurrent() {
+    let (batch_size, time, input_size, hidden_size) = (2, 4, 3, 5);
+    let x: Array3<f64> = Array::random((batch_size, time, input_size), Uniform::new(-1.0, 1.0));
+    let dy_seq: Array3<f64> =
+        Array::random((batch_size, time, hidden_size), Uniform::new(-1.0, 1.0));
+    let dy_last: Array3<f64> = Array::random((batch_size, 1, hidden_size), Uniform::new(-1.0, 1.0));
+    for &return_sequences in [true, false].iter() {
+        let dy = if return_sequences { &dy_seq } else { &dy_last };
+        let rnn = RNN::new(
+            input_size,
+            hidden_size,
+            return_sequences,
+            WeightInitEnum::Xavier,
+            0.5,
+        );
+        check_recurrent_grads!(rnn, &x, dy);
+        let lstm = LSTM::new(
+            input_size,
+            hidden_size,
+            return_sequences,
+            WeightInitEnum::Xavier,
+            0.5,
+        );
+        check_recurrent_grads!(lstm, &x, dy);
+        let gru = GRU::new(
+            input_size,
+            hidden_size,
+            return_sequences,
+            WeightInitEnum::Xavier,
+            0.5,
+        );
+        check_recurrent_grads!(gru, &x, dy);
+    }
+
+    // gradients of the last state do not reach the first steps with truncated BPTT
+    let mut lstm = LSTM::new(input_size, hidden_size, false, WeightInitEnum::Xavier, 0.5);
+    assert_eq!(lstm.forward(&x).shape(), &[batch_size, 1, hidden_size]);
+    lstm.set_bptt_steps(Some(2));
+    let dx = lstm.backward(&dy_last);
+    assert!(dx.slice(s![.., ..2, ..]).iter().all(|&v| v == 0.0));
+    assert!(dx.slice(s![.., 2.., ..]).iter().any(|&v| v != 0.0));
+
+    // a stateful layer starts the next batch from the last state
+    let mut gru = GRU::new(input_size, hidden_size, true, WeightInitEnum::Xavier, 0.5);
+    let first = gru.forward(&x);
+    gru.set_stateful(true);
+    gru.forward(&x);
+    let carried = gru.forward(&x);
+    assert!((&carried - &first).mapv(f64::abs).sum() > 1e-6);
+    gru.reset_state();
+    assert_eq!(gru.forward(&x), first);
+
+    // the last state composes with Affine and the optimizers
+    let mut rnn = RNN::new(input_size, hidden_size, false, WeightInitEnum::Xavier, 0.5);
+    let mut affine = Affine::new((hidden_size, 2), WeightInitEnum::Xavier, 0.5);
+    let mut optimizer = call_optimizer(
+        OptimizerEnum::Adam(0.01, 0.9, 0.999),
+        rnn.weight_h.raw_dim(),
+    )
+    .unwrap();
+    let h = rnn.forward(&x).index_axis(Axis(1), 0).to_owned();
+    let y = affine.forward(&h);
+    let dh = affine.backward(&y);
+    rnn.backward(&dh.insert_axis(Axis(1)));
+    let weight_h = rnn.weight_h.clone();
+    optimizer.update(&mut rnn.weight_h, &rnn.dwh);
+    assert_ne!(rnn.weight_h, weight_h);
+}
+
+#[test]
+fn test_embedding() {
+    let weight: Array2<f64> = Array::random((10, 3), Uniform::new(-1.0, 1.0));
+    let mut layer = Embedding::from(&weight);
+    let ids: Array2<usize> = array![[1, 4, 1], [7, 4, 4]];
+    let y = layer.forward(&ids);
+    assert_eq!(y.shape(), &[2, 3, 3]);
+    assert_eq!(y.slice(s![1, 0, ..]), weight.row(7));
+
+    // gradients are accumulated only into the touched rows
+    let dy: Array3<f64> = Array::random((2, 3, 3), Uniform::new(-1.0, 1.0));
+    layer.backward(&dy);
+    assert_eq!(layer.dw_rows, vec![1, 4, 7]);
+    let expected = &dy.slice(s![0, 1, ..]) + &dy.slice(s![1, 1, ..]) + &dy.slice(s![1, 2, ..]);
+    assert!((&layer.dw.row(1) - &expected).mapv(f64::abs).sum() < 1e-12);
+    let mut dense_dw = Array2::<f64>::zeros(weight.raw_dim());
+    for (&row, dw) in layer.dw_rows.iter().zip(layer.dw.outer_iter()) {
+        dense_dw.row_mut(row).assign(&dw);
+    }
+
+    // the sparse update path agrees with the dense one
+    let mut dense = weight.clone();
+    SGD::<f64, Ix2>::new(0.1).update(&mut dense, &dense_dw);
+    let mut sgd = call_sparse_optimizer(OptimizerEnum::SGD(0.1), weight.raw_dim()).unwrap();
+    layer.update_sparse(sgd.as_mut());
+    assert!((&layer.weight - &dense).mapv(f64::abs).sum() < 1e-12);
+    assert_eq!(layer.weight.row(0), weight.row(0));
+
+    let mut dense_adam = Adam::new(0.01, 0.9, 0.999, weight.raw_dim());
+    let mut sparse_adam =
+        call_sparse_optimizer(OptimizerEnum::Adam(0.01, 0.9, 0.999), weight.raw_dim()).unwrap();
+    let mut dense = weight.clone();
+    let mut sparse = weight.clone();
+    let all_rows: Vec<usize> = (0..10).collect();
+    for _ in 0..3 {
+        let grads: Array2<f64> = Array::random(weight.raw_dim(), Uniform::new(-1.0, 1.0));
+        dense_adam.update(&mut dense, &grads);
+        sparse_adam.update_rows(&mut sparse, &all_rows, &grads);
+    }
+    assert!((&sparse - &dense).mapv(f64::abs).sum() < 1e-12);
+    let before = sparse.clone();
+    sparse_adam.update_rows(&mut sparse, &[2], &Array2::ones((1, 3)));
+    assert_ne!(sparse.row(2), before.row(2));
+    assert_eq!(sparse.row(3), before.row(3));
+
+    match call_sparse_optimizer(OptimizerEnum::Momentum(0.1, 0.9), (10, 3)) {
+        Err(CrateError::ConfigError(_)) => (),
+        _ => panic!("optimizers without sparse updates must be rejected."),
+    }
+
+    // embeddings feed recurrent layers
+    let mut rnn = RNN::new(3, 4, false, WeightInitEnum::Xavier, 0.5);
+    let h = rnn.forward(&layer.forward(&ids));
+    assert_eq!(h.shape(), &[2, 1, 4]);
+    layer.backward(&rnn.backward(&h));
+    layer.update(0.1);
+}
+
+#[test]
+fn test_attention() {
+    let (batch_size, time, model_size) = (2, 4, 6);
+    let x: Array3<f64> = Array::random((batch_size, time, model_size), Uniform::new(-1.0, 1.0));
+    let dy: Array3<f64> = Array::random((batch_size, time, model_size), Uniform::new(-1.0, 1.0));
+    assert!(
+        MultiHeadAttention::<f64>::new(model_size, 4, false, WeightInitEnum::Xavier, 0.5).is_err()
+    );
+
+    let mut attention =
+        MultiHeadAttention::new(model_size, 2, true, WeightInitEnum::Xavier, 0.5).unwrap();
+    attention.set_padding_mask(Some(array![
+        [false, false, false, false],
+        [false, false, true, true]
+    ]));
+    let numerical_dx = numerical_layer_grad(&mut attention, &x, &dy);
+    let numerical_dwq = numerical_param_grad(&mut attention, |v| &mut v.query.weight, &x, &dy);
+    let numerical_dwk = numerical_param_grad(&mut attention, |v| &mut v.key.weight, &x, &dy);
+    attention.forward(&x);
+    let dx = attention.backward(&dy);
+    assert!(*(&dx - &numerical_dx).mapv(f64::abs).max().unwrap() < 1e-6);
+    assert!(
+        *(&attention.query.dw - &numerical_dwq)
+            .mapv(f64::abs)
+            .max()
+            .unwrap()
+            < 1e-6
+    );
+    assert!(
+        *(&attention.key.dw - &numerical_dwk)
+            .mapv(f64::abs)
+            .max()
+            .unwrap()
+            < 1e-6
+    );
+
+    // later and padded time steps are not attended to
+    let weights = attention.attention_weights();
+    assert_eq!(weights.shape(), &[batch_size, 2, time, time]);
+    for ((b, _, i, j), &w) in weights.indexed_iter() {
+        if j > i || (b == 1 && j >= 2) {
+            assert_eq!(w, 0.0);
+        }
+    }
+    let row_sums = weights.sum_axis(Axis(3));
+    assert!(row_sums.iter().all(|&v| (v - 1.0).abs() < 1e-12));
+
+    // fully padded rows have no weights, and large scores stay finite
+    let mut attention =
+        MultiHeadAttention::new(model_size, 2, false, WeightInitEnum::Xavier, 0.5).unwrap();
+    attention.set_padding_mask(Some(array![
+        [false, false, false, false],
+        [true, true, true, true]
+    ]));
+    let y = attention.forward(&(&x * 1e4));
+    assert!(y.iter().all(|v| v.is_finite()));
+    let weights = attention.attention_weights();
+    assert!(weights.slice(s![1, .., .., ..]).iter().all(|&w| w == 0.0));
+    let row_sums = weights.slice(s![0, .., .., ..]).sum_axis(Axis(2));
+    assert!(row_sums.iter().all(|&v| (v - 1.0).abs() < 1e-12));
+
+    let mut block = TransformerEncoderBlock::new(
+        model_size,
+        3,
+        8,
+        ActivatorEnum::GELU,
+        false,
+        WeightInitEnum::Xavier,
+        0.5,
+    )
+    .unwrap();
+    let numerical_dx = numerical_layer_grad(&mut block, &x, &dy);
+    let numerical_dw1 = numerical_param_grad(&mut block, |v| &mut v.ffn1.weight, &x, &dy);
+    let numerical_dwv =
+        numerical_param_grad(&mut block, |v| &mut v.attention.value.weight, &x, &dy);
+    block.forward(&x);
+    let dx = block.backward(&dy);
+    assert!(*(&dx - &numerical_dx).mapv(f64::abs).max().unwrap() < 1e-6);
+    assert!(
+        *(&block.ffn1.dw - &numerical_dw1)
+            .mapv(f64::abs)
+            .max()
+            .unwrap()
+            < 1e-6
+    );
+    assert!(
+        *(&block.attention.value.dw - &numerical_dwv)
+            .mapv(f64::abs)
+            .max()
+            .unwrap()
+            < 1e-6
+    );
+    // gammas and betas of the two layer normalizations
+    assert_eq!(block.parameters().len(), 4);
+
+    // a few steps of gradient descent reduce a regression loss
+    let loss = |y: &Array3<f64>| (y - &x).mapv(|v| v * v).sum();
+    let first = loss(&block.forward(&x));
+    for _ in 0..20 {
+        let y = block.forward(&x);
+        block.backward(&((&y - &x) * 2.0));
+        block.update(0.01);
+    }
+    assert!(loss(&block.forward(&x)) < first);
+}
+
+#[test]
+fn test_merge() {
+    let a: Array2<f64> = Array::random((2, 3), Uniform::new(-1.0, 1.0));
+    let b: Array2<f64> = Array::random((2, 3), Uniform::new(-1.0, 1.0));
+    let c: Array2<f64> = Array::random((2, 2), Uniform::new(-1.0, 1.0));
+    let dy: Array2<f64> = Array::random((2, 3), Uniform::new(-1.0, 1.0));
+
+    let mut add = Add2::new();
+    assert_eq!(add.forward(&[&a, &b, &a]), &a * 2.0 + &b);
+    let grads = add.backward(&dy);
+    assert_eq!(grads.len(), 3);
+    assert!(grads.iter().all(|g| g == dy));
+
+    let mut multiply = Multiply2::new();
+    assert_eq!(multiply.forward(&[&a, &b]), &a * &b);
+    let grads = multiply.backward(&dy);
+    assert_eq!(grads[0], &dy * &b);
+    assert_eq!(grads[1], &dy * &a);
+
+    let mut concatenate = Concatenate2::new(1);
+    let y = concatenate.forward(&[&a, &c]);
+    assert_eq!(y.shape(), &[2, 5]);
+    let grads = concatenate.backward(&y);
+    assert_eq!(grads, vec![a.clone(), c]);
+
+    // gradients of the body and the shortcut are summed
+    let x: Array2<f64> = Array::random((4, 3), Uniform::new(-1.0, 1.0));
+    let dy: Array2<f64> = Array::random((4, 3), Uniform::new(-1.0, 1.0));
+    let body: Vec<BranchLayer<f64, Ix2>> = vec![
+        Box::new(Affine::new((3, 3), WeightInitEnum::Xavier, 0.5)),
+        Box::new(Tanh2::new((4, 3))),
+        Box::new(Affine::new((3, 3), WeightInitEnum::Xavier, 0.5)),
+    ];
+    let mut residual = Residual::new(body);
+    let numerical_dx = numerical_layer_grad(&mut residual, &x, &dy);
+    residual.forward(&x);
+    assert!(
+        *(&residual.backward(&dy) - &numerical_dx)
+            .mapv(f64::abs)
+            .max()
+            .unwrap()
+            < 1e-6
+    );
+
+    let body: Vec<BranchLayer<f64, Ix2>> = vec![
+        Box::new(Affine::new((3, 3), WeightInitEnum::Xavier, 0.5)),
+        Box::new(Tanh2::new((4, 3))),
+    ];
+    let mut residual = Residual::new(body).with_shortcut(Box::new(Affine::new(
+        (3, 3),
+        WeightInitEnum::Xavier,
+        0.5,
+    )));
+    let numerical_dx = numerical_layer_grad(&mut residual, &x, &dy);
+    residual.forward(&x);
+    assert!(
+        *(&residual.backward(&dy) - &numerical_dx)
+            .mapv(f64::abs)
+            .max()
+            .unwrap()
+            < 1e-6
+    );
+
+    let body: Vec<BranchLayer<f64, Ix2>> = vec![
+        Box::new(Affine::new((3, 3), WeightInitEnum::Xavier, 0.5)),
+        Box::new(Tanh2::new((4, 3))),
+    ];
+    let mut dense = DenseConnection::new(body, 1);
+    let dy: Array2<f64> = Array::random((4, 6), Uniform::new(-1.0, 1.0));
+    let numerical_dx = numerical_layer_grad(&mut dense, &x, &dy);
+    let y = dense.forward(&x);
+    assert_eq!(y.slice(s![.., ..3]), x);
+    assert!(
+        *(&dense.backward(&dy) - &numerical_dx)
+            .mapv(f64::abs)
+            .max()
+            .unwrap()
+            < 1e-6
+    );
+
+    // blocks nest as ordinary layers
+    let inner: Vec<BranchLayer<f64, Ix2>> = vec![Box::new(residual)];
+    let mut outer = Residual::new(inner);
+    let dy: Array2<f64> = Array::random((4, 3), Uniform::new(-1.0, 1.0));
+    let numerical_dx = numerical_layer_grad(&mut outer, &x, &dy);
+    outer.forward(&x);
+    assert!(
+        *(&outer.backward(&dy) - &numerical_dx)
+            .mapv(f64::abs)
+            .max()
+            .unwrap()
+            < 1e-6
+    );
+    outer.update(0.1);
+
+    // learnable parameters inside blocks are updated through `parameters`
+    let body: Vec<BranchLayer<f64, Ix2>> = vec![Box::new(PReLU2::new((4, 3), 0, 0.25))];
+    let mut residual = Residual::new(body);
+    residual.forward(&-x.mapv(f64::abs));
+    residual.backward(&dy);
+    let mut expected: Vec<Array1<f64>> = Vec::new();
+    for (mut param, grad) in residual.parameters() {
+        param.scaled_add(-0.1, &grad);
+        expected.push(param.to_owned());
+    }
+    assert_eq!(expected.len(), 1);
+    assert!(expected[0].iter().all(|&v| v != 0.25));
+    let params: Vec<Array1<f64>> = residual
+        .parameters()
+        .into_iter()
+        .map(|(param, _)| param.to_owned())
+        .collect();
+    assert_eq!(params, expected);
+}
+
+#[test]
+fn test_reshape() {
+    let x: Array4<f64> = Array::random((2, 3, 4, 5), Uniform::new(-1.0, 1.0));
+
+    // conv feature maps to Affine and back
+    let mut flatten = Flatten4::new();
+    let y = flatten.forward(&x);
+    assert_eq!(y.shape(), &[2, 60]);
+    assert_eq!(y[[1, 21]], x[[1, 1, 0, 1]]);
+    let mut affine = Affine::new((60, 7), WeightInitEnum::Xavier, 0.5);
+    let z = affine.forward(&y);
+    let dy = affine.backward(&z);
+    assert_eq!(flatten.backward(&dy).shape(), x.shape());
+    assert_eq!(flatten.backward(&y), x);
+
+    let mut reshape = Reshape::<f64, Ix4, Ix3>::new((0, 12, 5));
+    let y = reshape.forward(&x);
+    assert_eq!(y.shape(), &[2, 12, 5]);
+    assert_eq!(reshape.backward(&y), x);
+
+    // permutations are undone on backward, also for non-standard layouts
+    let mut permute = Permute4::new(&[0, 2, 3, 1]);
+    let y = permute.forward(&x);
+    assert_eq!(y.shape(), &[2, 4, 5, 3]);
+    assert_eq!(y[[1, 2, 3, 0]], x[[1, 0, 2, 3]]);
+    assert_eq!(permute.backward(&y), x);
+    let mut transpose = Permute2::transpose(2, 0, 1);
+    let a: Array2<f64> = Array::random((3, 2), Uniform::new(-1.0, 1.0));
+    assert_eq!(transpose.forward(&a.t().to_owned()), a);
+    assert_eq!(transpose.backward(&a), a.t());
+
+    let mut unsqueeze = Unsqueeze2::new(1);
+    let y = unsqueeze.forward(&a);
+    assert_eq!(y.shape(), &[3, 1, 2]);
+    assert_eq!(unsqueeze.backward(&y), a);
+    let mut squeeze = Squeeze3::new(1);
+    assert_eq!(squeeze.forward(&y), a);
+    assert_eq!(squeeze.backward(&a), y);
+
+    // dynamic pipelines
+    let mut into_dyn = IntoDyn::<f64, Ix4>::new();
+    let xd = into_dyn.forward(&x);
+    let mut reshape_d = ReshapeD::new(IxDyn(&[0, 3, 20]));
+    let mut squeeze_d = SqueezeD::new(0);
+    let yd = reshape_d.forward(&xd);
+    assert_eq!(yd.shape(), &[2, 3, 20]);
+    let zd = squeeze_d.forward(&yd.slice(s![..1, .., ..]).to_owned().into_dyn());
+    assert_eq!(zd.shape(), &[3, 20]);
+    let mut flatten_d = FlattenD::new();
+    assert_eq!(flatten_d.forward(&yd).shape(), &[2, 60]);
+    assert_eq!(into_dyn.backward(&reshape_d.backward(&yd)), x);
+}
+
+/// check the gradients of a convolution layer with respect to inputs and weights
+macro_rules! check_conv_grads {
+    ($layer:expr, $x:expr, $dy:expr) => {{
+        let mut layer = $layer;
+        let numerical_dx = numerical_layer_grad(&mut layer, $x, $dy);
+        let numerical_dw = numerical_param_grad(&mut layer, |v| &mut v.weight, $x, $dy);
+        layer.forward($x);
+        let dx = layer.backward($dy);
+        assert!(*(&dx - &numerical_dx).mapv(f64::abs).max().unwrap() < 1e-6);
+        assert!(*(&layer.dw - &numerical_dw).mapv(f64::abs).max().unwrap() < 1e-6);
+        let db: Array1<f64> = $dy
+            .view()
+            .into_shape((
+                $dy.shape()[0],
+                $dy.shape()[1],
+                $dy.len() / $dy.shape()[0] / $dy.shape()[1],
+            ))
+            .unwrap()
+            .sum_axis(Axis(2))
+            .sum_axis(Axis(0));
+        assert!(*(&layer.db - &db).mapv(f64::abs).max().unwrap() < 1e-9);
+        layer
+    }};
+}
+
+#[test]
+fn test_convolution_variants() {
+    let uniform = Uniform::new(-1.0, 1.0);
+
+    // dilated 2D convolution equals the one with zeros between the filter elements
+    let x: Array4<f64> = Array::random((2, 2, 7, 7), uniform);
+    let weight: Array4<f64> = Array::random((3, 2, 2, 2), uniform);
+    let bias: Array1<f64> = Array::random(3, uniform);
+    let mut dilated_weight = Array4::<f64>::zeros((3, 2, 3, 3));
+    dilated_weight
+        .slice_mut(s![.., .., ..;2, ..;2])
+        .assign(&weight);
+    let mut dense = Convolution3::from(&dilated_weight, &bias, (2, 7, 7), (1, 1), 1).unwrap();
+    let dilated = Convolution3::from(&weight, &bias, (2, 7, 7), (1, 1), 1)
+        .unwrap()
+        .with_dilation((2, 2))
+        .unwrap();
+    assert_eq!(dilated.get_output_data_shape(), (3, 7, 7));
+    let dy: Array4<f64> = Array::random((2, 3, 7, 7), uniform);
+    let mut dilated = check_conv_grads!(dilated, &x, &dy);
+    assert!(
+        *(&dilated.forward(&x) - &dense.forward(&x))
+            .mapv(f64::abs)
+            .max()
+            .unwrap()
+            < 1e-12
+    );
+    assert!(Convolution3::from(&weight, &bias, (2, 7, 7), (1, 1), 0)
+        .unwrap()
+        .with_dilation((0, 1))
+        .is_err());
+
+    // single-channel images give feature maps
+    let images: Array3<f64> = x.index_axis(Axis(1), 0).to_owned();
+    let weight2: Array3<f64> = weight.index_axis(Axis(1), 0).to_owned();
+    let mut conv2 = Convolution2::from(&weight2, &bias, (7, 7), (1, 1), 1).unwrap();
+    let mut conv3 = Convolution3::from(
+        &weight2.clone().insert_axis(Axis(1)),
+        &bias,
+        (1, 7, 7),
+        (1, 1),
+        1,
+    )
+    .unwrap();
+    let images4: Array4<f64> = images.clone().insert_axis(Axis(1));
+    assert_eq!(conv2.forward(&images), conv3.forward(&images4));
+    let dy: Array4<f64> = Array::random((2, 3, 8, 8), uniform);
+    assert_eq!(
+        conv2.backward(&dy),
+        conv3.backward(&dy).index_axis(Axis(1), 0)
+    );
+    assert_eq!(conv2.dw, conv3.dw.index_axis(Axis(1), 0));
+
+    // time series and volumes
+    let conv1d = Conv1d::new(4, &[3], &[2, 9], &[2], 1, WeightInitEnum::Xavier, 0.5)
+        .unwrap()
+        .with_dilation(&[2])
+        .unwrap();
+    assert_eq!(conv1d.get_output_data_shape(), vec![4, 4]);
+    let x1: Array3<f64> = Array::random((3, 2, 9), uniform);
+    let dy1: Array3<f64> = Array::random((3, 4, 4), uniform);
+    check_conv_grads!(conv1d, &x1, &dy1);
+    assert!(Conv1d::<f64>::new(4, &[3], &[2, 8], &[2], 0, WeightInitEnum::Xavier, 0.5).is_err());
+    assert!(Conv1d::<f64>::new(4, &[3, 3], &[2, 9], &[2], 0, WeightInitEnum::Xavier, 0.5).is_err());
+
+    let conv3d = Conv3d::new(
+        2,
+        &[2, 2, 2],
+        &[2, 4, 5, 5],
+        &[1, 1, 1],
+        0,
+        WeightInitEnum::Xavier,
+        0.5,
+    )
+    .unwrap();
+    assert_eq!(conv3d.get_output_data_shape(), vec![2, 3, 4, 4]);
+    let x3: Array5<f64> = Array::random((2, 2, 4, 5, 5), uniform);
+    let dy3: Array5<f64> = Array::random((2, 2, 3, 4, 4), uniform);
+    check_conv_grads!(conv3d, &x3, &dy3);
+
+    // transposed convolution is the adjoint of the convolution with the same weight
+    let weight: Array4<f64> = Array::random((3, 2, 3, 3), uniform);
+    let transpose = ConvTranspose2d::from(&weight, &Array1::zeros(2), (4, 4), (2, 2), 1).unwrap();
+    assert_eq!(transpose.get_output_data_shape(), (7, 7));
+    let padded = transpose.clone().with_output_padding((1, 0)).unwrap();
+    assert_eq!(padded.get_output_data_shape(), (8, 7));
+    assert!(ConvTranspose2d::from(&weight, &Array1::zeros(2), (1, 1), (2, 2), 2).is_err());
+    let mut conv = Convolution3::from(&weight, &Array1::zeros(3), (2, 7, 7), (2, 2), 1).unwrap();
+    let xt: Array4<f64> = Array::random((2, 3, 4, 4), uniform);
+    let y: Array4<f64> = Array::random((2, 2, 7, 7), uniform);
+    let mut transpose = check_conv_grads!(transpose, &xt, &y);
+    let lhs = (transpose.forward(&xt) * &y).sum();
+    let rhs = (conv.forward(&y) * &xt).sum();
+    assert!((lhs - rhs).abs() < 1e-9);
+    assert!(ConvTranspose2d::<f64>::new(
+        3,
+        2,
+        (3, 3),
+        (4, 4),
+        (2, 2),
+        1,
+        WeightInitEnum::Xavier,
+        0.5
+    )
+    .unwrap()
+    .with_output_padding((2, 0))
+    .is_err());
+    let dilated =
+        ConvTranspose2d::new(3, 2, (2, 2), (4, 4), (1, 1), 0, WeightInitEnum::Xavier, 0.5)
+            .unwrap()
+            .with_dilation((2, 2))
+            .unwrap();
+    let dy: Array4<f64> = Array::random((2, 2, 6, 6), uniform);
+    check_conv_grads!(dilated, &xt, &dy);
+}
+
+#[test]
+fn test_grouped_convolution() {
+    let uniform = Uniform::new(-1.0, 1.0);
+    let x: Array4<f64> = Array::random((2, 4, 6, 6), uniform);
+    let weight: Array4<f64> = Array::random((6, 2, 3, 3), uniform);
+    let bias: Array1<f64> = Array::random(6, uniform);
+
+    // each group of filters sees only its group of channels
+    let mut grouped = Convolution3::from(&weight, &bias, (4, 6, 6), (1, 1), 1).unwrap();
+    let y = grouped.forward(&x);
+    for g in 0..2 {
+        let mut conv = Convolution3::from(
+            &weight.slice(s![g * 3..(g + 1) * 3, .., .., ..]).to_owned(),
+            &bias.slice(s![g * 3..(g + 1) * 3]).to_owned(),
+            (2, 6, 6),
+            (1, 1),
+            1,
+        )
+        .unwrap();
+        let expected = conv.forward(&x.slice(s![.., g * 2..(g + 1) * 2, .., ..]).to_owned());
+        let diff = &y.slice(s![.., g * 3..(g + 1) * 3, .., ..]) - &expected;
+        assert!(*diff.mapv(f64::abs).max().unwrap() < 1e-12);
+    }
+    let dy: Array4<f64> = Array::random((2, 6, 6, 6), uniform);
+    let grouped = check_conv_grads!(grouped, &x, &dy);
+    assert_eq!(grouped.num_parameters(), 6 * 2 * 3 * 3 + 6);
+
+    // groups are given at construction, or by the shape of explicit weights
+    let conv =
+        Convolution3::new(6, (3, 3), (4, 6, 6), 2, (1, 1), 1, WeightInitEnum::He, 0.1).unwrap();
+    assert_eq!(conv.weight.shape(), &[6, 2, 3, 3]);
+    assert!(conv.with_groups(2).is_err());
+    let conv = Convolution3::new(6, (3, 3), (4, 6, 6), 1, (1, 1), 1, WeightInitEnum::He, 0.1)
+        .unwrap()
+        .with_groups(2)
+        .unwrap();
+    assert_eq!(conv.weight.shape(), &[6, 2, 3, 3]);
+    assert!(
+        Convolution3::<f64>::new(6, (3, 3), (4, 6, 6), 3, (1, 1), 1, WeightInitEnum::He, 0.1)
+            .is_err()
+    );
+    assert!(
+        Convolution3::<f64>::new(6, (3, 3), (4, 6, 6), 1, (1, 1), 1, WeightInitEnum::He, 0.1)
+            .unwrap()
+            .with_groups(3)
+            .is_err()
+    );
+    let full: Array4<f64> = Array::random((6, 4, 3, 3), uniform);
+    match Convolution3::from(&full, &bias, (4, 6, 6), (1, 1), 1)
+        .unwrap()
+        .with_groups(2)
+    {
+        Err(CrateError::ConfigError(_)) => (),
+        _ => panic!("explicit weights must keep their groups."),
+    }
+    let weight: Array4<f64> = Array::random((5, 2, 3, 3), uniform);
+    assert!(Convolution3::from(&weight, &Array1::zeros(5), (4, 6, 6), (1, 1), 1).is_err());
+    assert!(Convolution3::from(&weight, &Array1::zeros(5), (3, 6, 6), (1, 1), 1).is_err());
+
+    // depthwise separable convolution
+    let layer =
+        DepthwiseSeparableConv::new(8, (3, 3), (4, 6, 6), (1, 1), 0, WeightInitEnum::He, 0.1)
+            .unwrap();
+    assert_eq!(layer.get_output_data_shape(), (8, 4, 4));
+    assert_eq!(layer.num_parameters(), (4 * 3 * 3 + 4) + (8 * 4 + 8));
+    assert_eq!(layer.depthwise.weight.shape(), &[4, 1, 3, 3]);
+    let mut layer = layer;
+    let dy: Array4<f64> = Array::random((2, 8, 4, 4), uniform);
+    let numerical_dx = numerical_layer_grad(&mut layer, &x, &dy);
+    let numerical_dw = numerical_param_grad(&mut layer, |v| &mut v.depthwise.weight, &x, &dy);
+    layer.forward(&x);
+    let dx = layer.backward(&dy);
+    assert!(*(&dx - &numerical_dx).mapv(f64::abs).max().unwrap() < 1e-6);
+    assert!(
+        *(&layer.depthwise.dw - &numerical_dw)
+            .mapv(f64::abs)
+            .max()
+            .unwrap()
+            < 1e-6
+    );
+    layer.print_detail();
+    assert!(DepthwiseSeparableConv::from(
+        &Array4::<f64>::zeros((4, 2, 3, 3)),
+        &Array1::zeros(4),
+        &Array4::zeros((8, 4, 1, 1)),
+        &Array1::zeros(8),
+        (4, 6, 6),
+        (1, 1),
+        1,
+    )
+    .is_err());
+}
+
+#[test]
+fn test_global_pooling_and_upsampling() {
+    let uniform = Uniform::new(-1.0, 1.0);
+    let x: Array4<f64> = Array::random((2, 3, 5, 5), uniform);
+
+    let mut average = GlobalAveragePooling::new();
+    let y = average.forward(&x);
+    assert_eq!(y.shape(), &[2, 3]);
+    assert!((y[[1, 2]] - x.slice(s![1, 2, .., ..]).mean().unwrap()).abs() < 1e-12);
+    let dy: Array2<f64> = Array::random((2, 3), uniform);
+    let dx = average.backward(&dy);
+    assert!((dx[[1, 2, 4, 0]] - dy[[1, 2]] / 25.0).abs() < 1e-12);
+
+    let mut max = GlobalMaxPooling::new();
+    let y = max.forward(&x);
+    assert_eq!(y[[0, 1]], *x.slice(s![0, 1, .., ..]).max().unwrap());
+    let dx = max.backward(&dy);
+    assert_eq!(dx.sum(), dy.sum());
+    let arg: (usize, usize) = x.slice(s![0, 1, .., ..]).argmax().unwrap();
+    assert_eq!(dx[[0, 1, arg.0, arg.1]], dy[[0, 1]]);
+
+    // backward of linear layers is the adjoint of forward
+    fn check_adjoint(
+        layer: &mut dyn LayerBase<f64, A = Array4<f64>, B = Array4<f64>>,
+        x: &Array4<f64>,
+    ) {
+        let y = layer.forward(x);
+        let dy: Array4<f64> = Array::random(y.raw_dim(), Uniform::new(-1.0, 1.0));
+        let dx = layer.backward(&dy);
+        assert!(((&y * &dy).sum() - (x * &dx).sum()).abs() < 1e-9);
+    }
+
+    let mut adaptive = AdaptiveAvgPool::new((2, 3));
+    let y = adaptive.forward(&x.slice(s![.., .., ..4, ..]).to_owned());
+    assert_eq!(y.shape(), &[2, 3, 2, 3]);
+    let expected = x.slice(s![0, 0, ..2, 0..2]).mean().unwrap();
+    assert!((y[[0, 0, 0, 0]] - expected).abs() < 1e-12);
+    let expected = x.slice(s![0, 0, 2..4, 3..5]).mean().unwrap();
+    assert!((y[[0, 0, 1, 2]] - expected).abs() < 1e-12);
+    check_adjoint(&mut adaptive, &x);
+    assert_eq!(adaptive.forward(&x).shape(), &[2, 3, 2, 3]);
+
+    let mut nearest = Upsample::new((2, 3), UpsampleModeEnum::Nearest);
+    let y = nearest.forward(&x);
+    assert_eq!(y.shape(), &[2, 3, 10, 15]);
+    assert_eq!(y[[1, 1, 5, 8]], x[[1, 1, 2, 2]]);
+    check_adjoint(&mut nearest, &x);
+
+    let mut bilinear = Upsample::new((1, 2), UpsampleModeEnum::Bilinear);
+    let y = bilinear.forward(&array![[1.0, 2.0]].into_shape((1, 1, 1, 2)).unwrap());
+    assert!((&y - &array![1.0, 1.25, 1.75, 2.0]).mapv(f64::abs).sum() < 1e-12);
+    let mut bilinear = Upsample::new((2, 2), UpsampleModeEnum::Bilinear);
+    check_adjoint(&mut bilinear, &x);
+    // empty axes stay empty
+    let y = bilinear.forward(&Array4::<f64>::zeros((2, 3, 0, 4)));
+    assert_eq!(y.shape(), &[2, 3, 0, 8]);
+    assert_eq!(bilinear.backward(&y).shape(), &[2, 3, 0, 4]);
+}
+
+#[test]
+fn test_dropout_variants() {
+    let x: Array2<f64> = Array::random((200, 50), Uniform::new(0.5, 1.5));
+    let dy: Array2<f64> = Array::random((200, 50), Uniform::new(-1.0, 1.0));
+
+    // seeded masks are reproducible
+    let mut dropout1 = DropOut::<f64, Ix2>::new(0.3, (200, 50))
+        .unwrap()
+        .with_seed(7);
+    let mut dropout2 = DropOut::<f64, Ix2>::new(0.3, (200, 50))
+        .unwrap()
+        .with_seed(7);
+    assert_eq!(dropout1.forward(&x), dropout2.forward(&x));
+
+    // inverted dropout scales during training and is the identity at inference
+    let mut inverted = DropOut::<f64, Ix2>::new(0.3, (200, 50))
+        .unwrap()
+        .with_seed(1)
+        .with_inverted(true);
+    let y = inverted.forward(&x);
+    let kept = y.iter().filter(|&&v| v != 0.0).count() as f64 / y.len() as f64;
+    assert!((kept - 0.7).abs() < 0.03);
+    for (a, b) in y.iter().zip(x.iter()).filter(|(&a, _)| a != 0.0) {
+        assert!((a - b / 0.7).abs() < 1e-12);
+    }
+    let dx = inverted.backward(&dy);
+    assert!(((&y * &dy).sum() - (&x * &dx).sum()).abs() < 1e-9);
+    inverted.set_trainable(false);
+    assert_eq!(inverted.forward(&x), x);
+    // the options are also given by schemes
+    let use_dropout: UseDropoutEnum<f64> =
+        serde_json::from_str("{\"UseWith\": {\"ratio\": 0.3, \"inverted\": true, \"seed\": 1}}")
+            .unwrap();
+    let mut from_scheme = call_dropout_layer(use_dropout.clone(), (200, 50)).unwrap();
+    let mut seeded = DropOut::<f64, Ix2>::new(0.3, (200, 50))
+        .unwrap()
+        .with_seed(1)
+        .with_inverted(true);
+    assert_eq!(from_scheme.forward(&x), seeded.forward(&x));
+    from_scheme.set_trainable(false);
+    assert_eq!(from_scheme.forward(&x), x);
+    let mut params: ModelParameters<f64> = ModelParameters::from(
+        ModelEnum::MLPClassifier,
+        50,
+        vec![20, 20],
+        3,
+        0,
+        vec![ActivatorEnum::ReLU, ActivatorEnum::ReLU],
+        OptimizerEnum::SGD(0.1),
+        NormalizationEnum::None,
+        use_dropout,
+        RegularizerEnum::None,
+        WeightInitEnum::He,
+        0.1,
+    );
+    assert!(MLPClassifier::from(params.clone()).is_ok());
+    params.use_dropout = UseDropoutEnum::UseWith {
+        ratio: 1.5,
+        inverted: false,
+        seed: None,
+    };
+    match params.validate() {
+        Err(SchemeError::ValidationError(issues)) => {
+            assert_eq!(issues[0].path, "use_dropout.UseWith.ratio")
+        }
+        _ => panic!("invalid dropout ratios must be rejected."),
+    }
+
+    // spatial dropout drops whole channels
+    let x4: Array4<f64> = Array::random((4, 16, 3, 3), Uniform::new(0.5, 1.5));
+    let mut spatial = SpatialDropout4::new(0.5).unwrap().with_seed(3);
+    let y4 = spatial.forward(&x4);
+    for channel in y4.into_shape((64, 9)).unwrap().outer_iter() {
+        let dropped = channel.iter().filter(|&&v| v == 0.0).count();
+        assert!(dropped == 0 || dropped == 9);
+    }
+    let dy4: Array4<f64> = Array::random((4, 16, 3, 3), Uniform::new(-1.0, 1.0));
+    let y4 = spatial.forward(&x4);
+    let dx4 = spatial.backward(&dy4);
+    assert!(((&y4 * &dy4).sum() - (&x4 * &dx4).sum()).abs() < 1e-9);
+    spatial.set_trainable(false);
+    assert_eq!(spatial.forward(&x4), x4);
+
+    // alpha dropout keeps zero mean and unit variance
+    let normal: Array2<f64> = Array::random((400, 250), ndarray_rand::rand_distr::StandardNormal);
+    let mut alpha = AlphaDropout2::new(0.2).unwrap().with_seed(5);
+    let y = alpha.forward(&normal);
+    assert!(y.mean().unwrap().abs() < 0.02);
+    assert!((y.var(0.0) - 1.0).abs() < 0.05);
+    let dx = alpha.backward(&normal);
+    assert!(dx
+        .iter()
+        .zip(normal.iter())
+        .any(|(&a, &b)| a == 0.0 && b != 0.0));
+    alpha.set_trainable(false);
+    assert_eq!(alpha.forward(&normal), normal);
+
+    // drop connect is linear in both inputs and weights with the same mask
+    let affine = Affine::new((50, 8), WeightInitEnum::Normal, 0.1);
+    let mut drop_connect = DropConnect::new(affine.clone(), 0.4).unwrap().with_seed(11);
+    let dy = Array::random((200, 8), Uniform::new(-1.0, 1.0));
+    let y = drop_connect.forward(&x) - &affine.bias;
+    let dx = drop_connect.backward(&dy);
+    assert!(((&y * &dy).sum() - (&x * &dx).sum()).abs() < 1e-9);
+    assert!(((&y * &dy).sum() - (&drop_connect.affine.dw * &affine.weight).sum()).abs() < 1e-9);
+    assert_eq!(drop_connect.affine.db, dy.sum_axis(Axis(0)));
+    drop_connect.set_trainable(false);
+    let mut plain = affine;
+    assert_eq!(drop_connect.forward(&x), plain.forward(&x));
+
+    assert!(SpatialDropout4::<f64>::new(1.5).is_err());
+    assert!(AlphaDropout2::<f64>::new(-0.1).is_err());
+}
+
+/// numerical gradient of the loss of a loss layer with respect to `x`
+fn numerical_loss_grad<D: Dimension>(
+    layer: &mut dyn LossLayerBase<f64, A = Array<f64, D>>,
+    x: &Array<f64, D>,
+    t: &Array<f64, D>,
+) -> Array<f64, D> {
+    let h = 1e-6;
+    let x = x.as_standard_layout().into_owned();
+    let mut grad = Array::<f64, D>::zeros(x.raw_dim());
+    for (ii, g) in grad.iter_mut().enumerate() {
+        let mut xp = x.clone();
+        xp.as_slice_mut().unwrap()[ii] += h;
+        let fp = layer.forward(&xp, t);
+        let mut xm = x.clone();
+        xm.as_slice_mut().unwrap()[ii] -= h;
+        let fm = layer.forward(&xm, t);
+        *g = (fp - fm) / (2.0 * h);
+    }
+    grad
+}
+
+#[test]
+fn test_regression_losses() {
+    let x: Array2<f64> = array![[0.5, -1.0, 2.5], [0.0, 3.0, -0.2]];
+    let t = array![[0.2, -0.5, 0.0], [0.4, 0.5, -0.3]];
+
+    let mut mse = MeanSquaredError2::new(ReductionEnum::Mean);
+    let loss = mse.forward(&x, &t);
+    assert!((loss - (&x - &t).mapv(|v| v * v).mean().unwrap()).abs() < 1e-12);
+    assert_eq!(mse.get_output(), x);
+    let mut mae = MeanAbsoluteError2::new(ReductionEnum::Sum);
+    mae.forward(&x, &t);
+    let expected = (&x - &t).mapv(f64::abs).mean_axis(Axis(1)).unwrap();
+    assert!((&mae.get_sample_losses() - &expected).mapv(f64::abs).sum() < 1e-12);
+    let mut huber = Huber2::<f64>::new(1.0, ReductionEnum::Mean).unwrap();
+    huber.forward(&array![[0.5, 3.0]], &array![[0.0, 0.0]]);
+    assert!((huber.get_sample_losses()[0] - (0.125 + 2.5) / 2.0).abs() < 1e-12);
+    let mut log_cosh = LogCosh2::new(ReductionEnum::Mean);
+    let loss = log_cosh.forward(&array![[0.3, 800.0]], &array![[0.0, 0.0]]);
+    assert!((loss - (0.3f64.cosh().ln() + 800.0 - 2f64.ln()) / 2.0).abs() < 1e-9);
+
+    let layers: Vec<Box<dyn LossLayerBase<f64, A = Array2<f64>>>> = vec![
+        Box::new(MeanSquaredError2::new(ReductionEnum::Mean)),
+        Box::new(MeanAbsoluteError2::new(ReductionEnum::Sum)),
+        Box::new(Huber2::new(1.0, ReductionEnum::None).unwrap()),
+        Box::new(Huber2::new(0.3, ReductionEnum::Mean).unwrap()),
+        Box::new(LogCosh2::new(ReductionEnum::Sum)),
+    ];
+    for mut layer in layers {
+        let numerical = numerical_loss_grad(layer.as_mut(), &x, &t);
+        layer.forward(&x, &t);
+        let dx = layer.backward(1.0);
+        assert!(*(&dx - &numerical).mapv(f64::abs).max().unwrap() < 1e-6);
+    }
+
+    // reductions only change the scale of losses and gradients
+    let x3: Array3<f64> = Array::random((4, 2, 3), Uniform::new(-2.0, 2.0));
+    let t3: Array3<f64> = Array::random((4, 2, 3), Uniform::new(-2.0, 2.0));
+    let mut mean = MeanSquaredError3::new(ReductionEnum::Mean);
+    let mut none = MeanSquaredError3::new(ReductionEnum::None);
+    let loss = mean.forward(&x3, &t3);
+    assert!((none.forward(&x3, &t3) - 4.0 * loss).abs() < 1e-12);
+    assert_eq!(none.get_sample_losses().len(), 4);
+    assert!(
+        (&none.backward(1.0) - &(mean.backward(1.0) * 4.0))
+            .mapv(f64::abs)
+            .sum()
+            < 1e-12
+    );
+
+    assert!(Huber2::<f64>::new(0.0, ReductionEnum::Mean).is_err());
+}
+
+#[test]
+fn test_sigmoid_with_bce_loss() {
+    let x: Array2<f64> = array![[0.3, -1.2, 2.0], [-0.5, 0.8, -2.5]];
+    let t: Array2<f64> = array![[1.0, 0.0, 1.0], [0.0, 1.0, 1.0]];
+
+    let mut layer = SigmoidWithBCELoss2::new();
+    let loss = layer.forward(&x, &t);
+    let y = x.mapv(|v| 1.0 / (1.0 + (-v).exp()));
+    let expected =
+        -(&t * &y.mapv(f64::ln) + &t.mapv(|v| 1.0 - v) * &y.mapv(|v| (1.0 - v).ln())).sum() / 2.0;
+    assert!((loss - expected).abs() < 1e-12);
+    assert!((&layer.get_output() - &y).mapv(f64::abs).sum() < 1e-12);
+
+    // finite at extreme logits
+    let extreme = array![[1e4, -1e4], [-1e4, 1e4]];
+    let loss = layer.forward(&extreme, &array![[1.0, 0.0], [1.0, 0.0]]);
+    assert!((loss - 2e4 / 2.0).abs() < 1e-6);
+    assert!(layer.backward(1.0).iter().all(|v| v.is_finite()));
+
+    let layers: Vec<Box<dyn LossLayerBase<f64, A = Array2<f64>>>> = vec![
+        Box::new(SigmoidWithBCELoss2::new()),
+        Box::new(
+            SigmoidWithBCELoss2::new()
+                .with_pos_weight(array![2.0, 0.5, 3.0])
+                .unwrap(),
+        ),
+    ];
+    for mut layer in layers {
+        let numerical = numerical_loss_grad(layer.as_mut(), &x, &t);
+        layer.forward(&x, &t);
+        let dx = layer.backward(1.0);
+        assert!(*(&dx - &numerical).mapv(f64::abs).max().unwrap() < 1e-6);
+    }
+    assert!(SigmoidWithBCELoss2::<f64>::new()
+        .with_pos_weight(array![1.0, -1.0])
+        .is_err());
+
+    let prob: Array2<f64> = array![[0.9, 0.2, 0.6], [0.1, 0.7, 0.4], [0.3, 0.3, 0.3]];
+    let t = array![[1.0, 0.0, 1.0], [0.0, 1.0, 1.0], [0.0, 0.0, 0.0]];
+    assert!((multi_hot_accuracy(&prob, &t, 0.5) - 2.0 / 3.0).abs() < 1e-12);
+    assert!((multi_hot_accuracy(&prob, &t, 0.35) - 1.0).abs() < 1e-12);
+
+    // MLPClassifier with the BCE loss evaluates multi-hot targets with the threshold 0.5
+    let mut params: ModelParameters<f64> = ModelParameters::from(
+        ModelEnum::MLPClassifier,
+        3,
+        vec![4],
+        3,
+        0,
+        vec![ActivatorEnum::ReLU],
+        OptimizerEnum::SGD(0.1),
+        NormalizationEnum::None,
+        UseDropoutEnum::None,
+        RegularizerEnum::None,
+        WeightInitEnum::He,
+        0.1,
+    );
+    params.loss_enum = LossEnum::SigmoidWithBCELoss {
+        pos_weight: Vec::new(),
+    };
+    let mut model = MLPClassifier::from(params).unwrap();
+    let x: Array2<f64> = Array::random((3, 3), Uniform::new(-1.0, 1.0));
+    let prob = model.predict_prob(&x);
+    assert_eq!(model.accuracy(&x, &t), multi_hot_accuracy(&prob, &t, 0.5));
+    assert_eq!(
+        model.accuracy(&x, &t),
+        model.multi_label_accuracy(&x, &t, 0.5)
+    );
+    let all_positive: Array2<f64> = Array2::ones((3, 3));
+    let expected = prob
+        .outer_iter()
+        .filter(|p| p.iter().all(|&p| p >= 0.5))
+        .count() as f64
+        / 3.0;
+    assert_eq!(model.accuracy(&x, &all_positive), expected);
+}
+
+#[test]
+fn test_softmax_loss_options() {
+    let x: Array2<f64> = array![[0.3, -1.2, 2.0], [-0.5, 0.8, 0.1], [1.5, 0.2, -0.7]];
+    let t: Array2<f64> = array![[0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+
+    // each option reduces to the plain loss with neutral values
+    let mut plain = SoftmaxWithLoss2::new((3, 3), 0);
+    let mut neutral = SoftmaxWithLoss2::new((3, 3), 0)
+        .with_class_weights(array![1.0, 1.0, 1.0])
+        .unwrap()
+        .with_focal(0.0, 1.0)
+        .unwrap();
+    assert!((plain.forward(&x, &t) - neutral.forward(&x, &t)).abs() < 1e-12);
+    assert!(
+        (&plain.backward(1.0) - &neutral.backward(1.0))
+            .mapv(f64::abs)
+            .sum()
+            < 1e-12
+    );
+
+    let mut weighted = SoftmaxWithLoss2::new((3, 3), 0)
+        .with_class_weights(array![2.0, 1.0, 0.5])
+        .unwrap();
+    let p = plain.get_output();
+    let expected = -(2.0 * p[[1, 0]].ln() + p[[2, 1]].ln() + 0.5 * p[[0, 2]].ln()) / 3.0;
+    assert!((weighted.forward(&x, &t) - expected).abs() < 1e-6);
+
+    let layers: Vec<Box<dyn LossLayerBase<f64, A = Array2<f64>>>> = vec![
+        Box::new(weighted),
+        Box::new(
+            SoftmaxWithLoss2::new((3, 3), 0)
+                .with_label_smoothing(0.1)
+                .unwrap(),
+        ),
+        Box::new(
+            SoftmaxWithLoss2::new((3, 3), 0)
+                .with_focal(2.0, 0.25)
+                .unwrap(),
+        ),
+        Box::new(
+            SoftmaxWithLoss2::new((3, 3), 0)
+                .with_class_weights(array![0.3, 1.0, 2.0])
+                .unwrap()
+                .with_label_smoothing(0.2)
+                .unwrap()
+                .with_focal(0.5, 1.0)
+                .unwrap(),
+        ),
+    ];
+    for mut layer in layers {
+        let numerical = numerical_loss_grad(layer.as_mut(), &x, &t);
+        layer.forward(&x, &t);
+        let dx = layer.backward(1.0);
+        assert!(*(&dx - &numerical).mapv(f64::abs).max().unwrap() < 1e-6);
+    }
+    assert!(SoftmaxWithLoss2::<f64>::new((3, 3), 0)
+        .with_label_smoothing(1.0)
+        .is_err());
+    assert!(SoftmaxWithLoss2::<f64>::new((3, 3), 0)
+        .with_focal(-1.0, 1.0)
+        .is_err());
+
+    // the loss is configured in the scheme
+    let mut scheme = serde_json::json!({
+        "format_version": CURRENT_FORMAT_VERSION,
+        "model_enum": "MLPClassifier",
+        "input_size": 2,
+        "hidden_sizes": [10],
+        "output_size": 3,
+        "batch_axis": 0,
+        "activator_enums": ["ReLU"],
+        "optimizer_enum": {"SGD": {"lr": 0.1}},
+        "regularizer_enum": "None",
+        "weight_init_enum": "He",
+        "weight_init_std": 0.01,
+        "loss_enum": {"SoftmaxWithLoss": {"class_weights": [1.0, 2.0, 3.0], "focal": {"gamma": 2.0, "alpha": 0.25}}}
+    });
+    let params: ModelParameters<f64> = ModelParameters::from_value(scheme.clone()).unwrap();
+    assert_eq!(
+        params.loss_enum,
+        LossEnum::SoftmaxWithLoss {
+            class_weights: vec![1.0, 2.0, 3.0],
+            label_smoothing: None,
+            focal: Some(FocalParameters {
+                gamma: 2.0,
+                alpha: 0.25
+            }),
+        }
+    );
+    assert!(MLPClassifier::from(params).is_ok());
+
+    scheme["loss_enum"] = serde_json::json!({"SigmoidWithBCELoss": {}});
+    let params: ModelParameters<f64> = ModelParameters::from_value(scheme.clone()).unwrap();
+    let mut net = MLPClassifier::from(params).unwrap();
+    let x = array![[0.5, -0.2], [-0.3, 0.8]];
+    let t = array![[1.0, 0.0, 1.0], [0.0, 1.0, 1.0]];
+    net.loss(&x, &t);
+    assert!(net.get_output().iter().all(|&v| v > 0.0 && v < 1.0));
+
+    scheme["loss_enum"] = serde_json::json!({"SoftmaxWithLoss": {"class_weights": [1.0, -2.0], "label_smoothing": 1.0}});
+    let params: ModelParameters<f64> = ModelParameters::from_value(scheme).unwrap();
+    match params.validate() {
+        Err(SchemeError::ValidationError(issues)) => {
+            let paths: Vec<&str> = issues.iter().map(|v| v.path.as_str()).collect();
+            assert_eq!(
+                paths,
+                vec![
+                    "loss_enum.SoftmaxWithLoss.class_weights",
+                    "loss_enum.SoftmaxWithLoss.class_weights[1]",
+                    "loss_enum.SoftmaxWithLoss.label_smoothing",
+                ]
+            );
+        }
+        _ => panic!("invalid loss options must be rejected."),
+    }
+}
+
+#[test]
+fn test_sparse_labels() {
+    let labels: Array1<usize> = array![2, 0, 1, 2];
+    let one_hot: Array2<f64> = to_one_hot(&labels, 3).unwrap();
+    assert_eq!(
+        one_hot,
+        array![[0., 0., 1.], [1., 0., 0.], [0., 1., 0.], [0., 0., 1.]]
+    );
+    assert_eq!(to_class_indices(&one_hot), labels);
+    assert!(to_one_hot::<f64>(&array![3], 3).is_err());
+
+    let x: Array2<f64> = Array::random((4, 3), Uniform::new(-2.0, 2.0));
+    let mut dense = SoftmaxWithLoss2::new((4, 3), 0);
+    let mut sparse = SoftmaxWithLoss2::new((4, 3), 0);
+    let loss = dense.forward(&x, &one_hot);
+    assert_eq!(sparse.forward_sparse(&x, &labels).unwrap(), loss);
+    assert_eq!(sparse.backward(1.0), dense.backward(1.0));
+    assert!(sparse.forward_sparse(&x, &array![0, 5, 1, 2]).is_err());
+    assert!(sparse.forward_sparse(&x, &array![0, 1]).is_err());
+
+    // samples on the second axis, and one-hot targets with the options of the loss
+    let mut dense = SoftmaxWithLoss2::new((3, 4), 1);
+    let mut sparse = SoftmaxWithLoss2::new((3, 4), 1);
+    let loss = dense.forward(&x.t().to_owned(), &one_hot.t().to_owned());
+    assert!((sparse.forward_sparse(&x.t().to_owned(), &labels).unwrap() - loss).abs() < 1e-12);
+    assert_eq!(sparse.backward(1.0), dense.backward(1.0));
+    let mut dense = SoftmaxWithLoss2::new((4, 3), 0)
+        .with_label_smoothing(0.1)
+        .unwrap();
+    let mut sparse = SoftmaxWithLoss2::new((4, 3), 0)
+        .with_label_smoothing(0.1)
+        .unwrap();
+    let loss = dense.forward(&x, &one_hot);
+    assert_eq!(sparse.forward_sparse(&x, &labels).unwrap(), loss);
+    assert_eq!(sparse.backward(1.0), dense.backward(1.0));
+    let mut mse = MeanSquaredError::new(ReductionEnum::Mean);
+    assert!(mse.forward_sparse(&x, &labels).is_err());
+
+    // two separable clusters trained end to end with class indices
+    let mut rng = StdRng::seed_from_u64(0);
+    let centers = array![[2.0, 2.0], [-2.0, -2.0]];
+    let t_train: Array1<usize> = (0..200).map(|ii| ii % 2).collect();
+    let noise: Array2<f64> = Array::random_using((200, 2), Uniform::new(-1.0, 1.0), &mut rng);
+    let x_train: Array2<f64> = centers.select(Axis(0), t_train.as_slice().unwrap()) + noise;
+    let mut model: Box<dyn SparseModelBase<f64, A = Array2<f64>, B = Array2<f64>>> = Box::new(
+        MLPClassifier::new(
+            2,
+            &[8],
+            2,
+            &[ActivatorEnum::ReLU],
+            OptimizerEnum::SGD(0.1),
+            NormalizationEnum::None,
+            UseDropoutEnum::None,
+            RegularizerEnum::None,
+            0,
+            WeightInitEnum::He,
+            0.1,
+        )
+        .unwrap(),
+    );
+    let dense_loss = model.loss(&x_train, &to_one_hot(&t_train, 2).unwrap());
+    assert_eq!(model.loss_sparse(&x_train, &t_train).unwrap(), dense_loss);
+    let mut trainer = Trainer::new(
+        x_train.clone(),
+        t_train.clone(),
+        x_train.clone(),
+        t_train.clone(),
+        0,
+        20,
+        20,
+        0,
+        true,
+        0,
+    );
+    trainer.train_sparse(&mut model).unwrap();
+    let (_, train_acc, _) = trainer.get_results().train_result();
+    assert_eq!(train_acc.len(), 20);
+    assert!(model.accuracy_sparse(&x_train, &t_train).unwrap() > 0.95);
+
+    // labels outside the classes are reported instead of aborting
+    let t_bad: Array1<usize> = (0..200).map(|ii| ii % 3).collect();
+    assert!(model.loss_sparse(&x_train, &t_bad).is_err());
+    assert!(model.accuracy_sparse(&x_train, &t_bad).is_err());
+    assert!(model.update_sparse(&x_train, &t_bad).is_err());
+    let mut trainer = Trainer::new(
+        x_train.clone(),
+        t_bad.clone(),
+        x_train.clone(),
+        t_bad,
+        0,
+        20,
+        20,
+        0,
+        false,
+        0,
+    );
+    assert!(trainer.train_sparse(&mut model).is_err());
+}
+
+fn numerical_grad2<F: FnMut(&Array2<f64>) -> f64>(mut f: F, x: &Array2<f64>) -> Array2<f64> {
+    let h = 1e-6;
+    let mut grad = Array2::<f64>::zeros(x.raw_dim());
+    for (idx, g) in grad.indexed_iter_mut() {
+        let mut xp = x.clone();
+        xp[idx] += h;
+        let mut xm = x.clone();
+        xm[idx] -= h;
+        *g = (f(&xp) - f(&xm)) / (2.0 * h);
+    }
+    grad
+}
+
+#[test]
+fn test_metric_losses() {
+    let mut rng = StdRng::seed_from_u64(1);
+    let x1: Array2<f64> = Array::random_using((4, 3), Uniform::new(-1.0, 1.0), &mut rng);
+    let x2: Array2<f64> = Array::random_using((4, 3), Uniform::new(-1.0, 1.0), &mut rng);
+    let x3: Array2<f64> = Array::random_using((4, 3), Uniform::new(-1.0, 1.0), &mut rng);
+    let close = |a: &Array2<f64>, b: &Array2<f64>| (a - b).iter().all(|v| v.abs() < 1e-6);
+
+    // large margin so that dissimilar pairs are inside it
+    let same: Array1<f64> = array![1.0, 0.0, 1.0, 0.0];
+    let mut contrastive = ContrastiveLoss::new(3.0).unwrap();
+    assert!(ContrastiveLoss::<f64>::new(0.0).is_err());
+    let _ = contrastive.forward(&x1, &x2, &same);
+    let (dx1, dx2) = contrastive.backward(1.0);
+    let mut layer = ContrastiveLoss::new(3.0).unwrap();
+    assert!(close(
+        &dx1,
+        &numerical_grad2(|v| layer.forward(v, &x2, &same), &x1)
+    ));
+    assert!(close(
+        &dx2,
+        &numerical_grad2(|v| layer.forward(&x1, v, &same), &x2)
+    ));
+    // dissimilar pairs beyond the margin have no loss
+    let mut contrastive = ContrastiveLoss::new(1e-3).unwrap();
+    let loss = contrastive.forward(&x1, &x2, &array![0.0, 0.0, 0.0, 0.0]);
+    assert_eq!(loss, 0.0);
+
+    let mut triplet = TripletLoss::new(2.0).unwrap();
+    let loss = triplet.forward(&x1, &x2, &x3);
+    assert!(loss > 0.0);
+    let (da, dp, dn) = triplet.backward(1.0);
+    let mut layer = TripletLoss::new(2.0).unwrap();
+    assert!(close(
+        &da,
+        &numerical_grad2(|v| layer.forward(v, &x2, &x3), &x1)
+    ));
+    assert!(close(
+        &dp,
+        &numerical_grad2(|v| layer.forward(&x1, v, &x3), &x2)
+    ));
+    assert!(close(
+        &dn,
+        &numerical_grad2(|v| layer.forward(&x1, &x2, v), &x3)
+    ));
+
+    let t: Array1<f64> = array![1.0, -1.0, 1.0, -1.0];
+    let mut cosine = CosineEmbeddingLoss::new(-0.5).unwrap();
+    assert!(CosineEmbeddingLoss::<f64>::new(1.0).is_err());
+    let _ = cosine.forward(&x1, &x2, &t);
+    let (dx1, dx2) = cosine.backward(1.0);
+    let mut layer = CosineEmbeddingLoss::new(-0.5).unwrap();
+    assert!(close(
+        &dx1,
+        &numerical_grad2(|v| layer.forward(v, &x2, &t), &x1)
+    ));
+    assert!(close(
+        &dx2,
+        &numerical_grad2(|v| layer.forward(&x1, v, &t), &x2)
+    ));
+    let parallel: Array2<f64> = &x1 * 2.0;
+    assert!(
+        cosine
+            .forward(&x1, &parallel, &array![1.0, 1.0, 1.0, 1.0])
+            .abs()
+            < 1e-12
+    );
+
+    // batch-hard mining picks the farthest positive and the nearest negative
+    let x: Array2<f64> = array![[0.0, 0.0], [1.0, 0.0], [3.0, 0.0], [1.5, 0.0], [5.0, 0.0]];
+    let labels: Array1<usize> = array![0, 0, 0, 1, 2];
+    let mut batch_hard = BatchHardTripletLoss::new(1.0).unwrap();
+    let loss = batch_hard.forward(&x, &labels);
+    assert_eq!(
+        batch_hard.get_triplets(),
+        vec![[0, 2, 3], [1, 2, 3], [2, 0, 3]]
+    );
+    assert!((loss - (2.5 + 2.5 + 2.5) / 3.0).abs() < 1e-12);
+    let dx = batch_hard.backward(1.0);
+    let mut layer = BatchHardTripletLoss::new(1.0).unwrap();
+    assert!(close(
+        &dx,
+        &numerical_grad2(|v| layer.forward(v, &labels), &x)
+    ));
+
+    // sampling off a dataset with one-hot and sparse labels
+    let sparse = SparseMNISTDataSet2::<f64> {
+        train_images: Array::from_shape_fn((6, 2), |(ii, jj)| (ii * 2 + jj) as f64),
+        train_labels: array![0, 1, 0, 2, 1, 0],
+        test_images: Array2::zeros((0, 2)),
+        test_labels: Array1::zeros(0),
+    };
+    let triplets = sample_triplets(&sparse.train_labels, 50, &mut rng).unwrap();
+    for &[a, p, n] in triplets.iter() {
+        let label = sparse.train_labels[a];
+        assert!(a != p && sparse.train_labels[p] == label && sparse.train_labels[n] != label);
+    }
+    for ([a, b], same) in sample_pairs(&sparse.train_labels, 50, &mut rng).unwrap() {
+        assert_eq!(sparse.train_labels[a] == sparse.train_labels[b], same);
+    }
+    assert!(sample_triplets(&array![0, 1, 2], 1, &mut rng).is_err());
+    assert!(sample_triplets(&array![0, 0, 0], 1, &mut rng).is_err());
+    let dense = MNISTDataSet2::<f64> {
+        train_images: sparse.train_images.clone(),
+        train_labels: to_one_hot(&sparse.train_labels, 3).unwrap(),
+        test_images: Array2::zeros((0, 2)),
+        test_labels: Array2::zeros((0, 3)),
+    };
+    let [anchor, positive, negative] = dense
+        .sample_train_triplets(8, &mut StdRng::seed_from_u64(2))
+        .unwrap();
+    assert_eq!(
+        [anchor.clone(), positive.clone(), negative.clone()],
+        sparse
+            .sample_train_triplets(8, &mut StdRng::seed_from_u64(2))
+            .unwrap()
+    );
+    assert_eq!(anchor.dim(), (8, 2));
+
+    // an MLP backbone without a softmax head trained on embeddings
+    let centers = array![[2.0, 2.0], [-2.0, -2.0], [2.0, -2.0]];
+    let t_train: Array1<usize> = (0..60).map(|ii| ii % 3).collect();
+    let noise: Array2<f64> = Array::random_using((60, 2), Uniform::new(-1.0, 1.0), &mut rng);
+    let x_train: Array2<f64> = centers.select(Axis(0), t_train.as_slice().unwrap()) + noise;
+    let mut model = MLPClassifier::new(
+        2,
+        &[16],
+        4,
+        &[ActivatorEnum::ReLU],
+        OptimizerEnum::SGD(0.05),
+        NormalizationEnum::None,
+        UseDropoutEnum::None,
+        RegularizerEnum::None,
+        0,
+        WeightInitEnum::He,
+        0.1,
+    )
+    .unwrap();
+    let mut batch_hard = BatchHardTripletLoss::new(1.0).unwrap();
+    let initial_loss = batch_hard.forward(&model.predict_logits(&x_train), &t_train);
+    for _ in 0..100 {
+        let embeddings = model.predict_logits(&x_train);
+        let _ = batch_hard.forward(&embeddings, &t_train);
+        model.update_with_output_grad(&batch_hard.backward(1.0));
+    }
+    let final_loss = batch_hard.forward(&model.predict_logits(&x_train), &t_train);
+    assert!(final_loss < initial_loss * 0.5);
+}
+
+fn check_extreme_logits<T: CrateFloat>() {
+    let big: T = cast_t2u(1e4);
+    let (zero, one): (T, T) = (cast_t2u(0.0), cast_t2u(1.0));
+    let x: Array2<T> = array![[big, -big, zero], [-big, -big, big], [big, big, -big]];
+    let t: Array2<T> = array![[one, zero, zero], [one, zero, zero], [zero, one, zero]];
+
+    let mut layer = SoftmaxWithLoss2::<T>::new((3, 3), 0);
+    let loss = layer.forward(&x, &t);
+    // -log(softmax) of the targets are 0, 2e4 and log(2)
+    let expected: T = (cast_t2u::<f64, T>(2e4) + cast_t2u(std::f64::consts::LN_2)) / cast_t2u(3.0);
+    assert!(loss.is_finite());
+    assert!((loss - expected).abs() / expected < cast_t2u(1e-6));
+    let grad = layer.backward(one);
+    assert!(grad.iter().all(|v| v.is_finite()));
+    let expected_grad: Array2<T> = (array![[1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.5, 0.5, 0.0]]
+        - &t.mapv(cast_t2u::<T, f64>))
+        .mapv(|v| cast_t2u(v / 3.0));
+    assert!((&grad - &expected_grad)
+        .iter()
+        .all(|v| v.abs() < cast_t2u(1e-6)));
+
+    let mut layer = SoftmaxWithLoss2::<T>::new((3, 3), 0)
+        .with_class_weights(array![one, cast_t2u(2.0), one])
+        .unwrap()
+        .with_label_smoothing(cast_t2u(0.1))
+        .unwrap()
+        .with_focal(cast_t2u(2.0), one)
+        .unwrap();
+    assert!(layer.forward(&x, &t).is_finite());
+    assert!(layer.backward(one).iter().all(|v| v.is_finite()));
+
+    let mut log_softmax = LogSoftmax2::<T>::new((3, 3), 0);
+    let y = log_softmax.forward(&x);
+    assert!(y.iter().all(|v| v.is_finite()));
+    assert_eq!(y[[0, 0]], zero);
+    assert_eq!(y[[0, 1]], -big * cast_t2u(2.0));
+    let mut softmax = Softmax2::<T>::new((3, 3), 0);
+    let p = softmax.forward(&x);
+    assert!(p.iter().all(|v| v.is_finite()));
+    assert_eq!(p.sum_axis(Axis(1)), Array1::from_elem(3, one));
+    assert!((&p - &y.mapv(|v| v.exp()))
+        .iter()
+        .all(|v| v.abs() < cast_t2u(1e-6)));
+}
+
+#[test]
+fn test_log_softmax() {
+    check_extreme_logits::<f32>();
+    check_extreme_logits::<f64>();
+
+    // NaN no longer hides in the max
+    let mut softmax = Softmax2::<f64>::new((1, 3), 0);
+    assert!(softmax
+        .forward(&array![[1.0, f64::NAN, 0.0]])
+        .iter()
+        .all(|v| v.is_nan()));
+
+    // gradients of log-softmax and softmax against numerical ones
+    let x: Array2<f64> = array![[0.3, -1.2, 2.0], [1.5, 0.1, -0.4]];
+    let w: Array2<f64> = array![[1.0, -2.0, 0.5], [0.3, 0.7, -1.1]];
+    let close = |a: &Array2<f64>, b: &Array2<f64>| (a - b).iter().all(|v| v.abs() < 1e-6);
+    let mut log_softmax = LogSoftmax2::<f64>::new((2, 3), 0);
+    let _ = log_softmax.forward(&x);
+    let grad = log_softmax.backward(&w);
+    let mut layer = LogSoftmax2::<f64>::new((2, 3), 0);
+    assert!(close(
+        &grad,
+        &numerical_grad2(|v| (layer.forward(v) * &w).sum(), &x)
+    ));
+    let mut softmax = Softmax2::<f64>::new((2, 3), 0);
+    let _ = softmax.forward(&x);
+    let grad = softmax.backward(&w);
+    let mut layer = Softmax2::<f64>::new((2, 3), 0);
+    assert!(close(
+        &grad,
+        &numerical_grad2(|v| (layer.forward(v) * &w).sum(), &x)
+    ));
+
+    // log-softmax followed by the negative log-likelihood equals the softmax-with-loss
+    let t: Array2<f64> = array![[0.0, 0.0, 1.0], [1.0, 0.0, 0.0]];
+    let mut loss_layer = SoftmaxWithLoss2::<f64>::new((2, 3), 0);
+    let loss = loss_layer.forward(&x, &t);
+    let nll = -(log_softmax.forward(&x) * &t).sum() / 2.0;
+    assert!((loss - nll).abs() < 1e-12);
+    let grad = log_softmax.backward(&(-&t / 2.0));
+    assert!(close(&grad, &loss_layer.backward(1.0)));
+    assert_eq!(
+        call_activator::<f64, Ix2, _>(ActivatorEnum::LogSoftmax, (2, 3), 0).forward(&x),
+        log_softmax.forward(&x)
+    );
+}
+
+#[test]
+fn test_classification_metrics() {
+    let scores: Array2<f64> = array![
+        [0.7, 0.2, 0.1],
+        [0.1, 0.6, 0.3],
+        [0.2, 0.5, 0.3],
+        [0.3, 0.3, 0.4]
+    ];
+    let labels: Array1<usize> = array![0, 1, 2, 0];
+    let close = |a: f64, b: f64| (a - b).abs() < 1e-12;
+
+    assert_eq!(accuracy(&scores, &labels).unwrap(), 0.5);
+    assert_eq!(top_k_accuracy(&scores, &labels, 1).unwrap(), 0.5);
+    assert_eq!(top_k_accuracy(&scores, &labels, 2).unwrap(), 1.0);
+    assert!(top_k_accuracy(&scores, &labels, 0).is_err());
+    assert!(accuracy(&scores, &array![0, 1, 3, 0]).is_err());
+    assert!(accuracy(&scores, &array![0, 1, 2]).is_err());
+
+    let cm = confusion_matrix(&labels, &array![0, 1, 1, 2], 3).unwrap();
+    assert_eq!(cm, array![[1, 0, 1], [0, 1, 0], [0, 1, 0]]);
+    let (p, r, f1): (Array1<f64>, Array1<f64>, Array1<f64>) = per_class_scores(&cm);
+    assert_eq!(p, array![1.0, 0.5, 0.0]);
+    assert_eq!(r, array![0.5, 1.0, 0.0]);
+    assert!(close(f1[0], 2.0 / 3.0) && close(f1[1], 2.0 / 3.0) && f1[2] == 0.0);
+    assert_eq!(precision::<f64>(&cm, &AverageEnum::Macro), 0.5);
+    assert_eq!(recall::<f64>(&cm, &AverageEnum::Macro), 0.5);
+    assert!(close(f1_score(&cm, &AverageEnum::Macro), 4.0 / 9.0));
+    assert_eq!(f1_score::<f64>(&cm, &AverageEnum::Micro), 0.5);
+    assert_eq!(
+        ClassificationMetricEnum::F1(AverageEnum::Macro)
+            .compute(&scores, &labels)
+            .unwrap(),
+        f1_score::<f64>(&cm, &AverageEnum::Macro)
+    );
+
+    let expected = -(0.7f64.ln() + 0.6f64.ln() + 0.3f64.ln() + 0.3f64.ln()) / 4.0;
+    assert!(close(log_loss(&scores, &labels).unwrap(), expected));
+    let certain: Array2<f64> = array![[0.0, 1.0]];
+    assert!(log_loss(&certain, &array![0]).unwrap().is_finite());
+
+    // logits are turned into probabilities for the log-loss and the AUCs
+    assert_eq!(to_probabilities(&scores), scores);
+    let logits: Array2<f64> = scores.mapv(|p| 3.0 * p.ln() + 1.0);
+    let prob: Array2<f64> = Softmax2::new((4, 3), 0).forward(&logits);
+    assert!(close(
+        ClassificationMetricEnum::LogLoss
+            .compute(&logits, &labels)
+            .unwrap(),
+        log_loss(&prob, &labels).unwrap()
+    ));
+    assert!(close(
+        ClassificationMetricEnum::RocAuc(AverageEnum::Macro)
+            .compute(&logits, &labels)
+            .unwrap(),
+        roc_auc(&prob, &labels, &AverageEnum::Macro).unwrap()
+    ));
+
+    // class 2 has a tie between its positive and a negative
+    assert!(close(
+        roc_auc(&scores, &labels, &AverageEnum::Macro).unwrap(),
+        (1.0 + 1.0 + 0.5) / 3.0
+    ));
+    assert!(close(
+        pr_auc(&scores, &labels, &AverageEnum::Macro).unwrap(),
+        (1.0 + 1.0 + 1.0 / 3.0) / 3.0
+    ));
+    // classes without positives are left out
+    let auc = roc_auc(&scores, &array![0, 1, 1, 0], &AverageEnum::Macro).unwrap();
+    assert!(close(auc, 1.0));
+
+    // ROC-AUC is the fraction of correctly ordered pairs of a positive and a negative
+    let mut rng = StdRng::seed_from_u64(3);
+    let scores: Array2<f64> = Array::random_using((40, 4), Uniform::new(0.0, 1.0), &mut rng);
+    let labels: Array1<usize> = (0..40).map(|ii| (ii * 7) % 4).collect();
+    let flattened: Vec<(f64, bool)> = scores
+        .indexed_iter()
+        .map(|((ii, k), &v)| (v, labels[ii] == k))
+        .collect();
+    let mut num_ordered = 0.0;
+    let mut num_pairs = 0.0;
+    for &(p, _) in flattened.iter().filter(|v| v.1) {
+        for &(n, _) in flattened.iter().filter(|v| !v.1) {
+            num_pairs += 1.0;
+            if p > n {
+                num_ordered += 1.0;
+            }
+        }
+    }
+    let auc = roc_auc(&scores, &labels, &AverageEnum::Micro).unwrap();
+    assert!(close(auc, num_ordered / num_pairs));
+
+    // metrics logged by the trainer
+    let centers = array![[2.0, 2.0], [-2.0, -2.0], [2.0, -2.0]];
+    let t_train: Array1<usize> = (0..150).map(|ii| ii % 3).collect();
+    let noise: Array2<f64> = Array::random_using((150, 2), Uniform::new(-1.0, 1.0), &mut rng);
+    let x_train: Array2<f64> = centers.select(Axis(0), t_train.as_slice().unwrap()) + noise;
+    let t_one_hot: Array2<f64> = to_one_hot(&t_train, 3).unwrap();
+    let mut model: Box<dyn ModelBase<f64, A = Array2<f64>, B = Array2<f64>>> = Box::new(
+        MLPClassifier::new(
+            2,
+            &[8],
+            3,
+            &[ActivatorEnum::ReLU],
+            OptimizerEnum::SGD(0.1),
+            NormalizationEnum::None,
+            UseDropoutEnum::None,
+            RegularizerEnum::None,
+            0,
+            WeightInitEnum::He,
+            0.1,
+        )
+        .unwrap(),
+    );
+    let metrics = vec![
+        ClassificationMetricEnum::TopK(1),
+        ClassificationMetricEnum::F1(AverageEnum::Macro),
+        ClassificationMetricEnum::RocAuc(AverageEnum::Macro),
+    ];
+    let mut trainer = Trainer::new(
+        x_train.clone(),
+        t_one_hot.clone(),
+        x_train.clone(),
+        t_one_hot.clone(),
+        0,
+        10,
+        15,
+        0,
+        true,
+        0,
+    )
+    .with_metrics(metrics.clone());
+    trainer.train(&mut model).unwrap();
+    let results = trainer.get_results();
+    let (_, train_acc, _) = results.train_result();
+    let metric_results = results.metric_result();
+    assert_eq!(metric_results.len(), 3);
+    assert_eq!(metric_results[0].0, metrics[0]);
+    assert_eq!(metric_results[0].1, train_acc);
+    assert!(metric_results
+        .iter()
+        .all(|v| v.1.len() == 10 && v.2.len() == 10));
+    assert!(metric_results[2].1[9] > 0.9);
+
+    // metrics failing mid-run stop the training with an error
+    let mut trainer = Trainer::new(
+        x_train.clone(),
+        t_one_hot.clone(),
+        x_train.clone(),
+        t_one_hot,
+        0,
+        10,
+        15,
+        0,
+        true,
+        0,
+    )
+    .with_metrics(vec![ClassificationMetricEnum::TopK(0)]);
+    assert!(trainer.train(&mut model).is_err());
+}
+
+#[test]
+fn test_regression_and_calibration() {
+    let close = |a: f64, b: f64| (a - b).abs() < 1e-12;
+    let y: Array2<f64> = array![[1.0, 2.0], [3.0, 4.0], [5.0, 6.0]];
+    let t: Array2<f64> = array![[1.0, 1.0], [3.0, 5.0], [4.0, 6.0]];
+    assert!(close(mean_squared_error(&y, &t).unwrap(), 0.5));
+    assert!(close(
+        root_mean_squared_error(&y, &t).unwrap(),
+        0.5f64.sqrt()
+    ));
+    assert!(close(mean_absolute_error(&y, &t).unwrap(), 0.5));
+    assert!(close(r2_score(&y, &t).unwrap(), 23.0 / 28.0));
+    assert!(close(explained_variance_score(&y, &t).unwrap(), 6.0 / 7.0));
+    // a constant shift keeps the explained variance but not the R^2 score
+    let shifted: Array2<f64> = &t + 1.0;
+    assert!(close(explained_variance_score(&shifted, &t).unwrap(), 1.0));
+    assert!(r2_score(&shifted, &t).unwrap() < 1.0);
+    let constant: Array2<f64> = array![[2.0], [2.0]];
+    assert_eq!(r2_score(&constant, &constant).unwrap(), 1.0);
+    assert_eq!(r2_score(&array![[1.0], [2.0]], &constant).unwrap(), 0.0);
+    assert!(mean_squared_error(&y, &constant).is_err());
+
+    let prob: Array2<f64> = array![
+        [0.95, 0.05],
+        [0.85, 0.15],
+        [0.3, 0.7],
+        [0.45, 0.55],
+        [0.6, 0.4]
+    ];
+    let labels: Array1<usize> = array![0, 1, 1, 0, 0];
+    let diagram = reliability_diagram(&prob, &labels, 10).unwrap();
+    assert_eq!(diagram.bin_edges.len(), 11);
+    assert_eq!(diagram.counts, array![0, 0, 0, 0, 0, 1, 1, 1, 1, 1]);
+    assert_eq!(diagram.accuracies[9], 1.0);
+    assert_eq!(diagram.accuracies[8], 0.0);
+    assert_eq!(diagram.confidences[7], 0.7);
+    let ece = expected_calibration_error(&prob, &labels, 10).unwrap();
+    assert!(close(ece, (0.05 + 0.85 + 0.3 + 0.55 + 0.4) / 5.0));
+    assert!(close(diagram.maximum_calibration_error(), 0.85));
+    assert!(reliability_diagram(&prob, &labels, 0).is_err());
+
+    // MLPRegressor fits a smooth function of two inputs
+    let mut rng = StdRng::seed_from_u64(4);
+    let x_train: Array2<f64> = Array::random_using((200, 2), Uniform::new(-1.0, 1.0), &mut rng);
+    let t_train: Array2<f64> = Array::from_shape_fn((200, 2), |(ii, jj)| {
+        let (a, b) = (x_train[[ii, 0]], x_train[[ii, 1]]);
+        if jj == 0 {
+            a + 2.0 * b - 0.5
+        } else {
+            0.5 * a * a - b
+        }
+    });
+    let regressor = MLPRegressor::new(
+        2,
+        &[16],
+        2,
+        &[ActivatorEnum::Tanh],
+        OptimizerEnum::Adam(0.01, 0.9, 0.999),
+        NormalizationEnum::None,
+        UseDropoutEnum::None,
+        RegularizerEnum::None,
+        0,
+        WeightInitEnum::Xavier,
+        0.1,
+    )
+    .unwrap();
+    let mut model: Box<dyn ModelBase<f64, A = Array2<f64>, B = Array2<f64>>> = Box::new(regressor);
+    let initial_mse = model.loss(&x_train, &t_train);
+    let mut trainer = Trainer::new(
+        x_train.clone(),
+        t_train.clone(),
+        x_train.clone(),
+        t_train.clone(),
+        0,
+        50,
+        20,
+        0,
+        true,
+        0,
+    );
+    trainer.train(&mut model).unwrap();
+    let (_, train_r2, _) = trainer.get_results().train_result();
+    assert_eq!(train_r2.len(), 50);
+    let y_train = model.predict(&x_train);
+    assert!(close(
+        model.loss(&x_train, &t_train),
+        mean_squared_error(&y_train, &t_train).unwrap()
+    ));
+    assert!(model.loss(&x_train, &t_train) < initial_mse * 0.1);
+    assert!(model.accuracy(&x_train, &t_train) > 0.9);
+    assert!(model
+        .accuracy(&x_train, &t_train.slice(s![.., ..1]).to_owned())
+        .is_nan());
+    assert!(model
+        .accuracy(&Array2::zeros((0, 2)), &Array2::zeros((0, 2)))
+        .is_nan());
+
+    // the scheme keeps the model type and the loss
+    let dst = std::env::temp_dir().join(format!("rs_deep_regressor_{}.json", std::process::id()));
+    model.write_scheme(&dst).unwrap();
+    let params: ModelParameters<f64> = ModelParameters::from_file(&dst).unwrap();
+    assert_eq!(params.model_enum, ModelEnum::MLPRegressor);
+    assert_eq!(params.loss_enum, LossEnum::MeanSquaredError);
+    assert!(MLPRegressor::<f64>::read_scheme(&dst).is_ok());
+    assert!(MLPClassifier::<f64>::read_scheme(&dst).is_err());
+    std::fs::remove_file(&dst).unwrap();
+    // a regressor scheme without the loss is given the mean squared error
+    let mut scheme: serde_json::Value = serde_json::to_value(&params).unwrap();
+    scheme.as_object_mut().unwrap().remove("loss_enum");
+    let defaulted: ModelParameters<f64> = ModelParameters::from_value(scheme).unwrap();
+    assert_eq!(defaulted.loss_enum, LossEnum::MeanSquaredError);
+    assert!(MLPRegressor::from(defaulted).is_ok());
+    let mut params = params;
+    params.loss_enum = LossEnum::default();
+    match MLPRegressor::from(params) {
+        Err(CrateError::SchemeError(SchemeError::ValidationError(issues))) => {
+            assert_eq!(issues[0].path, "loss_enum")
+        }
+        _ => panic!("a regressor with a classification loss must be rejected."),
+    }
+}
+
+#[test]
+fn test_probability_calibration() {
+    let mut rng = StdRng::seed_from_u64(5);
+    let centers = array![[1.0, 1.0], [-1.0, -1.0], [1.0, -1.0]];
+    let t: Array1<usize> = (0..300).map(|ii| ii % 3).collect();
+    let noise: Array2<f64> = Array::random_using((300, 2), Uniform::new(-1.0, 1.0), &mut rng);
+    let x: Array2<f64> = centers.select(Axis(0), t.as_slice().unwrap()) + noise;
+    let mut model = MLPClassifier::new(
+        2,
+        &[16],
+        3,
+        &[ActivatorEnum::ReLU],
+        OptimizerEnum::Adam(0.05, 0.9, 0.999),
+        NormalizationEnum::None,
+        UseDropoutEnum::None,
+        RegularizerEnum::None,
+        0,
+        WeightInitEnum::He,
+        0.1,
+    )
+    .unwrap();
+    let t_one_hot: Array2<f64> = to_one_hot(&t, 3).unwrap();
+    for _ in 0..100 {
+        model.update(&x, &t_one_hot);
+    }
+
+    // probabilities are the softmax of the logits
+    let logits: Array2<f64> = model.predict_logits(&x);
+    let prob: Array2<f64> = model.predict_prob(&x);
+    for row in prob.outer_iter() {
+        assert!((row.sum() - 1.0).abs() < 1e-12);
+        assert!(row.iter().all(|&v| v > 0.0));
+    }
+    assert_eq!(to_class_indices(&prob), to_class_indices(&logits));
+    assert!(log_loss(&prob, &t).unwrap() < 1.0);
+
+    // temperature scaling softens overconfident logits without changing predictions
+    // logits favoring the labels by less than their noise, scaled up to be overconfident
+    let logit_noise: Array2<f64> = Array::random_using((300, 3), Uniform::new(-1.5, 1.5), &mut rng);
+    let overconfident: Array2<f64> = (&t_one_hot + &logit_noise) * 10.0;
+    let before = log_loss(&Softmax2::new((300, 3), 0).forward(&overconfident), &t).unwrap();
+    let calibration =
+        fit_calibration(&overconfident, &t, &CalibrationMethodEnum::Temperature).unwrap();
+    let temperature = match calibration {
+        CalibrationEnum::Temperature(temperature) => temperature,
+        _ => panic!("temperature scaling must give a temperature."),
+    };
+    assert!(temperature > 1.0);
+    let calibrated: Array2<f64> = calibration.apply(&overconfident);
+    let after = log_loss(&Softmax2::new((300, 3), 0).forward(&calibrated), &t).unwrap();
+    assert!(after < before);
+    assert_eq!(
+        to_class_indices(&calibrated),
+        to_class_indices(&overconfident)
+    );
+
+    // vector and matrix scaling are at least as good as temperature scaling
+    for method in [CalibrationMethodEnum::Vector, CalibrationMethodEnum::Matrix] {
+        let calibration = fit_calibration(&overconfident, &t, &method).unwrap();
+        let prob = Softmax2::new((300, 3), 0).forward(&calibration.apply(&overconfident));
+        assert!(log_loss(&prob, &t).unwrap() <= after + 1e-6);
+    }
+    assert!(fit_calibration(
+        &overconfident,
+        &array![0, 1],
+        &CalibrationMethodEnum::Vector
+    )
+    .is_err());
+
+    // the fitted temperature is applied by predict_prob and kept in the scheme
+    model
+        .calibrate(&x, &t, CalibrationMethodEnum::Temperature)
+        .unwrap();
+    let temperature = match *model.get_calibration() {
+        CalibrationEnum::Temperature(temperature) => temperature,
+        _ => panic!("the model must keep the temperature."),
+    };
+    let expected: Array2<f64> = Softmax2::new((300, 3), 0).forward(&(&logits / temperature));
+    assert!((&model.predict_prob(&x) - &expected)
+        .iter()
+        .all(|v| v.abs() < 1e-12));
+    for extension in ["json", "toml", "yaml"] {
+        let dst = std::env::temp_dir().join(format!(
+            "rs_deep_calibration_{}.{}",
+            std::process::id(),
+            extension
+        ));
+        model.write_scheme(&dst).unwrap();
+        let params: ModelParameters<f64> = ModelParameters::from_file(&dst).unwrap();
+        assert_eq!(
+            params.calibration,
+            CalibrationEnum::Temperature(temperature)
+        );
+        std::fs::remove_file(&dst).unwrap();
+    }
+
+    // invalid calibrations are rejected
+    assert!(model
+        .set_calibration(CalibrationEnum::Temperature(0.0))
+        .is_err());
+    let mut params: ModelParameters<f64> = ModelParameters::from(
+        ModelEnum::MLPClassifier,
+        2,
+        vec![4],
+        3,
+        0,
+        vec![ActivatorEnum::ReLU],
+        OptimizerEnum::SGD(0.1),
+        NormalizationEnum::None,
+        UseDropoutEnum::None,
+        RegularizerEnum::None,
+        WeightInitEnum::He,
+        0.01,
+    );
+    params.calibration = CalibrationEnum::Matrix {
+        weights: vec![vec![1.0, 0.0, 0.0], vec![0.0, 1.0]],
+        biases: vec![0.0; 2],
+    };
+    match params.validate() {
+        Err(SchemeError::ValidationError(issues)) => {
+            let paths: Vec<&str> = issues.iter().map(|v| v.path.as_str()).collect();
+            assert_eq!(
+                paths,
+                vec![
+                    "calibration.Matrix.weights",
+                    "calibration.Matrix.weights[1]",
+                    "calibration.Matrix.biases",
+                ]
+            );
+        }
+        _ => panic!("calibrations must match the output size."),
+    }
+    params.calibration = CalibrationEnum::Temperature(2.0);
+    let mut transposed = params.clone();
+    transposed.batch_axis = 1;
+    match transposed.validate() {
+        Err(SchemeError::ValidationError(issues)) => {
+            assert_eq!(issues[0].path, "calibration")
+        }
+        _ => panic!("calibrations need the batch axis 0."),
+    }
+    transposed.calibration = CalibrationEnum::None;
+    let mut transposed = MLPClassifier::from(transposed).unwrap();
+    assert!(transposed
+        .calibrate(&x, &t, CalibrationMethodEnum::Temperature)
+        .is_err());
+    params.loss_enum = LossEnum::SigmoidWithBCELoss {
+        pos_weight: Vec::new(),
+    };
+    match MLPClassifier::from(params) {
+        Err(CrateError::SchemeError(SchemeError::ValidationError(issues))) => {
+            assert_eq!(issues[0].path, "calibration")
+        }
+        _ => panic!("calibrations need the softmax loss."),
+    }
+}